@@ -0,0 +1,126 @@
+// Simulates a service and prints how it went.
+//
+// cargo run --bin simulate -- --seed 7 --cooks 2,3,4 --tables 2,2,4,4,6 --arrivals 15
+//
+// Several values for --cooks run the same evening once per kitchen size, so staffing changes
+// can be compared on identical arrivals.
+
+use std::env;
+use std::process;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use tutorial_13_module_system::menu::Menu;
+use tutorial_13_module_system::simulation::{self, Config};
+
+const USAGE: &str = "usage: simulate [--seed N] [--cooks N,N,..] [--tables N,N,..] \
+[--arrivals PER_HOUR] [--hours N] [--patience MINUTES] [--date YYYY-MM-DD] [--menu PATH]";
+
+fn parse_list(value: &str) -> Result<Vec<u32>, String> {
+    value
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse()
+                .map_err(|_| format!("{:?} is not a number", v))
+        })
+        .collect()
+}
+
+fn parse_args() -> Result<(Config, Vec<u32>, u64, String), String> {
+    let mut config = Config::default();
+    let mut cooks = vec![config.cooks];
+    let mut seed = 0;
+    let mut menu = String::from("menu.json");
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        let number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{:?} is not a number", value))
+        };
+        match flag.as_str() {
+            "--seed" => seed = value.parse().map_err(|_| format!("bad seed {:?}", value))?,
+            "--cooks" => cooks = parse_list(&value)?,
+            "--tables" => config.tables = parse_list(&value)?,
+            "--arrivals" => {
+                config.arrivals_per_hour = value
+                    .parse()
+                    .ok()
+                    .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+                    .ok_or_else(|| format!("{:?} is not a positive rate", value))?
+            }
+            "--hours" => {
+                config.service_length = number()?
+                    .checked_mul(60)
+                    .ok_or_else(|| format!("{:?} hours is too long", value))?
+            }
+            "--patience" => config.patience = number()?,
+            "--date" => config.date = value.parse().map_err(|e| format!("{}", e))?,
+            "--menu" => menu = value,
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    Ok((config, cooks, seed, menu))
+}
+
+fn main() {
+    let (config, cooks, seed, menu_path) = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+    let menu = Menu::load(&menu_path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", menu_path, e);
+        process::exit(1);
+    });
+
+    println!(
+        "{} tables, {:.1} parties/hour, {} minutes, seed {}",
+        config.tables.len(),
+        config.arrivals_per_hour,
+        config.service_length,
+        seed
+    );
+    println!(
+        "{:>5} {:>7} {:>6} {:>7} {:>8} {:>9} {:>8} {:>6} {:>11}",
+        "cooks",
+        "arrived",
+        "seated",
+        "walked",
+        "no-shows",
+        "avg wait",
+        "max wait",
+        "util",
+        "revenue"
+    );
+    for cooks in cooks {
+        let config = Config {
+            cooks,
+            ..config.clone()
+        };
+        // Reseeded for each run, so every kitchen size sees the same evening.
+        let report = simulation::run(&config, &menu, &mut StdRng::seed_from_u64(seed))
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+        println!(
+            "{:>5} {:>7} {:>6} {:>7} {:>8} {:>7.1}m {:>7}m {:>5.0}% {:>8}.{:02}",
+            cooks,
+            report.arrived,
+            report.seated,
+            report.walked_away,
+            report.no_shows,
+            report.average_wait,
+            report.longest_wait,
+            report.utilization * 100.0,
+            report.revenue / 100,
+            report.revenue % 100
+        );
+    }
+}
@@ -0,0 +1,538 @@
+// Checks and payments.
+//
+// A check lists the items of a table's served orders. Discounts come off the subtotal, tax is
+// charged on what's left, and the tip is added on top. All amounts are whole cents and every
+// rounding is done explicitly, so the parts of a split bill always add up to the total.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::front_of_house::serving::{Cents, Order, OrderError, OrderId, OrderStatus};
+
+// A percentage in hundredths of a percent, so 8.75% is `Rate(875)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rate(pub u32);
+
+impl Rate {
+    // Whole percents up to 65535%, which always fit in hundredths.
+    pub fn percent(percent: u16) -> Rate {
+        Rate(u32::from(percent) * 100)
+    }
+
+    // The rate applied to `amount`, rounded half up to the nearest cent. Worked out in u128 so
+    // nothing overflows, and capped at the most cents there can be.
+    pub fn of(self, amount: Cents) -> Cents {
+        let cents = (u128::from(amount) * u128::from(self.0) + 5_000) / 10_000;
+        Cents::try_from(cents).unwrap_or(Cents::MAX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discount {
+    Percent(Rate),
+    Amount(Cents),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tip {
+    // A share of the subtotal before discounts and tax.
+    Percent(Rate),
+    Amount(Cents),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub order: OrderId,
+    pub name: String,
+    pub amount: Cents,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BillingError {
+    EmptyCheck,
+    NotServed(OrderId),
+    // A split names a line the check doesn't have, or leaves a line unpaid.
+    BadSplit(String),
+    // The payments don't add up to what's owed.
+    PaymentMismatch { due: Cents, paid: Cents },
+    Declined(String),
+    Order(OrderError),
+}
+
+impl fmt::Display for BillingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BillingError::EmptyCheck => write!(f, "there is nothing to pay for"),
+            BillingError::NotServed(id) => write!(f, "order {} hasn't been served", id.0),
+            BillingError::BadSplit(why) => write!(f, "can't split the check: {}", why),
+            BillingError::PaymentMismatch { due, paid } => {
+                write!(f, "{} cents are due but {} were offered", due, paid)
+            }
+            BillingError::Declined(why) => write!(f, "payment declined: {}", why),
+            BillingError::Order(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BillingError {}
+
+impl From<OrderError> for BillingError {
+    fn from(e: OrderError) -> BillingError {
+        BillingError::Order(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    lines: Vec<Line>,
+    discounts: Vec<Discount>,
+    tax: Rate,
+    tip: Option<Tip>,
+}
+
+impl Check {
+    // Builds a check with one line per item. Every order must have been served.
+    pub fn new<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Result<Check, BillingError> {
+        let mut lines = Vec::new();
+        for order in orders {
+            if order.status() != OrderStatus::Served {
+                return Err(BillingError::NotServed(order.id()));
+            }
+            lines.extend(order.items().iter().map(|item| Line {
+                order: order.id(),
+                name: item.name.clone(),
+                amount: item.total(),
+            }));
+        }
+        if lines.is_empty() {
+            return Err(BillingError::EmptyCheck);
+        }
+
+        Ok(Check {
+            lines,
+            discounts: Vec::new(),
+            tax: Rate(0),
+            tip: None,
+        })
+    }
+
+    pub fn with_tax(mut self, tax: Rate) -> Check {
+        self.tax = tax;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: Discount) -> Check {
+        self.discounts.push(discount);
+        self
+    }
+
+    pub fn with_tip(mut self, tip: Tip) -> Check {
+        self.tip = Some(tip);
+        self
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    // The orders this check pays for, in the order they first appear.
+    pub fn orders(&self) -> Vec<OrderId> {
+        let mut seen = HashSet::new();
+        self.lines
+            .iter()
+            .map(|l| l.order)
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    pub fn subtotal(&self) -> Cents {
+        self.lines.iter().map(|l| l.amount).sum()
+    }
+
+    // Discounts are applied one after another and never take the check below zero.
+    pub fn discount(&self) -> Cents {
+        let subtotal = self.subtotal();
+        let remaining = self.discounts.iter().fold(subtotal, |left, d| {
+            left.saturating_sub(match d {
+                Discount::Percent(rate) => rate.of(left),
+                Discount::Amount(amount) => *amount,
+            })
+        });
+        subtotal - remaining
+    }
+
+    pub fn tax(&self) -> Cents {
+        self.tax.of(self.subtotal() - self.discount())
+    }
+
+    pub fn tip(&self) -> Cents {
+        match self.tip {
+            Some(Tip::Percent(rate)) => rate.of(self.subtotal()),
+            Some(Tip::Amount(amount)) => amount,
+            None => 0,
+        }
+    }
+
+    pub fn total(&self) -> Cents {
+        self.subtotal() - self.discount() + self.tax() + self.tip()
+    }
+
+    // Splits the total evenly between `guests`. Leftover cents go to the first guests.
+    pub fn split_even(&self, guests: usize) -> Result<Vec<Cents>, BillingError> {
+        if guests == 0 {
+            return Err(BillingError::BadSplit(String::from("nobody to pay")));
+        }
+        Ok(allocate(self.total(), &vec![1; guests]))
+    }
+
+    // Splits the total by shares that must add up to 100%.
+    pub fn split_by_percentage(&self, shares: &[Rate]) -> Result<Vec<Cents>, BillingError> {
+        // In u64, so shares can't wrap around to look like 100%.
+        let sum: u64 = shares.iter().map(|r| u64::from(r.0)).sum();
+        if sum != 10_000 {
+            return Err(BillingError::BadSplit(format!(
+                "shares add up to {}.{:02}%",
+                sum / 100,
+                sum % 100
+            )));
+        }
+        let weights: Vec<Cents> = shares.iter().map(|r| r.0 as Cents).collect();
+        Ok(allocate(self.total(), &weights))
+    }
+
+    // Each guest pays for the lines they had, given as indexes into `lines()`. Every line must
+    // be taken by exactly one guest. Discounts, tax and tip are shared in proportion to what
+    // each guest ordered.
+    pub fn split_by_items(&self, guests: &[Vec<usize>]) -> Result<Vec<Cents>, BillingError> {
+        let mut taken = vec![false; self.lines.len()];
+        let mut weights = Vec::with_capacity(guests.len());
+        for lines in guests {
+            let mut weight = 0;
+            for &i in lines {
+                match taken.get_mut(i) {
+                    Some(t) if !*t => *t = true,
+                    Some(_) => {
+                        return Err(BillingError::BadSplit(format!("line {} is taken twice", i)))
+                    }
+                    None => return Err(BillingError::BadSplit(format!("no line {}", i))),
+                }
+                weight += self.lines[i].amount;
+            }
+            weights.push(weight);
+        }
+        if let Some(i) = taken.iter().position(|t| !t) {
+            return Err(BillingError::BadSplit(format!(
+                "nobody pays for line {}",
+                i
+            )));
+        }
+        Ok(allocate(self.total(), &weights))
+    }
+}
+
+// Divides `total` in proportion to `weights` so that the parts add up exactly: each part is
+// rounded down, and the cents left over go to the parts that lost the most in rounding.
+fn allocate(total: Cents, weights: &[Cents]) -> Vec<Cents> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    // Products of cents and weights need u128; each part is at most `total`, so it fits back.
+    let sum: u128 = weights.iter().map(|&w| u128::from(w)).sum();
+    if sum == 0 {
+        return allocate(total, &vec![1; weights.len()]);
+    }
+    let product = |w: Cents| u128::from(total) * u128::from(w);
+
+    let mut parts: Vec<Cents> = weights
+        .iter()
+        .map(|&w| (product(w) / sum) as Cents)
+        .collect();
+    let mut by_remainder: Vec<usize> = (0..weights.len()).collect();
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse(product(weights[i]) % sum));
+    let left = total - parts.iter().sum::<Cents>();
+    for &i in by_remainder.iter().take(left as usize) {
+        parts[i] += 1;
+    }
+    parts
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentMethod {
+    Cash,
+    // A card, identified by the token the card terminal hands out.
+    Card(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    pub method: PaymentMethod,
+    pub amount: Cents,
+}
+
+impl Payment {
+    pub fn cash(amount: Cents) -> Payment {
+        Payment {
+            method: PaymentMethod::Cash,
+            amount,
+        }
+    }
+
+    pub fn card(token: &str, amount: Cents) -> Payment {
+        Payment {
+            method: PaymentMethod::Card(String::from(token)),
+            amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionId(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub transaction: TransactionId,
+    pub payment: Payment,
+}
+
+// Whatever actually moves the money: a card network, a cash drawer or a fake for tests.
+pub trait PaymentProcessor {
+    // Returns the reason as an error if the payment is refused.
+    fn charge(&mut self, payment: &Payment) -> Result<TransactionId, String>;
+
+    fn refund(&mut self, transaction: TransactionId) -> Result<(), String>;
+}
+
+// Charges every payment, or none of them: if one is declined, the ones already charged are
+// refunded.
+pub fn settle(
+    check: &Check,
+    payments: &[Payment],
+    processor: &mut dyn PaymentProcessor,
+) -> Result<Vec<Receipt>, BillingError> {
+    let due = check.total();
+    let paid = payments.iter().map(|p| p.amount).sum();
+    if paid != due {
+        return Err(BillingError::PaymentMismatch { due, paid });
+    }
+
+    let mut receipts: Vec<Receipt> = Vec::new();
+    for payment in payments {
+        match processor.charge(payment) {
+            Ok(transaction) => receipts.push(Receipt {
+                transaction,
+                payment: payment.clone(),
+            }),
+            Err(reason) => {
+                for receipt in &receipts {
+                    // Nothing more can be done if the refund fails too; the declined
+                    // payment is the error the caller needs to see.
+                    let _ = processor.refund(receipt.transaction);
+                }
+                return Err(BillingError::Declined(reason));
+            }
+        }
+    }
+    Ok(receipts)
+}
+
+// An in-memory processor that accepts everything except cards it was told to decline.
+#[derive(Debug, Default)]
+pub struct FakeProcessor {
+    declined_cards: HashSet<String>,
+    charges: Vec<(TransactionId, Payment)>,
+    refunds: Vec<TransactionId>,
+}
+
+impl FakeProcessor {
+    pub fn new() -> FakeProcessor {
+        FakeProcessor::default()
+    }
+
+    pub fn decline(mut self, token: &str) -> FakeProcessor {
+        self.declined_cards.insert(String::from(token));
+        self
+    }
+
+    pub fn charges(&self) -> &[(TransactionId, Payment)] {
+        &self.charges
+    }
+
+    pub fn refunds(&self) -> &[TransactionId] {
+        &self.refunds
+    }
+
+    // Money charged and not refunded.
+    pub fn balance(&self) -> Cents {
+        self.charges
+            .iter()
+            .filter(|(id, _)| !self.refunds.contains(id))
+            .map(|(_, p)| p.amount)
+            .sum()
+    }
+}
+
+impl PaymentProcessor for FakeProcessor {
+    fn charge(&mut self, payment: &Payment) -> Result<TransactionId, String> {
+        if let PaymentMethod::Card(token) = &payment.method {
+            if self.declined_cards.contains(token) {
+                return Err(format!("card {} was declined", token));
+            }
+        }
+        let id = TransactionId(self.charges.len() as u64 + 1);
+        self.charges.push((id, payment.clone()));
+        Ok(id)
+    }
+
+    fn refund(&mut self, transaction: TransactionId) -> Result<(), String> {
+        if !self.charges.iter().any(|(id, _)| *id == transaction) {
+            return Err(format!("no transaction {}", transaction.0));
+        }
+        if self.refunds.contains(&transaction) {
+            return Err(format!(
+                "transaction {} was already refunded",
+                transaction.0
+            ));
+        }
+        self.refunds.push(transaction);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_of_house::hosting::TableId;
+    use crate::front_of_house::serving::{OrderItem, Orders};
+
+    // Serves one order with a $12.50 burger, two $3.00 sodas and a $7.25 salad.
+    fn served() -> (Orders, OrderId) {
+        let mut orders = Orders::new();
+        let id = orders
+            .take_order(
+                TableId(1),
+                vec![
+                    OrderItem::new("Burger", 1250),
+                    OrderItem::new("Soda", 300).with_quantity(2),
+                    OrderItem::new("Salad", 725),
+                ],
+            )
+            .unwrap();
+        orders.send_to_kitchen(id).unwrap();
+        orders.mark_ready(id).unwrap();
+        orders.serve_order(id).unwrap();
+        (orders, id)
+    }
+
+    #[test]
+    fn totals_discount_tax_and_tip() {
+        let (orders, id) = served();
+        let check = Check::new(orders.get(id))
+            .unwrap()
+            .with_discount(Discount::Percent(Rate::percent(10)))
+            .with_discount(Discount::Amount(100))
+            .with_tax(Rate(875))
+            .with_tip(Tip::Percent(Rate::percent(18)));
+
+        assert_eq!(check.subtotal(), 2575);
+        assert_eq!(check.discount(), 358);
+        assert_eq!(check.tax(), 194);
+        assert_eq!(check.tip(), 464);
+        assert_eq!(check.total(), 2875);
+    }
+
+    #[test]
+    fn splits_always_add_up_to_the_total() {
+        let (orders, id) = served();
+        let check = Check::new(orders.get(id)).unwrap().with_tax(Rate(875));
+        let total = check.total();
+
+        let even = check.split_even(3).unwrap();
+        let shares = check
+            .split_by_percentage(&[Rate::percent(50), Rate(3333), Rate(1667)])
+            .unwrap();
+        let items = check.split_by_items(&[vec![0, 1], vec![2]]).unwrap();
+
+        assert_eq!(even, vec![934, 933, 933]);
+        for split in [even, shares, items] {
+            assert_eq!(split.iter().sum::<Cents>(), total);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_splits() {
+        let (orders, id) = served();
+        let check = Check::new(orders.get(id)).unwrap();
+
+        assert!(check.split_by_items(&[vec![0, 1]]).is_err());
+        assert!(check.split_by_items(&[vec![0, 1], vec![1, 2]]).is_err());
+        assert!(check.split_by_percentage(&[Rate::percent(60)]).is_err());
+        // These add up to 10_000 plus 2^32, which a u32 sum wraps to 100%.
+        assert!(check
+            .split_by_percentage(&[Rate(u32::MAX), Rate(10_001)])
+            .is_err());
+        assert!(check.split_even(0).is_err());
+    }
+
+    #[test]
+    fn large_amounts_do_not_overflow() {
+        let parts = allocate(Cents::MAX, &[Cents::MAX, Cents::MAX / 2, 1]);
+        assert_eq!(parts.iter().sum::<Cents>(), Cents::MAX);
+        assert!(parts[0] > parts[1] && parts[1] > parts[2]);
+        assert_eq!(Rate::percent(u16::MAX), Rate(6_553_500));
+        assert_eq!(Rate(20_000).of(Cents::MAX), Cents::MAX);
+        assert_eq!(Rate(5_000).of(Cents::MAX), Cents::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn only_served_orders_can_be_billed() {
+        let mut orders = Orders::new();
+        let id = orders
+            .take_order(TableId(1), vec![OrderItem::new("Tea", 250)])
+            .unwrap();
+
+        assert_eq!(Check::new(orders.get(id)), Err(BillingError::NotServed(id)));
+    }
+
+    #[test]
+    fn declined_card_refunds_earlier_payments() {
+        let (mut orders, id) = served();
+        let check = Check::new(orders.get(id)).unwrap();
+        let mut processor = FakeProcessor::new().decline("4000-0002");
+
+        let result = orders.take_payment(
+            &check,
+            &[Payment::cash(1000), Payment::card("4000-0002", 1575)],
+            &mut processor,
+        );
+
+        assert!(matches!(result, Err(BillingError::Declined(_))));
+        assert_eq!(processor.balance(), 0);
+        assert_eq!(orders.get(id).unwrap().status(), OrderStatus::Served);
+    }
+
+    #[test]
+    fn paying_the_check_marks_orders_paid() {
+        let (mut orders, id) = served();
+        let check = Check::new(orders.get(id)).unwrap();
+        let mut processor = FakeProcessor::new();
+
+        assert!(matches!(
+            orders.take_payment(&check, &[Payment::cash(100)], &mut processor),
+            Err(BillingError::PaymentMismatch {
+                due: 2575,
+                paid: 100
+            })
+        ));
+        let receipts = orders
+            .take_payment(
+                &check,
+                &[Payment::cash(1575), Payment::card("4111-1111", 1000)],
+                &mut processor,
+            )
+            .unwrap();
+
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(processor.balance(), 2575);
+        assert_eq!(orders.get(id).unwrap().status(), OrderStatus::Paid);
+    }
+}
@@ -1 +1,484 @@
-pub fn add_to_waitlist() {}
+// The host stand: walk-in parties wait in a queue until a table that fits them frees up.
+//
+// Time is counted in whole minutes since the restaurant opened.
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub type Minutes = u32;
+
+// How long a table is assumed to stay occupied before any party has left.
+const DEFAULT_TURNOVER: Minutes = 45;
+// How long a called party has to show up before their table is given away.
+const DEFAULT_NO_SHOW_GRACE: Minutes = 10;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct Ticket(pub u32);
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub struct TableId(pub u32);
+
+// Parties with a higher priority are seated first; within a priority, the earliest arrival
+// goes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Standard,
+    // Parties with a booking go ahead of walk-ins.
+    Reservation,
+    Vip,
+    // Guests who need an accessible table or can't wait for long.
+    Accessibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Party {
+    pub name: String,
+    pub size: u32,
+    // Phone number or pager the host uses to call the party.
+    pub contact: String,
+    pub priority: Priority,
+}
+
+impl Party {
+    pub fn new(name: &str, size: u32, contact: &str) -> Party {
+        Party {
+            name: String::from(name),
+            size,
+            contact: String::from(contact),
+            priority: Priority::Standard,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Party {
+        self.priority = priority;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableState {
+    Free,
+    // Held for a party that has been called but hasn't checked in yet.
+    Held(Ticket),
+    Occupied { ticket: Ticket, since: Minutes },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub id: TableId,
+    pub capacity: u32,
+    pub state: TableState,
+}
+
+// A party that has been told their table is ready.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub ticket: Ticket,
+    pub table: TableId,
+    pub name: String,
+    pub contact: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostingError {
+    UnknownTicket(Ticket),
+    UnknownTable(TableId),
+    // The party hasn't been called, so no table is being held for them.
+    NotCalled(Ticket),
+    // No table in the restaurant is big enough for the party.
+    PartyTooLarge(u32),
+    EmptyParty,
+    TableNotOccupied(TableId),
+}
+
+impl fmt::Display for HostingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HostingError::UnknownTicket(t) => write!(f, "no party with ticket {}", t.0),
+            HostingError::UnknownTable(t) => write!(f, "no table {}", t.0),
+            HostingError::NotCalled(t) => write!(f, "party {} has not been called yet", t.0),
+            HostingError::PartyTooLarge(size) => write!(f, "no table seats {} guests", size),
+            HostingError::EmptyParty => write!(f, "a party needs at least one guest"),
+            HostingError::TableNotOccupied(t) => write!(f, "table {} is not occupied", t.0),
+        }
+    }
+}
+
+impl std::error::Error for HostingError {}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    ticket: Ticket,
+    party: Party,
+    arrived: Minutes,
+    // Set once the party has been called to a table.
+    called: Option<(TableId, Minutes)>,
+}
+
+#[derive(Debug)]
+pub struct Waitlist {
+    tables: Vec<Table>,
+    waiting: Vec<Entry>,
+    seated: HashMap<Ticket, Party>,
+    next_ticket: u32,
+    no_show_grace: Minutes,
+    // Completed meals, used to estimate how long a table stays occupied.
+    turnovers: Vec<Minutes>,
+    no_shows: Vec<Party>,
+}
+
+impl Waitlist {
+    // Creates a waitlist for a dining room with one table per capacity given.
+    pub fn new(capacities: &[u32]) -> Waitlist {
+        let tables = capacities
+            .iter()
+            .enumerate()
+            .map(|(i, &capacity)| Table {
+                id: TableId(i as u32 + 1),
+                capacity,
+                state: TableState::Free,
+            })
+            .collect();
+
+        Waitlist {
+            tables,
+            waiting: Vec::new(),
+            seated: HashMap::new(),
+            next_ticket: 1,
+            no_show_grace: DEFAULT_NO_SHOW_GRACE,
+            turnovers: Vec::new(),
+            no_shows: Vec::new(),
+        }
+    }
+
+    pub fn with_no_show_grace(mut self, grace: Minutes) -> Waitlist {
+        self.no_show_grace = grace;
+        self
+    }
+
+    pub fn no_show_grace(&self) -> Minutes {
+        self.no_show_grace
+    }
+
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    // Parties that were called but never showed up.
+    pub fn no_shows(&self) -> &[Party] {
+        &self.no_shows
+    }
+
+    // The party sitting at or holding a ticket, whether still waiting or already seated.
+    pub fn party(&self, ticket: Ticket) -> Option<&Party> {
+        self.waiting
+            .iter()
+            .find(|e| e.ticket == ticket)
+            .map(|e| &e.party)
+            .or_else(|| self.seated.get(&ticket))
+    }
+
+    // Tickets of the parties still waiting, in the order they would be seated.
+    pub fn queue(&self) -> Vec<Ticket> {
+        self.seating_order().iter().map(|e| e.ticket).collect()
+    }
+
+    // Average minutes a table stays occupied, from the meals completed so far.
+    pub fn turnover(&self) -> Minutes {
+        if self.turnovers.is_empty() {
+            return DEFAULT_TURNOVER;
+        }
+        // Summed in u64, so many long meals can't overflow; the average fits back.
+        let total: u64 = self.turnovers.iter().map(|&t| u64::from(t)).sum();
+        (total / self.turnovers.len() as u64) as Minutes
+    }
+
+    pub fn add_to_waitlist(&mut self, party: Party, now: Minutes) -> Result<Ticket, HostingError> {
+        if party.size == 0 {
+            return Err(HostingError::EmptyParty);
+        }
+        if !self.tables.iter().any(|t| t.capacity >= party.size) {
+            return Err(HostingError::PartyTooLarge(party.size));
+        }
+
+        let ticket = Ticket(self.next_ticket);
+        self.next_ticket += 1;
+        self.waiting.push(Entry {
+            ticket,
+            party,
+            arrived: now,
+            called: None,
+        });
+        Ok(ticket)
+    }
+
+    // Removes a waiting party that decided to leave.
+    pub fn remove(&mut self, ticket: Ticket) -> Result<Party, HostingError> {
+        let entry = self.take_entry(ticket)?;
+        if let Some((table, _)) = entry.called {
+            self.table_mut(table)?.state = TableState::Free;
+        }
+        Ok(entry.party)
+    }
+
+    // Estimates how many minutes from `now` until the party gets a table.
+    //
+    // Every table is assumed to free up one turnover after it was taken, and the parties ahead
+    // in the queue take the earliest table that fits them.
+    pub fn estimated_wait(&self, ticket: Ticket, now: Minutes) -> Option<Minutes> {
+        let turnover = self.turnover();
+        let mut free_at: Vec<(u32, Minutes)> = self
+            .tables
+            .iter()
+            .map(|t| {
+                let at = match t.state {
+                    TableState::Free => now,
+                    TableState::Held(_) => now.saturating_add(turnover),
+                    TableState::Occupied { since, .. } => since.saturating_add(turnover).max(now),
+                };
+                (t.capacity, at)
+            })
+            .collect();
+
+        for entry in self.seating_order() {
+            if entry.called.is_some() {
+                if entry.ticket == ticket {
+                    return Some(0);
+                }
+                continue;
+            }
+            let (_, at) = free_at
+                .iter_mut()
+                .filter(|(capacity, _)| *capacity >= entry.party.size)
+                .min_by_key(|(capacity, at)| (*at, *capacity))?;
+            if entry.ticket == ticket {
+                return Some(*at - now);
+            }
+            *at = at.saturating_add(turnover);
+        }
+        None
+    }
+
+    // Calls the next party that fits a free table and holds the table for them.
+    //
+    // A party at the head of the queue that doesn't fit any free table doesn't block smaller
+    // parties behind it. The smallest free table that fits is used.
+    pub fn call_next(&mut self, now: Minutes) -> Option<Call> {
+        let (ticket, table) = self.seating_order().iter().find_map(|entry| {
+            if entry.called.is_some() {
+                return None;
+            }
+            self.tables
+                .iter()
+                .filter(|t| t.state == TableState::Free && t.capacity >= entry.party.size)
+                .min_by_key(|t| t.capacity)
+                .map(|t| (entry.ticket, t.id))
+        })?;
+
+        self.table_mut(table).ok()?.state = TableState::Held(ticket);
+        let entry = self.waiting.iter_mut().find(|e| e.ticket == ticket)?;
+        entry.called = Some((table, now));
+        Some(Call {
+            ticket,
+            table,
+            name: entry.party.name.clone(),
+            contact: entry.party.contact.clone(),
+        })
+    }
+
+    // Seats a called party at the table held for them.
+    pub fn seat_at_table(&mut self, ticket: Ticket, now: Minutes) -> Result<TableId, HostingError> {
+        let entry = self
+            .waiting
+            .iter()
+            .find(|e| e.ticket == ticket)
+            .ok_or(HostingError::UnknownTicket(ticket))?;
+        let (table, _) = entry.called.ok_or(HostingError::NotCalled(ticket))?;
+
+        let entry = self.take_entry(ticket)?;
+        self.table_mut(table)?.state = TableState::Occupied { ticket, since: now };
+        self.seated.insert(ticket, entry.party);
+        Ok(table)
+    }
+
+    // Gives away tables held for parties that didn't check in within the grace period.
+    pub fn release_no_shows(&mut self, now: Minutes) -> Vec<Ticket> {
+        let expired: Vec<Ticket> = self
+            .waiting
+            .iter()
+            .filter(|e| matches!(e.called, Some((_, at)) if now >= at.saturating_add(self.no_show_grace)))
+            .map(|e| e.ticket)
+            .collect();
+
+        for &ticket in &expired {
+            if let Ok(party) = self.remove(ticket) {
+                self.no_shows.push(party);
+            }
+        }
+        expired
+    }
+
+    // Frees a table once its party has left, returning the ticket of that party.
+    pub fn clear_table(&mut self, table: TableId, now: Minutes) -> Result<Ticket, HostingError> {
+        let table = self.table_mut(table)?;
+        let (ticket, since) = match table.state {
+            TableState::Occupied { ticket, since } => (ticket, since),
+            _ => return Err(HostingError::TableNotOccupied(table.id)),
+        };
+        table.state = TableState::Free;
+        self.seated.remove(&ticket);
+        self.turnovers.push(now.saturating_sub(since));
+        Ok(ticket)
+    }
+
+    fn seating_order(&self) -> Vec<&Entry> {
+        let mut order: Vec<&Entry> = self.waiting.iter().collect();
+        order.sort_by_key(|e| (std::cmp::Reverse(e.party.priority), e.arrived, e.ticket));
+        order
+    }
+
+    fn take_entry(&mut self, ticket: Ticket) -> Result<Entry, HostingError> {
+        let index = self
+            .waiting
+            .iter()
+            .position(|e| e.ticket == ticket)
+            .ok_or(HostingError::UnknownTicket(ticket))?;
+        Ok(self.waiting.remove(index))
+    }
+
+    fn table_mut(&mut self, id: TableId) -> Result<&mut Table, HostingError> {
+        self.tables
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or(HostingError::UnknownTable(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seats_parties_at_smallest_fitting_table() {
+        let mut waitlist = Waitlist::new(&[2, 4, 6]);
+        let ticket = waitlist
+            .add_to_waitlist(Party::new("Ada", 3, "555-0100"), 0)
+            .unwrap();
+
+        let call = waitlist.call_next(0).unwrap();
+
+        assert_eq!(call.ticket, ticket);
+        assert_eq!(waitlist.seat_at_table(ticket, 2), Ok(TableId(2)));
+    }
+
+    #[test]
+    fn large_party_does_not_block_smaller_ones() {
+        let mut waitlist = Waitlist::new(&[2, 6]);
+        let big = waitlist
+            .add_to_waitlist(Party::new("Big", 6, "1"), 0)
+            .unwrap();
+        let big_call = waitlist.call_next(0).unwrap();
+        waitlist.seat_at_table(big_call.ticket, 0).unwrap();
+        let bigger = waitlist
+            .add_to_waitlist(Party::new("Next", 5, "2"), 1)
+            .unwrap();
+        let pair = waitlist
+            .add_to_waitlist(Party::new("Pair", 2, "3"), 2)
+            .unwrap();
+
+        assert_eq!(waitlist.call_next(3).map(|c| c.ticket), Some(pair));
+        assert_eq!(waitlist.queue(), vec![bigger, pair]);
+        assert_eq!(big_call.ticket, big);
+    }
+
+    #[test]
+    fn vip_parties_go_first() {
+        let mut waitlist = Waitlist::new(&[4]);
+        waitlist
+            .add_to_waitlist(Party::new("A", 2, "1"), 0)
+            .unwrap();
+        let vip = waitlist
+            .add_to_waitlist(Party::new("B", 2, "2").with_priority(Priority::Vip), 5)
+            .unwrap();
+
+        assert_eq!(waitlist.call_next(5).map(|c| c.ticket), Some(vip));
+    }
+
+    #[test]
+    fn estimates_wait_from_turnover() {
+        let mut waitlist = Waitlist::new(&[4]);
+        let first = waitlist
+            .add_to_waitlist(Party::new("A", 4, "1"), 0)
+            .unwrap();
+        waitlist.call_next(0);
+        waitlist.seat_at_table(first, 0).unwrap();
+        waitlist.clear_table(TableId(1), 30).unwrap();
+        let second = waitlist
+            .add_to_waitlist(Party::new("B", 2, "2"), 30)
+            .unwrap();
+        waitlist.call_next(30);
+        waitlist.seat_at_table(second, 30).unwrap();
+        let third = waitlist
+            .add_to_waitlist(Party::new("C", 2, "3"), 40)
+            .unwrap();
+        let fourth = waitlist
+            .add_to_waitlist(Party::new("D", 2, "4"), 41)
+            .unwrap();
+
+        assert_eq!(waitlist.turnover(), 30);
+        assert_eq!(waitlist.estimated_wait(third, 40), Some(20));
+        assert_eq!(waitlist.estimated_wait(fourth, 40), Some(50));
+    }
+
+    #[test]
+    fn no_shows_release_their_table() {
+        let mut waitlist = Waitlist::new(&[2]).with_no_show_grace(5);
+        let gone = waitlist
+            .add_to_waitlist(Party::new("A", 2, "1"), 0)
+            .unwrap();
+        let next = waitlist
+            .add_to_waitlist(Party::new("B", 2, "2"), 1)
+            .unwrap();
+        waitlist.call_next(2);
+
+        assert!(waitlist.release_no_shows(6).is_empty());
+        assert_eq!(waitlist.release_no_shows(7), vec![gone]);
+        assert_eq!(waitlist.no_shows()[0].name, "A");
+        assert_eq!(waitlist.call_next(7).map(|c| c.ticket), Some(next));
+        assert_eq!(
+            waitlist.seat_at_table(gone, 8),
+            Err(HostingError::UnknownTicket(gone))
+        );
+    }
+
+    #[test]
+    fn times_at_the_end_of_the_clock_saturate() {
+        let end = Minutes::MAX;
+        let mut waitlist = Waitlist::new(&[2]).with_no_show_grace(5);
+        let held = waitlist
+            .add_to_waitlist(Party::new("A", 2, "1"), end - 1)
+            .unwrap();
+        let next = waitlist
+            .add_to_waitlist(Party::new("B", 2, "2"), end - 1)
+            .unwrap();
+        waitlist.call_next(end - 1);
+
+        assert_eq!(waitlist.estimated_wait(held, end), Some(0));
+        assert_eq!(waitlist.estimated_wait(next, end), Some(0));
+        assert_eq!(waitlist.release_no_shows(end), vec![held]);
+    }
+
+    #[test]
+    fn rejects_parties_no_table_can_seat() {
+        let mut waitlist = Waitlist::new(&[2, 4]);
+
+        assert_eq!(
+            waitlist.add_to_waitlist(Party::new("A", 9, "1"), 0),
+            Err(HostingError::PartyTooLarge(9))
+        );
+    }
+}
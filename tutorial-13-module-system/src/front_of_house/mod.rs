@@ -1,5 +1,7 @@
 // Define child modules and have their contents lived in separate files.
 // For  the child modules, the file name should be the same as the module name.
 // They have to live in the same directory as the parent module.
+pub mod billing;
 pub mod hosting;
+pub mod reservations;
 pub mod serving;
@@ -0,0 +1,593 @@
+// Reservations: parties that booked a table for a date and time.
+//
+// A booking holds a table for one slot, starting at a time counted in minutes since opening,
+// the same clock the waitlist uses. When the party turns up they join the waitlist ahead of
+// walk-ins. The book can be backed by a JSON file that is rewritten after every change; a
+// change only takes effect once it has been written.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::Date;
+use crate::front_of_house::hosting::{
+    HostingError, Minutes, Party, Priority, TableId, Ticket, Waitlist,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ReservationId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: ReservationId,
+    pub name: String,
+    pub size: u32,
+    pub contact: String,
+    pub date: Date,
+    pub start: Minutes,
+    // None for an overbooked reservation that will wait for whichever table frees up first.
+    pub table: Option<TableId>,
+    // Set once the party has arrived and joined the waitlist. Not saved, since the waitlist
+    // isn't either: after reopening the book, nobody has arrived yet.
+    #[serde(skip)]
+    pub ticket: Option<Ticket>,
+}
+
+impl Reservation {
+    // Every booking in a book holds its table for the policy's one slot length, so both share
+    // `slot_length`. The ends are worked out in u64, as a slot can run past the last minute.
+    fn overlaps(&self, date: Date, start: Minutes, slot_length: Minutes) -> bool {
+        let end = |start: Minutes| u64::from(start) + u64::from(slot_length);
+        self.date == date
+            && u64::from(self.start) < end(start)
+            && u64::from(start) < end(self.start)
+    }
+}
+
+// What a guest asks for when booking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub name: String,
+    pub size: u32,
+    pub contact: String,
+    pub date: Date,
+    pub start: Minutes,
+}
+
+impl Request {
+    pub fn new(name: &str, size: u32, contact: &str, date: Date, start: Minutes) -> Request {
+        Request {
+            name: String::from(name),
+            size,
+            contact: String::from(contact),
+            date,
+            start,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReservationError {
+    UnknownReservation(ReservationId),
+    // Bookings have to start on a slot boundary, such as every 15 minutes.
+    NotOnSlot(Minutes),
+    // Every table that fits the party is booked, and so is every overbooking place.
+    NoAvailability,
+    // The requested table is already booked by another reservation at that time.
+    Conflict(ReservationId),
+    AlreadyArrived(ReservationId),
+    Hosting(HostingError),
+    Io(io::Error),
+    Corrupt(serde_json::Error),
+}
+
+impl fmt::Display for ReservationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReservationError::UnknownReservation(id) => write!(f, "no reservation {}", id.0),
+            ReservationError::NotOnSlot(start) => {
+                write!(f, "minute {} is not the start of a slot", start)
+            }
+            ReservationError::NoAvailability => write!(f, "no table is free at that time"),
+            ReservationError::Conflict(id) => {
+                write!(f, "the table is already booked by reservation {}", id.0)
+            }
+            ReservationError::AlreadyArrived(id) => {
+                write!(f, "reservation {} has already arrived", id.0)
+            }
+            ReservationError::Hosting(e) => write!(f, "{}", e),
+            ReservationError::Io(e) => write!(f, "couldn't save reservations: {}", e),
+            ReservationError::Corrupt(e) => write!(f, "reservation file is corrupt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReservationError {}
+
+impl From<HostingError> for ReservationError {
+    fn from(e: HostingError) -> ReservationError {
+        ReservationError::Hosting(e)
+    }
+}
+
+impl From<io::Error> for ReservationError {
+    fn from(e: io::Error) -> ReservationError {
+        ReservationError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    // How long a booking holds its table.
+    pub slot_length: Minutes,
+    // Bookings start on multiples of this many minutes.
+    pub granularity: Minutes,
+    // How many bookings without a table may overlap at any time, to make up for no-shows.
+    pub max_overbooked: usize,
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy {
+            slot_length: 90,
+            granularity: 15,
+            max_overbooked: 0,
+        }
+    }
+}
+
+// The part of the book that goes into the file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Saved {
+    next_id: u32,
+    reservations: Vec<Reservation>,
+}
+
+#[derive(Debug)]
+pub struct ReservationBook {
+    // Capacity of each table, numbered from 1 like the waitlist's tables.
+    tables: Vec<u32>,
+    policy: Policy,
+    saved: Saved,
+    path: Option<PathBuf>,
+}
+
+impl ReservationBook {
+    // An in-memory book for a dining room with one table per capacity given.
+    pub fn new(capacities: &[u32], policy: Policy) -> ReservationBook {
+        ReservationBook {
+            tables: capacities.to_vec(),
+            policy,
+            saved: Saved {
+                next_id: 1,
+                reservations: Vec::new(),
+            },
+            path: None,
+        }
+    }
+
+    // Loads the book from `path`, or starts an empty one if the file doesn't exist yet. Every
+    // change is written back to the file.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        capacities: &[u32],
+        policy: Policy,
+    ) -> Result<ReservationBook, ReservationError> {
+        let mut book = ReservationBook::new(capacities, policy);
+        match fs::read_to_string(&path) {
+            Ok(json) => {
+                book.saved = serde_json::from_str(&json).map_err(ReservationError::Corrupt)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        book.path = Some(path.as_ref().to_path_buf());
+        Ok(book)
+    }
+
+    pub fn get(&self, id: ReservationId) -> Option<&Reservation> {
+        self.saved.reservations.iter().find(|r| r.id == id)
+    }
+
+    // Reservations on `date`, earliest first.
+    pub fn on(&self, date: Date) -> Vec<&Reservation> {
+        let mut day: Vec<&Reservation> = self
+            .saved
+            .reservations
+            .iter()
+            .filter(|r| r.date == date)
+            .collect();
+        day.sort_by_key(|r| (r.start, r.id));
+        day
+    }
+
+    // Books the smallest free table that fits the party, or an overbooking place if every
+    // fitting table is taken.
+    pub fn book(&mut self, request: Request) -> Result<ReservationId, ReservationError> {
+        let table = self.find_table(&request, None)?;
+        self.insert(request, table)
+    }
+
+    // Books a specific table, failing if another reservation holds it at that time.
+    pub fn book_table(
+        &mut self,
+        request: Request,
+        table: TableId,
+    ) -> Result<ReservationId, ReservationError> {
+        self.check_request(&request)?;
+        match self.tables.get((table.0 as usize).wrapping_sub(1)) {
+            Some(&capacity) if capacity >= request.size => {}
+            Some(_) => return Err(HostingError::PartyTooLarge(request.size).into()),
+            None => return Err(HostingError::UnknownTable(table).into()),
+        }
+        if let Some(other) = self.booked(table, &request, None) {
+            return Err(ReservationError::Conflict(other));
+        }
+
+        self.insert(request, Some(table))
+    }
+
+    pub fn cancel(&mut self, id: ReservationId) -> Result<Reservation, ReservationError> {
+        let index = self
+            .saved
+            .reservations
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or(ReservationError::UnknownReservation(id))?;
+        let mut saved = self.saved.clone();
+        let reservation = saved.reservations.remove(index);
+        self.commit(saved)?;
+        Ok(reservation)
+    }
+
+    // Changes the time, date or size of a booking. The table may change too; if nothing is
+    // free, the original booking is kept as it was.
+    pub fn modify(&mut self, id: ReservationId, request: Request) -> Result<(), ReservationError> {
+        if self
+            .get(id)
+            .ok_or(ReservationError::UnknownReservation(id))?
+            .ticket
+            .is_some()
+        {
+            return Err(ReservationError::AlreadyArrived(id));
+        }
+        let table = self.find_table(&request, Some(id))?;
+
+        let mut saved = self.saved.clone();
+        let reservation = saved
+            .reservations
+            .iter_mut()
+            .find(|r| r.id == id)
+            .ok_or(ReservationError::UnknownReservation(id))?;
+        reservation.name = request.name;
+        reservation.size = request.size;
+        reservation.contact = request.contact;
+        reservation.date = request.date;
+        reservation.start = request.start;
+        reservation.table = table;
+        self.commit(saved)
+    }
+
+    // The party has arrived: they join the waitlist ahead of walk-ins.
+    pub fn check_in(
+        &mut self,
+        id: ReservationId,
+        waitlist: &mut Waitlist,
+        now: Minutes,
+    ) -> Result<Ticket, ReservationError> {
+        let reservation = self
+            .get(id)
+            .ok_or(ReservationError::UnknownReservation(id))?;
+        if reservation.ticket.is_some() {
+            return Err(ReservationError::AlreadyArrived(id));
+        }
+        let party = Party::new(&reservation.name, reservation.size, &reservation.contact)
+            .with_priority(Priority::Reservation);
+
+        let ticket = waitlist.add_to_waitlist(party, now)?;
+        self.get_mut(id)?.ticket = Some(ticket);
+        Ok(ticket)
+    }
+
+    // Puts every reservation on `date` that is due by `now` and hasn't arrived yet onto the
+    // waitlist, as the host does when going through the book at the start of each slot. If any
+    // of them can't join, none of them do.
+    pub fn merge_into_waitlist(
+        &mut self,
+        date: Date,
+        waitlist: &mut Waitlist,
+        now: Minutes,
+    ) -> Result<Vec<(ReservationId, Ticket)>, ReservationError> {
+        let due: Vec<ReservationId> = self
+            .on(date)
+            .into_iter()
+            .filter(|r| r.start <= now && r.ticket.is_none())
+            .map(|r| r.id)
+            .collect();
+
+        let mut merged = Vec::new();
+        for id in due {
+            match self.check_in(id, waitlist, now) {
+                Ok(ticket) => merged.push((id, ticket)),
+                Err(e) => {
+                    for (id, ticket) in merged {
+                        waitlist.remove(ticket)?;
+                        self.get_mut(id)?.ticket = None;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(merged)
+    }
+
+    fn insert(
+        &mut self,
+        request: Request,
+        table: Option<TableId>,
+    ) -> Result<ReservationId, ReservationError> {
+        let mut saved = self.saved.clone();
+        let id = ReservationId(saved.next_id);
+        saved.next_id += 1;
+        saved.reservations.push(Reservation {
+            id,
+            name: request.name,
+            size: request.size,
+            contact: request.contact,
+            date: request.date,
+            start: request.start,
+            table,
+            ticket: None,
+        });
+        self.commit(saved)?;
+        Ok(id)
+    }
+
+    fn check_request(&self, request: &Request) -> Result<(), ReservationError> {
+        if request.size == 0 {
+            return Err(HostingError::EmptyParty.into());
+        }
+        if !request.start.is_multiple_of(self.policy.granularity.max(1)) {
+            return Err(ReservationError::NotOnSlot(request.start));
+        }
+        if !self.tables.iter().any(|&c| c >= request.size) {
+            return Err(HostingError::PartyTooLarge(request.size).into());
+        }
+        Ok(())
+    }
+
+    // The reservation, other than `ignore`, holding `table` during the requested slot.
+    fn booked(
+        &self,
+        table: TableId,
+        request: &Request,
+        ignore: Option<ReservationId>,
+    ) -> Option<ReservationId> {
+        self.saved
+            .reservations
+            .iter()
+            .filter(|r| Some(r.id) != ignore && r.table == Some(table))
+            .find(|r| r.overlaps(request.date, request.start, self.policy.slot_length))
+            .map(|r| r.id)
+    }
+
+    // Picks a table for the request, or None for an overbooking place.
+    fn find_table(
+        &self,
+        request: &Request,
+        ignore: Option<ReservationId>,
+    ) -> Result<Option<TableId>, ReservationError> {
+        self.check_request(request)?;
+
+        let mut fitting: Vec<(u32, TableId)> = self
+            .tables
+            .iter()
+            .enumerate()
+            .filter(|(_, &capacity)| capacity >= request.size)
+            .map(|(i, &capacity)| (capacity, TableId(i as u32 + 1)))
+            .collect();
+        fitting.sort();
+        if let Some(&(_, table)) = fitting
+            .iter()
+            .find(|(_, table)| self.booked(*table, request, ignore).is_none())
+        {
+            return Ok(Some(table));
+        }
+
+        let overbooked = self
+            .saved
+            .reservations
+            .iter()
+            .filter(|r| Some(r.id) != ignore && r.table.is_none())
+            .filter(|r| r.overlaps(request.date, request.start, self.policy.slot_length))
+            .count();
+        if overbooked < self.policy.max_overbooked {
+            Ok(None)
+        } else {
+            Err(ReservationError::NoAvailability)
+        }
+    }
+
+    fn get_mut(&mut self, id: ReservationId) -> Result<&mut Reservation, ReservationError> {
+        self.saved
+            .reservations
+            .iter_mut()
+            .find(|r| r.id == id)
+            .ok_or(ReservationError::UnknownReservation(id))
+    }
+
+    // Replaces the book with `saved` once it is safely in the file, so a failed write leaves
+    // the book as it was.
+    fn commit(&mut self, saved: Saved) -> Result<(), ReservationError> {
+        if let Some(path) = &self.path {
+            persist(path, &saved)?;
+        }
+        self.saved = saved;
+        Ok(())
+    }
+}
+
+// Writes to a temporary file first so a crash never leaves half a file behind.
+fn persist(path: &Path, saved: &Saved) -> Result<(), ReservationError> {
+    let json = serde_json::to_string_pretty(saved).map_err(ReservationError::Corrupt)?;
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, json)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date() -> Date {
+        Date::new(2024, 12, 31).unwrap()
+    }
+
+    fn request(name: &str, size: u32, start: Minutes) -> Request {
+        Request::new(name, size, "555-0100", date(), start)
+    }
+
+    #[test]
+    fn books_the_smallest_free_table() {
+        let mut book = ReservationBook::new(&[2, 4, 6], Policy::default());
+
+        let first = book.book(request("A", 3, 60)).unwrap();
+        let second = book.book(request("B", 3, 90)).unwrap();
+        let third = book.book(request("C", 3, 150)).unwrap();
+
+        assert_eq!(book.get(first).unwrap().table, Some(TableId(2)));
+        assert_eq!(book.get(second).unwrap().table, Some(TableId(3)));
+        assert_eq!(book.get(third).unwrap().table, Some(TableId(2)));
+        assert!(matches!(
+            book.book(request("D", 5, 120)),
+            Err(ReservationError::NoAvailability)
+        ));
+    }
+
+    #[test]
+    fn detects_conflicts_and_slot_boundaries() {
+        let mut book = ReservationBook::new(&[4, 4], Policy::default());
+        let first = book.book_table(request("A", 2, 0), TableId(1)).unwrap();
+
+        assert!(matches!(
+            book.book_table(request("B", 2, 45), TableId(1)),
+            Err(ReservationError::Conflict(id)) if id == first
+        ));
+        assert!(book.book_table(request("B", 2, 90), TableId(1)).is_ok());
+        assert!(matches!(
+            book.book(request("C", 2, 10)),
+            Err(ReservationError::NotOnSlot(10))
+        ));
+
+        // Slots at the end of the clock still overlap.
+        let last = book
+            .book_table(request("D", 2, Minutes::MAX - 15), TableId(2))
+            .unwrap();
+        assert!(matches!(
+            book.book_table(request("E", 2, Minutes::MAX), TableId(2)),
+            Err(ReservationError::Conflict(id)) if id == last
+        ));
+    }
+
+    #[test]
+    fn overbooking_is_limited() {
+        let policy = Policy {
+            max_overbooked: 1,
+            ..Policy::default()
+        };
+        let mut book = ReservationBook::new(&[2], policy);
+        book.book(request("A", 2, 0)).unwrap();
+
+        let extra = book.book(request("B", 2, 30)).unwrap();
+
+        assert_eq!(book.get(extra).unwrap().table, None);
+        assert!(book.book(request("C", 2, 60)).is_err());
+    }
+
+    #[test]
+    fn failed_modification_keeps_the_booking() {
+        let mut book = ReservationBook::new(&[2, 4], Policy::default());
+        let small = book.book(request("A", 2, 0)).unwrap();
+        book.book(request("B", 4, 0)).unwrap();
+
+        assert!(book.modify(small, request("A", 4, 30)).is_err());
+        assert_eq!(book.get(small).unwrap().start, 0);
+
+        book.modify(small, request("A", 4, 90)).unwrap();
+        assert_eq!(book.get(small).unwrap().table, Some(TableId(2)));
+        book.cancel(small).unwrap();
+        assert!(book.get(small).is_none());
+    }
+
+    #[test]
+    fn due_reservations_go_ahead_of_walk_ins() {
+        let mut book = ReservationBook::new(&[2], Policy::default());
+        let mut waitlist = Waitlist::new(&[2]);
+        let walk_in = waitlist
+            .add_to_waitlist(Party::new("Walk-in", 2, "1"), 0)
+            .unwrap();
+        let id = book.book(request("Booked", 2, 15)).unwrap();
+        book.book(request("Later", 2, 120)).unwrap();
+
+        let merged = book.merge_into_waitlist(date(), &mut waitlist, 15).unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].0, id);
+        assert_eq!(waitlist.queue(), vec![merged[0].1, walk_in]);
+        assert!(book
+            .merge_into_waitlist(date(), &mut waitlist, 15)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn bookings_survive_reopening_the_file() {
+        let path = std::env::temp_dir().join(format!("reservations-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut book = ReservationBook::open(&path, &[2, 4], Policy::default()).unwrap();
+        let id = book.book(request("A", 4, 60)).unwrap();
+        drop(book);
+
+        let mut book = ReservationBook::open(&path, &[2, 4], Policy::default()).unwrap();
+        assert_eq!(book.get(id).unwrap().name, "A");
+        assert_eq!(
+            book.book(request("B", 4, 180)).unwrap(),
+            ReservationId(id.0 + 1)
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_writes_change_nothing() {
+        // A directory where the file should be, so every write fails.
+        let path = std::env::temp_dir().join(format!("reservations-dir-{}", std::process::id()));
+        let mut book = ReservationBook::open(&path, &[2, 4], Policy::default()).unwrap();
+        fs::create_dir_all(path.with_extension("json.tmp")).unwrap();
+
+        assert!(matches!(
+            book.book(request("A", 2, 0)),
+            Err(ReservationError::Io(_))
+        ));
+        assert!(book.on(date()).is_empty());
+        assert_eq!(book.saved.next_id, 1);
+        fs::remove_dir(path.with_extension("json.tmp")).unwrap();
+    }
+
+    #[test]
+    fn merging_is_all_or_nothing() {
+        let mut book = ReservationBook::new(&[2, 6], Policy::default());
+        // The waitlist's dining room has no table big enough for the second party.
+        let mut waitlist = Waitlist::new(&[2]);
+        let small = book.book(request("Small", 2, 0)).unwrap();
+        book.book(request("Large", 6, 0)).unwrap();
+
+        assert!(book.merge_into_waitlist(date(), &mut waitlist, 0).is_err());
+        assert!(waitlist.queue().is_empty());
+        assert_eq!(book.get(small).unwrap().ticket, None);
+    }
+}
@@ -1,5 +1,362 @@
-pub mod serving {
-    fn take_order() {}
-    fn serve_order() {}
-    fn take_payment() {}
+// Orders from the dining room: what each table asked for and how far along it is.
+//
+// An order moves through the kitchen and back to the table in a fixed sequence:
+// Taken -> Cooking -> Ready -> Served -> Paid
+// Orders the guests send back start again from Taken as a remake.
+
+use std::fmt;
+
+use crate::front_of_house::billing::{
+    self, BillingError, Check, Payment, PaymentProcessor, Receipt,
+};
+use crate::front_of_house::hosting::TableId;
+
+// Prices are kept in cents so they add up exactly.
+pub type Cents = u64;
+
+// A change to a dish, such as "no onions" or "extra cheese".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modifier {
+    pub name: String,
+    pub price: Cents,
+}
+
+impl Modifier {
+    pub fn new(name: &str, price: Cents) -> Modifier {
+        Modifier {
+            name: String::from(name),
+            price,
+        }
+    }
+
+    pub fn free(name: &str) -> Modifier {
+        Modifier::new(name, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderItem {
+    pub name: String,
+    pub price: Cents,
+    pub quantity: u32,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl OrderItem {
+    pub fn new(name: &str, price: Cents) -> OrderItem {
+        OrderItem {
+            name: String::from(name),
+            price,
+            quantity: 1,
+            modifiers: Vec::new(),
+        }
+    }
+
+    pub fn with_quantity(mut self, quantity: u32) -> OrderItem {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn with_modifier(mut self, modifier: Modifier) -> OrderItem {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    // Price of a single unit including its modifiers.
+    pub fn unit_price(&self) -> Cents {
+        self.price + self.modifiers.iter().map(|m| m.price).sum::<Cents>()
+    }
+
+    pub fn total(&self) -> Cents {
+        self.unit_price() * self.quantity as Cents
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Taken,
+    Cooking,
+    Ready,
+    Served,
+    Paid,
+}
+
+impl OrderStatus {
+    // The only status an order can move on to from this one.
+    pub fn next(self) -> Option<OrderStatus> {
+        match self {
+            OrderStatus::Taken => Some(OrderStatus::Cooking),
+            OrderStatus::Cooking => Some(OrderStatus::Ready),
+            OrderStatus::Ready => Some(OrderStatus::Served),
+            OrderStatus::Served => Some(OrderStatus::Paid),
+            OrderStatus::Paid => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OrderId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    EmptyOrder,
+    UnknownOrder(OrderId),
+    InvalidTransition { from: OrderStatus, to: OrderStatus },
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderError::EmptyOrder => write!(f, "an order needs at least one item"),
+            OrderError::UnknownOrder(id) => write!(f, "no order {}", id.0),
+            OrderError::InvalidTransition { from, to } => {
+                write!(f, "an order can't go from {:?} to {:?}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    id: OrderId,
+    table: TableId,
+    items: Vec<OrderItem>,
+    status: OrderStatus,
+    remakes: u32,
+}
+
+impl Order {
+    pub fn id(&self) -> OrderId {
+        self.id
+    }
+
+    pub fn table(&self) -> TableId {
+        self.table
+    }
+
+    pub fn items(&self) -> &[OrderItem] {
+        &self.items
+    }
+
+    pub fn status(&self) -> OrderStatus {
+        self.status
+    }
+
+    // How many times the kitchen had to make this order again.
+    pub fn remakes(&self) -> u32 {
+        self.remakes
+    }
+
+    pub fn total(&self) -> Cents {
+        self.items.iter().map(|i| i.total()).sum()
+    }
+
+    // Moves the order on to `to`, which must directly follow the current status.
+    pub fn advance(&mut self, to: OrderStatus) -> Result<(), OrderError> {
+        if self.status.next() != Some(to) {
+            return Err(OrderError::InvalidTransition {
+                from: self.status,
+                to,
+            });
+        }
+        self.status = to;
+        Ok(())
+    }
+
+    // Puts a finished order back to Taken so the kitchen can make it again.
+    pub fn send_back(&mut self) -> Result<(), OrderError> {
+        match self.status {
+            OrderStatus::Ready | OrderStatus::Served => {
+                self.status = OrderStatus::Taken;
+                self.remakes += 1;
+                Ok(())
+            }
+            from => Err(OrderError::InvalidTransition {
+                from,
+                to: OrderStatus::Taken,
+            }),
+        }
+    }
+}
+
+// Every order taken during a service. Orders are never removed, so ids count up from 1.
+#[derive(Debug, Default)]
+pub struct Orders {
+    orders: Vec<Order>,
+}
+
+impl Orders {
+    pub fn new() -> Orders {
+        Orders { orders: Vec::new() }
+    }
+
+    pub fn get(&self, id: OrderId) -> Option<&Order> {
+        self.orders.iter().find(|o| o.id == id)
+    }
+
+    pub fn for_table(&self, table: TableId) -> impl Iterator<Item = &Order> {
+        self.orders.iter().filter(move |o| o.table == table)
+    }
+
+    pub fn with_status(&self, status: OrderStatus) -> impl Iterator<Item = &Order> {
+        self.orders.iter().filter(move |o| o.status == status)
+    }
+
+    pub fn take_order(
+        &mut self,
+        table: TableId,
+        items: Vec<OrderItem>,
+    ) -> Result<OrderId, OrderError> {
+        if items.is_empty() {
+            return Err(OrderError::EmptyOrder);
+        }
+
+        let id = OrderId(self.orders.len() as u32 + 1);
+        self.orders.push(Order {
+            id,
+            table,
+            items,
+            status: OrderStatus::Taken,
+            remakes: 0,
+        });
+        Ok(id)
+    }
+
+    // Hands a taken order to the kitchen.
+    pub fn send_to_kitchen(&mut self, id: OrderId) -> Result<(), OrderError> {
+        crate::back_of_house::cook_order(self.get_mut(id)?)
+    }
+
+    // Called by the kitchen once the order is plated.
+    pub fn mark_ready(&mut self, id: OrderId) -> Result<(), OrderError> {
+        self.get_mut(id)?.advance(OrderStatus::Ready)
+    }
+
+    pub fn serve_order(&mut self, id: OrderId) -> Result<(), OrderError> {
+        crate::serve_order(self.get_mut(id)?)
+    }
+
+    // The guests didn't get what they asked for: the kitchen remakes the order.
+    pub fn send_back(&mut self, id: OrderId) -> Result<(), OrderError> {
+        crate::back_of_house::fix_incorrect_order(self.get_mut(id)?)
+    }
+
+    // Charges the payments for a check and marks all of its orders paid. Nothing is charged
+    // unless every order on the check is still waiting to be paid.
+    pub fn take_payment(
+        &mut self,
+        check: &Check,
+        payments: &[Payment],
+        processor: &mut dyn PaymentProcessor,
+    ) -> Result<Vec<Receipt>, BillingError> {
+        for id in check.orders() {
+            let order = self.get(id).ok_or(OrderError::UnknownOrder(id))?;
+            if order.status != OrderStatus::Served {
+                return Err(BillingError::NotServed(id));
+            }
+        }
+
+        let receipts = billing::settle(check, payments, processor)?;
+        for id in check.orders() {
+            self.get_mut(id)?.advance(OrderStatus::Paid)?;
+        }
+        Ok(receipts)
+    }
+
+    fn get_mut(&mut self, id: OrderId) -> Result<&mut Order, OrderError> {
+        self.orders
+            .iter_mut()
+            .find(|o| o.id == id)
+            .ok_or(OrderError::UnknownOrder(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_of_house::billing::FakeProcessor;
+
+    fn burger() -> OrderItem {
+        OrderItem::new("Burger", 1250)
+            .with_quantity(2)
+            .with_modifier(Modifier::new("Extra cheese", 150))
+            .with_modifier(Modifier::free("No onions"))
+    }
+
+    #[test]
+    fn order_goes_through_every_status() {
+        let mut orders = Orders::new();
+        let id = orders.take_order(TableId(1), vec![burger()]).unwrap();
+
+        orders.send_to_kitchen(id).unwrap();
+        orders.mark_ready(id).unwrap();
+        orders.serve_order(id).unwrap();
+        let check = Check::new(orders.get(id)).unwrap();
+        let mut processor = FakeProcessor::new();
+        orders
+            .take_payment(&check, &[Payment::cash(2800)], &mut processor)
+            .unwrap();
+
+        let order = orders.get(id).unwrap();
+        assert_eq!(order.status(), OrderStatus::Paid);
+        assert_eq!(order.total(), 2800);
+    }
+
+    #[test]
+    fn rejects_skipping_a_status() {
+        let mut orders = Orders::new();
+        let id = orders.take_order(TableId(1), vec![burger()]).unwrap();
+
+        assert_eq!(
+            orders.serve_order(id),
+            Err(OrderError::InvalidTransition {
+                from: OrderStatus::Taken,
+                to: OrderStatus::Served,
+            })
+        );
+        assert_eq!(
+            orders.take_order(TableId(1), vec![]),
+            Err(OrderError::EmptyOrder)
+        );
+    }
+
+    #[test]
+    fn sent_back_orders_are_remade_and_served_again() {
+        let mut orders = Orders::new();
+        let id = orders.take_order(TableId(2), vec![burger()]).unwrap();
+        orders.send_to_kitchen(id).unwrap();
+        orders.mark_ready(id).unwrap();
+        orders.serve_order(id).unwrap();
+
+        orders.send_back(id).unwrap();
+
+        let order = orders.get(id).unwrap();
+        assert_eq!(order.status(), OrderStatus::Served);
+        assert_eq!(order.remakes(), 1);
+        assert!(orders.send_back(OrderId(99)).is_err());
+    }
+
+    #[test]
+    fn paid_orders_cannot_be_sent_back() {
+        let mut orders = Orders::new();
+        let id = orders.take_order(TableId(3), vec![burger()]).unwrap();
+        for status in [
+            OrderStatus::Cooking,
+            OrderStatus::Ready,
+            OrderStatus::Served,
+            OrderStatus::Paid,
+        ] {
+            orders.get_mut(id).unwrap().advance(status).unwrap();
+        }
+
+        assert_eq!(
+            orders.send_back(id),
+            Err(OrderError::InvalidTransition {
+                from: OrderStatus::Paid,
+                to: OrderStatus::Taken,
+            })
+        );
+    }
 }
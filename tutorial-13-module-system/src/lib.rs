@@ -1,4 +1,3 @@
-use rand::{CryptoRng, Rng};
 use std::io::{self, Write};
 
 // By default, all items (functions, methods, structs, enums, modules, and constants) are private
@@ -11,32 +10,54 @@ use std::io::{self, Write};
 // if a module is defined in a directory, the directory should contain a file named `mod.rs`.
 mod front_of_house;
 
+pub mod calendar;
+pub mod menu;
+pub mod simulation;
+
 pub fn eat_at_restaurant() {
     // Absolute path
-    crate::front_of_house::hosting::add_to_waitlist();
+    let mut waitlist = crate::front_of_house::hosting::Waitlist::new(&[2, 4, 4, 6]);
 
     // Relative path
     // start from the current module
-    front_of_house::hosting::add_to_waitlist();
+    let party = front_of_house::hosting::Party::new("Lovelace", 4, "555-0100");
+
+    let ticket = waitlist.add_to_waitlist(party, 0).unwrap();
+    if let Some(call) = waitlist.call_next(0) {
+        println!("{}, your table is ready", call.name);
+    }
+    waitlist.seat_at_table(ticket, 2).unwrap();
 }
 
 // Example #2
-fn serve_order() {}
+fn serve_order(order: &mut serving::Order) -> Result<(), serving::OrderError> {
+    order.advance(serving::OrderStatus::Served)
+}
 
 mod back_of_house {
-    fn fix_incorrect_order() {
+    use crate::front_of_house::serving::{Order, OrderError, OrderStatus};
+
+    // Remakes an order the guests sent back and takes it out to them again.
+    pub fn fix_incorrect_order(order: &mut Order) -> Result<(), OrderError> {
+        order.send_back()?;
         // access the sibling function within the same module
-        cook_order();
+        cook_order(order)?;
+        order.advance(OrderStatus::Ready)?;
         // super keyword is used to access the parent module
-        super::serve_order();
+        super::serve_order(order)
     }
 
-    fn cook_order() {}
+    pub fn cook_order(order: &mut Order) -> Result<(), OrderError> {
+        order.advance(OrderStatus::Cooking)
+    }
 }
 
 // Example #3
 
 mod back_of_house2 {
+    use crate::calendar::{Date, Season};
+    use crate::menu::{Inventory, Menu, MenuError};
+
     pub struct Breakfast {
         // By default, struct fields are private
         pub toast: String,
@@ -44,34 +65,128 @@ mod back_of_house2 {
     }
 
     impl Breakfast {
-        pub fn summer(toast: &str) -> Breakfast {
-            Breakfast {
+        // The fruit is the menu's favourite for the summer.
+        pub fn summer(toast: &str, menu: &Menu) -> Result<Breakfast, MenuError> {
+            let fruit = menu
+                .fruit_of(Season::Summer)
+                .ok_or_else(|| MenuError::OutOfSeason(String::from("seasonal fruit")))?;
+            Ok(Breakfast {
                 toast: String::from(toast),
-                seasonal_fruit: String::from("peaches"),
-            }
+                seasonal_fruit: String::from(fruit),
+            })
+        }
+
+        // The kitchen picks the fruit: whatever the menu has in season and in stock on `date`.
+        // One portion of it is taken from the inventory.
+        pub fn on(
+            toast: &str,
+            date: Date,
+            menu: &Menu,
+            inventory: &mut Inventory,
+        ) -> Result<Breakfast, MenuError> {
+            let fruit = menu
+                .seasonal_fruit(date, inventory)
+                .ok_or_else(|| MenuError::OutOfStock(String::from("seasonal fruit")))?;
+            let seasonal_fruit = String::from(fruit);
+            inventory.take(&[(seasonal_fruit.clone(), 1)].into())?;
+
+            Ok(Breakfast {
+                toast: String::from(toast),
+                seasonal_fruit,
+            })
+        }
+
+        // Customers can see which fruit they got, but can't choose it
+        pub fn seasonal_fruit(&self) -> &str {
+            &self.seasonal_fruit
         }
     }
 }
 
-pub fn eat_at_restaurant2() {
+pub fn eat_at_restaurant2(menu: &menu::Menu) -> Result<(), menu::MenuError> {
     // Order a breakfast in the summer with Rye toast
-    let mut meal = back_of_house2::Breakfast::summer("Rye");
+    let mut meal = back_of_house2::Breakfast::summer("Rye", menu)?;
     // Change our mind about what bread we'd like
     meal.toast = String::from("Wheat");
     println!("I'd like {} toast please", meal.toast);
 
     // The next line won't compile if uncommented because seasonal_fruit is private
     // meal.seasonal_fruit = String::from("blueberries");
+    println!("It comes with {}", meal.seasonal_fruit());
+    Ok(())
+}
+
+pub fn eat_seasonal_breakfast(
+    date: calendar::Date,
+    menu: &menu::Menu,
+    inventory: &mut menu::Inventory,
+) -> Result<(), menu::MenuError> {
+    // The fruit depends on the day we come in and what the kitchen has left
+    let meal = back_of_house2::Breakfast::on("Rye", date, menu, inventory)?;
+    println!(
+        "I'd like {} toast please, it comes with {}",
+        meal.toast,
+        meal.seasonal_fruit()
+    );
+    Ok(())
 }
 
 // Example #4
 // use keyword
 
 // self refers to the current module
+pub use crate::front_of_house::billing;
 pub use crate::front_of_house::hosting;
+pub use crate::front_of_house::reservations;
+pub use crate::front_of_house::serving;
+
+pub fn eat_at_restaurant3() -> Result<(), hosting::HostingError> {
+    let mut waitlist = hosting::Waitlist::new(&[2, 4]);
+    let first = hosting::Party::new("Hopper", 2, "555-0101");
+    let second = hosting::Party::new("Turing", 4, "555-0102");
+    let third = hosting::Party::new("Liskov", 2, "555-0103").with_priority(hosting::Priority::Vip);
+
+    waitlist.add_to_waitlist(first, 0)?;
+    waitlist.add_to_waitlist(second, 1)?;
+    waitlist.add_to_waitlist(third, 2)?;
+
+    // The VIP party is called first even though they arrived last
+    if let Some(call) = waitlist.call_next(3) {
+        println!("{}, your table is ready", call.name);
+    }
+    println!(
+        "Estimated wait: {:?} minutes",
+        waitlist.estimated_wait(waitlist.queue()[1], 3)
+    );
+    Ok(())
+}
 
-pub fn eat_at_restaurant3() {
-    hosting::add_to_waitlist();
-    hosting::add_to_waitlist();
-    hosting::add_to_waitlist();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_the_vip_party_first() {
+        let mut waitlist = hosting::Waitlist::new(&[2, 4]);
+        for (name, size) in [("Hopper", 2), ("Turing", 4)] {
+            waitlist
+                .add_to_waitlist(hosting::Party::new(name, size, ""), 0)
+                .unwrap();
+        }
+        let vip = hosting::Party::new("Liskov", 2, "").with_priority(hosting::Priority::Vip);
+        let vip = waitlist.add_to_waitlist(vip, 2).unwrap();
+        assert_eq!(waitlist.call_next(3).unwrap().ticket, vip);
+        assert_eq!(eat_at_restaurant3(), Ok(()));
+    }
+
+    #[test]
+    fn breakfast_fruit_comes_from_the_menu() {
+        let menu = menu::Menu::from_json(include_str!("../menu.json")).unwrap();
+        let meal = back_of_house2::Breakfast::summer("Rye", &menu).unwrap();
+        assert_eq!(meal.seasonal_fruit(), "strawberries");
+        assert!(eat_at_restaurant2(&menu).is_ok());
+
+        let no_fruit = menu::Menu::from_json(r#"{"ingredients": [], "dishes": []}"#).unwrap();
+        assert!(back_of_house2::Breakfast::summer("Rye", &no_fruit).is_err());
+    }
 }
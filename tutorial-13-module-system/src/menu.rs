@@ -0,0 +1,379 @@
+// The menu: which dishes the kitchen can make, what goes into them and what they cost.
+//
+// A menu is loaded from a JSON data file (see `menu.json`). Dishes are only available in their
+// seasons and while the inventory holds enough of every ingredient for another serving.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::calendar::{Date, Season};
+use crate::front_of_house::serving::{Cents, OrderItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Allergen {
+    Gluten,
+    Dairy,
+    Egg,
+    Peanuts,
+    TreeNuts,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Ingredient {
+    name: String,
+    #[serde(default)]
+    allergens: Vec<Allergen>,
+    // Seasons the ingredient can be bought fresh in. Empty means all year round.
+    #[serde(default)]
+    seasons: Vec<Season>,
+}
+
+impl Ingredient {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn allergens(&self) -> &[Allergen] {
+        &self.allergens
+    }
+
+    pub fn in_season(&self, season: Season) -> bool {
+        self.seasons.is_empty() || self.seasons.contains(&season)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Dish {
+    name: String,
+    price: Cents,
+    // Portions of each ingredient used by one serving.
+    ingredients: HashMap<String, u32>,
+    // Seasons the dish is on the menu. Empty means all year round.
+    #[serde(default)]
+    seasons: Vec<Season>,
+    // Replaces the regular price in the given seasons.
+    #[serde(default)]
+    seasonal_prices: HashMap<Season, Cents>,
+}
+
+impl Dish {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ingredients(&self) -> &HashMap<String, u32> {
+        &self.ingredients
+    }
+
+    pub fn in_season(&self, season: Season) -> bool {
+        self.seasons.is_empty() || self.seasons.contains(&season)
+    }
+
+    pub fn price_on(&self, date: Date) -> Cents {
+        *self
+            .seasonal_prices
+            .get(&date.season())
+            .unwrap_or(&self.price)
+    }
+}
+
+#[derive(Debug)]
+pub enum MenuError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    UnknownIngredient { dish: String, ingredient: String },
+    UnknownDish(String),
+    OutOfSeason(String),
+    OutOfStock(String),
+}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MenuError::Io(e) => write!(f, "couldn't read the menu: {}", e),
+            MenuError::Parse(e) => write!(f, "couldn't parse the menu: {}", e),
+            MenuError::UnknownIngredient { dish, ingredient } => {
+                write!(
+                    f,
+                    "{} uses {}, which is not an ingredient",
+                    dish, ingredient
+                )
+            }
+            MenuError::UnknownDish(name) => write!(f, "{} is not on the menu", name),
+            MenuError::OutOfSeason(name) => write!(f, "{} is out of season", name),
+            MenuError::OutOfStock(name) => write!(f, "we've run out of {}", name),
+        }
+    }
+}
+
+impl std::error::Error for MenuError {}
+
+// The kitchen's stock, counted in portions per ingredient.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    stock: HashMap<String, u32>,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory {
+            stock: HashMap::new(),
+        }
+    }
+
+    // Stock stops counting at u32::MAX portions, far more than any kitchen holds.
+    pub fn restock(&mut self, ingredient: &str, portions: u32) {
+        let stock = self.stock.entry(String::from(ingredient)).or_insert(0);
+        *stock = stock.saturating_add(portions);
+    }
+
+    pub fn stock(&self, ingredient: &str) -> u32 {
+        self.stock.get(ingredient).copied().unwrap_or(0)
+    }
+
+    pub fn has(&self, portions: &HashMap<String, u32>) -> bool {
+        portions
+            .iter()
+            .all(|(name, &needed)| self.stock(name) >= needed)
+    }
+
+    // Takes every portion or, if anything is short, nothing at all.
+    pub fn take(&mut self, portions: &HashMap<String, u32>) -> Result<(), MenuError> {
+        if let Some((name, _)) = portions
+            .iter()
+            .find(|(name, &needed)| self.stock(name) < needed)
+        {
+            return Err(MenuError::OutOfStock(name.clone()));
+        }
+        for (name, &needed) in portions {
+            if let Some(stock) = self.stock.get_mut(name) {
+                *stock -= needed;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Menu {
+    ingredients: Vec<Ingredient>,
+    dishes: Vec<Dish>,
+    // Fruits served with breakfast, most preferred first.
+    #[serde(default)]
+    seasonal_fruits: Vec<String>,
+}
+
+impl Menu {
+    pub fn from_json(json: &str) -> Result<Menu, MenuError> {
+        let menu: Menu = serde_json::from_str(json).map_err(MenuError::Parse)?;
+
+        let known = |name: &String| menu.ingredients.iter().any(|i| &i.name == name);
+        for dish in &menu.dishes {
+            if let Some(name) = dish.ingredients.keys().find(|name| !known(name)) {
+                return Err(MenuError::UnknownIngredient {
+                    dish: dish.name.clone(),
+                    ingredient: name.clone(),
+                });
+            }
+        }
+        if let Some(name) = menu.seasonal_fruits.iter().find(|name| !known(name)) {
+            return Err(MenuError::UnknownIngredient {
+                dish: String::from("seasonal fruit"),
+                ingredient: name.clone(),
+            });
+        }
+        Ok(menu)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Menu, MenuError> {
+        Menu::from_json(&fs::read_to_string(path).map_err(MenuError::Io)?)
+    }
+
+    pub fn dishes(&self) -> &[Dish] {
+        &self.dishes
+    }
+
+    pub fn dish(&self, name: &str) -> Option<&Dish> {
+        self.dishes.iter().find(|d| d.name == name)
+    }
+
+    pub fn ingredient(&self, name: &str) -> Option<&Ingredient> {
+        self.ingredients.iter().find(|i| i.name == name)
+    }
+
+    // Every allergen in any ingredient of the dish, sorted.
+    pub fn allergens(&self, dish: &Dish) -> Vec<Allergen> {
+        let mut allergens: Vec<Allergen> = dish
+            .ingredients
+            .keys()
+            .filter_map(|name| self.ingredient(name))
+            .flat_map(|i| i.allergens.iter().copied())
+            .collect();
+        allergens.sort();
+        allergens.dedup();
+        allergens
+    }
+
+    pub fn is_available(&self, dish: &Dish, date: Date, inventory: &Inventory) -> bool {
+        dish.in_season(date.season()) && inventory.has(&dish.ingredients)
+    }
+
+    // Dishes that can be served on `date` and contain none of `avoid`.
+    pub fn available<'a>(
+        &'a self,
+        date: Date,
+        inventory: &'a Inventory,
+        avoid: &'a [Allergen],
+    ) -> impl Iterator<Item = &'a Dish> {
+        self.dishes.iter().filter(move |d| {
+            self.is_available(d, date, inventory)
+                && !self.allergens(d).iter().any(|a| avoid.contains(a))
+        })
+    }
+
+    // The most preferred fruit of `season`, whether or not there's any in stock.
+    pub fn fruit_of(&self, season: Season) -> Option<&str> {
+        self.seasonal_fruits
+            .iter()
+            .filter_map(|name| self.ingredient(name))
+            .find(|i| i.in_season(season))
+            .map(|i| i.name.as_str())
+    }
+
+    // The most preferred fruit that is in season and still in stock.
+    pub fn seasonal_fruit(&self, date: Date, inventory: &Inventory) -> Option<&str> {
+        self.seasonal_fruits
+            .iter()
+            .filter_map(|name| self.ingredient(name))
+            .find(|i| i.in_season(date.season()) && inventory.stock(&i.name) > 0)
+            .map(|i| i.name.as_str())
+    }
+
+    // Uses up the ingredients for one serving and returns it as an item to put on an order.
+    pub fn prepare(
+        &self,
+        name: &str,
+        date: Date,
+        inventory: &mut Inventory,
+    ) -> Result<OrderItem, MenuError> {
+        let dish = self
+            .dish(name)
+            .ok_or_else(|| MenuError::UnknownDish(String::from(name)))?;
+        if !dish.in_season(date.season()) {
+            return Err(MenuError::OutOfSeason(dish.name.clone()));
+        }
+        inventory.take(&dish.ingredients)?;
+        Ok(OrderItem::new(&dish.name, dish.price_on(date)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu() -> Menu {
+        Menu::from_json(include_str!("../menu.json")).unwrap()
+    }
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn seasonal_fruit_follows_the_calendar_and_stock() {
+        let menu = menu();
+        let mut inventory = Inventory::new();
+        inventory.restock("peaches", 1);
+        inventory.restock("strawberries", 1);
+        inventory.restock("apples", 1);
+
+        assert_eq!(
+            menu.seasonal_fruit(date("2024-07-01"), &inventory),
+            Some("strawberries")
+        );
+        assert_eq!(
+            menu.seasonal_fruit(date("2024-10-01"), &inventory),
+            Some("apples")
+        );
+
+        inventory
+            .take(&HashMap::from([(String::from("strawberries"), 1)]))
+            .unwrap();
+        assert_eq!(
+            menu.seasonal_fruit(date("2024-07-01"), &inventory),
+            Some("peaches")
+        );
+        assert_eq!(menu.fruit_of(Season::Summer), Some("strawberries"));
+        assert_eq!(menu.fruit_of(Season::Winter), Some("apples"));
+
+        inventory.restock("apples", u32::MAX);
+        assert_eq!(inventory.stock("apples"), u32::MAX);
+    }
+
+    #[test]
+    fn preparing_depletes_inventory() {
+        let menu = menu();
+        let mut inventory = Inventory::new();
+        inventory.restock("rye bread", 2);
+        inventory.restock("butter", 1);
+
+        let item = menu
+            .prepare("Rye toast", date("2024-01-10"), &mut inventory)
+            .unwrap();
+
+        assert_eq!(item.price, 450);
+        assert_eq!(inventory.stock("rye bread"), 0);
+        assert!(matches!(
+            menu.prepare("Rye toast", date("2024-01-10"), &mut inventory),
+            Err(MenuError::OutOfStock(_))
+        ));
+        assert_eq!(inventory.stock("butter"), 0);
+    }
+
+    #[test]
+    fn filters_by_season_and_allergen() {
+        let menu = menu();
+        let mut inventory = Inventory::new();
+        for i in &menu.ingredients {
+            inventory.restock(&i.name, 10);
+        }
+
+        let winter: Vec<&str> = menu
+            .available(date("2024-01-10"), &inventory, &[Allergen::Gluten])
+            .map(|d| d.name())
+            .collect();
+
+        assert!(winter.contains(&"Porridge"));
+        assert!(!winter.contains(&"Rye toast"));
+        assert!(!winter.contains(&"Peach melba"));
+    }
+
+    #[test]
+    fn seasonal_prices_override_the_regular_price() {
+        let dish = menu().dish("Porridge").cloned().unwrap();
+
+        assert_eq!(dish.price_on(date("2024-01-10")), 650);
+        assert_eq!(dish.price_on(date("2024-05-10")), 600);
+    }
+
+    #[test]
+    fn rejects_dishes_with_unknown_ingredients() {
+        let json = r#"{"ingredients": [], "dishes": [
+            {"name": "Soup", "price": 500, "ingredients": {"stone": 1}}
+        ]}"#;
+
+        assert!(matches!(
+            Menu::from_json(json),
+            Err(MenuError::UnknownIngredient { .. })
+        ));
+    }
+}
@@ -0,0 +1,506 @@
+// A discrete-event simulation of one service.
+//
+// Parties arrive at random, wait for a table through the host stand, order from the menu, wait
+// for the kitchen, eat and pay. Everything random comes from the `Rng` passed in, so a seeded
+// generator replays the same evening, and only the staffing or layout changes between runs.
+//
+// Each party's luck is drawn up front, as a plan with its own generator for what it orders,
+// how long its food and its meal take and what it tips. A party's evening then doesn't depend
+// on the order things happen in, so comparing staffing levels compares the staffing alone.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::error;
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::billing::{Check, FakeProcessor, Payment, Rate, Tip};
+use crate::calendar::Date;
+use crate::hosting::{Minutes, Party, TableId, TableState, Ticket, Waitlist};
+use crate::menu::{Inventory, Menu};
+use crate::serving::{Cents, OrderId, Orders};
+
+// How long it takes a called party to walk up to the host stand.
+const WALK_TO_TABLE: Minutes = 1;
+
+// One party a minute on average. Every arrival is planned before the evening starts, so the
+// rate bounds how many plans a service can need.
+pub const MAX_ARRIVALS_PER_HOUR: f64 = 60.0;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    // One entry per table, giving the number of seats.
+    pub tables: Vec<u32>,
+    // Orders the kitchen can cook at the same time.
+    pub cooks: u32,
+    // Parties stop arriving after this many minutes.
+    pub service_length: Minutes,
+    pub arrivals_per_hour: f64,
+    pub max_party_size: u32,
+    pub mean_dining_time: Minutes,
+    pub mean_cooking_time: Minutes,
+    // How long a party waits for a table before leaving.
+    pub patience: Minutes,
+    // Chance, from 0 to 1, that a called party never shows up.
+    pub no_show_rate: f64,
+    pub tax: Rate,
+    pub date: Date,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tables: vec![2, 2, 2, 4, 4, 4, 6, 8],
+            cooks: 3,
+            service_length: 4 * 60,
+            arrivals_per_hour: 12.0,
+            max_party_size: 6,
+            mean_dining_time: 50,
+            mean_cooking_time: 15,
+            patience: 40,
+            no_show_rate: 0.05,
+            tax: Rate(875),
+            date: Date::new(2024, 7, 1).expect("valid date"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    // A setting that would make the simulation hang or make no sense.
+    InvalidConfig(String),
+    // No dish is in season on the simulated date.
+    NothingOnTheMenu(Date),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::InvalidConfig(why) => write!(f, "invalid configuration: {}", why),
+            SimulationError::NothingOnTheMenu(date) => {
+                write!(f, "nothing on the menu on {}", date)
+            }
+        }
+    }
+}
+
+impl error::Error for SimulationError {}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        let invalid = |why: &str| Err(SimulationError::InvalidConfig(String::from(why)));
+        if !(self.arrivals_per_hour.is_finite() && self.arrivals_per_hour > 0.0) {
+            return invalid("arrivals per hour must be a positive number");
+        }
+        if self.arrivals_per_hour > MAX_ARRIVALS_PER_HOUR {
+            return invalid("at most 60 parties can arrive per hour");
+        }
+        if self.cooks == 0 {
+            return invalid("the kitchen needs at least one cook");
+        }
+        if !(0.0..=1.0).contains(&self.no_show_rate) {
+            return invalid("the no-show rate must be between 0 and 1");
+        }
+        if self.max_party_size == 0 {
+            return invalid("parties need at least one guest");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub arrived: u32,
+    pub seated: u32,
+    pub walked_away: u32,
+    pub no_shows: u32,
+    // Minutes from arrival to being seated, for the parties that got a table.
+    pub average_wait: f64,
+    pub longest_wait: Minutes,
+    // Share of table-minutes that had guests, from opening until the last party left.
+    pub utilization: f64,
+    pub revenue: Cents,
+    pub closing_time: Minutes,
+}
+
+// What happens to one party, drawn before the evening starts.
+struct Plan {
+    size: u32,
+    no_show: bool,
+    rng: StdRng,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    // The party with this plan walks in.
+    Arrival(usize),
+    CheckIn(Ticket),
+    GiveUp(Ticket),
+    ReleaseNoShows,
+    OrderReady(OrderId),
+    MealFinished(TableId, OrderId),
+}
+
+struct Simulation<'a, R: Rng> {
+    config: &'a Config,
+    menu: &'a Menu,
+    rng: &'a mut R,
+    plans: Vec<Plan>,
+    // Which plan each ticket and order belongs to.
+    plan_of_ticket: HashMap<Ticket, usize>,
+    plan_of_order: HashMap<OrderId, usize>,
+    // What can be ordered; the same all evening, since nothing runs out.
+    dishes: Vec<String>,
+    // Min-heap on (time, sequence number) so events at the same minute keep their order.
+    events: BinaryHeap<Reverse<(Minutes, u64, Event)>>,
+    sequence: u64,
+    waitlist: Waitlist,
+    orders: Orders,
+    inventory: Inventory,
+    processor: FakeProcessor,
+    arrived_at: HashMap<Ticket, Minutes>,
+    called: HashSet<Ticket>,
+    kitchen_queue: VecDeque<OrderId>,
+    cooking: u32,
+    occupied_minutes: u64,
+    waits: Vec<Minutes>,
+    report: Report,
+}
+
+// Runs one service with the given configuration and menu.
+pub fn run<R: Rng>(config: &Config, menu: &Menu, rng: &mut R) -> Result<Report, SimulationError> {
+    config.validate()?;
+    // The simulation is about staffing and tables, so the kitchen never runs out of anything.
+    let mut inventory = Inventory::new();
+    for dish in menu.dishes() {
+        for name in dish.ingredients().keys() {
+            if inventory.stock(name) == 0 {
+                inventory.restock(name, 1_000_000);
+            }
+        }
+    }
+
+    let dishes: Vec<String> = menu
+        .available(config.date, &inventory, &[])
+        .map(|d| String::from(d.name()))
+        .collect();
+    if dishes.is_empty() {
+        return Err(SimulationError::NothingOnTheMenu(config.date));
+    }
+
+    let simulation = Simulation {
+        config,
+        menu,
+        rng,
+        plans: Vec::new(),
+        plan_of_ticket: HashMap::new(),
+        plan_of_order: HashMap::new(),
+        dishes,
+        events: BinaryHeap::new(),
+        sequence: 0,
+        waitlist: Waitlist::new(&config.tables),
+        orders: Orders::new(),
+        inventory,
+        processor: FakeProcessor::new(),
+        arrived_at: HashMap::new(),
+        called: HashSet::new(),
+        kitchen_queue: VecDeque::new(),
+        cooking: 0,
+        occupied_minutes: 0,
+        waits: Vec::new(),
+        report: Report::default(),
+    };
+    Ok(simulation.run())
+}
+
+impl<R: Rng> Simulation<'_, R> {
+    fn run(mut self) -> Report {
+        // All arrivals are drawn before anything else, so runs with the same seed see the
+        // same parties at the door whatever the staffing.
+        let mut at = self.next_arrival(0);
+        while at < self.config.service_length {
+            let plan = Plan {
+                size: self.rng.gen_range(1..=self.config.max_party_size),
+                no_show: self.rng.gen_bool(self.config.no_show_rate),
+                rng: StdRng::seed_from_u64(self.rng.gen()),
+            };
+            self.schedule(at, Event::Arrival(self.plans.len()));
+            self.plans.push(plan);
+            at = self.next_arrival(at);
+        }
+
+        let mut now = 0;
+        while let Some(Reverse((time, _, event))) = self.events.pop() {
+            now = time;
+            match event {
+                Event::Arrival(plan) => self.arrival(plan, now),
+                Event::CheckIn(ticket) => self.check_in(ticket, now),
+                Event::GiveUp(ticket) => self.give_up(ticket),
+                Event::ReleaseNoShows => {
+                    let released = self.waitlist.release_no_shows(now);
+                    self.report.no_shows += released.len() as u32;
+                }
+                Event::OrderReady(order) => self.order_ready(order, now),
+                Event::MealFinished(table, order) => self.meal_finished(table, order, now),
+            }
+            self.call_parties(now);
+        }
+
+        let waits = &self.waits;
+        self.report.seated = waits.len() as u32;
+        self.report.longest_wait = waits.iter().copied().max().unwrap_or(0);
+        if !waits.is_empty() {
+            self.report.average_wait =
+                waits.iter().map(|&w| w as f64).sum::<f64>() / waits.len() as f64;
+        }
+        let capacity = self.config.tables.len() as u64 * now as u64;
+        if capacity > 0 {
+            self.report.utilization = self.occupied_minutes as f64 / capacity as f64;
+        }
+        self.report.closing_time = now;
+        self.report
+    }
+
+    fn schedule(&mut self, at: Minutes, event: Event) {
+        self.sequence += 1;
+        self.events.push(Reverse((at, self.sequence, event)));
+    }
+
+    // Arrivals follow a Poisson process, so the gaps between them are exponential. A very low
+    // rate gives gaps past the end of time, which saturate there.
+    fn next_arrival(&mut self, now: Minutes) -> Minutes {
+        let per_minute = self.config.arrivals_per_hour / 60.0;
+        let uniform: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        now.saturating_add((-uniform.ln() / per_minute).round() as Minutes)
+    }
+
+    fn arrival(&mut self, plan: usize, now: Minutes) {
+        self.report.arrived += 1;
+        let name = format!("Party {}", self.report.arrived);
+        let size = self.plans[plan].size;
+        // Parties too big for every table are turned away at the door.
+        if let Ok(ticket) = self
+            .waitlist
+            .add_to_waitlist(Party::new(&name, size, ""), now)
+        {
+            self.plan_of_ticket.insert(ticket, plan);
+            self.arrived_at.insert(ticket, now);
+            self.schedule(
+                now.saturating_add(self.config.patience),
+                Event::GiveUp(ticket),
+            );
+        } else {
+            self.report.walked_away += 1;
+        }
+    }
+
+    fn call_parties(&mut self, now: Minutes) {
+        while let Some(call) = self.waitlist.call_next(now) {
+            self.called.insert(call.ticket);
+            if self.plans[self.plan_of_ticket[&call.ticket]].no_show {
+                let grace = self.waitlist.no_show_grace();
+                self.schedule(now.saturating_add(grace), Event::ReleaseNoShows);
+            } else {
+                self.schedule(
+                    now.saturating_add(WALK_TO_TABLE),
+                    Event::CheckIn(call.ticket),
+                );
+            }
+        }
+    }
+
+    fn give_up(&mut self, ticket: Ticket) {
+        if !self.called.contains(&ticket) && self.waitlist.remove(ticket).is_ok() {
+            self.report.walked_away += 1;
+        }
+    }
+
+    fn check_in(&mut self, ticket: Ticket, now: Minutes) {
+        let table = match self.waitlist.seat_at_table(ticket, now) {
+            Ok(table) => table,
+            Err(_) => return,
+        };
+        self.waits.push(now - self.arrived_at[&ticket]);
+
+        let plan = self.plan_of_ticket[&ticket];
+        let size = self.plans[plan].size;
+        let items = (0..size)
+            .filter_map(|_| {
+                let rng = &mut self.plans[plan].rng;
+                let dish = &self.dishes[rng.gen_range(0..self.dishes.len())];
+                self.menu
+                    .prepare(dish, self.config.date, &mut self.inventory)
+                    .ok()
+            })
+            .collect();
+        let order = self
+            .orders
+            .take_order(table, items)
+            .expect("the menu always has something in stock");
+        self.plan_of_order.insert(order, plan);
+
+        if self.cooking < self.config.cooks {
+            self.start_cooking(order, now);
+        } else {
+            self.kitchen_queue.push_back(order);
+        }
+    }
+
+    fn start_cooking(&mut self, order: OrderId, now: Minutes) {
+        self.orders
+            .send_to_kitchen(order)
+            .expect("new orders go straight to the kitchen");
+        self.cooking += 1;
+        let rng = &mut self.plans[self.plan_of_order[&order]].rng;
+        let ready = now.saturating_add(duration_around(rng, self.config.mean_cooking_time));
+        self.schedule(ready, Event::OrderReady(order));
+    }
+
+    fn order_ready(&mut self, order: OrderId, now: Minutes) {
+        self.cooking -= 1;
+        if let Some(next) = self.kitchen_queue.pop_front() {
+            self.start_cooking(next, now);
+        }
+
+        self.orders.mark_ready(order).expect("order was cooking");
+        self.orders.serve_order(order).expect("order was ready");
+        let table = self
+            .orders
+            .get(order)
+            .map(|o| o.table())
+            .expect("order exists");
+        let rng = &mut self.plans[self.plan_of_order[&order]].rng;
+        let finished = now.saturating_add(duration_around(rng, self.config.mean_dining_time));
+        self.schedule(finished, Event::MealFinished(table, order));
+    }
+
+    fn meal_finished(&mut self, table: TableId, order: OrderId, now: Minutes) {
+        let rng = &mut self.plans[self.plan_of_order[&order]].rng;
+        let tip = Rate::percent(rng.gen_range(10..=25));
+        let check = Check::new(self.orders.get(order))
+            .expect("order was served")
+            .with_tax(self.config.tax)
+            .with_tip(Tip::Percent(tip));
+        let total = check.total();
+        self.orders
+            .take_payment(&check, &[Payment::card("sim", total)], &mut self.processor)
+            .expect("the fake processor accepts every card");
+        self.report.revenue += total;
+
+        let seated_at = self.waitlist.tables().iter().find_map(|t| match t.state {
+            TableState::Occupied { since, .. } if t.id == table => Some(since),
+            _ => None,
+        });
+        if let (Some(since), Ok(_)) = (seated_at, self.waitlist.clear_table(table, now)) {
+            self.occupied_minutes += (now - since) as u64;
+        }
+    }
+}
+
+// A random duration between two thirds and four thirds of `mean`, at least a minute.
+fn duration_around(rng: &mut StdRng, mean: Minutes) -> Minutes {
+    let low = (mean * 2 / 3).max(1);
+    let high = (mean * 4 / 3).max(low);
+    rng.gen_range(low..=high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn menu() -> Menu {
+        Menu::from_json(include_str!("../menu.json")).unwrap()
+    }
+
+    fn evening(cooks: u32, seed: u64) -> Report {
+        let config = Config {
+            cooks,
+            ..Config::default()
+        };
+        run(&config, &menu(), &mut StdRng::seed_from_u64(seed)).unwrap()
+    }
+
+    #[test]
+    fn same_seed_replays_the_same_evening() {
+        assert_eq!(evening(3, 42), evening(3, 42));
+    }
+
+    #[test]
+    fn every_party_is_accounted_for() {
+        for cooks in 1..=4 {
+            let report = evening(cooks, 7);
+
+            assert_eq!(evening(1, 7).arrived, report.arrived);
+            assert_eq!(
+                report.arrived,
+                report.seated + report.walked_away + report.no_shows
+            );
+            assert!(report.revenue > 0);
+            assert!(report.utilization > 0.0 && report.utilization <= 1.0);
+        }
+    }
+
+    #[test]
+    fn staffing_changes_only_the_waiting() {
+        // Enough tables that everyone is seated at once, however slow the kitchen is.
+        let config = Config {
+            tables: vec![8; 40],
+            patience: 60,
+            no_show_rate: 0.0,
+            ..Config::default()
+        };
+        let evening = |cooks| {
+            let config = Config {
+                cooks,
+                ..config.clone()
+            };
+            run(&config, &menu(), &mut StdRng::seed_from_u64(3)).unwrap()
+        };
+        let (slow, fast) = (evening(1), evening(4));
+
+        assert_eq!(slow.seated, slow.arrived);
+        // Every party orders and tips the same, so only the timing differs.
+        assert_eq!(slow.revenue, fast.revenue);
+        assert!(slow.closing_time > fast.closing_time);
+    }
+
+    #[test]
+    fn rejects_settings_that_cannot_run() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for rate in [-5.0, 0.0, f64::NAN, f64::INFINITY, 1e12] {
+            let config = Config {
+                arrivals_per_hour: rate,
+                ..Config::default()
+            };
+            assert!(matches!(
+                run(&config, &menu(), &mut rng),
+                Err(SimulationError::InvalidConfig(_))
+            ));
+        }
+        let no_cooks = Config {
+            cooks: 0,
+            ..Config::default()
+        };
+        assert_eq!(
+            run(&no_cooks, &menu(), &mut rng),
+            Err(SimulationError::InvalidConfig(String::from(
+                "the kitchen needs at least one cook"
+            )))
+        );
+        // So rare nobody comes, rather than overflowing the clock.
+        let rare = Config {
+            arrivals_per_hour: 1e-300,
+            ..Config::default()
+        };
+        assert_eq!(run(&rare, &menu(), &mut rng).unwrap().arrived, 0);
+        let empty = Menu::from_json(r#"{"ingredients": [], "dishes": []}"#).unwrap();
+        assert_eq!(
+            run(&Config::default(), &empty, &mut rng),
+            Err(SimulationError::NothingOnTheMenu(Config::default().date))
+        );
+    }
+}
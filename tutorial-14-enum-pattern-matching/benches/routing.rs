@@ -0,0 +1,95 @@
+// Routing table benchmarks at a million routes, roughly the size of a full Internet table.
+//
+// cargo bench --bench routing
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use tutorial_14_enum_pattern_matching::ip::{Cidr, Family, IpAddrKind};
+use tutorial_14_enum_pattern_matching::random::Random;
+use tutorial_14_enum_pattern_matching::routing::RoutingTable;
+
+const ROUTES: usize = 1_000_000;
+
+// Prefix lengths weighted like a real table: mostly /24 for IPv4 and /48 for IPv6, with a
+// spread of shorter aggregates.
+fn routes(family: Family, random: &mut Random) -> Vec<Cidr> {
+    let lengths: &[u8] = match family {
+        Family::V4 => &[
+            8, 12, 16, 18, 20, 22, 22, 23, 24, 24, 24, 24, 24, 24, 24, 24,
+        ],
+        Family::V6 => &[
+            19, 28, 29, 32, 32, 36, 40, 44, 48, 48, 48, 48, 48, 48, 48, 64,
+        ],
+    };
+    (0..ROUTES)
+        .map(|_| {
+            let bits = match family {
+                Family::V4 => random.next_u64() as u32 as u128,
+                Family::V6 => (0x2000u128 << 112) | (random.next_u64() as u128) << 60,
+            };
+            let prefix = lengths[random.next_u64() as usize % lengths.len()];
+            Cidr::new(&IpAddrKind::from_bits(family, bits), prefix).unwrap()
+        })
+        .collect()
+}
+
+fn addresses(family: Family, random: &mut Random) -> Vec<IpAddrKind> {
+    (0..1024)
+        .map(|_| {
+            let bits = match family {
+                Family::V4 => random.next_u64() as u32 as u128,
+                Family::V6 => (0x2000u128 << 112) | (random.next_u64() as u128) << 60,
+            };
+            IpAddrKind::from_bits(family, bits)
+        })
+        .collect()
+}
+
+fn bench_family(c: &mut Criterion, name: &str, family: Family) {
+    let mut random = Random::new(0x9e37_79b9_7f4a_7c15);
+    let routes = routes(family, &mut random);
+    let table: RoutingTable<usize> = routes.iter().copied().zip(0..).collect();
+    let targets = addresses(family, &mut random);
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.bench_function("insert 1M", |b| {
+        b.iter(|| {
+            let mut table = RoutingTable::new();
+            for (i, prefix) in routes.iter().enumerate() {
+                table.insert(*prefix, i);
+            }
+            table
+        })
+    });
+    group.bench_function("remove 1M", |b| {
+        b.iter_batched(
+            || table.clone(),
+            |mut table| {
+                for prefix in &routes {
+                    table.remove(prefix);
+                }
+                table
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("iterate 1M", |b| b.iter(|| table.iter().count()));
+    group.finish();
+
+    let mut i = 0;
+    c.bench_function(&format!("{}/lookup", name), |b| {
+        b.iter(|| {
+            i = (i + 1) % targets.len();
+            table.lookup(black_box(&targets[i])).map(|(_, hop)| *hop)
+        })
+    });
+}
+
+fn benches(c: &mut Criterion) {
+    bench_family(c, "ipv4", Family::V4);
+    bench_family(c, "ipv6", Family::V6);
+}
+
+criterion_group!(routing, benches);
+criterion_main!(routing);
@@ -0,0 +1,410 @@
+// Making change with the fewest coins.
+//
+// Always handing out the largest coin that fits is optimal for US coins, but not for every set
+// of denominations: with 1, 3 and 4, greedy pays 6 as 4+1+1 where 3+3 is better. It also fails
+// when the drawer is short of some coin, so change is worked out by dynamic programming instead.
+// That takes memory in proportion to the amount, so it is only done up to `EXACT_CHANGE_LIMIT`;
+// past that, only currencies where greedy is known to be optimal can make change.
+
+use std::fmt;
+
+// The largest amount, in the smallest unit, that `make_change` will work out.
+pub const EXACT_CHANGE_LIMIT: u32 = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeError {
+    NoDenominations,
+    ZeroDenomination,
+    DuplicateDenomination(u32),
+    // The coins available can't add up to exactly this amount.
+    CannotMakeChange(u32),
+    // The drawer holds fewer of the coin with this value than were asked for.
+    OutOfCoin(u32),
+    // Past `EXACT_CHANGE_LIMIT`, for coins where the largest-first shortcut can't be trusted.
+    AmountTooLarge(u32),
+    // The drawer can't count any more of the coin with this value.
+    DrawerFull(u32),
+}
+
+impl fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChangeError::NoDenominations => write!(f, "a currency needs at least one denomination"),
+            ChangeError::ZeroDenomination => write!(f, "denominations must be worth something"),
+            ChangeError::DuplicateDenomination(value) => {
+                write!(f, "denomination {} is listed twice", value)
+            }
+            ChangeError::CannotMakeChange(amount) => {
+                write!(f, "can't make exactly {} from the coins available", amount)
+            }
+            ChangeError::OutOfCoin(value) => write!(f, "not enough coins worth {}", value),
+            ChangeError::AmountTooLarge(amount) => {
+                write!(f, "{} is too much to work out change for", amount)
+            }
+            ChangeError::DrawerFull(value) => write!(f, "no room for more coins worth {}", value),
+        }
+    }
+}
+
+impl std::error::Error for ChangeError {}
+
+// The fewest coins adding up to `amount`, given each coin's value and how many are available.
+// Returns how many of each coin to use, in the same order.
+//
+// Each coin's supply is split into lots of 1, 2, 4, ... so that picking a subset of the lots
+// can reach any count up to the supply, and the lots are then packed as a 0/1 knapsack.
+pub fn make_change(amount: u32, coins: &[(u32, u32)]) -> Result<Vec<u32>, ChangeError> {
+    const UNREACHABLE: u32 = u32::MAX;
+    if amount > EXACT_CHANGE_LIMIT {
+        return Err(ChangeError::AmountTooLarge(amount));
+    }
+    let target = amount as usize;
+
+    let mut lots = Vec::new();
+    for (i, &(value, available)) in coins.iter().enumerate() {
+        if value == 0 {
+            continue;
+        }
+        let mut left = available.min(amount / value);
+        let mut size = 1;
+        while left > 0 {
+            let lot = size.min(left);
+            lots.push((i, lot, (value * lot) as usize));
+            left -= lot;
+            size *= 2;
+        }
+    }
+
+    // fewest[a] is the fewest coins making a from the lots so far, and used[j][a] records
+    // whether lot j was part of that.
+    let mut fewest = vec![UNREACHABLE; target + 1];
+    fewest[0] = 0;
+    let mut used = vec![vec![false; target + 1]; lots.len()];
+    for (j, &(_, count, worth)) in lots.iter().enumerate() {
+        for a in (worth..=target).rev() {
+            let before = fewest[a - worth];
+            if before != UNREACHABLE && before + count < fewest[a] {
+                fewest[a] = before + count;
+                used[j][a] = true;
+            }
+        }
+    }
+    if fewest[target] == UNREACHABLE {
+        return Err(ChangeError::CannotMakeChange(amount));
+    }
+
+    let mut counts = vec![0; coins.len()];
+    let mut left = target;
+    for (j, &(i, count, worth)) in lots.iter().enumerate().rev() {
+        if used[j][left] {
+            counts[i] += count;
+            left -= worth;
+        }
+    }
+    Ok(counts)
+}
+
+// A currency's denominations, in its smallest unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Currency {
+    code: String,
+    // Smallest first.
+    denominations: Vec<u32>,
+    // Whether greedy change is always the fewest coins; see `check_canonical`.
+    canonical: bool,
+}
+
+impl Currency {
+    pub fn new(code: &str, denominations: &[u32]) -> Result<Currency, ChangeError> {
+        let mut sorted = denominations.to_vec();
+        sorted.sort_unstable();
+        if sorted.is_empty() {
+            return Err(ChangeError::NoDenominations);
+        }
+        if sorted[0] == 0 {
+            return Err(ChangeError::ZeroDenomination);
+        }
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(ChangeError::DuplicateDenomination(pair[0]));
+        }
+        let mut currency = Currency {
+            code: String::from(code),
+            denominations: sorted,
+            canonical: false,
+        };
+        currency.canonical = currency.check_canonical();
+        Ok(currency)
+    }
+
+    // US coins, as in `Coin`.
+    pub fn usd() -> Currency {
+        let cents: Vec<u32> = super::Coin::ALL.iter().map(|coin| coin.cents()).collect();
+        Currency::new("USD", &cents).unwrap()
+    }
+
+    // Euro coins, from 1 cent to €2.
+    pub fn eur() -> Currency {
+        Currency::new("EUR", &[1, 2, 5, 10, 20, 50, 100, 200]).unwrap()
+    }
+
+    // Pound coins, from 1p to £2.
+    pub fn gbp() -> Currency {
+        Currency::new("GBP", &[1, 2, 5, 10, 20, 50, 100, 200]).unwrap()
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn denominations(&self) -> &[u32] {
+        &self.denominations
+    }
+
+    // The fewest coins making `amount` with as many of each as needed, as (value, count)
+    // pairs from the largest coin down.
+    pub fn make_change(&self, amount: u32) -> Result<Vec<(u32, u32)>, ChangeError> {
+        let counts = if self.canonical {
+            self.greedy(amount)
+                .ok_or(ChangeError::CannotMakeChange(amount))?
+        } else {
+            let coins: Vec<(u32, u32)> =
+                self.denominations.iter().map(|&d| (d, u32::MAX)).collect();
+            make_change(amount, &coins)?
+        };
+        Ok(self
+            .denominations
+            .iter()
+            .copied()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .rev()
+            .collect())
+    }
+
+    // Whether taking the largest coin that fits, over and over, always gives the fewest coins.
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    // With a 1 among the coins, Pearson's test (2005) finds the smallest counterexample if there
+    // is one: it is a greedy way of paying one less than some coin, with one more of some
+    // smaller coin added and everything smaller dropped.
+    //
+    // Without a 1, a counterexample exists below the sum of the two largest coins (Kozen and
+    // Zaks, 1994), so the fewest coins for every amount up to there are worked out and compared.
+    // Where that is past `EXACT_CHANGE_LIMIT`, the currency is taken not to be canonical.
+    fn check_canonical(&self) -> bool {
+        if self.denominations[0] == 1 {
+            return self.pearson();
+        }
+        let limit = match self.denominations[..] {
+            [.., second, largest] => second.saturating_add(largest),
+            _ => return true,
+        };
+        if limit > EXACT_CHANGE_LIMIT {
+            return false;
+        }
+        let mut fewest = vec![None; limit as usize];
+        fewest[0] = Some(0);
+        for amount in 1..limit {
+            fewest[amount as usize] = self
+                .denominations
+                .iter()
+                .filter(|&&d| d <= amount)
+                .filter_map(|&d| fewest[(amount - d) as usize])
+                .min()
+                .map(|n: u32| n + 1);
+            let greedy = self.greedy(amount).map(|g| g.iter().sum());
+            if greedy != fewest[amount as usize] {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn pearson(&self) -> bool {
+        // Largest first, as in the paper.
+        let coins: Vec<u64> = self.denominations.iter().rev().map(|&d| d as u64).collect();
+        for i in 1..coins.len() {
+            let below = greedy(&coins, coins[i - 1] - 1);
+            for j in i..coins.len() {
+                let mut candidate = below.clone();
+                candidate[j] += 1;
+                candidate[j + 1..].iter_mut().for_each(|count| *count = 0);
+                let worth: u64 = candidate.iter().zip(&coins).map(|(n, c)| n * c).sum();
+                let fewest: u64 = candidate.iter().sum();
+                if greedy(&coins, worth).iter().sum::<u64>() > fewest {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // How many of each coin, smallest first, taking the largest that fits over and over.
+    fn greedy(&self, mut amount: u32) -> Option<Vec<u32>> {
+        let mut counts = vec![0; self.denominations.len()];
+        for (i, &value) in self.denominations.iter().enumerate().rev() {
+            counts[i] = amount / value;
+            amount %= value;
+        }
+        (amount == 0).then_some(counts)
+    }
+}
+
+// Greedy counts for `coins`, largest first, which have to include a 1.
+fn greedy(coins: &[u64], mut amount: u64) -> Vec<u64> {
+    coins
+        .iter()
+        .map(|&coin| {
+            let count = amount / coin;
+            amount %= coin;
+            count
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_the_fewest_coins() {
+        assert_eq!(
+            Currency::usd().make_change(68),
+            Ok(vec![(25, 2), (10, 1), (5, 1), (1, 3)])
+        );
+        assert_eq!(
+            Currency::eur().make_change(388),
+            Ok(vec![
+                (200, 1),
+                (100, 1),
+                (50, 1),
+                (20, 1),
+                (10, 1),
+                (5, 1),
+                (2, 1),
+                (1, 1)
+            ])
+        );
+        assert_eq!(Currency::usd().make_change(0), Ok(vec![]));
+
+        // Greedy would give 4+1+1.
+        let odd = Currency::new("ODD", &[4, 1, 3]).unwrap();
+        assert_eq!(odd.make_change(6), Ok(vec![(3, 2)]));
+        let no_pennies = Currency::new("X", &[5, 2]).unwrap();
+        assert_eq!(
+            no_pennies.make_change(3),
+            Err(ChangeError::CannotMakeChange(3))
+        );
+        assert_eq!(no_pennies.make_change(11), Ok(vec![(5, 1), (2, 3)]));
+    }
+
+    #[test]
+    fn respects_limited_supply() {
+        // With no nickels and only four pennies, 30 can't use the quarter.
+        let coins = [(25, 1), (10, 3), (5, 0), (1, 4)];
+        assert_eq!(make_change(30, &coins), Ok(vec![0, 3, 0, 0]));
+        assert_eq!(make_change(34, &coins), Ok(vec![0, 3, 0, 4]));
+        assert_eq!(
+            make_change(60, &coins),
+            Err(ChangeError::CannotMakeChange(60))
+        );
+        assert_eq!(make_change(59, &coins), Ok(vec![1, 3, 0, 4]));
+
+        // Cross-check against trying every combination.
+        let coins = [(1, 3), (3, 2), (4, 3), (9, 1)];
+        for amount in 0..=30 {
+            let mut best: Option<u32> = None;
+            for a in 0..=3 {
+                for b in 0..=2 {
+                    for c in 0..=3 {
+                        for d in 0..=1 {
+                            if a + 3 * b + 4 * c + 9 * d == amount {
+                                best = Some(best.map_or(a + b + c + d, |n| n.min(a + b + c + d)));
+                            }
+                        }
+                    }
+                }
+            }
+            let found = make_change(amount, &coins);
+            assert_eq!(
+                found.as_ref().ok().map(|counts| counts.iter().sum()),
+                best,
+                "{}",
+                amount
+            );
+            if let Ok(counts) = found {
+                let total: u32 = counts
+                    .iter()
+                    .zip(&coins)
+                    .map(|(n, (value, _))| n * value)
+                    .sum();
+                assert_eq!(total, amount);
+                assert!(counts
+                    .iter()
+                    .zip(&coins)
+                    .all(|(n, (_, available))| n <= available));
+            }
+        }
+    }
+
+    #[test]
+    fn tells_canonical_systems_apart() {
+        assert!(Currency::usd().is_canonical());
+        assert!(Currency::eur().is_canonical());
+        assert!(!Currency::new("ODD", &[1, 3, 4]).unwrap().is_canonical());
+        // Pre-decimal British coins: 48d is two florins, not a half-crown and change.
+        assert!(!Currency::new("LSD", &[1, 3, 6, 12, 24, 30])
+            .unwrap()
+            .is_canonical());
+        assert!(Currency::new("X", &[2, 4]).unwrap().is_canonical());
+        assert!(!Currency::new("X", &[2, 5]).unwrap().is_canonical());
+
+        // Pearson's test against checking every amount, for every set of coins up to 12 with
+        // a 1 among them.
+        for set in (1u32..1 << 11).map(|bits| bits << 1 | 1) {
+            let coins: Vec<u32> = (0..12)
+                .filter(|i| set & 1 << i != 0)
+                .map(|i| i + 1)
+                .collect();
+            let currency = Currency::new("X", &coins).unwrap();
+            let every: Vec<(u32, u32)> = coins.iter().map(|&c| (c, u32::MAX)).collect();
+            let by_search = (1..24).all(|amount| {
+                let fewest: u32 = make_change(amount, &every).unwrap().iter().sum();
+                Some(fewest) == currency.greedy(amount).map(|g| g.iter().sum())
+            });
+            assert_eq!(currency.is_canonical(), by_search, "{:?}", coins);
+        }
+    }
+
+    #[test]
+    fn bounds_the_exact_search() {
+        // Greedy is right for these, so any amount will do.
+        let big = Currency::usd().make_change(u32::MAX).unwrap();
+        assert_eq!(big[0], (25, u32::MAX / 25));
+        assert!(Currency::new("X", &[1, 1_000_000_000])
+            .unwrap()
+            .is_canonical());
+
+        let odd = Currency::new("ODD", &[1, 3, 4]).unwrap();
+        assert_eq!(
+            odd.make_change(EXACT_CHANGE_LIMIT + 1),
+            Err(ChangeError::AmountTooLarge(EXACT_CHANGE_LIMIT + 1))
+        );
+        assert!(odd.make_change(EXACT_CHANGE_LIMIT).is_ok());
+    }
+
+    #[test]
+    fn validates_denominations() {
+        assert_eq!(Currency::new("X", &[]), Err(ChangeError::NoDenominations));
+        assert_eq!(
+            Currency::new("X", &[5, 0]),
+            Err(ChangeError::ZeroDenomination)
+        );
+        assert_eq!(
+            Currency::new("X", &[1, 5, 1]),
+            Err(ChangeError::DuplicateDenomination(1))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// A till that knows how many of each coin it holds, and only gives change it actually has.
+
+use std::collections::BTreeMap;
+
+use super::{make_change, ChangeError, Denomination};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CashDrawer<D: Denomination> {
+    counts: BTreeMap<D, u32>,
+}
+
+impl<D: Denomination> Default for CashDrawer<D> {
+    fn default() -> Self {
+        CashDrawer::new()
+    }
+}
+
+impl<D: Denomination> CashDrawer<D> {
+    pub fn new() -> CashDrawer<D> {
+        CashDrawer {
+            counts: BTreeMap::new(),
+        }
+    }
+
+    // Starts the drawer with `count` of `coin`, for setting up a float. Panics if that's more
+    // of one coin than the drawer counts, which a float written into the code never is.
+    pub fn with(mut self, coin: D, count: u32) -> Self {
+        self.add(coin, count)
+            .expect("a float holds less than u32::MAX of each coin");
+        self
+    }
+
+    // Puts coins in, or nothing if the drawer would hold more than a u32 of that coin.
+    pub fn add(&mut self, coin: D, count: u32) -> Result<(), ChangeError> {
+        let held = self.counts.entry(coin).or_insert(0);
+        *held = held
+            .checked_add(count)
+            .ok_or(ChangeError::DrawerFull(coin.value()))?;
+        Ok(())
+    }
+
+    pub fn count(&self, coin: D) -> u32 {
+        self.counts.get(&coin).copied().unwrap_or(0)
+    }
+
+    // Everything in the drawer, in its smallest unit.
+    pub fn total(&self) -> u64 {
+        self.counts
+            .iter()
+            .map(|(coin, &count)| coin.value() as u64 * count as u64)
+            .sum()
+    }
+
+    // Coins held, smallest denomination first, leaving out any that have run out.
+    pub fn contents(&self) -> impl Iterator<Item = (D, u32)> + '_ {
+        self.counts
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(&coin, &count)| (coin, count))
+    }
+
+    // Takes out exactly these coins, or nothing if any are missing.
+    pub fn remove(&mut self, coins: &[(D, u32)]) -> Result<(), ChangeError> {
+        let mut needed: BTreeMap<D, u32> = BTreeMap::new();
+        for &(coin, count) in coins.iter().filter(|&&(_, count)| count > 0) {
+            let total = needed.entry(coin).or_insert(0);
+            // More than a u32 of one coin is more than the drawer can hold.
+            *total = total
+                .checked_add(count)
+                .ok_or(ChangeError::OutOfCoin(coin.value()))?;
+        }
+        if let Some((&coin, _)) = needed
+            .iter()
+            .find(|(&coin, &count)| self.count(coin) < count)
+        {
+            return Err(ChangeError::OutOfCoin(coin.value()));
+        }
+        for (coin, count) in needed {
+            *self.counts.entry(coin).or_insert(0) -= count;
+        }
+        Ok(())
+    }
+
+    // The fewest coins from the drawer adding up to `amount`, largest first, without taking
+    // them out.
+    pub fn change_for(&self, amount: u32) -> Result<Vec<(D, u32)>, ChangeError> {
+        if amount as u64 > self.total() {
+            return Err(ChangeError::CannotMakeChange(amount));
+        }
+        let coins: Vec<(D, u32)> = self.contents().collect();
+        let values: Vec<(u32, u32)> = coins
+            .iter()
+            .map(|&(coin, count)| (coin.value(), count))
+            .collect();
+        let counts = make_change(amount, &values)?;
+        Ok(coins
+            .iter()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .map(|(&(coin, _), count)| (coin, count))
+            .rev()
+            .collect())
+    }
+
+    // Takes the fewest coins adding up to `amount` out of the drawer.
+    pub fn pay_out(&mut self, amount: u32) -> Result<Vec<(D, u32)>, ChangeError> {
+        let change = self.change_for(amount)?;
+        self.remove(&change)?;
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Coin;
+    use super::*;
+
+    #[test]
+    fn pays_out_from_what_it_holds() {
+        let mut drawer = CashDrawer::new()
+            .with(Coin::Quarter, 1)
+            .with(Coin::Dime, 5)
+            .with(Coin::Penny, 2);
+        assert_eq!(drawer.total(), 77);
+
+        // With no nickels, 30 can only come out as three dimes.
+        assert_eq!(drawer.pay_out(30), Ok(vec![(Coin::Dime, 3)]));
+        assert_eq!(
+            drawer.pay_out(27),
+            Ok(vec![(Coin::Quarter, 1), (Coin::Penny, 2)])
+        );
+        assert_eq!(drawer.pay_out(20), Ok(vec![(Coin::Dime, 2)]));
+        assert_eq!(drawer.pay_out(5), Err(ChangeError::CannotMakeChange(5)));
+        assert_eq!(drawer.contents().collect::<Vec<_>>(), []);
+    }
+
+    #[test]
+    fn removes_all_or_nothing() {
+        let mut drawer = CashDrawer::new().with(Coin::Nickel, 2).with(Coin::Dime, 1);
+
+        assert_eq!(
+            drawer.remove(&[(Coin::Nickel, 1), (Coin::Dime, 2)]),
+            Err(ChangeError::OutOfCoin(10))
+        );
+        assert_eq!(drawer.count(Coin::Nickel), 2);
+        assert_eq!(
+            drawer.remove(&[(Coin::Nickel, 1), (Coin::Nickel, 1)]),
+            Ok(())
+        );
+        assert_eq!(drawer.count(Coin::Nickel), 0);
+
+        // None of a coin it never held, and more than any drawer could hold.
+        assert_eq!(drawer.remove(&[(Coin::Quarter, 0)]), Ok(()));
+        assert_eq!(
+            drawer.remove(&[(Coin::Dime, u32::MAX), (Coin::Dime, 2)]),
+            Err(ChangeError::OutOfCoin(10))
+        );
+        assert_eq!(drawer.count(Coin::Dime), 1);
+        assert_eq!(
+            drawer.add(Coin::Dime, u32::MAX),
+            Err(ChangeError::DrawerFull(10))
+        );
+        assert_eq!(drawer.count(Coin::Dime), 1);
+        assert_eq!(drawer.add(Coin::Dime, u32::MAX - 1), Ok(()));
+    }
+
+    #[test]
+    fn works_with_other_currencies() {
+        // Euro cents.
+        let mut drawer: CashDrawer<u32> = CashDrawer::new()
+            .with(200, 1)
+            .with(50, 2)
+            .with(20, 2)
+            .with(5, 3);
+
+        assert_eq!(drawer.pay_out(245), Ok(vec![(200, 1), (20, 2), (5, 1)]));
+        // Only 50s and 5s are left.
+        assert_eq!(
+            drawer.change_for(40),
+            Err(ChangeError::CannotMakeChange(40))
+        );
+        assert_eq!(drawer.change_for(60), Ok(vec![(50, 1), (5, 2)]));
+        assert_eq!(drawer.change_for(110), Ok(vec![(50, 2), (5, 2)]));
+        // More than the drawer holds is turned down without working anything out.
+        assert_eq!(
+            drawer.change_for(u32::MAX),
+            Err(ChangeError::CannotMakeChange(u32::MAX))
+        );
+        assert_eq!(drawer.total(), 110);
+    }
+}
@@ -0,0 +1,47 @@
+// The `Coin` enum from main.rs, with change-making for it and for other currencies.
+
+mod change;
+mod drawer;
+
+pub use self::change::{make_change, ChangeError, Currency};
+pub use self::drawer::CashDrawer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Coin {
+    Penny,
+    Nickel,
+    Dime,
+    Quarter,
+}
+
+impl Coin {
+    pub const ALL: [Coin; 4] = [Coin::Penny, Coin::Nickel, Coin::Dime, Coin::Quarter];
+
+    pub fn cents(self) -> u32 {
+        match self {
+            Coin::Penny => 1,
+            Coin::Nickel => 5,
+            Coin::Dime => 10,
+            Coin::Quarter => 25,
+        }
+    }
+}
+
+// Something a cash drawer can hold, worth a whole number of the currency's smallest unit.
+pub trait Denomination: Copy + Ord {
+    fn value(self) -> u32;
+}
+
+impl Denomination for Coin {
+    fn value(self) -> u32 {
+        self.cents()
+    }
+}
+
+// For currencies without a type of their own, a coin or note is just its value, such as 200
+// for a €2 coin.
+impl Denomination for u32 {
+    fn value(self) -> u32 {
+        self
+    }
+}
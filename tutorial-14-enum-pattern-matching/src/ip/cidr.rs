@@ -0,0 +1,393 @@
+// Address blocks written as a network address and a prefix length, like 10.0.0.0/8.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Family, IpAddrKind, IpError};
+
+// Bits after a prefix of `prefix` bits in an address of the given family.
+fn host_mask(family: Family, prefix: u8) -> u128 {
+    host_mask_bits(family.width() - prefix)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cidr {
+    family: Family,
+    // The network address, with every host bit cleared.
+    bits: u128,
+    prefix: u8,
+}
+
+impl Cidr {
+    // The block of `prefix` bits containing `addr`. Host bits and any zone ID are dropped.
+    pub fn new(addr: &IpAddrKind, prefix: u8) -> Result<Cidr, IpError> {
+        let family = addr.family();
+        if prefix > family.width() {
+            return Err(IpError::InvalidPrefix(prefix.to_string()));
+        }
+        Ok(Cidr {
+            family,
+            bits: addr.to_bits() & !host_mask(family, prefix),
+            prefix,
+        })
+    }
+
+    // Used where the parts are already known to be valid, such as keys read back out of a
+    // routing table.
+    pub(crate) fn from_parts(family: Family, bits: u128, prefix: u8) -> Cidr {
+        debug_assert!(prefix <= family.width() && bits & host_mask(family, prefix) == 0);
+        Cidr {
+            family,
+            bits,
+            prefix,
+        }
+    }
+
+    pub fn family(&self) -> Family {
+        self.family
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix
+    }
+
+    // The network address as a number, like `IpAddrKind::to_bits`.
+    pub fn to_bits(&self) -> u128 {
+        self.bits
+    }
+
+    pub fn network(&self) -> IpAddrKind {
+        self.first()
+    }
+
+    pub fn first(&self) -> IpAddrKind {
+        IpAddrKind::from_bits(self.family, self.bits)
+    }
+
+    pub fn last(&self) -> IpAddrKind {
+        IpAddrKind::from_bits(self.family, self.last_bits())
+    }
+
+    fn last_bits(&self) -> u128 {
+        self.bits | host_mask(self.family, self.prefix)
+    }
+
+    pub fn contains(&self, addr: &IpAddrKind) -> bool {
+        addr.family() == self.family
+            && addr.to_bits() & !host_mask(self.family, self.prefix) == self.bits
+    }
+
+    // Whether `other` lies entirely inside this block.
+    pub fn contains_cidr(&self, other: &Cidr) -> bool {
+        other.family == self.family
+            && other.prefix >= self.prefix
+            && other.bits & !host_mask(self.family, self.prefix) == self.bits
+    }
+
+    // The block one bit shorter that contains this one, or None for /0.
+    pub fn supernet(&self) -> Option<Cidr> {
+        let prefix = self.prefix.checked_sub(1)?;
+        Some(Cidr {
+            family: self.family,
+            bits: self.bits & !host_mask(self.family, prefix),
+            prefix,
+        })
+    }
+
+    // Splits the block into every block with the longer `prefix`, in address order.
+    pub fn subnets(&self, prefix: u8) -> Result<Subnets, IpError> {
+        if prefix < self.prefix || prefix > self.family.width() {
+            return Err(IpError::InvalidPrefix(prefix.to_string()));
+        }
+        Ok(Subnets {
+            family: self.family,
+            next: Some(self.bits),
+            last: self.last_bits() & !host_mask(self.family, prefix),
+            prefix,
+        })
+    }
+
+    // Every address in the block, from the network address up.
+    pub fn addresses(&self) -> IpRange {
+        IpRange {
+            family: self.family,
+            next: Some(self.bits),
+            last: self.last_bits(),
+        }
+    }
+
+    // The fewest blocks that exactly cover the addresses from `first` to `last`.
+    pub fn from_range(first: &IpAddrKind, last: &IpAddrKind) -> Result<Vec<Cidr>, IpError> {
+        if first.family() != last.family() || first.to_bits() > last.to_bits() {
+            return Err(IpError::InvalidRange);
+        }
+        Ok(cover(first.family(), first.to_bits(), last.to_bits()))
+    }
+
+    // Merges overlapping and adjacent blocks into the fewest blocks covering the same
+    // addresses, IPv4 before IPv6 and each in address order.
+    pub fn aggregate(cidrs: impl IntoIterator<Item = Cidr>) -> Vec<Cidr> {
+        let mut ranges: Vec<(Family, u128, u128)> = cidrs
+            .into_iter()
+            .map(|c| (c.family, c.bits, c.last_bits()))
+            .collect();
+        ranges.sort();
+
+        let mut merged: Vec<(Family, u128, u128)> = Vec::new();
+        for (family, first, last) in ranges {
+            match merged.last_mut() {
+                Some((f, _, end)) if *f == family && (*end == u128::MAX || first <= *end + 1) => {
+                    *end = (*end).max(last);
+                }
+                _ => merged.push((family, first, last)),
+            }
+        }
+        merged
+            .into_iter()
+            .flat_map(|(family, first, last)| cover(family, first, last))
+            .collect()
+    }
+}
+
+// Splits first..=last into aligned blocks, taking the largest block that fits at each step.
+fn cover(family: Family, mut first: u128, last: u128) -> Vec<Cidr> {
+    let width = family.width();
+    let mut blocks = Vec::new();
+    loop {
+        let aligned = if first == 0 {
+            width
+        } else {
+            (first.trailing_zeros() as u8).min(width)
+        };
+        let mut host_bits = aligned;
+        while host_bits > 0 && first | host_mask_bits(host_bits) > last {
+            host_bits -= 1;
+        }
+        blocks.push(Cidr {
+            family,
+            bits: first,
+            prefix: width - host_bits,
+        });
+
+        let end = first | host_mask_bits(host_bits);
+        if end >= last {
+            return blocks;
+        }
+        first = end + 1;
+    }
+}
+
+fn host_mask_bits(host_bits: u8) -> u128 {
+    if host_bits == 128 {
+        u128::MAX
+    } else {
+        (1u128 << host_bits) - 1
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network(), self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = IpError;
+
+    // A bare address is a block of one. Host bits must be clear, so 10.0.0.1/8 is an error
+    // rather than quietly meaning 10.0.0.0/8.
+    fn from_str(s: &str) -> Result<Cidr, IpError> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddrKind>()?, Some(prefix)),
+            None => (s.parse::<IpAddrKind>()?, None),
+        };
+        let prefix = match prefix {
+            Some(p) if !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()) => p
+                .parse()
+                .map_err(|_| IpError::InvalidPrefix(String::from(p)))?,
+            Some(p) => return Err(IpError::InvalidPrefix(String::from(p))),
+            None => addr.family().width(),
+        };
+
+        let cidr = Cidr::new(&addr, prefix)?;
+        if cidr.bits != addr.to_bits() {
+            return Err(IpError::HostBitsSet(String::from(s)));
+        }
+        Ok(cidr)
+    }
+}
+
+// Blocks of equal size in address order, from `Cidr::subnets`.
+#[derive(Debug, Clone)]
+pub struct Subnets {
+    family: Family,
+    next: Option<u128>,
+    last: u128,
+    prefix: u8,
+}
+
+impl Iterator for Subnets {
+    type Item = Cidr;
+
+    fn next(&mut self) -> Option<Cidr> {
+        let bits = self.next?;
+        self.next = if bits == self.last {
+            None
+        } else {
+            Some(bits + host_mask(self.family, self.prefix) + 1)
+        };
+        Some(Cidr {
+            family: self.family,
+            bits,
+            prefix: self.prefix,
+        })
+    }
+}
+
+// Consecutive addresses from a first to a last one, both included.
+#[derive(Debug, Clone)]
+pub struct IpRange {
+    family: Family,
+    next: Option<u128>,
+    last: u128,
+}
+
+impl IpRange {
+    pub fn new(first: &IpAddrKind, last: &IpAddrKind) -> Result<IpRange, IpError> {
+        if first.family() != last.family() || first.to_bits() > last.to_bits() {
+            return Err(IpError::InvalidRange);
+        }
+        Ok(IpRange {
+            family: first.family(),
+            next: Some(first.to_bits()),
+            last: last.to_bits(),
+        })
+    }
+}
+
+impl Iterator for IpRange {
+    type Item = IpAddrKind;
+
+    fn next(&mut self) -> Option<IpAddrKind> {
+        let bits = self.next?;
+        self.next = if bits == self.last {
+            None
+        } else {
+            Some(bits + 1)
+        };
+        Some(IpAddrKind::from_bits(self.family, bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddrKind {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_checks_containment() {
+        let net = cidr("10.1.0.0/16");
+
+        assert!(net.contains(&addr("10.1.255.7")));
+        assert!(!net.contains(&addr("10.2.0.0")));
+        assert!(!net.contains(&addr("::a01:0")));
+        assert!(net.contains_cidr(&cidr("10.1.128.0/17")));
+        assert!(!net.contains_cidr(&cidr("10.0.0.0/8")));
+        assert_eq!(
+            cidr("2001:db8::/32").last(),
+            addr("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff")
+        );
+        assert_eq!(cidr("192.0.2.7").to_string(), "192.0.2.7/32");
+    }
+
+    #[test]
+    fn rejects_bad_prefixes_and_host_bits() {
+        assert_eq!(
+            "10.0.0.1/8".parse::<Cidr>(),
+            Err(IpError::HostBitsSet(String::from("10.0.0.1/8")))
+        );
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/+8".parse::<Cidr>().is_err());
+        assert!("::/129".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn splits_into_subnets_and_back() {
+        let subnets: Vec<String> = cidr("192.168.0.0/22")
+            .subnets(24)
+            .unwrap()
+            .map(|c| c.to_string())
+            .collect();
+
+        assert_eq!(
+            subnets,
+            [
+                "192.168.0.0/24",
+                "192.168.1.0/24",
+                "192.168.2.0/24",
+                "192.168.3.0/24"
+            ]
+        );
+        assert_eq!(
+            cidr("192.168.1.0/24").supernet(),
+            Some(cidr("192.168.0.0/23"))
+        );
+        assert_eq!(cidr("0.0.0.0/0").supernet(), None);
+        assert_eq!(cidr("::/0").subnets(1).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn aggregates_adjacent_and_overlapping_blocks() {
+        let merged = Cidr::aggregate([
+            cidr("10.0.1.0/24"),
+            cidr("10.0.0.0/24"),
+            cidr("10.0.2.0/23"),
+            cidr("10.0.2.128/25"),
+            cidr("2001:db8::/33"),
+            cidr("2001:db8:8000::/33"),
+            cidr("10.0.5.0/24"),
+        ]);
+        let merged: Vec<String> = merged.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(merged, ["10.0.0.0/22", "10.0.5.0/24", "2001:db8::/32"]);
+        assert_eq!(
+            Cidr::aggregate([cidr("::/0"), cidr("::1")]),
+            vec![cidr("::/0")]
+        );
+    }
+
+    #[test]
+    fn covers_ranges_and_iterates_addresses() {
+        let blocks: Vec<String> = Cidr::from_range(&addr("10.0.0.1"), &addr("10.0.0.10"))
+            .unwrap()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        assert_eq!(
+            blocks,
+            [
+                "10.0.0.1/32",
+                "10.0.0.2/31",
+                "10.0.0.4/30",
+                "10.0.0.8/31",
+                "10.0.0.10/32"
+            ]
+        );
+        assert_eq!(
+            Cidr::from_range(&addr("0.0.0.0"), &addr("255.255.255.255")).unwrap(),
+            vec![cidr("0.0.0.0/0")]
+        );
+        let last_two: Vec<IpAddrKind> = cidr("255.255.255.254/31").addresses().collect();
+        assert_eq!(last_two, [addr("255.255.255.254"), addr("255.255.255.255")]);
+        assert!(IpRange::new(&addr("10.0.0.2"), &addr("10.0.0.1")).is_err());
+    }
+}
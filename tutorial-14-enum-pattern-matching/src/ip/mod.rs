@@ -0,0 +1,426 @@
+// IP addresses as an enum of the two versions, like `IpAddrKind` in main.rs, but holding
+// validated values instead of strings.
+//
+// Addresses parse from and format to their usual text forms: dotted decimal for IPv4, and for
+// IPv6 colon-separated hex groups with `::` compression, an optional embedded IPv4 address and
+// an optional `%zone` suffix.
+
+use std::fmt;
+use std::str::FromStr;
+
+mod cidr;
+
+pub use self::cidr::{Cidr, IpRange, Subnets};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpError {
+    InvalidV4(String),
+    InvalidV6(String),
+    // A zone ID has to be non-empty and can't contain spaces or '/'.
+    InvalidZone(String),
+    InvalidPrefix(String),
+    // A CIDR like 10.0.0.1/8 has bits set after the prefix.
+    HostBitsSet(String),
+    // A range or list mixes IPv4 and IPv6, or runs backwards.
+    InvalidRange,
+}
+
+impl fmt::Display for IpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpError::InvalidV4(s) => write!(f, "{:?} is not an IPv4 address", s),
+            IpError::InvalidV6(s) => write!(f, "{:?} is not an IPv6 address", s),
+            IpError::InvalidZone(s) => write!(f, "{:?} is not a valid zone ID", s),
+            IpError::InvalidPrefix(s) => write!(f, "{:?} is not a valid prefix", s),
+            IpError::HostBitsSet(s) => write!(f, "{} has bits set after the prefix", s),
+            IpError::InvalidRange => write!(f, "not a valid address range"),
+        }
+    }
+}
+
+impl std::error::Error for IpError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    // Number of bits in an address.
+    pub fn width(self) -> u8 {
+        match self {
+            Family::V4 => 32,
+            Family::V6 => 128,
+        }
+    }
+
+    // The highest address, as bits.
+    pub fn max(self) -> u128 {
+        match self {
+            Family::V4 => u32::MAX as u128,
+            Family::V6 => u128::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv4([u8; 4]);
+
+impl Ipv4 {
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Ipv4 {
+        Ipv4([a, b, c, d])
+    }
+
+    pub fn octets(&self) -> [u8; 4] {
+        self.0
+    }
+}
+
+impl From<u32> for Ipv4 {
+    fn from(bits: u32) -> Ipv4 {
+        Ipv4(bits.to_be_bytes())
+    }
+}
+
+impl From<Ipv4> for u32 {
+    fn from(addr: Ipv4) -> u32 {
+        u32::from_be_bytes(addr.0)
+    }
+}
+
+impl fmt::Display for Ipv4 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+impl FromStr for Ipv4 {
+    type Err = IpError;
+
+    // Leading zeros are rejected, since some tools read "010" as octal.
+    fn from_str(s: &str) -> Result<Ipv4, IpError> {
+        let error = || IpError::InvalidV4(String::from(s));
+        let mut octets = [0u8; 4];
+        let mut parts = s.split('.');
+        for octet in octets.iter_mut() {
+            let part = parts.next().ok_or_else(error)?;
+            let valid = !part.is_empty()
+                && part.len() <= 3
+                && part.bytes().all(|b| b.is_ascii_digit())
+                && !(part.len() > 1 && part.starts_with('0'));
+            if !valid {
+                return Err(error());
+            }
+            *octet = part.parse().map_err(|_| error())?;
+        }
+        if parts.next().is_some() {
+            return Err(error());
+        }
+        Ok(Ipv4(octets))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv6 {
+    segments: [u16; 8],
+    // The interface a link-local address belongs to, such as "eth0" in fe80::1%eth0.
+    zone: Option<String>,
+}
+
+impl Ipv6 {
+    pub const fn new(segments: [u16; 8]) -> Ipv6 {
+        Ipv6 {
+            segments,
+            zone: None,
+        }
+    }
+
+    pub fn with_zone(mut self, zone: &str) -> Result<Ipv6, IpError> {
+        if zone.is_empty() || zone.contains(|c: char| c.is_whitespace() || c == '/' || c == '%') {
+            return Err(IpError::InvalidZone(String::from(zone)));
+        }
+        self.zone = Some(String::from(zone));
+        Ok(self)
+    }
+
+    pub fn segments(&self) -> [u16; 8] {
+        self.segments
+    }
+
+    pub fn zone(&self) -> Option<&str> {
+        self.zone.as_deref()
+    }
+
+    // The IPv4 address inside an IPv4-mapped address like ::ffff:192.0.2.1.
+    pub fn to_ipv4_mapped(&self) -> Option<Ipv4> {
+        match self.segments {
+            [0, 0, 0, 0, 0, 0xffff, hi, lo] => Some(Ipv4::from((hi as u32) << 16 | lo as u32)),
+            _ => None,
+        }
+    }
+}
+
+impl From<u128> for Ipv6 {
+    fn from(bits: u128) -> Ipv6 {
+        let mut segments = [0u16; 8];
+        for (i, segment) in segments.iter_mut().enumerate() {
+            *segment = (bits >> (112 - 16 * i)) as u16;
+        }
+        Ipv6::new(segments)
+    }
+}
+
+impl From<&Ipv6> for u128 {
+    fn from(addr: &Ipv6) -> u128 {
+        addr.segments
+            .iter()
+            .fold(0, |bits, &segment| bits << 16 | segment as u128)
+    }
+}
+
+impl fmt::Display for Ipv6 {
+    // Follows RFC 5952: lowercase hex, no leading zeros, and the longest run of two or more
+    // zero groups (the first one on a tie) replaced by "::".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(v4) = self.to_ipv4_mapped() {
+            write!(f, "::ffff:{}", v4)?;
+        } else {
+            let mut longest = (0, 0);
+            let mut start = 0;
+            for i in 0..=8 {
+                if i < 8 && self.segments[i] == 0 {
+                    continue;
+                }
+                if i - start > longest.1 {
+                    longest = (start, i - start);
+                }
+                start = i + 1;
+            }
+
+            let groups = |range: &[u16]| -> String {
+                range
+                    .iter()
+                    .map(|s| format!("{:x}", s))
+                    .collect::<Vec<_>>()
+                    .join(":")
+            };
+            if longest.1 >= 2 {
+                let (at, len) = longest;
+                write!(
+                    f,
+                    "{}::{}",
+                    groups(&self.segments[..at]),
+                    groups(&self.segments[at + len..])
+                )?;
+            } else {
+                write!(f, "{}", groups(&self.segments))?;
+            }
+        }
+        if let Some(zone) = &self.zone {
+            write!(f, "%{}", zone)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Ipv6 {
+    type Err = IpError;
+
+    fn from_str(s: &str) -> Result<Ipv6, IpError> {
+        let error = || IpError::InvalidV6(String::from(s));
+        let (address, zone) = match s.split_once('%') {
+            Some((address, zone)) => (address, Some(zone)),
+            None => (s, None),
+        };
+
+        // Parses the groups on one side of "::". Only the very last group of the address may
+        // be an embedded IPv4 address, which takes up two groups.
+        let parse_groups = |part: &str, last: bool| -> Result<Vec<u16>, IpError> {
+            if part.is_empty() {
+                return Ok(Vec::new());
+            }
+            let pieces: Vec<&str> = part.split(':').collect();
+            let mut groups = Vec::new();
+            for (i, piece) in pieces.iter().enumerate() {
+                if last && i == pieces.len() - 1 && piece.contains('.') {
+                    let v4: u32 = piece.parse::<Ipv4>().map_err(|_| error())?.into();
+                    groups.push((v4 >> 16) as u16);
+                    groups.push(v4 as u16);
+                } else if (1..=4).contains(&piece.len())
+                    && piece.bytes().all(|b| b.is_ascii_hexdigit())
+                {
+                    groups.push(u16::from_str_radix(piece, 16).map_err(|_| error())?);
+                } else {
+                    return Err(error());
+                }
+            }
+            Ok(groups)
+        };
+
+        let segments: Vec<u16> = match address.split_once("::") {
+            Some((head, tail)) => {
+                if tail.contains("::") {
+                    return Err(error());
+                }
+                let head = parse_groups(head, false)?;
+                let tail = parse_groups(tail, true)?;
+                if head.len() + tail.len() > 7 {
+                    return Err(error());
+                }
+                let zeros = 8 - head.len() - tail.len();
+                head.into_iter()
+                    .chain(std::iter::repeat_n(0, zeros))
+                    .chain(tail)
+                    .collect()
+            }
+            None => parse_groups(address, true)?,
+        };
+
+        let segments: [u16; 8] = segments.try_into().map_err(|_| error())?;
+        match zone {
+            Some(zone) => Ipv6::new(segments).with_zone(zone),
+            None => Ok(Ipv6::new(segments)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IpAddrKind {
+    V4(Ipv4),
+    V6(Ipv6),
+}
+
+impl IpAddrKind {
+    pub fn family(&self) -> Family {
+        match self {
+            IpAddrKind::V4(_) => Family::V4,
+            IpAddrKind::V6(_) => Family::V6,
+        }
+    }
+
+    // The address as a number, in the low 32 bits for IPv4. Any zone ID is dropped.
+    pub fn to_bits(&self) -> u128 {
+        match self {
+            IpAddrKind::V4(v4) => u32::from(*v4) as u128,
+            IpAddrKind::V6(v6) => u128::from(v6),
+        }
+    }
+
+    // The address with the given bits; for IPv4 only the low 32 bits are used.
+    pub fn from_bits(family: Family, bits: u128) -> IpAddrKind {
+        match family {
+            Family::V4 => IpAddrKind::V4(Ipv4::from(bits as u32)),
+            Family::V6 => IpAddrKind::V6(Ipv6::from(bits)),
+        }
+    }
+}
+
+impl fmt::Display for IpAddrKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IpAddrKind::V4(v4) => v4.fmt(f),
+            IpAddrKind::V6(v6) => v6.fmt(f),
+        }
+    }
+}
+
+impl FromStr for IpAddrKind {
+    type Err = IpError;
+
+    fn from_str(s: &str) -> Result<IpAddrKind, IpError> {
+        if s.contains(':') {
+            s.parse().map(IpAddrKind::V6)
+        } else {
+            s.parse().map(IpAddrKind::V4)
+        }
+    }
+}
+
+impl From<Ipv4> for IpAddrKind {
+    fn from(v4: Ipv4) -> IpAddrKind {
+        IpAddrKind::V4(v4)
+    }
+}
+
+impl From<Ipv6> for IpAddrKind {
+    fn from(v6: Ipv6) -> IpAddrKind {
+        IpAddrKind::V6(v6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str) -> String {
+        s.parse::<IpAddrKind>().unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_and_formats_ipv4() {
+        assert_eq!(round_trip("192.168.0.1"), "192.168.0.1");
+        assert_eq!("10.0.0.255".parse::<Ipv4>().map(u32::from), Ok(0x0a00_00ff));
+        for bad in [
+            "256.0.0.1",
+            "1.2.3",
+            "1.2.3.4.5",
+            "01.2.3.4",
+            "1..3.4",
+            "a.b.c.d",
+            "",
+        ] {
+            assert!(bad.parse::<Ipv4>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn formats_ipv6_in_canonical_form() {
+        assert_eq!(
+            round_trip("2001:0DB8:0000:0000:0000:0000:0002:0001"),
+            "2001:db8::2:1"
+        );
+        assert_eq!(round_trip("::"), "::");
+        assert_eq!(round_trip("::1"), "::1");
+        assert_eq!(round_trip("1::"), "1::");
+        // A single zero group isn't compressed, and the first of two equal runs is.
+        assert_eq!(round_trip("2001:db8:0:1:1:1:1:1"), "2001:db8:0:1:1:1:1:1");
+        assert_eq!(round_trip("1:0:0:2:0:0:3:4"), "1::2:0:0:3:4");
+        assert_eq!(round_trip("1:0:0:2:0:0:0:4"), "1:0:0:2::4");
+    }
+
+    #[test]
+    fn parses_embedded_ipv4_and_zones() {
+        assert_eq!(round_trip("::ffff:192.0.2.128"), "::ffff:192.0.2.128");
+        assert_eq!(round_trip("64:ff9b::192.0.2.33"), "64:ff9b::c000:221");
+        assert_eq!(round_trip("fe80::1%eth0"), "fe80::1%eth0");
+
+        let addr: Ipv6 = "fe80::abcd%en0".parse().unwrap();
+        assert_eq!(addr.zone(), Some("en0"));
+    }
+
+    #[test]
+    fn rejects_malformed_ipv6() {
+        for bad in [
+            "1:2:3:4:5:6:7",
+            "1:2:3:4:5:6:7:8:9",
+            "1::2::3",
+            "12345::",
+            "1:2:3:4:5:6:7::8",
+            "::1.2.3.4:5",
+            "fe80::1%",
+            "g::1",
+        ] {
+            assert!(bad.parse::<Ipv6>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let addr: IpAddrKind = "2001:db8::ff00:42:8329".parse().unwrap();
+
+        assert_eq!(IpAddrKind::from_bits(Family::V6, addr.to_bits()), addr);
+        assert!(IpAddrKind::V4(Ipv4::new(10, 0, 0, 1)) < addr);
+    }
+}
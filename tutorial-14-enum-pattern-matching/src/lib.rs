@@ -0,0 +1,11 @@
+// The types from main.rs grown into modules that other programs can use.
+
+pub mod coin;
+pub mod ip;
+pub mod message;
+// Public only so the benchmarks, which are a crate of their own, can share it with the tests.
+// It's not part of the supported API.
+#[doc(hidden)]
+pub mod random;
+pub mod routing;
+pub mod vending;
@@ -1,39 +1,32 @@
-enum IpAddrKind {
-    V4(u8, u8, u8, u8),
-    V6(String),
-}
-
-enum Message {
-    Quit,
-    Move { x: i32, y: i32 },
-    Write(String),
-    ChangeColor(i32, i32, i32),
-}
-
-enum Coin {
-    Penny,
-    Nickel,
-    Dime,
-    Quarter,
-}
-
-impl Message {
-    fn call(&self) {
-        // method body would be defined here
-        println!("Message called");
-    }
-}
-
-struct IpAddr {
-    kind: IpAddrKind,
-    address: String,
-}
+use tutorial_14_enum_pattern_matching::coin::{CashDrawer, Coin};
+use tutorial_14_enum_pattern_matching::ip::{Cidr, IpAddrKind, Ipv4};
+use tutorial_14_enum_pattern_matching::message::{Decoder, Format, Message, Session};
 
 fn main() {
     let four = IpAddrKind::V4;
     let six = IpAddrKind::V6;
 
-    let localhost = IpAddrKind::V4(127, 0, 0, 1);
+    let localhost = IpAddrKind::V4(Ipv4::new(127, 0, 0, 1));
+
+    // The address is validated when it is parsed, so it no longer needs to be kept as a string
+    // next to its kind
+    let router: IpAddrKind = "fe80::1%eth0".parse().expect("valid address");
+    let loopback: Cidr = "127.0.0.0/8".parse().expect("valid block");
+    println!(
+        "{} is loopback: {}",
+        localhost,
+        loopback.contains(&localhost)
+    );
+    println!("{} is loopback: {}", router, loopback.contains(&router));
+
+    // Messages can be encoded to bytes and decoded back on the other side
+    let message = Message::Move { x: 3, y: -4 };
+    let bytes = Format::Json.encode(&message).expect("small message");
+    let mut session = Session::new(1);
+    for received in Decoder::new(&bytes[..], Format::Json).flatten() {
+        session.apply(&received);
+    }
+    println!("moved to {:?}", session.position());
 
     // Option<T> enum
     let x: i8 = 5;
@@ -44,8 +37,17 @@ fn main() {
     // default value can be provided using unwrap_or(), in this case 0
     let sum = x + y.unwrap_or(0);
 
-    // match
-    value_in_cents(Coin::Penny);
+    // match: Coin::cents matches on the coin to give its value
+    println!("a penny is worth {} cent", Coin::Penny.cents());
+
+    // A drawer hands back the fewest coins it can from what it holds
+    let mut drawer = CashDrawer::new()
+        .with(Coin::Quarter, 2)
+        .with(Coin::Dime, 4)
+        .with(Coin::Penny, 10);
+    if let Ok(change) = drawer.pay_out(43) {
+        println!("change for 43 cents: {:?}", change);
+    }
 
     let five = Some(5);
     let six = plus_one(five);
@@ -59,19 +61,6 @@ fn main() {
     }
 }
 
-// match
-fn value_in_cents(coin: Coin) -> u8 {
-    match coin {
-        Coin::Penny => {
-            println!("Lucky penny!");
-            1
-        }
-        Coin::Nickel => 5,
-        Coin::Dime => 10,
-        Coin::Quarter => 25,
-    }
-}
-
 fn plus_one(x: Option<i8>) -> Option<i8> {
     match x {
         // Some(i) pattern will match any Some value, binding the inner value to i
@@ -0,0 +1,38 @@
+// The other end of a `Server` connection.
+
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+
+use super::{CodecError, Decoder, Format, Message};
+
+#[derive(Debug)]
+pub struct Client {
+    stream: TcpStream,
+    format: Format,
+}
+
+impl Client {
+    // `format` has to match the server's.
+    pub fn connect<A: ToSocketAddrs>(addr: A, format: Format) -> Result<Client, CodecError> {
+        let stream = TcpStream::connect(addr)?;
+        // Messages are small and sent one at a time, so don't hold them back to batch them.
+        stream.set_nodelay(true)?;
+        Ok(Client { stream, format })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), CodecError> {
+        self.format.write(&mut self.stream, message)
+    }
+
+    // Ends the session and waits for the server to confirm it, at which point every message
+    // sent before has been handled.
+    pub fn quit(mut self) -> Result<(), CodecError> {
+        self.send(&Message::Quit)?;
+        self.stream.shutdown(Shutdown::Write)?;
+        for reply in Decoder::new(&self.stream, self.format) {
+            if reply? == Message::Quit {
+                return Ok(());
+            }
+        }
+        Err(CodecError::Truncated)
+    }
+}
@@ -0,0 +1,484 @@
+// Two ways to put a `Message` on the wire.
+//
+// Binary frames are a 4-byte big-endian length followed by that many bytes: a version byte, a
+// tag byte naming the variant, then its fields as big-endian i32s or, for `Write`, the UTF-8
+// text. JSON messages are one object per line with the version in "v", so
+// {"v":1,"type":"move","data":{"x":1,"y":2}}.
+//
+// Tags are never reused: a new variant gets a new tag and a changed layout gets a new version,
+// so an old reader rejects what it doesn't understand instead of misreading it.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::Message;
+
+pub const VERSION: u8 = 1;
+
+// Larger frames or lines are refused, so a corrupt length can't make the reader allocate
+// gigabytes.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+const QUIT: u8 = 0;
+const MOVE: u8 = 1;
+const WRITE: u8 = 2;
+const CHANGE_COLOR: u8 = 3;
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    // The stream ended part way through a message.
+    Truncated,
+    FrameTooLarge(usize),
+    UnsupportedVersion(u8),
+    UnknownTag(u8),
+    // A frame or line with the right version that doesn't hold a valid message.
+    Malformed(String),
+}
+
+impl CodecError {
+    // Whether reading can go on after this error. A bad message is skipped whole, but after an
+    // I/O error or an oversized frame there is no telling where the next message starts.
+    fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            CodecError::UnsupportedVersion(_)
+                | CodecError::UnknownTag(_)
+                | CodecError::Malformed(_)
+        )
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "{}", e),
+            CodecError::Truncated => write!(f, "stream ended in the middle of a message"),
+            CodecError::FrameTooLarge(len) => {
+                write!(
+                    f,
+                    "{} byte message is over the {} byte limit",
+                    len, MAX_FRAME_LEN
+                )
+            }
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported message version {}", v),
+            CodecError::UnknownTag(tag) => write!(f, "unknown message tag {}", tag),
+            CodecError::Malformed(reason) => write!(f, "malformed message: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<io::Error> for CodecError {
+    fn from(e: io::Error) -> CodecError {
+        CodecError::Io(e)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Envelope<M> {
+    v: u8,
+    #[serde(flatten)]
+    message: M,
+}
+
+// Read on its own first, so a message from a newer version is reported as such rather than as
+// malformed.
+#[derive(serde::Deserialize)]
+struct Header {
+    v: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Json,
+}
+
+impl Format {
+    pub fn encode(self, message: &Message) -> Result<Vec<u8>, CodecError> {
+        let bytes = match self {
+            Format::Binary => {
+                let mut body = vec![VERSION];
+                match message {
+                    Message::Quit => body.push(QUIT),
+                    Message::Move { x, y } => {
+                        body.push(MOVE);
+                        body.extend_from_slice(&x.to_be_bytes());
+                        body.extend_from_slice(&y.to_be_bytes());
+                    }
+                    Message::Write(text) => {
+                        body.push(WRITE);
+                        body.extend_from_slice(text.as_bytes());
+                    }
+                    Message::ChangeColor(r, g, b) => {
+                        body.push(CHANGE_COLOR);
+                        for channel in [r, g, b] {
+                            body.extend_from_slice(&channel.to_be_bytes());
+                        }
+                    }
+                }
+                if body.len() > MAX_FRAME_LEN {
+                    return Err(CodecError::FrameTooLarge(body.len()));
+                }
+                let mut frame = (body.len() as u32).to_be_bytes().to_vec();
+                frame.append(&mut body);
+                frame
+            }
+            Format::Json => {
+                let envelope = Envelope {
+                    v: VERSION,
+                    message,
+                };
+                let mut line = serde_json::to_vec(&envelope)
+                    .map_err(|e| CodecError::Malformed(e.to_string()))?;
+                if line.len() > MAX_FRAME_LEN {
+                    return Err(CodecError::FrameTooLarge(line.len()));
+                }
+                line.push(b'\n');
+                line
+            }
+        };
+        Ok(bytes)
+    }
+
+    pub fn write<W: Write>(self, writer: &mut W, message: &Message) -> Result<(), CodecError> {
+        writer.write_all(&self.encode(message)?)?;
+        Ok(())
+    }
+
+    // Takes the first whole message off the front of `buffer`, or returns None if more bytes
+    // are needed. A message that fails to decode is still removed.
+    fn decode(self, buffer: &mut Vec<u8>) -> Result<Option<Message>, CodecError> {
+        match self {
+            Format::Binary => {
+                let Some(header) = buffer.first_chunk::<4>() else {
+                    return Ok(None);
+                };
+                let len = u32::from_be_bytes(*header) as usize;
+                if len > MAX_FRAME_LEN {
+                    return Err(CodecError::FrameTooLarge(len));
+                }
+                if buffer.len() < 4 + len {
+                    return Ok(None);
+                }
+                let body: Vec<u8> = buffer.drain(..4 + len).skip(4).collect();
+                decode_binary(&body).map(Some)
+            }
+            Format::Json => loop {
+                let Some(end) = buffer.iter().position(|&b| b == b'\n') else {
+                    if buffer.len() > MAX_FRAME_LEN {
+                        return Err(CodecError::FrameTooLarge(buffer.len()));
+                    }
+                    return Ok(None);
+                };
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if !line.trim_ascii().is_empty() {
+                    return decode_json(&line).map(Some);
+                }
+            },
+        }
+    }
+
+    // Called once the stream has ended with `buffer` still holding bytes. The last JSON line
+    // doesn't need a newline, but a binary frame has to be complete.
+    fn finish(self, buffer: &mut Vec<u8>) -> Result<Option<Message>, CodecError> {
+        let rest = std::mem::take(buffer);
+        match self {
+            Format::Json if rest.trim_ascii().is_empty() => Ok(None),
+            Format::Json => decode_json(&rest).map(Some),
+            Format::Binary => Err(CodecError::Truncated),
+        }
+    }
+}
+
+fn decode_binary(body: &[u8]) -> Result<Message, CodecError> {
+    let malformed = |reason: &str| CodecError::Malformed(String::from(reason));
+    let (&version, rest) = body.split_first().ok_or_else(|| malformed("empty frame"))?;
+    if version != VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    let (&tag, payload) = rest.split_first().ok_or_else(|| malformed("missing tag"))?;
+
+    let ints = |count: usize| -> Result<Vec<i32>, CodecError> {
+        if payload.len() != 4 * count {
+            return Err(malformed("wrong payload length"));
+        }
+        Ok(payload
+            .chunks_exact(4)
+            .map(|chunk| i32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect())
+    };
+    match tag {
+        QUIT => ints(0).map(|_| Message::Quit),
+        MOVE => ints(2).map(|v| Message::Move { x: v[0], y: v[1] }),
+        WRITE => String::from_utf8(payload.to_vec())
+            .map(Message::Write)
+            .map_err(|_| malformed("text is not UTF-8")),
+        CHANGE_COLOR => ints(3).map(|v| Message::ChangeColor(v[0], v[1], v[2])),
+        _ => Err(CodecError::UnknownTag(tag)),
+    }
+}
+
+fn decode_json(line: &[u8]) -> Result<Message, CodecError> {
+    let malformed = |e: serde_json::Error| CodecError::Malformed(e.to_string());
+    let header: Header = serde_json::from_slice(line).map_err(malformed)?;
+    if header.v != VERSION {
+        return Err(CodecError::UnsupportedVersion(header.v));
+    }
+    let envelope: Envelope<Message> = serde_json::from_slice(line).map_err(malformed)?;
+    Ok(envelope.message)
+}
+
+// Reads messages from a stream however its bytes arrive, buffering partial messages across
+// reads.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    reader: R,
+    format: Format,
+    buffer: Vec<u8>,
+    // Set after an error that leaves the stream unreadable, to end iteration.
+    failed: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, format: Format) -> Decoder<R> {
+        Decoder {
+            reader,
+            format,
+            buffer: Vec::new(),
+            failed: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    // The next message, or None once the stream ends cleanly between messages.
+    pub fn read_message(&mut self) -> Result<Option<Message>, CodecError> {
+        let result = self.fill_and_decode();
+        if let Err(e) = &result {
+            self.failed = !e.is_recoverable();
+        }
+        result
+    }
+
+    fn fill_and_decode(&mut self) -> Result<Option<Message>, CodecError> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(message) = self.format.decode(&mut self.buffer)? {
+                return Ok(Some(message));
+            }
+            match self.reader.read(&mut chunk) {
+                Ok(0) if self.buffer.is_empty() => return Ok(None),
+                Ok(0) => return self.format.finish(&mut self.buffer),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+// Yields each message or the error decoding it, and stops at the end of the stream or after
+// an error that can't be skipped.
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<Message, CodecError>;
+
+    fn next(&mut self) -> Option<Result<Message, CodecError>> {
+        if self.failed {
+            return None;
+        }
+        self.read_message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    fn int(random: &mut Random) -> i32 {
+        match random.next_u64() % 4 {
+            0 => i32::MIN,
+            1 => i32::MAX,
+            _ => random.next_u64() as i32,
+        }
+    }
+
+    fn message(random: &mut Random) -> Message {
+        match random.next_u64() % 4 {
+            0 => Message::Quit,
+            1 => Message::Move {
+                x: int(random),
+                y: int(random),
+            },
+            2 => {
+                let pieces = [
+                    "", "a", "hello", "\n", "\"{}\"", "é", "日本", "🦀", "\\", "\0",
+                ];
+                let len = random.next_u64() % 12;
+                Message::Write(
+                    (0..len)
+                        .map(|_| pieces[random.next_u64() as usize % pieces.len()])
+                        .collect(),
+                )
+            }
+            _ => Message::ChangeColor(int(random), int(random), int(random)),
+        }
+    }
+
+    // Hands out its bytes a few at a time, with the odd interrupted read, like a socket.
+    struct Trickle {
+        bytes: Vec<u8>,
+        at: usize,
+        random: Random,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.random.next_u64().is_multiple_of(10) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let len = (1 + self.random.next_u64() as usize % 7)
+                .min(buf.len())
+                .min(self.bytes.len() - self.at);
+            buf[..len].copy_from_slice(&self.bytes[self.at..self.at + len]);
+            self.at += len;
+            Ok(len)
+        }
+    }
+
+    fn decode_all(bytes: &[u8], format: Format) -> Vec<Result<Message, String>> {
+        Decoder::new(bytes, format)
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn encodes_the_documented_layouts() {
+        let message = Message::Move { x: 1, y: -2 };
+
+        assert_eq!(
+            Format::Binary.encode(&message).unwrap(),
+            [0, 0, 0, 10, 1, 1, 0, 0, 0, 1, 255, 255, 255, 254]
+        );
+        assert_eq!(
+            Format::Json.encode(&message).unwrap(),
+            b"{\"v\":1,\"type\":\"move\",\"data\":{\"x\":1,\"y\":-2}}\n"
+        );
+        assert_eq!(
+            Format::Json.encode(&Message::Quit).unwrap(),
+            b"{\"v\":1,\"type\":\"quit\"}\n"
+        );
+        assert_eq!(
+            Format::Json.encode(&Message::ChangeColor(1, 2, 3)).unwrap(),
+            b"{\"v\":1,\"type\":\"change_color\",\"data\":[1,2,3]}\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_random_messages_through_partial_reads() {
+        let mut random = Random::new(0x853c_49e6_748f_ea9b);
+        for format in [Format::Binary, Format::Json] {
+            for _ in 0..50 {
+                let messages: Vec<Message> = (0..random.next_u64() % 20)
+                    .map(|_| message(&mut random))
+                    .collect();
+                let mut bytes = Vec::new();
+                for message in &messages {
+                    format.write(&mut bytes, message).unwrap();
+                }
+                let reader = Trickle {
+                    bytes,
+                    at: 0,
+                    random: Random::new(random.next_u64()),
+                };
+
+                let decoded: Vec<Message> = Decoder::new(reader, format)
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                assert_eq!(decoded, messages, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn skips_messages_it_cannot_read() {
+        let mut bytes = Format::Binary.encode(&Message::Quit).unwrap();
+        bytes.extend_from_slice(&[0, 0, 0, 2, 2, 0]);
+        bytes.extend_from_slice(&[0, 0, 0, 2, 1, 9]);
+        bytes.extend_from_slice(&[0, 0, 0, 3, 1, 2, 0xff]);
+        bytes.extend_from_slice(&[0, 0, 0, 3, 1, 1, 0]);
+        bytes.extend(Format::Binary.encode(&Message::Write("ok".into())).unwrap());
+
+        assert_eq!(
+            decode_all(&bytes, Format::Binary),
+            [
+                Ok(Message::Quit),
+                Err(String::from("unsupported message version 2")),
+                Err(String::from("unknown message tag 9")),
+                Err(String::from("malformed message: text is not UTF-8")),
+                Err(String::from("malformed message: wrong payload length")),
+                Ok(Message::Write("ok".into())),
+            ]
+        );
+
+        let lines = "{\"v\":2,\"type\":\"teleport\"}\n\n{\"v\":1,\"type\":\"jump\"}\n{\"v\":1,\"type\":\"quit\"}";
+        let decoded = decode_all(lines.as_bytes(), Format::Json);
+        assert_eq!(
+            decoded[0],
+            Err(String::from("unsupported message version 2"))
+        );
+        assert!(decoded[1].is_err());
+        assert_eq!(decoded[2], Ok(Message::Quit));
+        assert_eq!(decoded.len(), 3);
+    }
+
+    #[test]
+    fn stops_at_truncated_and_oversized_frames() {
+        let bytes = Format::Binary
+            .encode(&Message::Move { x: 1, y: 2 })
+            .unwrap();
+        let mut decoder = Decoder::new(&bytes[..bytes.len() - 1], Format::Binary);
+        assert!(matches!(decoder.next(), Some(Err(CodecError::Truncated))));
+        assert!(decoder.next().is_none());
+
+        let mut decoder = Decoder::new(&[0xff, 0xff, 0xff, 0xff, 1][..], Format::Binary);
+        assert!(matches!(
+            decoder.next(),
+            Some(Err(CodecError::FrameTooLarge(_)))
+        ));
+        assert!(decoder.next().is_none());
+
+        let text = "x".repeat(MAX_FRAME_LEN);
+        assert!(Format::Binary.encode(&Message::Write(text)).is_err());
+    }
+
+    // Whatever the bytes, decoding ends without panicking.
+    #[test]
+    fn survives_corrupted_streams() {
+        let mut random = Random::new(0xda3e_39cb_94b9_5bdb);
+        for format in [Format::Binary, Format::Json] {
+            for _ in 0..200 {
+                let mut bytes = Vec::new();
+                for _ in 0..5 {
+                    format.write(&mut bytes, &message(&mut random)).unwrap();
+                }
+                for _ in 0..1 + random.next_u64() % 4 {
+                    let at = random.next_u64() as usize % bytes.len();
+                    bytes[at] = random.next_u64() as u8;
+                }
+                for result in Decoder::new(&bytes[..], format) {
+                    let _ = result;
+                }
+            }
+        }
+    }
+}
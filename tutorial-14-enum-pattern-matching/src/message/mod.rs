@@ -0,0 +1,21 @@
+// The `Message` enum from main.rs, with encodings for sending it between processes and a TCP
+// server and client that use them.
+
+mod client;
+mod codec;
+mod server;
+
+pub use self::client::Client;
+pub use self::codec::{CodecError, Decoder, Format, MAX_FRAME_LEN, VERSION};
+pub use self::server::{Handler, Server, ServerHandle, Session, DEFAULT_IDLE_TIMEOUT};
+
+// In JSON the variant name goes in "type" and its fields in "data", so a `Move` is
+// {"type":"move","data":{"x":1,"y":2}}.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Message {
+    Quit,
+    Move { x: i32, y: i32 },
+    Write(String),
+    ChangeColor(i32, i32, i32),
+}
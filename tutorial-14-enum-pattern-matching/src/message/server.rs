@@ -0,0 +1,336 @@
+// Serves framed messages over TCP, one thread and one `Session` per connection.
+//
+// A connection is a stream of messages in the server's format. Each one updates the session and
+// is then passed to the `Handler`. `Quit` ends the session: the server answers with a `Quit` of
+// its own and closes its side, so the client knows every earlier message was handled.
+
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{CodecError, Decoder, Format, Message};
+
+// How long a connection can go without sending anything before it's closed, unless set with
+// `Server::with_idle_timeout`.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+// How often the accept loop checks for a shutdown while nobody connects, and how long it waits
+// after failing to accept, so errors like running out of file descriptors don't spin a core.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// What the server knows about one connection, built up from its messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    id: u64,
+    position: (i32, i32),
+    color: (i32, i32, i32),
+    log: Vec<String>,
+}
+
+impl Session {
+    pub fn new(id: u64) -> Session {
+        Session {
+            id,
+            position: (0, 0),
+            color: (0, 0, 0),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Where the last `Move` went.
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    pub fn color(&self) -> (i32, i32, i32) {
+        self.color
+    }
+
+    // The text of every `Write`, oldest first.
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+
+    // Updates the session for `message`. Returns false for `Quit`, after which the session is
+    // over.
+    pub fn apply(&mut self, message: &Message) -> bool {
+        match message {
+            Message::Quit => return false,
+            Message::Move { x, y } => self.position = (*x, *y),
+            Message::Write(text) => self.log.push(text.clone()),
+            Message::ChangeColor(r, g, b) => self.color = (*r, *g, *b),
+        }
+        true
+    }
+}
+
+// Called from every connection's thread, so implementations need to be thread-safe.
+pub trait Handler: Send + Sync + 'static {
+    // Called for each message after `session` has been updated for it.
+    fn handle(&self, session: &Session, message: &Message);
+
+    // Called for a message that couldn't be decoded. The connection carries on with the next
+    // one.
+    fn rejected(&self, _session: &Session, _error: &CodecError) {}
+
+    // Called once when the connection ends, whether by `Quit` or not.
+    fn closed(&self, _session: &Session) {}
+}
+
+pub struct Server {
+    listener: TcpListener,
+    format: Format,
+    handler: Arc<dyn Handler>,
+    idle_timeout: Duration,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(
+        addr: A,
+        format: Format,
+        handler: impl Handler,
+    ) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            format,
+            handler: Arc::new(handler),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        })
+    }
+
+    // A zero timeout is taken as the shortest there is, since a socket can't have one.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Server {
+        self.idle_timeout = timeout.max(Duration::from_millis(1));
+        self
+    }
+
+    // Useful after binding to port 0 to find out which port was picked.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accepts connections until the process exits.
+    pub fn run(self) -> io::Result<()> {
+        self.accept(&AtomicBool::new(false))
+    }
+
+    // Accepts connections on a background thread until the handle is shut down.
+    pub fn spawn(self) -> io::Result<ServerHandle> {
+        let addr = self.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || self.accept(&stop))
+        };
+        Ok(ServerHandle { addr, stop, thread })
+    }
+
+    fn accept(&self, stop: &AtomicBool) -> io::Result<()> {
+        // Never blocking, so the loop sees `stop` without needing a connection to wake it.
+        self.listener.set_nonblocking(true)?;
+        let mut next_id = 0;
+        while !stop.load(Ordering::SeqCst) {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // Nobody waiting to connect, or a failure that only affects that client or will
+                // take time to clear.
+                Err(_) => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+            };
+            // Some platforms pass the listener's non-blocking mode on. A connection that can't
+            // be set up only affects that client.
+            if stream.set_nonblocking(false).is_err()
+                || stream.set_read_timeout(Some(self.idle_timeout)).is_err()
+            {
+                continue;
+            }
+            next_id += 1;
+            let session = Session::new(next_id);
+            let format = self.format;
+            let handler = Arc::clone(&self.handler);
+            thread::spawn(move || serve(stream, session, format, &*handler));
+        }
+        Ok(())
+    }
+}
+
+fn serve(stream: TcpStream, mut session: Session, format: Format, handler: &dyn Handler) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => {
+            handler.closed(&session);
+            return;
+        }
+    };
+
+    let mut quit = false;
+    for message in Decoder::new(stream, format) {
+        match message {
+            Ok(message) => {
+                quit = !session.apply(&message);
+                handler.handle(&session, &message);
+                if quit {
+                    break;
+                }
+            }
+            Err(e) => handler.rejected(&session, &e),
+        }
+    }
+    handler.closed(&session);
+
+    // The reply comes last so that a client waiting for it sees the session fully handled.
+    // If the client has already gone there is nobody left to tell.
+    if quit {
+        let _ = format.write(&mut writer, &Message::Quit);
+    }
+    let _ = writer.shutdown(Shutdown::Both);
+}
+
+// A server running on a background thread, from `Server::spawn`.
+#[derive(Debug)]
+pub struct ServerHandle {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<io::Result<()>>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Stops accepting connections. Sessions already open run until their clients finish or go
+    // idle.
+    pub fn shutdown(self) -> io::Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.thread
+            .join()
+            .map_err(|_| io::Error::other("server thread panicked"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::super::Client;
+    use super::*;
+
+    // Sends every closed session, and why each rejected message was rejected, back to the test.
+    struct Recorder {
+        closed: Mutex<Sender<Session>>,
+        rejected: Mutex<Sender<String>>,
+    }
+
+    fn start(format: Format) -> (ServerHandle, Receiver<Session>, Receiver<String>) {
+        start_with_timeout(format, DEFAULT_IDLE_TIMEOUT)
+    }
+
+    fn start_with_timeout(
+        format: Format,
+        idle_timeout: Duration,
+    ) -> (ServerHandle, Receiver<Session>, Receiver<String>) {
+        let (closed, closed_rx) = mpsc::channel();
+        let (rejected, rejected_rx) = mpsc::channel();
+        let recorder = Recorder {
+            closed: Mutex::new(closed),
+            rejected: Mutex::new(rejected),
+        };
+        let server = Server::bind("127.0.0.1:0", format, recorder)
+            .unwrap()
+            .with_idle_timeout(idle_timeout);
+        (server.spawn().unwrap(), closed_rx, rejected_rx)
+    }
+
+    impl Handler for Recorder {
+        fn handle(&self, _session: &Session, _message: &Message) {}
+
+        fn rejected(&self, _session: &Session, error: &CodecError) {
+            self.rejected
+                .lock()
+                .unwrap()
+                .send(error.to_string())
+                .unwrap();
+        }
+
+        fn closed(&self, session: &Session) {
+            self.closed.lock().unwrap().send(session.clone()).unwrap();
+        }
+    }
+
+    fn wait<T>(rx: &Receiver<T>) -> T {
+        rx.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn keeps_state_per_connection() {
+        for format in [Format::Binary, Format::Json] {
+            let (server, closed, _) = start(format);
+            let mut first = Client::connect(server.local_addr(), format).unwrap();
+            let mut second = Client::connect(server.local_addr(), format).unwrap();
+
+            first.send(&Message::Move { x: 1, y: 2 }).unwrap();
+            second.send(&Message::Write("hello".into())).unwrap();
+            first.send(&Message::ChangeColor(255, 0, 128)).unwrap();
+            first.send(&Message::Write("one".into())).unwrap();
+            second.send(&Message::Move { x: -5, y: 5 }).unwrap();
+            first.send(&Message::Write("two".into())).unwrap();
+
+            first.quit().unwrap();
+            let session = wait(&closed);
+            assert_eq!(session.position(), (1, 2));
+            assert_eq!(session.color(), (255, 0, 128));
+            assert_eq!(session.log(), ["one", "two"]);
+
+            second.quit().unwrap();
+            let session = wait(&closed);
+            assert_eq!(session.position(), (-5, 5));
+            assert_eq!(session.log(), ["hello"]);
+
+            server.shutdown().unwrap();
+        }
+    }
+
+    #[test]
+    fn skips_bad_messages_and_notices_dropped_clients() {
+        let (server, closed, rejected) = start(Format::Binary);
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+
+        stream.write_all(&[0, 0, 0, 2, 1, 42]).unwrap();
+        Format::Binary
+            .write(&mut stream, &Message::Move { x: 7, y: 8 })
+            .unwrap();
+        drop(stream);
+
+        assert_eq!(wait(&rejected), "unknown message tag 42");
+        let session = wait(&closed);
+        assert_eq!(session.position(), (7, 8));
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn closes_idle_connections() {
+        let (server, closed, rejected) =
+            start_with_timeout(Format::Binary, Duration::from_millis(100));
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+
+        // The read times out, which ends the session; the error's wording depends on the OS.
+        wait(&rejected);
+        assert_eq!(wait(&closed).id(), 1);
+        drop(stream);
+        server.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+// A small xorshift generator for the tests and benchmarks, so they see the same numbers every
+// run and failures reproduce, without a dependency on rand. Not for anything that needs to be
+// unpredictable.
+
+#[derive(Debug, Clone)]
+pub struct Random(u64);
+
+impl Random {
+    // Xorshift never leaves zero, so a zero seed is swapped for another.
+    pub fn new(seed: u64) -> Random {
+        Random(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
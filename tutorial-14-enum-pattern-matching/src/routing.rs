@@ -0,0 +1,431 @@
+// A routing table that finds the most specific prefix covering an address.
+//
+// Each family has its own Patricia trie: a binary trie where runs of nodes with a single child
+// are collapsed, so a lookup visits at most one node per distinct prefix length on its path
+// rather than one per bit. Keys are kept left-aligned in a u128, so IPv4 prefixes use the top
+// 32 bits and both families share the same code.
+
+use crate::ip::{Cidr, Family, IpAddrKind};
+
+#[derive(Debug, Clone)]
+struct Node<V> {
+    // Left-aligned prefix with every bit after `len` cleared.
+    key: u128,
+    len: u8,
+    // None for the forks that only exist to join two longer prefixes.
+    value: Option<V>,
+    children: [Option<Box<Node<V>>>; 2],
+}
+
+impl<V> Node<V> {
+    fn new(key: u128, len: u8, value: Option<V>) -> Box<Node<V>> {
+        Box::new(Node {
+            key,
+            len,
+            value,
+            children: [None, None],
+        })
+    }
+}
+
+// Bits from the left that `a` and `b` share, at most `max`.
+fn common_len(a: u128, b: u128, max: u8) -> u8 {
+    ((a ^ b).leading_zeros() as u8).min(max)
+}
+
+// The bit of `key` after the first `len` bits, which picks the child to follow.
+fn branch(key: u128, len: u8) -> usize {
+    (key >> (127 - len as u32)) as usize & 1
+}
+
+fn mask(len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - len as u32).unwrap_or(0)
+}
+
+fn align(family: Family, bits: u128) -> u128 {
+    bits << (128 - family.width() as u32)
+}
+
+fn unalign(family: Family, key: u128) -> u128 {
+    key >> (128 - family.width() as u32)
+}
+
+#[derive(Debug, Clone)]
+pub struct RoutingTable<V> {
+    v4: Option<Box<Node<V>>>,
+    v6: Option<Box<Node<V>>>,
+    len: usize,
+}
+
+impl<V> Default for RoutingTable<V> {
+    fn default() -> Self {
+        RoutingTable::new()
+    }
+}
+
+impl<V> RoutingTable<V> {
+    pub fn new() -> RoutingTable<V> {
+        RoutingTable {
+            v4: None,
+            v6: None,
+            len: 0,
+        }
+    }
+
+    // Number of routes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn root(&self, family: Family) -> &Option<Box<Node<V>>> {
+        match family {
+            Family::V4 => &self.v4,
+            Family::V6 => &self.v6,
+        }
+    }
+
+    fn root_mut(&mut self, family: Family) -> &mut Option<Box<Node<V>>> {
+        match family {
+            Family::V4 => &mut self.v4,
+            Family::V6 => &mut self.v6,
+        }
+    }
+
+    // Adds a route, returning the value it replaced if the prefix was already present.
+    pub fn insert(&mut self, prefix: Cidr, value: V) -> Option<V> {
+        let key = align(prefix.family(), prefix.to_bits());
+        let replaced = insert(
+            self.root_mut(prefix.family()),
+            key,
+            prefix.prefix_len(),
+            value,
+        );
+        if replaced.is_none() {
+            self.len += 1;
+        }
+        replaced
+    }
+
+    pub fn remove(&mut self, prefix: &Cidr) -> Option<V> {
+        let key = align(prefix.family(), prefix.to_bits());
+        let removed = remove(self.root_mut(prefix.family()), key, prefix.prefix_len());
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    // The value for exactly this prefix, without falling back to shorter ones.
+    pub fn get(&self, prefix: &Cidr) -> Option<&V> {
+        let key = align(prefix.family(), prefix.to_bits());
+        let len = prefix.prefix_len();
+        let mut current = self.root(prefix.family()).as_deref();
+        while let Some(node) = current {
+            if node.len > len || common_len(node.key, key, node.len) < node.len {
+                return None;
+            }
+            if node.len == len {
+                return node.value.as_ref();
+            }
+            current = node.children[branch(key, node.len)].as_deref();
+        }
+        None
+    }
+
+    // The longest prefix containing `addr` and its value.
+    pub fn lookup(&self, addr: &IpAddrKind) -> Option<(Cidr, &V)> {
+        let family = addr.family();
+        let key = align(family, addr.to_bits());
+        let mut best = None;
+        let mut current = self.root(family).as_deref();
+        while let Some(node) = current {
+            if (node.key ^ key) & mask(node.len) != 0 {
+                break;
+            }
+            if let Some(value) = &node.value {
+                best = Some((node, value));
+            }
+            if node.len == family.width() {
+                break;
+            }
+            current = node.children[branch(key, node.len)].as_deref();
+        }
+        best.map(|(node, value)| {
+            let prefix = Cidr::from_parts(family, unalign(family, node.key), node.len);
+            (prefix, value)
+        })
+    }
+
+    // Routes in the same order as sorting their prefixes: IPv4 first, then by network address,
+    // shorter prefixes before the longer ones inside them.
+    pub fn iter(&self) -> Iter<'_, V> {
+        let mut stack = Vec::new();
+        if let Some(root) = &self.v6 {
+            stack.push((Family::V6, &**root));
+        }
+        if let Some(root) = &self.v4 {
+            stack.push((Family::V4, &**root));
+        }
+        Iter { stack }
+    }
+}
+
+fn insert<V>(slot: &mut Option<Box<Node<V>>>, key: u128, len: u8, value: V) -> Option<V> {
+    let node = match slot {
+        Some(node) => node,
+        None => {
+            *slot = Some(Node::new(key, len, Some(value)));
+            return None;
+        }
+    };
+
+    let common = common_len(node.key, key, node.len.min(len));
+    if common == node.len {
+        if len == node.len {
+            return node.value.replace(value);
+        }
+        return insert(&mut node.children[branch(key, node.len)], key, len, value);
+    }
+
+    // The new prefix diverges from this node part way along, so either it sits above the node
+    // or a fork is needed where they split.
+    let old = slot.take().unwrap();
+    let mut parent = if common == len {
+        Node::new(key, len, Some(value))
+    } else {
+        let mut fork = Node::new(key & mask(common), common, None);
+        fork.children[branch(key, common)] = Some(Node::new(key, len, Some(value)));
+        fork
+    };
+    let side = branch(old.key, common);
+    parent.children[side] = Some(old);
+    *slot = Some(parent);
+    None
+}
+
+fn remove<V>(slot: &mut Option<Box<Node<V>>>, key: u128, len: u8) -> Option<V> {
+    let node = slot.as_mut()?;
+    if node.len > len || common_len(node.key, key, node.len) < node.len {
+        return None;
+    }
+    let removed = if node.len == len {
+        node.value.take()
+    } else {
+        remove(&mut node.children[branch(key, node.len)], key, len)
+    };
+    if removed.is_some() {
+        compact(slot);
+    }
+    removed
+}
+
+// Drops a node with no value that no longer joins two subtries, so the trie stays compressed.
+fn compact<V>(slot: &mut Option<Box<Node<V>>>) {
+    let Some(node) = slot else {
+        return;
+    };
+    if node.value.is_some() || node.children.iter().all(Option::is_some) {
+        return;
+    }
+    let child = node.children[0].take().or_else(|| node.children[1].take());
+    *slot = child;
+}
+
+impl<V> FromIterator<(Cidr, V)> for RoutingTable<V> {
+    fn from_iter<I: IntoIterator<Item = (Cidr, V)>>(routes: I) -> RoutingTable<V> {
+        let mut table = RoutingTable::new();
+        for (prefix, value) in routes {
+            table.insert(prefix, value);
+        }
+        table
+    }
+}
+
+impl<'a, V> IntoIterator for &'a RoutingTable<V> {
+    type Item = (Cidr, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Iter<'a, V> {
+        self.iter()
+    }
+}
+
+// Routes in prefix order, from `RoutingTable::iter`.
+#[derive(Debug, Clone)]
+pub struct Iter<'a, V> {
+    stack: Vec<(Family, &'a Node<V>)>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (Cidr, &'a V);
+
+    fn next(&mut self) -> Option<(Cidr, &'a V)> {
+        while let Some((family, node)) = self.stack.pop() {
+            for child in node.children.iter().rev().flatten() {
+                self.stack.push((family, child));
+            }
+            if let Some(value) = &node.value {
+                let prefix = Cidr::from_parts(family, unalign(family, node.key), node.len);
+                return Some((prefix, value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddrKind {
+        s.parse().unwrap()
+    }
+
+    fn route<'a>(table: &'a RoutingTable<&'a str>, s: &str) -> Option<(String, &'a str)> {
+        table
+            .lookup(&addr(s))
+            .map(|(prefix, hop)| (prefix.to_string(), *hop))
+    }
+
+    #[test]
+    fn picks_the_longest_matching_prefix() {
+        let table: RoutingTable<&str> = [
+            (cidr("0.0.0.0/0"), "default"),
+            (cidr("10.0.0.0/8"), "core"),
+            (cidr("10.1.0.0/16"), "branch"),
+            (cidr("10.1.2.3"), "host"),
+            (cidr("2001:db8::/32"), "v6"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(table.len(), 5);
+        assert_eq!(
+            route(&table, "10.1.2.3"),
+            Some(("10.1.2.3/32".into(), "host"))
+        );
+        assert_eq!(
+            route(&table, "10.1.2.4"),
+            Some(("10.1.0.0/16".into(), "branch"))
+        );
+        assert_eq!(
+            route(&table, "10.2.0.0"),
+            Some(("10.0.0.0/8".into(), "core"))
+        );
+        assert_eq!(
+            route(&table, "192.0.2.1"),
+            Some(("0.0.0.0/0".into(), "default"))
+        );
+        assert_eq!(
+            route(&table, "2001:db8::1"),
+            Some(("2001:db8::/32".into(), "v6"))
+        );
+        // The IPv4 default route doesn't cover IPv6 addresses.
+        assert_eq!(route(&table, "2001:db9::1"), None);
+        assert_eq!(table.get(&cidr("10.1.0.0/16")), Some(&"branch"));
+        assert_eq!(table.get(&cidr("10.1.0.0/17")), None);
+    }
+
+    #[test]
+    fn replaces_and_removes_routes() {
+        let mut table = RoutingTable::new();
+        table.insert(cidr("10.0.0.0/8"), "a");
+        table.insert(cidr("10.128.0.0/9"), "b");
+        table.insert(cidr("10.64.0.0/10"), "c");
+
+        assert_eq!(table.insert(cidr("10.0.0.0/8"), "d"), Some("a"));
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.remove(&cidr("10.0.0.0/8")), Some("d"));
+        assert_eq!(table.remove(&cidr("10.0.0.0/8")), None);
+        assert_eq!(table.remove(&cidr("10.0.0.0/9")), None);
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            route(&table, "10.200.0.1"),
+            Some(("10.128.0.0/9".into(), "b"))
+        );
+        assert_eq!(
+            route(&table, "10.70.0.1"),
+            Some(("10.64.0.0/10".into(), "c"))
+        );
+        assert_eq!(route(&table, "10.0.0.1"), None);
+
+        table.remove(&cidr("10.128.0.0/9"));
+        table.remove(&cidr("10.64.0.0/10"));
+        assert!(table.is_empty());
+        assert!(table.v4.is_none());
+    }
+
+    #[test]
+    fn iterates_in_prefix_order() {
+        let prefixes = [
+            "2001:db8::/32",
+            "10.1.0.0/16",
+            "::/0",
+            "10.0.0.0/8",
+            "192.168.0.0/24",
+            "10.0.0.0/9",
+            "0.0.0.0/0",
+        ];
+        let table: RoutingTable<()> = prefixes.iter().map(|p| (cidr(p), ())).collect();
+
+        let mut sorted: Vec<Cidr> = prefixes.iter().map(|p| cidr(p)).collect();
+        sorted.sort();
+        let iterated: Vec<Cidr> = table.iter().map(|(prefix, _)| prefix).collect();
+        assert_eq!(iterated, sorted);
+    }
+
+    // Checks lookups against a scan of every route, over random prefixes clustered in a small
+    // part of the address space so that many of them nest and share forks.
+    #[test]
+    fn agrees_with_a_linear_scan() {
+        let mut random = Random::new(0x2545_f491_4f6c_dd1d);
+
+        let mut table = RoutingTable::new();
+        let mut routes = Vec::new();
+        for i in 0..2000 {
+            let bits = 0x0a00_0000 | (random.next_u64() as u128 & 0xffff) << 8;
+            let prefix = Cidr::new(
+                &IpAddrKind::from_bits(Family::V4, bits),
+                8 + (random.next_u64() % 25) as u8,
+            )
+            .unwrap();
+            if table.insert(prefix, i).is_none() {
+                routes.push((prefix, i));
+            } else {
+                routes.iter_mut().find(|(p, _)| *p == prefix).unwrap().1 = i;
+            }
+        }
+        for (prefix, _) in routes.iter().step_by(3) {
+            table.remove(prefix);
+        }
+        let routes: Vec<(Cidr, i32)> = routes
+            .into_iter()
+            .enumerate()
+            .filter(|(n, _)| n % 3 != 0)
+            .map(|(_, route)| route)
+            .collect();
+        assert_eq!(table.len(), routes.len());
+
+        for _ in 0..5000 {
+            let target = IpAddrKind::from_bits(
+                Family::V4,
+                0x0a00_0000 | random.next_u64() as u128 & 0xff_ffff,
+            );
+            let expected = routes
+                .iter()
+                .filter(|(prefix, _)| prefix.contains(&target))
+                .max_by_key(|(prefix, _)| prefix.prefix_len())
+                .map(|(prefix, value)| (*prefix, value));
+            assert_eq!(table.lookup(&target), expected, "{}", target);
+        }
+    }
+}
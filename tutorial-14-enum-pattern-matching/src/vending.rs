@@ -0,0 +1,488 @@
+// A coin-operated vending machine.
+//
+// Everything that can happen to the machine is an `Event`, and `handle` matches on the current
+// `State` and the event to decide what happens next, the same way `Coin::cents` matches on a
+// `Coin`. Every event and its result goes into an audit log.
+//
+// Inserted coins are held apart from the cash drawer until a sale goes through, so a refund
+// gives back the very coins that went in.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::coin::{CashDrawer, Coin};
+
+// The most change a customer who stops paying once they have enough can be owed. They overpay
+// most by putting in the largest coin when the smallest would have done, so for US coins a
+// quarter when a penny short: 24 cents.
+fn largest_overshoot() -> u32 {
+    let cents = Coin::ALL.map(Coin::cents);
+    let largest = cents.iter().max().expect("there are coins");
+    let smallest = cents.iter().min().expect("there are coins");
+    largest - smallest
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Product {
+    name: String,
+    price: u32,
+    stock: u32,
+}
+
+impl Product {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+
+    pub fn stock(&self) -> u32 {
+        self.stock
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    // Coins have gone in but nothing has been bought yet.
+    Collecting { escrow: Vec<Coin> },
+    // Opened up for restocking. Coins are returned and nothing can be bought.
+    Maintenance,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Insert(Coin),
+    Select(String),
+    Refund,
+    OpenForService,
+    Restock { slot: String, count: u32 },
+    CloseAfterService,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    // Credit so far.
+    Credit(u32),
+    Vended {
+        product: String,
+        change: Vec<(Coin, u32)>,
+    },
+    Returned(Vec<Coin>),
+    InService,
+    Restocked {
+        slot: String,
+        stock: u32,
+    },
+    BackInService,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VendError {
+    UnknownSlot(String),
+    SoldOut(String),
+    NotEnoughCredit { price: u32, credit: u32 },
+    // The drawer can't give this much change, so the customer has to pay the exact price.
+    ExactChangeOnly { change: u32 },
+    NothingToRefund,
+    // The machine is open for maintenance. Any coin put in comes straight back.
+    OutOfService,
+    // Restocking and closing only work while the machine is open.
+    NotInService,
+    // A restock would take the slot's count past what a u32 holds.
+    TooMuchStock(String),
+    // The drawer has no room for the coins paid in.
+    DrawerFull,
+}
+
+impl fmt::Display for VendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VendError::UnknownSlot(slot) => write!(f, "there is no slot {}", slot),
+            VendError::SoldOut(slot) => write!(f, "slot {} is sold out", slot),
+            VendError::NotEnoughCredit { price, credit } => {
+                write!(f, "costs {} cents but only {} inserted", price, credit)
+            }
+            VendError::ExactChangeOnly { change } => {
+                write!(f, "can't give {} cents change, exact change only", change)
+            }
+            VendError::NothingToRefund => write!(f, "no coins to return"),
+            VendError::OutOfService => write!(f, "out of service"),
+            VendError::NotInService => write!(f, "the machine isn't open for service"),
+            VendError::TooMuchStock(slot) => write!(f, "slot {} can't hold that much stock", slot),
+            VendError::DrawerFull => write!(f, "the coin box is full"),
+        }
+    }
+}
+
+impl std::error::Error for VendError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub event: Event,
+    pub result: Result<Response, VendError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VendingMachine {
+    state: State,
+    slots: BTreeMap<String, Product>,
+    drawer: CashDrawer<Coin>,
+    audit: Vec<AuditEntry>,
+}
+
+impl VendingMachine {
+    // `drawer` is the float the machine starts with for giving change.
+    pub fn new(drawer: CashDrawer<Coin>) -> VendingMachine {
+        VendingMachine {
+            state: State::Idle,
+            slots: BTreeMap::new(),
+            drawer,
+            audit: Vec::new(),
+        }
+    }
+
+    pub fn with_slot(mut self, slot: &str, name: &str, price: u32, stock: u32) -> Self {
+        let product = Product {
+            name: String::from(name),
+            price,
+            stock,
+        };
+        self.slots.insert(String::from(slot), product);
+        self
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn product(&self, slot: &str) -> Option<&Product> {
+        self.slots.get(slot)
+    }
+
+    pub fn drawer(&self) -> &CashDrawer<Coin> {
+        &self.drawer
+    }
+
+    pub fn audit_log(&self) -> &[AuditEntry] {
+        &self.audit
+    }
+
+    pub fn credit(&self) -> u32 {
+        match &self.state {
+            State::Collecting { escrow } => escrow.iter().map(|coin| coin.cents()).sum(),
+            State::Idle | State::Maintenance => 0,
+        }
+    }
+
+    // Whether to light the "exact change only" sign: some overpayment a customer could make
+    // can't be given back from the drawer.
+    pub fn exact_change_only(&self) -> bool {
+        (1..=largest_overshoot()).any(|change| self.drawer.change_for(change).is_err())
+    }
+
+    pub fn handle(&mut self, event: Event) -> Result<Response, VendError> {
+        let result = self.transition(&event);
+        self.audit.push(AuditEntry {
+            seq: self.audit.len() as u64 + 1,
+            event,
+            result: result.clone(),
+        });
+        result
+    }
+
+    fn transition(&mut self, event: &Event) -> Result<Response, VendError> {
+        let state = std::mem::replace(&mut self.state, State::Idle);
+        let (next, result) = match (state, event) {
+            (State::Maintenance, Event::Restock { slot, count }) => {
+                let result = match self.slots.get_mut(slot) {
+                    Some(product) => match product.stock.checked_add(*count) {
+                        Some(stock) => {
+                            product.stock = stock;
+                            Ok(Response::Restocked {
+                                slot: slot.clone(),
+                                stock,
+                            })
+                        }
+                        None => Err(VendError::TooMuchStock(slot.clone())),
+                    },
+                    None => Err(VendError::UnknownSlot(slot.clone())),
+                };
+                (State::Maintenance, result)
+            }
+            (State::Maintenance, Event::CloseAfterService) => {
+                (State::Idle, Ok(Response::BackInService))
+            }
+            (State::Maintenance, _) => (State::Maintenance, Err(VendError::OutOfService)),
+            (state, Event::Restock { .. } | Event::CloseAfterService) => {
+                (state, Err(VendError::NotInService))
+            }
+
+            (State::Idle, Event::Insert(coin)) => (
+                State::Collecting {
+                    escrow: vec![*coin],
+                },
+                Ok(Response::Credit(coin.cents())),
+            ),
+            (State::Collecting { mut escrow }, Event::Insert(coin)) => {
+                escrow.push(*coin);
+                let credit = escrow.iter().map(|coin| coin.cents()).sum();
+                (State::Collecting { escrow }, Ok(Response::Credit(credit)))
+            }
+
+            (State::Idle, Event::Select(slot)) => (State::Idle, self.sell(slot, &[])),
+            (State::Collecting { escrow }, Event::Select(slot)) => match self.sell(slot, &escrow) {
+                Ok(response) => (State::Idle, Ok(response)),
+                // The customer keeps their credit to choose something else or ask for a refund.
+                Err(e) => (State::Collecting { escrow }, Err(e)),
+            },
+
+            (State::Idle, Event::Refund) => (State::Idle, Err(VendError::NothingToRefund)),
+            (State::Collecting { escrow }, Event::Refund) => {
+                (State::Idle, Ok(Response::Returned(escrow)))
+            }
+
+            (State::Idle, Event::OpenForService) => (State::Maintenance, Ok(Response::InService)),
+            // Anyone part way through buying something gets their coins back.
+            (State::Collecting { escrow }, Event::OpenForService) => {
+                (State::Maintenance, Ok(Response::Returned(escrow)))
+            }
+        };
+        self.state = next;
+        result
+    }
+
+    // Sells from `slot` for the coins in `escrow`, which go into the drawer if it works.
+    fn sell(&mut self, slot: &str, escrow: &[Coin]) -> Result<Response, VendError> {
+        let product = self
+            .slots
+            .get_mut(slot)
+            .ok_or_else(|| VendError::UnknownSlot(String::from(slot)))?;
+        if product.stock == 0 {
+            return Err(VendError::SoldOut(String::from(slot)));
+        }
+        let credit: u32 = escrow.iter().map(|coin| coin.cents()).sum();
+        if credit < product.price {
+            return Err(VendError::NotEnoughCredit {
+                price: product.price,
+                credit,
+            });
+        }
+
+        // The customer's own coins can be part of their change.
+        let mut drawer = self.drawer.clone();
+        for &coin in escrow {
+            drawer.add(coin, 1).map_err(|_| VendError::DrawerFull)?;
+        }
+        let due = credit - product.price;
+        let change = drawer
+            .pay_out(due)
+            .map_err(|_| VendError::ExactChangeOnly { change: due })?;
+
+        self.drawer = drawer;
+        product.stock -= 1;
+        Ok(Response::Vended {
+            product: product.name.clone(),
+            change,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Coin::*;
+    use Event::*;
+
+    fn select(slot: &str) -> Event {
+        Select(String::from(slot))
+    }
+
+    fn vended(product: &str, change: &[(Coin, u32)]) -> Result<Response, VendError> {
+        Ok(Response::Vended {
+            product: String::from(product),
+            change: change.to_vec(),
+        })
+    }
+
+    // Plays each event in turn and checks what the machine said back.
+    fn play(machine: &mut VendingMachine, script: Vec<(Event, Result<Response, VendError>)>) {
+        for (step, (event, expected)) in script.into_iter().enumerate() {
+            let description = format!("step {}: {:?}", step + 1, event);
+            assert_eq!(machine.handle(event), expected, "{}", description);
+        }
+    }
+
+    fn machine(float: &[(Coin, u32)]) -> VendingMachine {
+        let drawer = float
+            .iter()
+            .fold(CashDrawer::new(), |drawer, &(coin, count)| {
+                drawer.with(coin, count)
+            });
+        VendingMachine::new(drawer)
+            .with_slot("A1", "Crisps", 65, 2)
+            .with_slot("A2", "Water", 50, 1)
+            .with_slot("B1", "Gum", 35, 0)
+    }
+
+    #[test]
+    fn sells_and_gives_change() {
+        let mut machine = machine(&[(Dime, 5), (Nickel, 5), (Penny, 10)]);
+        assert!(!machine.exact_change_only());
+
+        play(
+            &mut machine,
+            vec![
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Quarter), Ok(Response::Credit(50))),
+                (Insert(Quarter), Ok(Response::Credit(75))),
+                (select("A1"), vended("Crisps", &[(Dime, 1)])),
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Quarter), Ok(Response::Credit(50))),
+                (select("A2"), vended("Water", &[])),
+            ],
+        );
+        assert_eq!(machine.state(), &State::Idle);
+        assert_eq!(machine.product("A1").unwrap().stock(), 1);
+        assert_eq!(machine.drawer().count(Quarter), 5);
+        assert_eq!(machine.drawer().total(), 50 + 25 + 10 + 65 + 50);
+    }
+
+    #[test]
+    fn keeps_credit_when_a_sale_is_refused() {
+        let mut machine = machine(&[(Dime, 5), (Nickel, 5), (Penny, 10)]);
+
+        play(
+            &mut machine,
+            vec![
+                (
+                    select("A1"),
+                    Err(VendError::NotEnoughCredit {
+                        price: 65,
+                        credit: 0,
+                    }),
+                ),
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Dime), Ok(Response::Credit(35))),
+                (select("B1"), Err(VendError::SoldOut(String::from("B1")))),
+                (
+                    select("C9"),
+                    Err(VendError::UnknownSlot(String::from("C9"))),
+                ),
+                (
+                    select("A1"),
+                    Err(VendError::NotEnoughCredit {
+                        price: 65,
+                        credit: 35,
+                    }),
+                ),
+                (Refund, Ok(Response::Returned(vec![Quarter, Dime]))),
+                (Refund, Err(VendError::NothingToRefund)),
+            ],
+        );
+        assert_eq!(machine.drawer().count(Quarter), 0);
+    }
+
+    #[test]
+    fn asks_for_exact_change_when_the_drawer_is_short() {
+        assert_eq!(largest_overshoot(), 24);
+        // Only nickels to give change with.
+        let mut machine = machine(&[(Nickel, 1)]);
+        assert!(machine.exact_change_only());
+
+        play(
+            &mut machine,
+            vec![
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Quarter), Ok(Response::Credit(50))),
+                (Insert(Quarter), Ok(Response::Credit(75))),
+                (select("A1"), Err(VendError::ExactChangeOnly { change: 10 })),
+                (
+                    Refund,
+                    Ok(Response::Returned(vec![Quarter, Quarter, Quarter])),
+                ),
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Quarter), Ok(Response::Credit(50))),
+                (Insert(Dime), Ok(Response::Credit(60))),
+                (Insert(Dime), Ok(Response::Credit(70))),
+                (select("A1"), vended("Crisps", &[(Nickel, 1)])),
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Quarter), Ok(Response::Credit(50))),
+                (Insert(Dime), Ok(Response::Credit(60))),
+                (Insert(Dime), Ok(Response::Credit(70))),
+                (select("A1"), Err(VendError::ExactChangeOnly { change: 5 })),
+                (Insert(Nickel), Ok(Response::Credit(75))),
+                // The dimes from the last sale can go back out as change.
+                (select("A1"), vended("Crisps", &[(Dime, 1)])),
+            ],
+        );
+    }
+
+    #[test]
+    fn restocks_only_while_open() {
+        let mut machine = machine(&[]);
+
+        play(
+            &mut machine,
+            vec![
+                (
+                    Restock {
+                        slot: String::from("B1"),
+                        count: 5,
+                    },
+                    Err(VendError::NotInService),
+                ),
+                (Insert(Dime), Ok(Response::Credit(10))),
+                (OpenForService, Ok(Response::Returned(vec![Dime]))),
+                (Insert(Quarter), Err(VendError::OutOfService)),
+                (select("A1"), Err(VendError::OutOfService)),
+                (
+                    Restock {
+                        slot: String::from("B1"),
+                        count: 5,
+                    },
+                    Ok(Response::Restocked {
+                        slot: String::from("B1"),
+                        stock: 5,
+                    }),
+                ),
+                (
+                    Restock {
+                        slot: String::from("B1"),
+                        count: u32::MAX,
+                    },
+                    Err(VendError::TooMuchStock(String::from("B1"))),
+                ),
+                (CloseAfterService, Ok(Response::BackInService)),
+                (Insert(Quarter), Ok(Response::Credit(25))),
+                (Insert(Dime), Ok(Response::Credit(35))),
+                (select("B1"), vended("Gum", &[])),
+            ],
+        );
+    }
+
+    #[test]
+    fn records_every_event_in_the_audit_log() {
+        let mut machine = machine(&[]);
+        machine.handle(Insert(Penny)).ok();
+        machine.handle(select("A2")).ok();
+        machine.handle(Refund).ok();
+
+        let log = machine.audit_log();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].seq, 1);
+        assert_eq!(log[1].event, select("A2"));
+        assert_eq!(
+            log[1].result,
+            Err(VendError::NotEnoughCredit {
+                price: 50,
+                credit: 1
+            })
+        );
+        assert_eq!(log[2].result, Ok(Response::Returned(vec![Penny])));
+    }
+}
@@ -0,0 +1,411 @@
+// A shoe shop's stock, grown from `shoes_in_my_size` in main.rs. That takes the shoes by value
+// and looks at every one for an exact size; here the inventory keeps its items, and searches
+// borrow them:
+//
+//   inventory.query().sizes(9..=11).style("boot").in_stock().iter()
+//
+// Each item has a SKU (stock keeping unit) naming one shoe in one size and width, and how many
+// are in stock. Besides the SKU, items are indexed by size, style and width, so a search starts
+// from the items its criteria pick out instead of all of them; see `query`.
+
+mod query;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+pub use query::{Order, Page, Query, SortKey};
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Shoe {
+    pub size: u32,
+    pub style: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Width {
+    Narrow,
+    Medium,
+    Wide,
+    ExtraWide,
+}
+
+impl Width {
+    pub const ALL: [Width; 4] = [Width::Narrow, Width::Medium, Width::Wide, Width::ExtraWide];
+
+    // The letters on the box.
+    pub fn code(self) -> &'static str {
+        match self {
+            Width::Narrow => "N",
+            Width::Medium => "M",
+            Width::Wide => "W",
+            Width::ExtraWide => "XW",
+        }
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Width {
+    type Err = InventoryError;
+
+    fn from_str(s: &str) -> Result<Width, InventoryError> {
+        Width::ALL
+            .into_iter()
+            .find(|w| w.code().eq_ignore_ascii_case(s))
+            .ok_or_else(|| InventoryError::InvalidWidth(String::from(s)))
+    }
+}
+
+// Up to 32 letters, digits and dashes, kept in capitals: "BOOT-10-W".
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sku(String);
+
+impl Sku {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Sku {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Sku {
+    type Err = InventoryError;
+
+    fn from_str(s: &str) -> Result<Sku, InventoryError> {
+        let valid = (1..=32).contains(&s.len())
+            && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+        if !valid {
+            return Err(InventoryError::InvalidSku(String::from(s)));
+        }
+        Ok(Sku(s.to_ascii_uppercase()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub sku: Sku,
+    pub shoe: Shoe,
+    pub width: Width,
+    pub stock: u32,
+}
+
+impl Item {
+    pub fn new(sku: Sku, shoe: Shoe, width: Width, stock: u32) -> Item {
+        Item {
+            sku,
+            shoe,
+            width,
+            stock,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} size {}{}, {} in stock",
+            self.sku, self.shoe.style, self.shoe.size, self.width, self.stock
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryError {
+    InvalidSku(String),
+    InvalidWidth(String),
+    DuplicateSku(Sku),
+    UnknownSku(Sku),
+    OutOfStock {
+        sku: Sku,
+        wanted: u32,
+        available: u32,
+    },
+    // Stock past what a u32 counts.
+    TooMuchStock(Sku),
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::InvalidSku(s) => write!(f, "'{}' is not a valid SKU", s),
+            InventoryError::InvalidWidth(s) => write!(f, "'{}' is not a width", s),
+            InventoryError::DuplicateSku(sku) => write!(f, "{} is already in the inventory", sku),
+            InventoryError::UnknownSku(sku) => write!(f, "{} is not in the inventory", sku),
+            InventoryError::OutOfStock {
+                sku,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "{} has {} in stock, {} were wanted",
+                sku, available, wanted
+            ),
+            InventoryError::TooMuchStock(sku) => write!(f, "{} can't hold that much stock", sku),
+        }
+    }
+}
+
+impl error::Error for InventoryError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    // Removed items leave a hole, so the indexes' positions stay valid and items keep the order
+    // they were added in. Once holes outnumber the items they're closed up; see `compact`.
+    items: Vec<Option<Item>>,
+    len: usize,
+    by_sku: HashMap<Sku, usize>,
+    by_size: BTreeMap<u32, BTreeSet<usize>>,
+    by_style: BTreeMap<String, BTreeSet<usize>>,
+    by_width: BTreeMap<Width, BTreeSet<usize>>,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory::default()
+    }
+
+    pub fn add(&mut self, item: Item) -> Result<(), InventoryError> {
+        if self.by_sku.contains_key(&item.sku) {
+            return Err(InventoryError::DuplicateSku(item.sku));
+        }
+        self.push(item);
+        Ok(())
+    }
+
+    // Adds an item whose SKU isn't taken at the end, indexing it.
+    fn push(&mut self, item: Item) {
+        let i = self.items.len();
+        self.by_sku.insert(item.sku.clone(), i);
+        self.by_size.entry(item.shoe.size).or_default().insert(i);
+        self.by_style
+            .entry(item.shoe.style.clone())
+            .or_default()
+            .insert(i);
+        self.by_width.entry(item.width).or_default().insert(i);
+        self.items.push(Some(item));
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, sku: &Sku) -> Option<Item> {
+        let i = self.by_sku.remove(sku)?;
+        let item = self.items[i].take()?;
+        unindex(&mut self.by_size, &item.shoe.size, i);
+        unindex(&mut self.by_style, &item.shoe.style, i);
+        unindex(&mut self.by_width, &item.width, i);
+        self.len -= 1;
+        if self.items.len() - self.len > self.len {
+            self.compact();
+        }
+        Some(item)
+    }
+
+    // Drops the holes, which moves the items after them, so the indexes are built again.
+    fn compact(&mut self) {
+        let items = std::mem::take(&mut self.items);
+        *self = Inventory::new();
+        for item in items.into_iter().flatten() {
+            self.push(item);
+        }
+    }
+
+    pub fn get(&self, sku: &Sku) -> Option<&Item> {
+        self.items[*self.by_sku.get(sku)?].as_ref()
+    }
+
+    // Adds to an item's stock, giving the new count.
+    pub fn restock(&mut self, sku: &Sku, count: u32) -> Result<u32, InventoryError> {
+        let item = self.item_mut(sku)?;
+        item.stock = item
+            .stock
+            .checked_add(count)
+            .ok_or_else(|| InventoryError::TooMuchStock(sku.clone()))?;
+        Ok(item.stock)
+    }
+
+    // Takes from an item's stock, giving what's left. Nothing is taken if there isn't enough.
+    pub fn sell(&mut self, sku: &Sku, count: u32) -> Result<u32, InventoryError> {
+        let item = self.item_mut(sku)?;
+        if item.stock < count {
+            return Err(InventoryError::OutOfStock {
+                sku: sku.clone(),
+                wanted: count,
+                available: item.stock,
+            });
+        }
+        item.stock -= count;
+        Ok(item.stock)
+    }
+
+    // Only the stock can be changed in place; the rest is indexed.
+    fn item_mut(&mut self, sku: &Sku) -> Result<&mut Item, InventoryError> {
+        self.by_sku
+            .get(sku)
+            .and_then(|&i| self.items[i].as_mut())
+            .ok_or_else(|| InventoryError::UnknownSku(sku.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Every item, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().flatten()
+    }
+
+    // How many of each style are in stock, in all sizes and widths.
+    // Summed as u64, since the stock of several items can be more than a u32 holds.
+    pub fn stock_by_style(&self) -> BTreeMap<&str, u64> {
+        let mut totals = BTreeMap::new();
+        for item in self.iter() {
+            *totals.entry(item.shoe.style.as_str()).or_insert(0) += u64::from(item.stock);
+        }
+        totals
+    }
+
+    pub fn query(&self) -> Query<'_> {
+        Query::new(self)
+    }
+}
+
+fn unindex<K: Ord>(index: &mut BTreeMap<K, BTreeSet<usize>>, key: &K, i: usize) {
+    if let Some(positions) = index.get_mut(key) {
+        positions.remove(&i);
+        if positions.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Inventory {
+    type Item = &'a Item;
+    type IntoIter = std::iter::Flatten<std::slice::Iter<'a, Option<Item>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn item(sku: &str, size: u32, style: &str, width: Width, stock: u32) -> Item {
+        Item::new(
+            sku.parse().unwrap(),
+            Shoe {
+                size,
+                style: String::from(style),
+            },
+            width,
+            stock,
+        )
+    }
+
+    #[test]
+    fn keeps_items_by_sku() {
+        let mut inventory = Inventory::new();
+        inventory
+            .add(item("boot-10-m", 10, "boot", Width::Medium, 3))
+            .unwrap();
+        inventory
+            .add(item("SNK-9-W", 9, "sneaker", Width::Wide, 0))
+            .unwrap();
+        let boot: Sku = "BOOT-10-M".parse().unwrap();
+        assert_eq!(inventory.get(&boot).unwrap().stock, 3);
+        assert_eq!(
+            inventory.add(item("Boot-10-M", 1, "x", Width::Narrow, 1)),
+            Err(InventoryError::DuplicateSku(boot.clone()))
+        );
+        assert_eq!(inventory.len(), 2);
+
+        let removed = inventory.remove(&boot).unwrap();
+        assert_eq!(removed.shoe.style, "boot");
+        assert_eq!(inventory.get(&boot), None);
+        assert_eq!(inventory.remove(&boot), None);
+        let skus: Vec<&str> = inventory.iter().map(|i| i.sku.as_str()).collect();
+        assert_eq!(skus, ["SNK-9-W"]);
+        assert!(inventory.query().style("boot").iter().next().is_none());
+    }
+
+    #[test]
+    fn counts_stock() {
+        let mut inventory = Inventory::new();
+        inventory
+            .add(item("A-1", 10, "boot", Width::Medium, 2))
+            .unwrap();
+        let sku: Sku = "A-1".parse().unwrap();
+        assert_eq!(inventory.restock(&sku, 3), Ok(5));
+        assert_eq!(inventory.sell(&sku, 4), Ok(1));
+        assert_eq!(
+            inventory.sell(&sku, 2).unwrap_err().to_string(),
+            "A-1 has 1 in stock, 2 were wanted"
+        );
+        assert_eq!(inventory.get(&sku).unwrap().stock, 1);
+        assert_eq!(
+            inventory.restock(&sku, u32::MAX),
+            Err(InventoryError::TooMuchStock(sku.clone()))
+        );
+        let missing: Sku = "B-2".parse().unwrap();
+        assert_eq!(
+            inventory.sell(&missing, 1),
+            Err(InventoryError::UnknownSku(missing))
+        );
+        assert_eq!(inventory.stock_by_style()["boot"], 1);
+        inventory
+            .add(item("A-2", 11, "boot", Width::Medium, u32::MAX))
+            .unwrap();
+        assert_eq!(inventory.stock_by_style()["boot"], u64::from(u32::MAX) + 1);
+    }
+
+    #[test]
+    fn closes_up_holes() {
+        let mut inventory = Inventory::new();
+        for i in 0..10 {
+            let style = if i % 2 == 0 { "boot" } else { "sneaker" };
+            inventory
+                .add(item(&format!("S-{}", i), 8 + i, style, Width::Medium, i))
+                .unwrap();
+        }
+        // The sixth leaves more holes than items.
+        for i in [0, 1, 2, 4, 5, 7] {
+            inventory
+                .remove(&format!("S-{}", i).parse().unwrap())
+                .unwrap();
+        }
+        assert_eq!((inventory.len(), inventory.items.len()), (4, 4));
+        let skus: Vec<&str> = inventory.iter().map(|i| i.sku.as_str()).collect();
+        assert_eq!(skus, ["S-3", "S-6", "S-8", "S-9"]);
+        let boots = inventory.query().style("boot").sizes(10..);
+        assert_eq!(boots.iter().map(|i| i.stock).collect::<Vec<_>>(), [6, 8]);
+        assert_eq!(inventory.get(&"S-3".parse().unwrap()).unwrap().stock, 3);
+    }
+
+    #[test]
+    fn checks_skus_and_widths() {
+        assert_eq!("ab-12".parse::<Sku>().unwrap().to_string(), "AB-12");
+        for bad in ["", "has space", "ÜBER", &"X".repeat(33)] {
+            assert!(bad.parse::<Sku>().is_err(), "{}", bad);
+        }
+        assert_eq!("xw".parse::<Width>(), Ok(Width::ExtraWide));
+        assert_eq!(
+            "huge".parse::<Width>().unwrap_err().to_string(),
+            "'huge' is not a width"
+        );
+    }
+}
@@ -0,0 +1,352 @@
+// Searching an inventory without consuming it. A query is built up from criteria, all of which
+// an item must meet, and gives back references to the items:
+//
+//   let boots: Vec<&Item> = inventory.query().style("boot").sizes(9..=11).iter().collect();
+//
+// The size, style and width criteria are answered from the inventory's indexes: each gives the
+// positions of the items it allows, and only the positions in every one of them are looked at.
+// Without any of those criteria the search goes over every item.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::{Bound, RangeBounds};
+
+use super::{Inventory, Item, Width};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Sku,
+    Size,
+    Style,
+    Stock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    inventory: &'a Inventory,
+    sizes: Option<(Bound<u32>, Bound<u32>)>,
+    styles: Vec<String>,
+    widths: Vec<Width>,
+    in_stock: bool,
+    sort: Option<(SortKey, Order)>,
+}
+
+// One page of a query's results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<'a> {
+    pub items: Vec<&'a Item>,
+    // Counting from 1.
+    pub number: usize,
+    pub per_page: usize,
+    // Items on all the pages together.
+    pub total: usize,
+}
+
+impl Page<'_> {
+    pub fn pages(&self) -> usize {
+        self.total.div_ceil(self.per_page)
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.number < self.pages()
+    }
+}
+
+impl<'a> Query<'a> {
+    pub(super) fn new(inventory: &'a Inventory) -> Query<'a> {
+        Query {
+            inventory,
+            sizes: None,
+            styles: Vec::new(),
+            widths: Vec::new(),
+            in_stock: false,
+            sort: None,
+        }
+    }
+
+    pub fn size(self, size: u32) -> Self {
+        self.sizes(size..=size)
+    }
+
+    pub fn sizes(mut self, sizes: impl RangeBounds<u32>) -> Self {
+        self.sizes = Some((sizes.start_bound().cloned(), sizes.end_bound().cloned()));
+        self
+    }
+
+    // Calling it again allows another style as well.
+    pub fn style(mut self, style: &str) -> Self {
+        self.styles.push(String::from(style));
+        self
+    }
+
+    // Calling it again allows another width as well.
+    pub fn width(mut self, width: Width) -> Self {
+        self.widths.push(width);
+        self
+    }
+
+    // Only items with at least one in stock.
+    pub fn in_stock(mut self) -> Self {
+        self.in_stock = true;
+        self
+    }
+
+    // Without it, items come in the order they were added. Items that tie keep that order.
+    pub fn sort_by(mut self, key: SortKey, order: Order) -> Self {
+        self.sort = Some((key, order));
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a Item> {
+        let items: Box<dyn Iterator<Item = &'a Item>> = match self.sort {
+            // Unsorted, the items can be handed out as they're found.
+            None => Box::new(self.matching()),
+            Some(sort) => Box::new(self.sorted(sort).into_iter()),
+        };
+        items
+    }
+
+    pub fn count(&self) -> usize {
+        self.matching().count()
+    }
+
+    // The `number`th page of `per_page` items, counting pages from 1. An empty page past the
+    // last one.
+    pub fn page(&self, number: usize, per_page: usize) -> Page<'a> {
+        let (number, per_page) = (number.max(1), per_page.max(1));
+        let skip = (number - 1).saturating_mul(per_page);
+        // One pass, counting every result and keeping the ones on the page.
+        let mut items = Vec::new();
+        let mut total = 0;
+        for item in self.iter() {
+            if total >= skip && items.len() < per_page {
+                items.push(item);
+            }
+            total += 1;
+        }
+        Page {
+            items,
+            number,
+            per_page,
+            total,
+        }
+    }
+
+    // The items meeting every criterion, in the order they were added.
+    fn matching(&self) -> impl Iterator<Item = &'a Item> + 'a {
+        let items = &self.inventory.items;
+        let in_stock = self.in_stock;
+        self.candidates()
+            .into_iter()
+            .filter_map(move |i| items[i].as_ref())
+            .filter(move |item| !in_stock || item.stock > 0)
+    }
+
+    fn sorted(&self, (key, order): (SortKey, Order)) -> Vec<&'a Item> {
+        let mut items: Vec<&'a Item> = self.matching().collect();
+        items.sort_by(|a, b| {
+            let ordering = compare(key, a, b);
+            match order {
+                Order::Ascending => ordering,
+                Order::Descending => ordering.reverse(),
+            }
+        });
+        items
+    }
+
+    // The positions of the items the indexed criteria allow, in order.
+    fn candidates(&self) -> Vec<usize> {
+        let inventory = self.inventory;
+        let mut allowed: Vec<BTreeSet<usize>> = Vec::new();
+        if let Some(sizes) = self.sizes {
+            let positions = if is_empty(sizes) {
+                BTreeSet::new()
+            } else {
+                inventory
+                    .by_size
+                    .range(sizes)
+                    .flat_map(|(_, positions)| positions)
+                    .copied()
+                    .collect()
+            };
+            allowed.push(positions);
+        }
+        if !self.styles.is_empty() {
+            let positions = self
+                .styles
+                .iter()
+                .filter_map(|style| inventory.by_style.get(style))
+                .flatten()
+                .copied()
+                .collect();
+            allowed.push(positions);
+        }
+        if !self.widths.is_empty() {
+            let positions = self
+                .widths
+                .iter()
+                .filter_map(|width| inventory.by_width.get(width))
+                .flatten()
+                .copied()
+                .collect();
+            allowed.push(positions);
+        }
+        // Starting from the fewest, so the others are only asked about those.
+        allowed.sort_by_key(BTreeSet::len);
+        let Some((fewest, others)) = allowed.split_first() else {
+            return (0..inventory.items.len()).collect();
+        };
+        fewest
+            .iter()
+            .copied()
+            .filter(|i| others.iter().all(|positions| positions.contains(i)))
+            .collect()
+    }
+}
+
+// BTreeMap::range panics on these rather than giving nothing.
+fn is_empty((start, end): (Bound<u32>, Bound<u32>)) -> bool {
+    match (start, end) {
+        (Bound::Included(a), Bound::Included(b)) => a > b,
+        (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => {
+            a >= b
+        }
+        _ => false,
+    }
+}
+
+fn compare(key: SortKey, a: &Item, b: &Item) -> Ordering {
+    match key {
+        SortKey::Sku => a.sku.cmp(&b.sku),
+        SortKey::Size => a.shoe.size.cmp(&b.shoe.size),
+        SortKey::Style => a.shoe.style.cmp(&b.shoe.style),
+        SortKey::Stock => a.stock.cmp(&b.stock),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::item;
+    use super::*;
+
+    fn inventory() -> Inventory {
+        let mut inventory = Inventory::new();
+        for item in [
+            item("SNK-10-M", 10, "sneaker", Width::Medium, 4),
+            item("SND-13-M", 13, "sandal", Width::Medium, 2),
+            item("BOOT-10-W", 10, "boot", Width::Wide, 0),
+            item("BOOT-9-M", 9, "boot", Width::Medium, 7),
+            item("BOOT-11-XW", 11, "boot", Width::ExtraWide, 1),
+            item("LOAF-12-N", 12, "loafer", Width::Narrow, 5),
+        ] {
+            inventory.add(item).unwrap();
+        }
+        inventory
+    }
+
+    fn skus<'a>(items: impl IntoIterator<Item = &'a Item>) -> Vec<&'a str> {
+        items.into_iter().map(|item| item.sku.as_str()).collect()
+    }
+
+    #[test]
+    fn combines_criteria() {
+        let inventory = inventory();
+        let query = inventory.query().sizes(9..=11).style("boot");
+        assert_eq!(skus(query.iter()), ["BOOT-10-W", "BOOT-9-M", "BOOT-11-XW"]);
+        assert_eq!(
+            skus(query.clone().in_stock().iter()),
+            ["BOOT-9-M", "BOOT-11-XW"]
+        );
+        assert_eq!(
+            skus(query.width(Width::Wide).width(Width::ExtraWide).iter()),
+            ["BOOT-10-W", "BOOT-11-XW"]
+        );
+        assert_eq!(
+            skus(inventory.query().style("loafer").style("sandal").iter()),
+            ["SND-13-M", "LOAF-12-N"]
+        );
+        assert_eq!(
+            skus(inventory.query().size(10).iter()),
+            ["SNK-10-M", "BOOT-10-W"]
+        );
+        assert_eq!(inventory.query().sizes(12..).count(), 2);
+        assert_eq!(inventory.query().style("clog").count(), 0);
+        assert_eq!(inventory.query().count(), 6);
+        // The inventory is only borrowed, so it's still there to search again.
+        assert_eq!(inventory.len(), 6);
+    }
+
+    #[test]
+    fn uses_the_indexes() {
+        let inventory = inventory();
+        // Boots in size 10, found from the indexes without looking at any item.
+        let query = inventory.query().style("boot").size(10);
+        assert_eq!(query.candidates(), [2]);
+        assert_eq!(inventory.query().width(Width::Narrow).candidates(), [5]);
+        assert_eq!(inventory.query().in_stock().candidates().len(), 6);
+        // Empty ranges, which the size index can't be asked about.
+        for (low, high) in [(11, 10), (10, 10)] {
+            assert!(inventory.query().sizes(low..high).candidates().is_empty());
+        }
+        let (low, high) = (11, 10);
+        assert!(inventory.query().sizes(low..=high).candidates().is_empty());
+        assert_eq!(inventory.query().sizes(10..=10).candidates(), [0, 2]);
+    }
+
+    #[test]
+    fn sorts_with_ties_in_order_added() {
+        let inventory = inventory();
+        let sorted = |key, order| skus(inventory.query().sort_by(key, order).iter());
+        assert_eq!(
+            sorted(SortKey::Size, Order::Ascending),
+            [
+                "BOOT-9-M",
+                "SNK-10-M",
+                "BOOT-10-W",
+                "BOOT-11-XW",
+                "LOAF-12-N",
+                "SND-13-M"
+            ]
+        );
+        assert_eq!(
+            sorted(SortKey::Stock, Order::Descending),
+            [
+                "BOOT-9-M",
+                "LOAF-12-N",
+                "SNK-10-M",
+                "SND-13-M",
+                "BOOT-11-XW",
+                "BOOT-10-W"
+            ]
+        );
+        assert_eq!(
+            sorted(SortKey::Style, Order::Ascending)[..3],
+            ["BOOT-10-W", "BOOT-9-M", "BOOT-11-XW"]
+        );
+        assert_eq!(sorted(SortKey::Sku, Order::Ascending)[0], "BOOT-10-W");
+    }
+
+    #[test]
+    fn pages_through_results() {
+        let inventory = inventory();
+        let query = inventory.query().sort_by(SortKey::Sku, Order::Ascending);
+        let first = query.page(1, 4);
+        assert_eq!(
+            skus(first.items.iter().copied()),
+            ["BOOT-10-W", "BOOT-11-XW", "BOOT-9-M", "LOAF-12-N"]
+        );
+        assert_eq!((first.total, first.pages(), first.has_next()), (6, 2, true));
+        let second = query.page(2, 4);
+        assert_eq!(skus(second.items.iter().copied()), ["SND-13-M", "SNK-10-M"]);
+        assert!(!second.has_next());
+        assert!(query.page(3, 4).items.is_empty());
+        assert_eq!(query.page(0, 0).items.len(), 1);
+        assert_eq!(query.page(usize::MAX, usize::MAX).items.len(), 0);
+    }
+}
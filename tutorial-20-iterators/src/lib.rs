@@ -0,0 +1,3 @@
+// The types from main.rs grown into modules that other programs can use.
+
+pub mod inventory;
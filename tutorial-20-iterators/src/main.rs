@@ -1,8 +1,4 @@
-#[derive(PartialEq, Debug)]
-struct Shoe {
-    size: u32,
-    style: String,
-}
+use tutorial_20_iterators::inventory::{Inventory, Item, Order, Shoe, SortKey, Width};
 
 fn shoes_in_my_size(shoes: Vec<Shoe>, shoe_size: u32) -> Vec<Shoe> {
     // into_iter() consumes the vector and takes ownership of its elements
@@ -35,6 +31,53 @@ fn main() {
     let v2_iter: Vec<_> = v2.iter().map(|x| x + 1).collect();
 
     assert_eq!(v2_iter, vec![2, 3, 4]);
+
+    // Example 3
+    // shoes_in_my_size takes the shoes by value, so they can't be used after the call
+    let shoes = vec![
+        Shoe {
+            size: 10,
+            style: String::from("sneaker"),
+        },
+        Shoe {
+            size: 13,
+            style: String::from("sandal"),
+        },
+    ];
+    let in_my_size = shoes_in_my_size(shoes, 10);
+    println!("In my size: {:?}", in_my_size);
+
+    // Example 4
+    // An inventory keeps its shoes, and its queries borrow them rather than consuming them
+    let mut inventory = Inventory::new();
+    for (sku, size, style, width, stock) in [
+        ("SNK-10-M", 10, "sneaker", Width::Medium, 4),
+        ("SND-13-M", 13, "sandal", Width::Medium, 2),
+        ("BOOT-10-W", 10, "boot", Width::Wide, 0),
+        ("BOOT-11-M", 11, "boot", Width::Medium, 6),
+    ] {
+        let shoe = Shoe {
+            size,
+            style: String::from(style),
+        };
+        let item = Item::new(sku.parse().unwrap(), shoe, width, stock);
+        inventory.add(item).unwrap();
+    }
+    let in_stock = inventory
+        .query()
+        .sizes(9..=11)
+        .in_stock()
+        .sort_by(SortKey::Stock, Order::Descending);
+    for item in in_stock.iter() {
+        println!("{}", item);
+    }
+    let page = inventory.query().page(2, 3);
+    println!(
+        "Page {} of {}: {} item(s)",
+        page.number,
+        page.pages(),
+        page.items.len()
+    );
 }
 
 #[cfg(test)]
@@ -4,10 +4,15 @@
 
 pub use self::kinds::PrimaryColor;
 pub use self::kinds::SecondaryColor;
+pub use self::raster::{BlendMode, Palette, Raster, Rgba};
 pub use self::utils::mix;
 
+pub mod pnm;
+pub mod raster;
+
 pub mod kinds {
     /// The primary colors according to the RYB color model.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum PrimaryColor {
         Red,
         Yellow,
@@ -15,6 +20,7 @@ pub mod kinds {
     }
 
     /// The secondary colors according to the RYB color model.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum SecondaryColor {
         Orange,
         Green,
@@ -26,7 +32,7 @@ pub mod utils {
     use crate::kinds::*;
 
     /// Combines two primary colors in equal amounts to create a secondary color.
-    pub fn mix(c1: PrimaryColor, c2: PrimaryColor) -> SecondaryColor {
+    pub fn mix(_c1: PrimaryColor, _c2: PrimaryColor) -> SecondaryColor {
         // --snip--
         SecondaryColor::Orange
     }
@@ -0,0 +1,369 @@
+//! Reading and writing Netpbm (PNM) images.
+//!
+//! All six classic formats can be read: bitmaps (`P1`/`P4`), graymaps (`P2`/`P5`) and pixmaps
+//! (`P3`/`P6`), with sample values up to 65535 scaled down to 8 bits. Rasters are written as
+//! pixmaps. PNM has no alpha channel, so pixels read are opaque and alpha is dropped on write.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::raster::{Raster, Rgba};
+
+/// The encoding used when writing a pixmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `P3`: samples as decimal text.
+    Ascii,
+    /// `P6`: samples as raw bytes.
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum PnmError {
+    Io(io::Error),
+    /// The file doesn't start with `P1` to `P6`.
+    UnknownFormat,
+    /// The header or pixel data couldn't be parsed.
+    Malformed(&'static str),
+    /// The file ended before all pixels were read.
+    Truncated,
+}
+
+impl fmt::Display for PnmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PnmError::Io(e) => write!(f, "I/O error: {}", e),
+            PnmError::UnknownFormat => write!(f, "not a PNM file"),
+            PnmError::Malformed(what) => write!(f, "malformed PNM data: {}", what),
+            PnmError::Truncated => write!(f, "PNM data ends early"),
+        }
+    }
+}
+
+impl std::error::Error for PnmError {}
+
+impl From<io::Error> for PnmError {
+    fn from(e: io::Error) -> PnmError {
+        PnmError::Io(e)
+    }
+}
+
+/// Reads a PNM image from `reader`.
+pub fn read<R: Read>(mut reader: R) -> Result<Raster, PnmError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    decode(&data)
+}
+
+/// Reads a PNM image from the file at `path`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Raster, PnmError> {
+    read(File::open(path)?)
+}
+
+/// Writes `raster` to `writer` as a pixmap with 8-bit samples.
+pub fn write<W: Write>(mut writer: W, raster: &Raster, encoding: Encoding) -> io::Result<()> {
+    let magic = match encoding {
+        Encoding::Ascii => "P3",
+        Encoding::Binary => "P6",
+    };
+    write!(
+        writer,
+        "{}\n{} {}\n255\n",
+        magic,
+        raster.width(),
+        raster.height()
+    )?;
+
+    match encoding {
+        Encoding::Ascii => {
+            for row in raster.pixels().chunks(raster.width().max(1) as usize) {
+                let line: Vec<String> = row
+                    .iter()
+                    .map(|p| format!("{} {} {}", p.r, p.g, p.b))
+                    .collect();
+                writeln!(writer, "{}", line.join("  "))?;
+            }
+        }
+        Encoding::Binary => {
+            let bytes: Vec<u8> = raster
+                .pixels()
+                .iter()
+                .flat_map(|p| [p.r, p.g, p.b])
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Writes `raster` to the file at `path`, replacing it if it exists.
+pub fn save<P: AsRef<Path>>(path: P, raster: &Raster, encoding: Encoding) -> io::Result<()> {
+    write(BufWriter::new(File::create(path)?), raster, encoding)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Bitmap,
+    Graymap,
+    Pixmap,
+}
+
+fn decode(data: &[u8]) -> Result<Raster, PnmError> {
+    let (kind, binary) = match data.get(..2) {
+        Some(b"P1") => (Kind::Bitmap, false),
+        Some(b"P2") => (Kind::Graymap, false),
+        Some(b"P3") => (Kind::Pixmap, false),
+        Some(b"P4") => (Kind::Bitmap, true),
+        Some(b"P5") => (Kind::Graymap, true),
+        Some(b"P6") => (Kind::Pixmap, true),
+        _ => return Err(PnmError::UnknownFormat),
+    };
+
+    let mut cursor = Cursor { data, pos: 2 };
+    let width = cursor.header_number()?;
+    let height = cursor.header_number()?;
+    let maxval = if kind == Kind::Bitmap {
+        1
+    } else {
+        cursor.header_number()?
+    };
+    if maxval == 0 || maxval > 65535 {
+        return Err(PnmError::Malformed("maxval must be between 1 and 65535"));
+    }
+    // The header is untrusted, so sizes derived from it are checked before anything is
+    // allocated or sliced with them.
+    let too_large = PnmError::Malformed("image dimensions too large");
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .ok_or(too_large)?;
+
+    let pixels = if binary {
+        // A single whitespace byte separates the header from the raster data.
+        if !cursor.next_byte().is_some_and(|b| b.is_ascii_whitespace()) {
+            return Err(PnmError::Malformed("missing whitespace after header"));
+        }
+        let body = &data[cursor.pos..];
+        match kind {
+            Kind::Bitmap => decode_packed_bits(body, width as usize, height as usize)?,
+            Kind::Graymap | Kind::Pixmap => {
+                let channels = if kind == Kind::Pixmap { 3 } else { 1 };
+                let sample_size = if maxval > 255 { 2 } else { 1 };
+                let needed = count
+                    .checked_mul(channels * sample_size)
+                    .ok_or(PnmError::Malformed("image dimensions too large"))?;
+                if body.len() < needed {
+                    return Err(PnmError::Truncated);
+                }
+                let samples: Vec<u32> = body[..needed]
+                    .chunks(sample_size)
+                    .map(|s| s.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+                    .collect();
+                to_pixels(&samples, channels, maxval)?
+            }
+        }
+    } else {
+        let channels = if kind == Kind::Pixmap { 3 } else { 1 };
+        let total = count
+            .checked_mul(channels)
+            .ok_or(PnmError::Malformed("image dimensions too large"))?;
+        // Every sample takes at least one byte, so the data left bounds how many there can be.
+        let mut samples = Vec::with_capacity(total.min(data.len().saturating_sub(cursor.pos)));
+        for _ in 0..total {
+            let sample = if kind == Kind::Bitmap {
+                // Plain bitmaps may pack digits together without whitespace.
+                cursor.skip_whitespace();
+                match cursor.next_byte() {
+                    Some(b'0') => 0,
+                    Some(b'1') => 1,
+                    Some(_) => return Err(PnmError::Malformed("bitmap digits must be 0 or 1")),
+                    None => return Err(PnmError::Truncated),
+                }
+            } else {
+                cursor.number()?.ok_or(PnmError::Truncated)?
+            };
+            samples.push(sample);
+        }
+        if kind == Kind::Bitmap {
+            samples.iter().map(|&s| bit_to_pixel(s == 1)).collect()
+        } else {
+            to_pixels(&samples, channels, maxval)?
+        }
+    };
+
+    Ok(Raster::from_pixels(width, height, pixels).expect("pixel count matches dimensions"))
+}
+
+// In bitmaps 1 means ink, so it is black.
+fn bit_to_pixel(set: bool) -> Rgba {
+    if set {
+        Rgba::BLACK
+    } else {
+        Rgba::WHITE
+    }
+}
+
+// Each row of a `P4` bitmap starts on a byte boundary, most significant bit first.
+fn decode_packed_bits(body: &[u8], width: usize, height: usize) -> Result<Vec<Rgba>, PnmError> {
+    // An empty image has no rows to split the data into.
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+    let row_bytes = width.div_ceil(8);
+    let needed = row_bytes
+        .checked_mul(height)
+        .ok_or(PnmError::Malformed("image dimensions too large"))?;
+    if body.len() < needed {
+        return Err(PnmError::Truncated);
+    }
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in body.chunks(row_bytes).take(height) {
+        for x in 0..width {
+            pixels.push(bit_to_pixel(row[x / 8] & (0x80 >> (x % 8)) != 0));
+        }
+    }
+    Ok(pixels)
+}
+
+fn to_pixels(samples: &[u32], channels: usize, maxval: u32) -> Result<Vec<Rgba>, PnmError> {
+    if samples.iter().any(|&s| s > maxval) {
+        return Err(PnmError::Malformed("sample larger than maxval"));
+    }
+    let scale = |s: u32| ((s * 255 + maxval / 2) / maxval) as u8;
+    Ok(samples
+        .chunks(channels)
+        .map(|s| match *s {
+            [v] => Rgba::opaque(scale(v), scale(v), scale(v)),
+            [r, g, b] => Rgba::opaque(scale(r), scale(g), scale(b)),
+            _ => unreachable!("graymaps have one channel and pixmaps three"),
+        })
+        .collect())
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn next_byte(&mut self) -> Option<u8> {
+        let b = self.data.get(self.pos).copied();
+        self.pos += 1;
+        b
+    }
+
+    // Skips whitespace and `#` comments, which run to the end of the line.
+    fn skip_whitespace(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if b == b'#' {
+                while !matches!(self.data.get(self.pos), None | Some(b'\n' | b'\r')) {
+                    self.pos += 1;
+                }
+            } else if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<u32>, PnmError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return match self.data.get(self.pos) {
+                None => Ok(None),
+                Some(_) => Err(PnmError::Malformed("expected a number")),
+            };
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Some)
+            .ok_or(PnmError::Malformed("number too large"))
+    }
+
+    fn header_number(&mut self) -> Result<u32, PnmError> {
+        self.number()?.ok_or(PnmError::Truncated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Raster {
+        let mut raster = Raster::filled(3, 2, Rgba::WHITE);
+        raster.set(0, 0, Rgba::opaque(255, 0, 0));
+        raster.set(2, 1, Rgba::opaque(1, 2, 3));
+        raster
+    }
+
+    #[test]
+    fn round_trips_both_encodings() {
+        for encoding in [Encoding::Ascii, Encoding::Binary] {
+            let mut bytes = Vec::new();
+            write(&mut bytes, &sample(), encoding).unwrap();
+
+            assert_eq!(read(&bytes[..]).unwrap(), sample());
+        }
+    }
+
+    #[test]
+    fn reads_comments_and_wide_graymaps() {
+        let data = b"P2\n# a comment\n2 1 # trailing\n65535\n0 65535\n";
+
+        let raster = read(&data[..]).unwrap();
+
+        assert_eq!(raster.pixels(), &[Rgba::BLACK, Rgba::WHITE]);
+    }
+
+    #[test]
+    fn reads_packed_bitmaps() {
+        let mut data = b"P4\n10 1\n".to_vec();
+        data.extend_from_slice(&[0b1000_0000, 0b0100_0000]);
+
+        let raster = read(&data[..]).unwrap();
+
+        assert_eq!(raster.get(0, 0), Some(Rgba::BLACK));
+        assert_eq!(raster.get(1, 0), Some(Rgba::WHITE));
+        assert_eq!(raster.get(9, 0), Some(Rgba::BLACK));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        assert!(matches!(
+            read(&b"P6\n2 2\n255\n\x00\x00"[..]),
+            Err(PnmError::Truncated)
+        ));
+        assert!(matches!(read(&b"GIF89a"[..]), Err(PnmError::UnknownFormat)));
+    }
+
+    #[test]
+    fn rejects_headers_larger_than_the_data() {
+        assert!(matches!(
+            read(&b"P6\n4294967295 4294967295\n255\n1"[..]),
+            Err(PnmError::Malformed(_) | PnmError::Truncated)
+        ));
+        assert!(matches!(
+            read(&b"P2\n100000 100000\n255\n1 2"[..]),
+            Err(PnmError::Truncated)
+        ));
+        assert!(matches!(
+            read(&b"P4\n4294967295 4294967295\n\x00"[..]),
+            Err(PnmError::Malformed(_) | PnmError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn reads_empty_bitmaps() {
+        for header in [&b"P4\n0 5\n"[..], b"P4\n5 0\n", b"P4\n0 0\n"] {
+            let raster = read(header).unwrap();
+            assert_eq!(raster.width() * raster.height(), 0);
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! In-memory RGBA images.
+//!
+//! A `Raster` is a grid of `Rgba` pixels stored row by row. It can be blended with another
+//! raster, reduced to a palette of RYB colors and dithered, and read from or written to PNM
+//! files through the `pnm` module.
+
+use crate::kinds::{PrimaryColor, SecondaryColor};
+
+/// A color with red, green, blue and alpha channels, 8 bits each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Rgba = Rgba::new(0, 0, 0, 0);
+    pub const BLACK: Rgba = Rgba::opaque(0, 0, 0);
+    pub const WHITE: Rgba = Rgba::opaque(255, 255, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+        Rgba { r, g, b, a }
+    }
+
+    /// Creates a fully opaque color.
+    pub const fn opaque(r: u8, g: u8, b: u8) -> Rgba {
+        Rgba::new(r, g, b, 255)
+    }
+
+    fn channels(self) -> [u8; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// Squared euclidean distance between the color channels, ignoring alpha.
+    fn distance_sq(self, other: Rgba) -> u32 {
+        self.channels()
+            .iter()
+            .zip(other.channels().iter())
+            .map(|(&a, &b)| {
+                let d = a as i32 - b as i32;
+                (d * d) as u32
+            })
+            .sum()
+    }
+}
+
+impl From<PrimaryColor> for Rgba {
+    fn from(color: PrimaryColor) -> Rgba {
+        match color {
+            PrimaryColor::Red => Rgba::opaque(255, 0, 0),
+            PrimaryColor::Yellow => Rgba::opaque(255, 255, 0),
+            PrimaryColor::Blue => Rgba::opaque(0, 0, 255),
+        }
+    }
+}
+
+impl From<SecondaryColor> for Rgba {
+    fn from(color: SecondaryColor) -> Rgba {
+        match color {
+            SecondaryColor::Orange => Rgba::opaque(255, 165, 0),
+            SecondaryColor::Green => Rgba::opaque(0, 128, 0),
+            SecondaryColor::Purple => Rgba::opaque(128, 0, 128),
+        }
+    }
+}
+
+/// How the color channels of a top layer combine with the layer below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The top color replaces the base color.
+    Normal,
+    /// Darkens: the channels are multiplied together.
+    Multiply,
+    /// Lightens: the inverted channels are multiplied together and inverted back.
+    Screen,
+    /// Multiplies dark base channels and screens light ones.
+    Overlay,
+}
+
+impl BlendMode {
+    /// Blends `top` onto `base`, then composites the result using the alpha of `top`.
+    pub fn blend(self, base: Rgba, top: Rgba) -> Rgba {
+        let channel = |b: u8, t: u8| -> u32 {
+            let (b, t) = (b as u32, t as u32);
+            match self {
+                BlendMode::Normal => t,
+                BlendMode::Multiply => mul_div255(b, t),
+                BlendMode::Screen => 255 - mul_div255(255 - b, 255 - t),
+                BlendMode::Overlay => {
+                    if b < 128 {
+                        mul_div255(2 * b, t)
+                    } else {
+                        255 - mul_div255(2 * (255 - b), 255 - t)
+                    }
+                }
+            }
+        };
+        let alpha = top.a as u32;
+        let over = |b: u8, t: u8| -> u8 {
+            let mixed = channel(b, t);
+            (mul_div255(mixed, alpha) + mul_div255(b as u32, 255 - alpha)) as u8
+        };
+
+        Rgba {
+            r: over(base.r, top.r),
+            g: over(base.g, top.g),
+            b: over(base.b, top.b),
+            a: (alpha + mul_div255(base.a as u32, 255 - alpha)) as u8,
+        }
+    }
+}
+
+// Computes `a * b / 255` rounded to the nearest integer.
+fn mul_div255(a: u32, b: u32) -> u32 {
+    (a * b + 127) / 255
+}
+
+/// A fixed set of colors that an image can be reduced to.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Palette {
+    colors: Vec<Rgba>,
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        Palette { colors: Vec::new() }
+    }
+
+    /// The three primary and three secondary colors of the RYB model.
+    pub fn ryb() -> Palette {
+        Palette::new()
+            .with(PrimaryColor::Red)
+            .with(PrimaryColor::Yellow)
+            .with(PrimaryColor::Blue)
+            .with(SecondaryColor::Orange)
+            .with(SecondaryColor::Green)
+            .with(SecondaryColor::Purple)
+    }
+
+    /// Adds a color to the palette.
+    pub fn with(mut self, color: impl Into<Rgba>) -> Palette {
+        self.colors.push(color.into());
+        self
+    }
+
+    pub fn colors(&self) -> &[Rgba] {
+        &self.colors
+    }
+
+    /// Returns the palette color closest to `color`, keeping the alpha of `color`.
+    ///
+    /// An empty palette returns `color` unchanged.
+    pub fn nearest(&self, color: Rgba) -> Rgba {
+        match self.colors.iter().min_by_key(|c| c.distance_sq(color)) {
+            Some(c) => Rgba { a: color.a, ..*c },
+            None => color,
+        }
+    }
+}
+
+/// An image made of `width * height` pixels stored in row-major order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Raster {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl Raster {
+    /// Creates a fully transparent raster.
+    pub fn new(width: u32, height: u32) -> Raster {
+        Raster::filled(width, height, Rgba::TRANSPARENT)
+    }
+
+    /// Creates a raster where every pixel is `color`.
+    pub fn filled(width: u32, height: u32, color: impl Into<Rgba>) -> Raster {
+        Raster {
+            width,
+            height,
+            pixels: vec![color.into(); width as usize * height as usize],
+        }
+    }
+
+    /// Wraps existing pixels. Returns `None` if their count doesn't match the dimensions.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Rgba>) -> Option<Raster> {
+        if pixels.len() != width as usize * height as usize {
+            return None;
+        }
+        Some(Raster {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the pixel at `(x, y)`, or `None` if it lies outside the raster.
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgba> {
+        self.index(x, y).map(|i| self.pixels[i])
+    }
+
+    /// Sets the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` lies outside the raster.
+    pub fn set(&mut self, x: u32, y: u32, color: impl Into<Rgba>) {
+        let i = self.index(x, y).unwrap_or_else(|| {
+            panic!(
+                "pixel ({}, {}) is outside a {}x{} raster",
+                x, y, self.width, self.height
+            )
+        });
+        self.pixels[i] = color.into();
+    }
+
+    /// Blends `top` onto this raster, aligned at the top-left corner.
+    ///
+    /// Only the area covered by both rasters is affected.
+    pub fn blend(&mut self, top: &Raster, mode: BlendMode) {
+        for y in 0..self.height.min(top.height) {
+            for x in 0..self.width.min(top.width) {
+                let i = y as usize * self.width as usize + x as usize;
+                let t = y as usize * top.width as usize + x as usize;
+                self.pixels[i] = mode.blend(self.pixels[i], top.pixels[t]);
+            }
+        }
+    }
+
+    /// Replaces every pixel with the nearest color in `palette`.
+    pub fn quantize(&mut self, palette: &Palette) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = palette.nearest(*pixel);
+        }
+    }
+
+    /// Reduces the raster to `palette` using Floyd-Steinberg error diffusion.
+    ///
+    /// The rounding error of each pixel is pushed onto its unvisited neighbours, so areas of
+    /// in-between colors become patterns of palette colors instead of flat bands.
+    pub fn dither(&mut self, palette: &Palette) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        // Working copy of the color channels that can go out of 0..=255 while errors spread.
+        let mut work: Vec<[i32; 3]> = self
+            .pixels
+            .iter()
+            .map(|p| [p.r as i32, p.g as i32, p.b as i32])
+            .collect();
+
+        for y in 0..h {
+            for x in 0..w {
+                let i = y * w + x;
+                let [r, g, b] = work[i].map(|c| c.clamp(0, 255) as u8);
+                let chosen = palette.nearest(Rgba::new(r, g, b, self.pixels[i].a));
+                let error = [
+                    work[i][0] - chosen.r as i32,
+                    work[i][1] - chosen.g as i32,
+                    work[i][2] - chosen.b as i32,
+                ];
+                self.pixels[i] = chosen;
+
+                let mut spread = |nx: usize, ny: usize, weight: i32| {
+                    let j = ny * w + nx;
+                    for c in 0..3 {
+                        work[j][c] += error[c] * weight / 16;
+                    }
+                };
+                if x + 1 < w {
+                    spread(x + 1, y, 7);
+                }
+                if y + 1 < h {
+                    if x > 0 {
+                        spread(x - 1, y + 1, 3);
+                    }
+                    spread(x, y + 1, 5);
+                    if x + 1 < w {
+                        spread(x + 1, y + 1, 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiply_and_screen_with_white_and_black() {
+        let gray = Rgba::opaque(100, 150, 200);
+
+        assert_eq!(BlendMode::Multiply.blend(gray, Rgba::WHITE), gray);
+        assert_eq!(BlendMode::Multiply.blend(gray, Rgba::BLACK), Rgba::BLACK);
+        assert_eq!(BlendMode::Screen.blend(gray, Rgba::BLACK), gray);
+        assert_eq!(BlendMode::Screen.blend(gray, Rgba::WHITE), Rgba::WHITE);
+    }
+
+    #[test]
+    fn overlay_keeps_contrast_of_base() {
+        let mid = Rgba::opaque(128, 128, 128);
+
+        assert_eq!(
+            BlendMode::Overlay.blend(Rgba::opaque(64, 192, 0), mid),
+            Rgba::opaque(64, 192, 0)
+        );
+    }
+
+    #[test]
+    fn transparent_top_leaves_base_untouched() {
+        let base = Rgba::opaque(10, 20, 30);
+
+        for mode in [
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+        ] {
+            assert_eq!(mode.blend(base, Rgba::TRANSPARENT), base);
+        }
+    }
+
+    #[test]
+    fn quantize_snaps_to_ryb_colors() {
+        let mut raster = Raster::filled(2, 1, Rgba::opaque(240, 10, 10));
+        raster.set(1, 0, Rgba::opaque(10, 140, 20));
+
+        raster.quantize(&Palette::ryb());
+
+        assert_eq!(raster.get(0, 0), Some(PrimaryColor::Red.into()));
+        assert_eq!(raster.get(1, 0), Some(SecondaryColor::Green.into()));
+    }
+
+    #[test]
+    fn dither_mixes_palette_colors() {
+        let palette = Palette::new().with(Rgba::BLACK).with(Rgba::WHITE);
+        let mut raster = Raster::filled(8, 8, Rgba::opaque(128, 128, 128));
+
+        raster.dither(&palette);
+
+        let white = raster
+            .pixels()
+            .iter()
+            .filter(|&&p| p == Rgba::WHITE)
+            .count();
+        assert!(raster.pixels().iter().all(|p| palette.colors().contains(p)));
+        assert!((24..=40).contains(&white), "{} white pixels", white);
+    }
+}
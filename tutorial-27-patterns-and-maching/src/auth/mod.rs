@@ -0,0 +1,397 @@
+// Role-based authorization, in place of the `if let ... else if` chain over
+// `authorization_status`, `is_admin` and `group_id` in main.rs.
+//
+// A policy defines roles, each allowing or denying actions on resources, and each able to
+// inherit everything another role has. A subject gets roles directly, through the groups it's
+// in, or, when neither gives it any, the policy's default role. Asking whether a subject may do
+// something gives a `Decision` that says why:
+//
+//   - a matching deny in any of the subject's roles wins,
+//   - otherwise a matching allow permits it,
+//   - otherwise it's denied, since nothing allowed it.
+//
+// Policies are usually read from a file; see `policy` for the format.
+
+mod pattern;
+mod policy;
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub use crate::text::LoadError;
+pub use pattern::glob_matches;
+pub use policy::PolicyError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+// Permission to do, or not do, an action on the resources matching a pattern. Either can be
+// `*` for any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    pub effect: Effect,
+    pub action: String,
+    pub resource: String,
+}
+
+impl Grant {
+    fn covers(&self, action: &str, resource: &str) -> bool {
+        (self.action == "*" || self.action == action) && glob_matches(&self.resource, resource)
+    }
+}
+
+impl fmt::Display for Grant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let effect = match self.effect {
+            Effect::Allow => "allow",
+            Effect::Deny => "deny",
+        };
+        write!(f, "{} {} {}", effect, self.action, self.resource)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Role {
+    pub name: String,
+    // Roles whose grants this one has as well.
+    pub inherits: Vec<String>,
+    pub grants: Vec<Grant>,
+}
+
+impl Role {
+    pub fn new(name: &str) -> Role {
+        Role {
+            name: String::from(name),
+            inherits: Vec::new(),
+            grants: Vec::new(),
+        }
+    }
+
+    pub fn with_parent(mut self, role: &str) -> Self {
+        self.inherits.push(String::from(role));
+        self
+    }
+
+    pub fn with_allow(self, action: &str, resource: &str) -> Self {
+        self.with_grant(Effect::Allow, action, resource)
+    }
+
+    pub fn with_deny(self, action: &str, resource: &str) -> Self {
+        self.with_grant(Effect::Deny, action, resource)
+    }
+
+    fn with_grant(mut self, effect: Effect, action: &str, resource: &str) -> Self {
+        self.grants.push(Grant {
+            effect,
+            action: String::from(action),
+            resource: String::from(resource),
+        });
+        self
+    }
+}
+
+// Which groups a group-to-role mapping applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupMatch {
+    Name(String),
+    // Groups whose name is a number in this range, both ends included.
+    Ids(u64, u64),
+}
+
+impl GroupMatch {
+    fn matches(&self, group: &str) -> bool {
+        match self {
+            GroupMatch::Name(name) => name == group,
+            GroupMatch::Ids(low, high) => group
+                .parse::<u64>()
+                .is_ok_and(|id| (*low..=*high).contains(&id)),
+        }
+    }
+}
+
+impl fmt::Display for GroupMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupMatch::Name(name) => write!(f, "{}", name),
+            GroupMatch::Ids(low, high) => write!(f, "{}..={}", low, high),
+        }
+    }
+}
+
+// Who is asking.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subject {
+    pub name: String,
+    pub roles: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl Subject {
+    pub fn new(name: &str) -> Subject {
+        Subject {
+            name: String::from(name),
+            ..Subject::default()
+        }
+    }
+
+    pub fn with_role(mut self, role: &str) -> Self {
+        self.roles.push(String::from(role));
+        self
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.groups.push(String::from(group));
+        self
+    }
+}
+
+// How a subject came to have a role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Direct,
+    Group(String),
+    Default,
+    Inherited(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Direct => write!(f, "given directly"),
+            Source::Group(group) => write!(f, "from group {}", group),
+            Source::Default => write!(f, "by default"),
+            Source::Inherited(role) => write!(f, "inherited from {}", role),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub subject: String,
+    pub action: String,
+    pub resource: String,
+    // Every role the subject had, and how.
+    pub roles: Vec<(String, Source)>,
+    // The grant that decided, and the role it belongs to. None when nothing matched.
+    pub decided_by: Option<(String, Grant)>,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verdict = if self.allowed { "allowed" } else { "denied" };
+        writeln!(
+            f,
+            "{}: {} {} {}",
+            verdict, self.subject, self.action, self.resource
+        )?;
+        match &self.decided_by {
+            Some((role, grant)) => writeln!(f, "  because role {} has '{}'", role, grant)?,
+            None => writeln!(f, "  because no role allows it")?,
+        }
+        write!(f, "  roles:")?;
+        if self.roles.is_empty() {
+            write!(f, " none")?;
+        }
+        for (i, (role, source)) in self.roles.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{} ({})", separator, role, source)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    roles: HashMap<String, Role>,
+    groups: Vec<(GroupMatch, String)>,
+    default_role: Option<String>,
+}
+
+impl Policy {
+    pub fn new() -> Policy {
+        Policy::default()
+    }
+
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.roles.insert(role.name.clone(), role);
+        self
+    }
+
+    pub fn with_group(mut self, groups: GroupMatch, role: &str) -> Self {
+        self.groups.push((groups, String::from(role)));
+        self
+    }
+
+    // The role for subjects that get none otherwise.
+    pub fn with_default_role(mut self, role: &str) -> Self {
+        self.default_role = Some(String::from(role));
+        self
+    }
+
+    pub fn role(&self, name: &str) -> Option<&Role> {
+        self.roles.get(name)
+    }
+
+    // The subject's roles, with how it got each: its own, then its groups', then the default if
+    // it had none, then what those inherit. A role is listed once, with the first way it was got.
+    pub fn roles_of(&self, subject: &Subject) -> Vec<(String, Source)> {
+        let mut roles: Vec<(String, Source)> = Vec::new();
+        let add = |roles: &mut Vec<(String, Source)>, role: &str, source: Source| {
+            if !roles.iter().any(|(r, _)| r == role) {
+                roles.push((String::from(role), source));
+            }
+        };
+        for role in &subject.roles {
+            add(&mut roles, role, Source::Direct);
+        }
+        for group in &subject.groups {
+            for (groups, role) in &self.groups {
+                if groups.matches(group) {
+                    add(&mut roles, role, Source::Group(group.clone()));
+                }
+            }
+        }
+        if roles.is_empty() {
+            if let Some(role) = &self.default_role {
+                add(&mut roles, role, Source::Default);
+            }
+        }
+        // Grows while it's walked, so inherited roles have their own parents added in turn.
+        let mut i = 0;
+        while i < roles.len() {
+            let name = roles[i].0.clone();
+            if let Some(role) = self.roles.get(&name) {
+                for parent in &role.inherits {
+                    add(&mut roles, parent, Source::Inherited(name.clone()));
+                }
+            }
+            i += 1;
+        }
+        roles
+    }
+
+    pub fn check(&self, subject: &Subject, action: &str, resource: &str) -> Decision {
+        let roles = self.roles_of(subject);
+        let matching = |effect: Effect| {
+            roles.iter().find_map(|(name, _)| {
+                let role = self.roles.get(name)?;
+                let grant = role
+                    .grants
+                    .iter()
+                    .find(|g| g.effect == effect && g.covers(action, resource))?;
+                Some((name.clone(), grant.clone()))
+            })
+        };
+        let decided_by = matching(Effect::Deny).or_else(|| matching(Effect::Allow));
+        Decision {
+            allowed: matches!(&decided_by, Some((_, grant)) if grant.effect == Effect::Allow),
+            subject: subject.name.clone(),
+            action: String::from(action),
+            resource: String::from(resource),
+            roles,
+            decided_by,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Policy {
+        Policy::new()
+            .with_role(Role::new("guest").with_allow("read", "docs/public/*"))
+            .with_role(
+                Role::new("basic")
+                    .with_parent("guest")
+                    .with_allow("read", "docs/*"),
+            )
+            .with_role(
+                Role::new("privileged")
+                    .with_parent("basic")
+                    .with_allow("write", "docs/*")
+                    .with_deny("write", "docs/policies/*"),
+            )
+            .with_role(
+                Role::new("admin")
+                    .with_parent("privileged")
+                    .with_allow("*", "*"),
+            )
+            .with_group(GroupMatch::Ids(31, 255), "privileged")
+            .with_group(GroupMatch::Ids(0, 30), "basic")
+            .with_group(GroupMatch::Name("ops".into()), "admin")
+            .with_default_role("guest")
+    }
+
+    #[test]
+    fn gathers_roles_with_their_sources() {
+        let policy = policy();
+        let roles = policy.roles_of(&Subject::new("ann").with_group("34"));
+        assert_eq!(
+            roles,
+            [
+                ("privileged".into(), Source::Group("34".into())),
+                ("basic".into(), Source::Inherited("privileged".into())),
+                ("guest".into(), Source::Inherited("basic".into())),
+            ]
+        );
+        let roles = policy.roles_of(&Subject::new("bob").with_group("staff"));
+        assert_eq!(roles, [("guest".into(), Source::Default)]);
+        let roles = policy.roles_of(&Subject::new("cy").with_role("basic").with_group("7"));
+        assert_eq!(roles.len(), 2);
+        assert_eq!(roles[0], ("basic".into(), Source::Direct));
+    }
+
+    #[test]
+    fn denies_win_over_allows() {
+        let policy = policy();
+        let ann = Subject::new("ann").with_group("34");
+        assert!(policy.check(&ann, "write", "docs/report").allowed);
+        assert!(!policy.check(&ann, "write", "docs/policies/leave").allowed);
+        // Even for an admin, whose `* *` would otherwise allow it.
+        let root = Subject::new("root").with_group("ops");
+        assert!(!policy.check(&root, "write", "docs/policies/leave").allowed);
+        assert!(policy.check(&root, "delete", "servers/db1").allowed);
+
+        let guest = Subject::new("guest");
+        assert!(policy.check(&guest, "read", "docs/public/faq").allowed);
+        assert!(!policy.check(&guest, "read", "docs/report").allowed);
+    }
+
+    #[test]
+    fn explains_decisions() {
+        let policy = policy();
+        let decision = policy.check(
+            &Subject::new("ann").with_group("34"),
+            "write",
+            "docs/policies/leave",
+        );
+        let expected = [
+            "denied: ann write docs/policies/leave",
+            "  because role privileged has 'deny write docs/policies/*'",
+            "  roles: privileged (from group 34), basic (inherited from privileged), \
+             guest (inherited from basic)",
+        ];
+        assert_eq!(decision.to_string(), expected.join("\n"));
+
+        let decision = Policy::new().check(&Subject::new("x"), "read", "a");
+        assert_eq!(
+            decision.to_string(),
+            "denied: x read a\n  because no role allows it\n  roles: none"
+        );
+    }
+
+    #[test]
+    fn survives_inheritance_cycles() {
+        let policy = Policy::new()
+            .with_role(Role::new("a").with_parent("b"))
+            .with_role(Role::new("b").with_parent("a").with_allow("read", "*"));
+        let decision = policy.check(&Subject::new("x").with_role("a"), "read", "r");
+        assert!(decision.allowed);
+        assert_eq!(decision.roles.len(), 2);
+    }
+}
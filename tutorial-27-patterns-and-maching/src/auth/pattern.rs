@@ -0,0 +1,47 @@
+// Matching resource names like "docs/2024/report" against patterns like "docs/*".
+
+// `*` matches any run of characters, '/' included; everything else matches itself.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it had taken when we last retried.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the `*` take one more character and try again from there.
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(glob_matches("docs/*", "docs/report"));
+        assert!(glob_matches("docs/*", "docs/2024/report"));
+        assert!(glob_matches("docs/*", "docs/"));
+        assert!(!glob_matches("docs/*", "doc"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*/report", "docs/2024/report"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+        assert!(glob_matches("exact", "exact"));
+        assert!(!glob_matches("exact", "exactly"));
+        assert!(glob_matches("日本/*", "日本/東京"));
+    }
+}
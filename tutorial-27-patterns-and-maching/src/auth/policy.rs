@@ -0,0 +1,339 @@
+// Reading a `Policy` from text:
+//
+//   # Lines starting with '#' are comments
+//   role guest
+//     allow read docs/public/*
+//   role privileged inherits basic, auditor
+//     allow write docs/*
+//     deny write docs/policies/*
+//
+//   group 31..=255 privileged
+//   group ops admin
+//   default guest
+//
+// `allow` and `deny` lines belong to the role above them. A `group` line gives a role to a named
+// group, or to every group whose name is a number in a range. A role can be named before the line
+// defining it, but it must be defined somewhere in the file, and inheritance may not go round in
+// a circle.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::{Effect, Grant, GroupMatch, Policy, Role};
+use crate::text::{self, LineError, LoadError};
+
+// A line of a policy that couldn't be read.
+pub type PolicyError = LineError;
+
+impl Policy {
+    pub fn load(path: impl AsRef<Path>) -> Result<Policy, LoadError> {
+        text::load(path.as_ref(), Policy::parse)
+    }
+
+    pub fn parse(src: &str) -> Result<Policy, PolicyError> {
+        let mut policy = Policy::new();
+        // Where each role was defined, and the lines naming roles that must exist.
+        let mut defined: HashMap<String, usize> = HashMap::new();
+        let mut references: Vec<(String, usize)> = Vec::new();
+        let mut current: Option<String> = None;
+
+        for (i, line) in src.lines().enumerate() {
+            let number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| PolicyError::new(number, message);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["role", name, rest @ ..] => {
+                    check_name(name).map_err(error)?;
+                    if defined.insert(String::from(*name), number).is_some() {
+                        return Err(error(format!("role '{}' is defined twice", name)));
+                    }
+                    let mut role = Role::new(name);
+                    match rest {
+                        [] => {}
+                        ["inherits", ..] if rest.len() > 1 => {
+                            let parents = rest[1..].join(" ");
+                            for parent in parents.split(',').map(str::trim) {
+                                check_name(parent).map_err(error)?;
+                                references.push((String::from(parent), number));
+                                role = role.with_parent(parent);
+                            }
+                        }
+                        _ => {
+                            return Err(error(format!(
+                                "expected 'role <name> [inherits <role>, ...]', found '{}'",
+                                line
+                            )))
+                        }
+                    }
+                    policy = policy.with_role(role);
+                    current = Some(String::from(*name));
+                }
+                [effect @ ("allow" | "deny"), action, resource] => {
+                    let Some(role) = current.as_ref().and_then(|r| policy.roles.get_mut(r)) else {
+                        return Err(error(format!("'{}' outside a role", effect)));
+                    };
+                    role.grants.push(Grant {
+                        effect: if *effect == "allow" {
+                            Effect::Allow
+                        } else {
+                            Effect::Deny
+                        },
+                        action: String::from(*action),
+                        resource: String::from(*resource),
+                    });
+                }
+                ["allow" | "deny", ..] => {
+                    return Err(error(format!(
+                        "expected '{} <action> <resource>', found '{}'",
+                        words[0], line
+                    )))
+                }
+                ["group", groups, role] => {
+                    // Grants after this line would otherwise land in the role above it.
+                    current = None;
+                    references.push((String::from(*role), number));
+                    policy = policy.with_group(parse_groups(groups).map_err(error)?, role);
+                }
+                ["default", role] => {
+                    current = None;
+                    if policy.default_role.is_some() {
+                        return Err(error(String::from("the default role is set twice")));
+                    }
+                    references.push((String::from(*role), number));
+                    policy = policy.with_default_role(role);
+                }
+                [keyword @ ("group" | "default"), ..] => {
+                    let usage = if *keyword == "group" {
+                        "group <name or a..=b> <role>"
+                    } else {
+                        "default <role>"
+                    };
+                    return Err(error(format!("expected '{}', found '{}'", usage, line)));
+                }
+                [keyword, ..] => {
+                    return Err(error(format!(
+                        "expected 'role', 'allow', 'deny', 'group' or 'default', found '{}'",
+                        keyword
+                    )))
+                }
+                [] => unreachable!("blank lines are skipped"),
+            }
+        }
+
+        for (role, line) in references {
+            if !defined.contains_key(&role) {
+                return Err(PolicyError::new(line, format!("no role named '{}'", role)));
+            }
+        }
+        if let Some(cycle) = find_cycle(&policy) {
+            let line = defined[&cycle[0]];
+            return Err(PolicyError::new(
+                line,
+                format!("roles inherit in a circle: {}", cycle.join(" -> ")),
+            ));
+        }
+        Ok(policy)
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(format!("invalid role name '{}'", name))
+    }
+}
+
+// A group name, or `a..=b` or `a..b` for numbered groups.
+fn parse_groups(src: &str) -> Result<GroupMatch, String> {
+    let number = |s: &str| {
+        s.parse::<u64>()
+            .map_err(|_| format!("invalid group range '{}'", src))
+    };
+    // Both kinds of range end up inclusive, and either is an error if nothing is in it.
+    let (low, high) = if let Some((low, high)) = src.split_once("..=") {
+        (number(low)?, Some(number(high)?))
+    } else if let Some((low, high)) = src.split_once("..") {
+        (number(low)?, number(high)?.checked_sub(1))
+    } else {
+        return Ok(GroupMatch::Name(String::from(src)));
+    };
+    match high {
+        Some(high) if low <= high => Ok(GroupMatch::Ids(low, high)),
+        _ => Err(format!("empty group range '{}'", src)),
+    }
+}
+
+// The first circle of inheritance found, starting and ending with the same role. The search is
+// depth-first, with a stack of its own so a long chain of roles can't overflow the real one.
+fn find_cycle(policy: &Policy) -> Option<Vec<String>> {
+    let parents = |name: &str| policy.roles.get(name).map_or(&[][..], |r| &r.inherits[..]);
+    // Sorted, so the same file always reports the same circle.
+    let mut names: Vec<&String> = policy.roles.keys().collect();
+    names.sort();
+    let mut finished: HashSet<&str> = HashSet::new();
+    for name in names {
+        // The roles on the way here, each with how many of its parents have been looked at.
+        let mut path: Vec<(&str, usize)> = vec![(name, 0)];
+        let mut on_path: HashSet<&str> = HashSet::from([name.as_str()]);
+        while let Some(&(role, next)) = path.last() {
+            let Some(parent) = parents(role).get(next) else {
+                path.pop();
+                on_path.remove(role);
+                finished.insert(role);
+                continue;
+            };
+            let top = path.len() - 1;
+            path[top].1 += 1;
+            if on_path.contains(parent.as_str()) {
+                let start = path.iter().position(|(r, _)| r == parent)?;
+                let mut cycle: Vec<String> = path[start..]
+                    .iter()
+                    .map(|(r, _)| String::from(*r))
+                    .collect();
+                cycle.push(parent.clone());
+                return Some(cycle);
+            }
+            if !finished.contains(parent.as_str()) {
+                path.push((parent, 0));
+                on_path.insert(parent);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Subject;
+    use super::*;
+    use crate::text::Scratch;
+
+    const POLICY: &str = "
+        # Who may do what with documents
+        role guest
+          allow read docs/public/*
+        role basic inherits guest
+          allow read docs/*
+        role auditor
+          allow read logs/*
+        role privileged inherits basic, auditor
+          allow write docs/*
+          deny write docs/policies/*
+
+        group 31..=255 privileged
+        group 0..31 basic
+        default guest
+    ";
+
+    #[test]
+    fn reads_a_policy() {
+        let policy = Policy::parse(POLICY).unwrap();
+        let privileged = policy.role("privileged").unwrap();
+        assert_eq!(privileged.inherits, ["basic", "auditor"]);
+        assert_eq!(
+            privileged.grants[1].to_string(),
+            "deny write docs/policies/*"
+        );
+
+        let check = |group: &str, action, resource| {
+            let subject = Subject::new("s").with_group(group);
+            policy.check(&subject, action, resource).allowed
+        };
+        assert!(check("34", "read", "logs/today"));
+        assert!(check("30", "read", "docs/report"));
+        assert!(!check("30", "read", "logs/today"));
+        assert!(!check("guests", "read", "docs/report"));
+        assert!(check("guests", "read", "docs/public/faq"));
+    }
+
+    #[test]
+    fn reports_mistakes_by_line() {
+        let err = |src: &str| Policy::parse(src).unwrap_err().to_string();
+        assert_eq!(err("allow read x"), "line 1: 'allow' outside a role");
+        assert_eq!(err("role a\nrole a"), "line 2: role 'a' is defined twice");
+        assert_eq!(
+            err("role a inherits"),
+            "line 1: expected 'role <name> [inherits <role>, ...]', found 'role a inherits'"
+        );
+        assert_eq!(
+            err("role a\n  allow read"),
+            "line 2: expected 'allow <action> <resource>', found 'allow read'"
+        );
+        assert_eq!(
+            err("role a\ngroup 5..x a"),
+            "line 2: invalid group range '5..x'"
+        );
+        for range in ["3..0", "3..3", "3..=2", "0..0"] {
+            assert_eq!(
+                err(&format!("role a\ngroup {} a", range)),
+                format!("line 2: empty group range '{}'", range)
+            );
+        }
+        assert!(Policy::parse("role a\ngroup 3..=3 a\ngroup 3..4 a").is_ok());
+        // A grant after a group or default line belongs to no role.
+        assert_eq!(
+            err("role a\ngroup 1..=2 a\nallow read x"),
+            "line 3: 'allow' outside a role"
+        );
+        assert_eq!(
+            err("role a\ndefault a\n  deny read x"),
+            "line 3: 'deny' outside a role"
+        );
+        assert_eq!(err("role a\ndefault b"), "line 2: no role named 'b'");
+        assert_eq!(
+            err("role a\ndefault a\ndefault a"),
+            "line 3: the default role is set twice"
+        );
+        assert_eq!(
+            err("permit a"),
+            "line 1: expected 'role', 'allow', 'deny', 'group' or 'default', found 'permit'"
+        );
+        assert_eq!(err("role a/b"), "line 1: invalid role name 'a/b'");
+    }
+
+    #[test]
+    fn rejects_inheritance_cycles() {
+        let src = "role a inherits b\nrole b inherits c\nrole c inherits a\nrole d inherits d";
+        assert_eq!(
+            Policy::parse(src).unwrap_err().to_string(),
+            "line 1: roles inherit in a circle: a -> b -> c -> a"
+        );
+        assert!(Policy::parse("role a\nrole b inherits a\nrole c inherits a, b").is_ok());
+
+        // Long chains are walked without recursing.
+        let chain: Vec<String> = (1..20_000)
+            .map(|i| format!("role r{} inherits r{}", i, i - 1))
+            .collect();
+        let src = format!("role r0\n{}", chain.join("\n"));
+        assert!(Policy::parse(&src).is_ok());
+        let src = format!("role r0 inherits r19999\n{}", chain.join("\n"));
+        assert!(Policy::parse(&src)
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 1: roles inherit in a circle: r0 -> r19999 -> r19998"));
+    }
+
+    #[test]
+    fn loads_from_a_file() {
+        let scratch = Scratch::new("policy-test");
+        let path = scratch.write("policy.txt", POLICY);
+        assert!(Policy::load(&path).unwrap().role("auditor").is_some());
+
+        let path = scratch.write("bad.txt", "allow read *");
+        let err = Policy::load(&path).unwrap_err().to_string();
+        assert!(
+            err.ends_with("bad.txt: line 1: 'allow' outside a role"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// One language's messages, and filling in their placeholders.
+//
+// A catalog file has a message per line:
+//
+//   # Lines starting with '#' are comments
+//   hello = Hello World!
+//   greeting = Hello, {name}!
+//   files.one = {count} file
+//   files.other = {count} files
+//
+// Plural forms of a message are keys ending in the form's name; see `plural`. `\n` in a message
+// is a line break, and `{{` and `}}` are literal braces.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::plural::Plural;
+use crate::text::LineError;
+
+// A line of a catalog that couldn't be read.
+pub type CatalogError = LineError;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    messages: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    pub fn with(mut self, key: &str, message: &str) -> Self {
+        self.messages
+            .insert(String::from(key), String::from(message));
+        self
+    }
+
+    pub fn parse(src: &str) -> Result<Catalog, CatalogError> {
+        let mut catalog = Catalog::new();
+        for (i, line) in src.lines().enumerate() {
+            let error = |message: String| CatalogError::new(i + 1, message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, message)) = line.split_once('=') else {
+                return Err(error(format!("expected 'key = message', found '{}'", line)));
+            };
+            let key = key.trim();
+            let valid = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+            if !valid {
+                return Err(error(format!("invalid key '{}'", key)));
+            }
+            let message = message.trim().replace("\\n", "\n");
+            if catalog
+                .messages
+                .insert(String::from(key), message)
+                .is_some()
+            {
+                return Err(error(format!("'{}' is defined twice", key)));
+            }
+        }
+        Ok(catalog)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    // The `form` of a plural message, or its `other` form if that one is missing.
+    pub fn get_plural(&self, key: &str, form: Plural) -> Option<&str> {
+        self.get(&plural_key(key, form))
+            .or_else(|| self.get(&plural_key(key, Plural::Other)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+pub fn plural_key(key: &str, form: Plural) -> String {
+    format!("{}.{}", key, form)
+}
+
+// Splits "files.one" into "files" and its form.
+pub fn split_plural(key: &str) -> Option<(&str, Plural)> {
+    let (base, form) = key.rsplit_once('.')?;
+    Some((base, Plural::from_name(form)?))
+}
+
+// Replaces each `{name}` with the argument of that name. Placeholders without an argument are
+// left as they are, so the mistake shows.
+pub fn interpolate(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if rest[i..].starts_with("{{") || rest[i..].starts_with("}}") {
+            out.push_str(&rest[i..i + 1]);
+            rest = &after[1..];
+            continue;
+        }
+        let arg = rest[i..]
+            .starts_with('{')
+            .then(|| after.find('}'))
+            .flatten()
+            .and_then(|close| {
+                let (_, value) = args.iter().find(|(name, _)| *name == &after[..close])?;
+                Some((value, close))
+            });
+        match arg {
+            Some((value, close)) => {
+                out.push_str(&value.to_string());
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push_str(&rest[i..i + 1]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_messages() {
+        let catalog = Catalog::parse(
+            "# greetings\nhello = Hello World!\n\ngreeting=Hi, {name}\nlines = a\\nb\nfiles.one = {count} file\nfiles.other = {count} files",
+        )
+        .unwrap();
+        assert_eq!(catalog.get("hello"), Some("Hello World!"));
+        assert_eq!(catalog.get("greeting"), Some("Hi, {name}"));
+        assert_eq!(catalog.get("lines"), Some("a\nb"));
+        assert_eq!(
+            catalog.get_plural("files", Plural::One),
+            Some("{count} file")
+        );
+        assert_eq!(
+            catalog.get_plural("files", Plural::Few),
+            Some("{count} files")
+        );
+        assert_eq!(catalog.get_plural("hello", Plural::One), None);
+        assert_eq!(catalog.len(), 5);
+        assert_eq!(split_plural("files.one"), Some(("files", Plural::One)));
+        assert_eq!(split_plural("menu.open"), None);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let err = |src| Catalog::parse(src).unwrap_err().to_string();
+        assert_eq!(
+            err("hello"),
+            "line 1: expected 'key = message', found 'hello'"
+        );
+        assert_eq!(
+            err("\nhello world = hi"),
+            "line 2: invalid key 'hello world'"
+        );
+        assert_eq!(err("a = 1\na = 2"), "line 2: 'a' is defined twice");
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let name = "Ann";
+        let args: [(&str, &dyn fmt::Display); 2] = [("name", &name), ("count", &3)];
+        assert_eq!(
+            interpolate("{name} has {count} files", &args),
+            "Ann has 3 files"
+        );
+        assert_eq!(
+            interpolate("{{name}} {missing} {", &args),
+            "{name} {missing} {"
+        );
+        assert_eq!(interpolate("}} } {name", &args), "} } {name");
+        assert_eq!(
+            interpolate("こんにちは、{name}さん", &args),
+            "こんにちは、Annさん"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+// Sorting text the way a language's readers expect, in place of comparing code points, which
+// puts "Zoe" before "adam" and "élan" after "zebra".
+//
+// Strings are compared letter by letter on three levels, each only breaking ties of the one
+// before: the base letter, then accents and the like, then case. So "resume" < "Resume" <
+// "résumé". On top of that, per language:
+//
+//   - Spanish has ñ as a letter of its own, between n and o,
+//   - Russian has ё as an е with a mark, and Cyrillic letters before Latin ones,
+//   - Japanese has katakana as hiragana, small kana as big ones and voiced kana (が) as unvoiced
+//     ones with a mark.
+//
+// Kanji and hanzi sort by code point: putting them in reading order, or by pinyin, takes a
+// dictionary far bigger than these tables.
+
+use std::cmp::Ordering;
+
+use super::Language;
+
+// Latin letters with marks, and the letter under them. The mark's position in its group is its
+// weight on the second level.
+const LATIN: &[(char, &str)] = &[
+    ('a', "áàâäãå"),
+    ('c', "ç"),
+    ('e', "éèêë"),
+    ('i', "íìîï"),
+    ('n', "ñ"),
+    ('o', "óòôöõ"),
+    ('u', "úùûü"),
+    ('y', "ýÿ"),
+];
+
+// Each unvoiced kana followed by its voiced and, for the h row, half-voiced forms.
+const KANA_VOICING: &[&str] = &[
+    "かが",
+    "きぎ",
+    "くぐ",
+    "けげ",
+    "こご",
+    "さざ",
+    "しじ",
+    "すず",
+    "せぜ",
+    "そぞ",
+    "ただ",
+    "ちぢ",
+    "つづ",
+    "てで",
+    "とど",
+    "はばぱ",
+    "ひびぴ",
+    "ふぶぷ",
+    "へべぺ",
+    "ほぼぽ",
+];
+
+// Small kana, each just before its big one.
+const SMALL_KANA: &str = "ぁぃぅぇぉっゃゅょゎ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Weights {
+    primary: u32,
+    secondary: u8,
+    tertiary: u8,
+}
+
+fn weights(language: Language, c: char) -> Weights {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    // Katakana sits with hiragana like capitals do with small letters.
+    let katakana = language == Language::Japanese && matches!(c, 'ァ'..='ヶ');
+    let tertiary = u8::from(lower != c || katakana);
+    let (base, secondary) = base_letter(language, lower);
+    // Doubled so a language can slot a letter in just after another one.
+    let mut primary = base as u32 * 2;
+    if language == Language::Spanish && lower == 'ñ' {
+        primary = 'n' as u32 * 2 + 1;
+    }
+    // Everything else comes first, then Cyrillic, then letters of other scripts. Code points
+    // stay below 2^21, so doubled they fit under the script.
+    if language == Language::Russian && base.is_alphabetic() {
+        let script = if matches!(base, '\u{400}'..='\u{4ff}') {
+            1
+        } else {
+            2
+        };
+        primary |= script << 22;
+    }
+    Weights {
+        primary,
+        secondary,
+        tertiary,
+    }
+}
+
+fn base_letter(language: Language, c: char) -> (char, u8) {
+    if let Some((base, marked)) = LATIN.iter().find(|(_, marked)| marked.contains(c)) {
+        let mark = marked.chars().position(|m| m == c).unwrap_or(0);
+        return (*base, mark as u8 + 1);
+    }
+    if c == 'ё' && language == Language::Russian {
+        return ('е', 1);
+    }
+    if language != Language::Japanese {
+        return (c, 0);
+    }
+    // Katakana, moved onto the matching hiragana.
+    let c = match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    };
+    if SMALL_KANA.contains(c) {
+        return (char::from_u32(c as u32 + 1).unwrap_or(c), 1);
+    }
+    for row in KANA_VOICING {
+        if let Some(mark) = row.chars().position(|k| k == c) {
+            return (row.chars().next().unwrap_or(c), mark as u8);
+        }
+    }
+    (c, 0)
+}
+
+impl Language {
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        let a: Vec<Weights> = a.chars().map(|c| weights(self, c)).collect();
+        let b: Vec<Weights> = b.chars().map(|c| weights(self, c)).collect();
+        let level = |key: fn(&Weights) -> u32| a.iter().map(key).cmp(b.iter().map(key));
+        level(|w| w.primary)
+            .then_with(|| level(|w| u32::from(w.secondary)))
+            .then_with(|| level(|w| u32::from(w.tertiary)))
+    }
+
+    pub fn sort<S: AsRef<str>>(self, items: &mut [S]) {
+        items.sort_by(|a, b| self.compare(a.as_ref(), b.as_ref()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(language: Language, words: &[&'static str]) -> Vec<&'static str> {
+        let mut words = words.to_vec();
+        language.sort(&mut words);
+        words
+    }
+
+    #[test]
+    fn ignores_case_and_accents_until_ties() {
+        assert_eq!(
+            sorted(Language::English, &["zebra", "Zoe", "élan", "adam", "Eve"]),
+            ["adam", "élan", "Eve", "zebra", "Zoe"]
+        );
+        assert_eq!(
+            sorted(Language::English, &["Resume", "résumé", "resume"]),
+            ["resume", "Resume", "résumé"]
+        );
+        assert_eq!(Language::English.compare("a", "a"), Ordering::Equal);
+        assert_eq!(Language::English.compare("ab", "a"), Ordering::Greater);
+    }
+
+    #[test]
+    fn has_spanish_n_with_tilde_as_a_letter() {
+        assert_eq!(
+            sorted(Language::Spanish, &["ñu", "nube", "oso", "nzz"]),
+            ["nube", "nzz", "ñu", "oso"]
+        );
+        // Elsewhere it's an n with a mark.
+        assert_eq!(
+            sorted(Language::English, &["ñu", "nube", "nzz"]),
+            ["ñu", "nube", "nzz"]
+        );
+    }
+
+    #[test]
+    fn has_russian_yo_as_ye() {
+        assert_eq!(
+            sorted(Language::Russian, &["ёж", "ель", "Еда", "жук", "дом"]),
+            ["дом", "Еда", "ёж", "ель", "жук"]
+        );
+    }
+
+    #[test]
+    fn puts_cyrillic_before_latin_in_russian() {
+        let words = ["zebra", "ёж", "Apple", "дом", "42"];
+        assert_eq!(
+            sorted(Language::Russian, &words),
+            ["42", "дом", "ёж", "Apple", "zebra"]
+        );
+        assert_eq!(
+            sorted(Language::English, &words),
+            ["42", "Apple", "zebra", "дом", "ёж"]
+        );
+    }
+
+    #[test]
+    fn sorts_kana_together() {
+        assert_eq!(
+            sorted(
+                Language::Japanese,
+                &["カメラ", "がっこう", "かさ", "きって", "かっぱ"]
+            ),
+            ["かさ", "がっこう", "かっぱ", "カメラ", "きって"]
+        );
+        assert_eq!(Language::Japanese.compare("カ", "か"), Ordering::Greater);
+        assert_eq!(Language::Japanese.compare("ゃ", "や"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,151 @@
+// Calendar dates, written the way each language does: 3/14/2024 or "Thursday, March 14, 2024"
+// in English, 14.03.2024 or "четверг, 14 марта 2024 г." in Russian, and so on.
+
+use std::fmt;
+
+use super::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    Short,
+    Long,
+}
+
+impl Date {
+    // None for a day the month doesn't have.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Date> {
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (1..=days)
+            .contains(&day)
+            .then_some(Date { year, month, day })
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    // Counting from 0 for Monday.
+    pub fn weekday(&self) -> usize {
+        // Days since 1970-01-01, a Thursday; see Howard Hinnant's `days_from_civil`.
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from((self.month + 9) % 12);
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+        (days + 3).rem_euclid(7) as usize
+    }
+}
+
+// ISO 8601, the same in every language.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl Language {
+    pub fn format_date(self, date: &Date, style: DateStyle) -> String {
+        let locale = self.locale();
+        let pattern = match style {
+            DateStyle::Short => locale.short_date,
+            DateStyle::Long => locale.long_date,
+        };
+        let mut out = String::new();
+        let mut rest = pattern;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let Some(close) = rest[open..].find('}') else {
+                break;
+            };
+            let field = &rest[open + 1..open + close];
+            match field {
+                "Y" => out.push_str(&date.year.to_string()),
+                "M" => out.push_str(&date.month.to_string()),
+                "MM" => out.push_str(&format!("{:02}", date.month)),
+                "D" => out.push_str(&date.day.to_string()),
+                "DD" => out.push_str(&format!("{:02}", date.day)),
+                "month" => out.push_str(locale.months[date.month as usize - 1]),
+                "weekday" => out.push_str(locale.weekdays[date.weekday()]),
+                _ => out.push_str(&rest[open..=open + close]),
+            }
+            rest = &rest[open + close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_days_and_weekdays() {
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2023, 2, 29).is_none());
+        assert!(Date::new(1900, 2, 29).is_none());
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert!(Date::new(2024, 13, 1).is_none());
+        assert!(Date::new(2024, 4, 31).is_none());
+        let weekday = |y, m, d| Date::new(y, m, d).unwrap().weekday();
+        assert_eq!(weekday(1970, 1, 1), 3);
+        assert_eq!(weekday(2024, 3, 14), 3);
+        assert_eq!(weekday(2000, 1, 1), 5);
+        assert_eq!(weekday(1969, 12, 28), 6);
+        assert_eq!(Date::new(812, 3, 4).unwrap().to_string(), "0812-03-04");
+    }
+
+    #[test]
+    fn formats_in_each_language() {
+        let date = Date::new(2024, 3, 4).unwrap();
+        let formats = |style| {
+            Language::ALL
+                .iter()
+                .map(|language| language.format_date(&date, style))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            formats(DateStyle::Short),
+            [
+                "3/4/2024",
+                "4/3/2024",
+                "04.03.2024",
+                "2024/03/04",
+                "2024/3/4"
+            ]
+        );
+        assert_eq!(
+            formats(DateStyle::Long),
+            [
+                "Monday, March 4, 2024",
+                "lunes, 4 de marzo de 2024",
+                "понедельник, 4 марта 2024\u{a0}г.",
+                "2024年3月4日月曜日",
+                "2024年3月4日星期一",
+            ]
+        );
+    }
+}
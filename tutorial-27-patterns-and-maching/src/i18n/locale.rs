@@ -0,0 +1,222 @@
+// What each language's conventions are for numbers and dates. The tables follow CLDR, kept to
+// what `number` and `date` use.
+
+use super::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocaleData {
+    pub decimal: &'static str,
+    pub group: &'static str,
+    // Numbers need at least this many whole digits before they're grouped: Spanish writes 1234
+    // but 12.345.
+    pub min_grouping_digits: usize,
+    // Date layouts, filled in by `date`: {Y} year, {M} month, {MM} month with two digits, {D}
+    // and {DD} likewise for the day, {month} and {weekday} names.
+    pub short_date: &'static str,
+    pub long_date: &'static str,
+    // As they appear in a long date, January first.
+    pub months: [&'static str; 12],
+    // Monday first.
+    pub weekdays: [&'static str; 7],
+    pub native: Option<NativeNumerals>,
+}
+
+// Numbers written in words of the language's own digits and units, like 一万二千.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NativeNumerals {
+    pub digits: [&'static str; 10],
+    // Ten, hundred and thousand, after an empty one for units.
+    pub small_units: [&'static str; 4],
+    // Ten thousand, and each power of ten thousand above it, after an empty one.
+    pub big_units: [&'static str; 5],
+    // Whether 一 is left out before 十, 百 and 千, as Japanese does: 千百 for 1100.
+    pub bare_units: bool,
+    // Whether skipped digits are marked with a zero, as Chinese does: 一千零五 for 1005.
+    pub marks_zeros: bool,
+    // Whether numbers from ten thousand up are written in digits with only the big units as
+    // words, as Japanese usually writes them: 1000万 and 1億2345万.
+    pub digit_groups: bool,
+    pub minus: &'static str,
+    pub point: &'static str,
+}
+
+static ENGLISH: LocaleData = LocaleData {
+    decimal: ".",
+    group: ",",
+    min_grouping_digits: 4,
+    short_date: "{M}/{D}/{Y}",
+    long_date: "{weekday}, {month} {D}, {Y}",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    native: None,
+};
+
+static SPANISH: LocaleData = LocaleData {
+    decimal: ",",
+    group: ".",
+    min_grouping_digits: 5,
+    short_date: "{D}/{M}/{Y}",
+    long_date: "{weekday}, {D} de {month} de {Y}",
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    weekdays: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    native: None,
+};
+
+static RUSSIAN: LocaleData = LocaleData {
+    decimal: ",",
+    // A no-break space, so a number isn't split across lines.
+    group: "\u{a0}",
+    min_grouping_digits: 5,
+    short_date: "{DD}.{MM}.{Y}",
+    long_date: "{weekday}, {D} {month} {Y}\u{a0}г.",
+    // In the genitive, as a date needs: "14 марта".
+    months: [
+        "января",
+        "февраля",
+        "марта",
+        "апреля",
+        "мая",
+        "июня",
+        "июля",
+        "августа",
+        "сентября",
+        "октября",
+        "ноября",
+        "декабря",
+    ],
+    weekdays: [
+        "понедельник",
+        "вторник",
+        "среда",
+        "четверг",
+        "пятница",
+        "суббота",
+        "воскресенье",
+    ],
+    native: None,
+};
+
+static JAPANESE: LocaleData = LocaleData {
+    decimal: ".",
+    group: ",",
+    min_grouping_digits: 4,
+    short_date: "{Y}/{MM}/{DD}",
+    long_date: "{Y}年{M}月{D}日{weekday}",
+    months: [
+        "1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月",
+    ],
+    weekdays: [
+        "月曜日",
+        "火曜日",
+        "水曜日",
+        "木曜日",
+        "金曜日",
+        "土曜日",
+        "日曜日",
+    ],
+    native: Some(NativeNumerals {
+        digits: ["〇", "一", "二", "三", "四", "五", "六", "七", "八", "九"],
+        small_units: ["", "十", "百", "千"],
+        big_units: ["", "万", "億", "兆", "京"],
+        bare_units: true,
+        marks_zeros: false,
+        digit_groups: true,
+        minus: "マイナス",
+        point: "点",
+    }),
+};
+
+static CHINESE: LocaleData = LocaleData {
+    decimal: ".",
+    group: ",",
+    min_grouping_digits: 4,
+    short_date: "{Y}/{M}/{D}",
+    long_date: "{Y}年{M}月{D}日{weekday}",
+    months: [
+        "一月",
+        "二月",
+        "三月",
+        "四月",
+        "五月",
+        "六月",
+        "七月",
+        "八月",
+        "九月",
+        "十月",
+        "十一月",
+        "十二月",
+    ],
+    weekdays: [
+        "星期一",
+        "星期二",
+        "星期三",
+        "星期四",
+        "星期五",
+        "星期六",
+        "星期日",
+    ],
+    native: Some(NativeNumerals {
+        digits: ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"],
+        small_units: ["", "十", "百", "千"],
+        big_units: ["", "万", "亿", "万亿", "亿亿"],
+        bare_units: false,
+        marks_zeros: true,
+        digit_groups: false,
+        minus: "负",
+        point: "点",
+    }),
+};
+
+impl Language {
+    pub fn locale(self) -> &'static LocaleData {
+        match self {
+            Language::English => &ENGLISH,
+            Language::Spanish => &SPANISH,
+            Language::Russian => &RUSSIAN,
+            Language::Japanese => &JAPANESE,
+            Language::Chinese => &CHINESE,
+        }
+    }
+}
@@ -0,0 +1,456 @@
+// Translated messages, in place of the `match language { ... }` of hardcoded strings in main.rs.
+//
+// Each locale has a `Catalog` of messages, usually read from a file named after it ("en.txt",
+// "zh-TW.txt"). Looking a message up for a locale tries a chain of locales: the locale itself,
+// then what it falls back to, then the default. Without a fallback set for it, a locale falls
+// back to itself less its last part, so "zh-TW" tries "zh-TW", then "zh", then the default.
+//
+// `lint` lists the messages some catalogs have and others lack, so a missing translation is
+// found before someone sees the fallback.
+//
+// Each `Language` also knows how to write numbers and dates and how to sort text, from the
+// tables in `locale`; see `number`, `date` and `collate`.
+
+mod catalog;
+mod collate;
+mod date;
+mod locale;
+mod number;
+mod plural;
+
+use std::collections::{BTreeSet, HashMap};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::text;
+
+pub use crate::text::LoadError;
+pub use catalog::{interpolate, Catalog, CatalogError};
+pub use date::{Date, DateStyle};
+pub use locale::{LocaleData, NativeNumerals};
+pub use number::NumberFormat;
+pub use plural::Plural;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+    Spanish,
+    Russian,
+    Japanese,
+    Chinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 5] = [
+        Language::English,
+        Language::Spanish,
+        Language::Russian,
+        Language::Japanese,
+        Language::Chinese,
+    ];
+
+    // The ISO 639-1 code, which is also the locale tag of its catalog.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+            Language::Russian => "ru",
+            Language::Japanese => "ja",
+            Language::Chinese => "zh",
+        }
+    }
+
+    // The language's name for itself.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+            Language::Russian => "Русский",
+            Language::Japanese => "日本語",
+            Language::Chinese => "中文",
+        }
+    }
+
+    // The language of a locale tag like "zh-TW" or "es".
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let code = primary(tag).to_ascii_lowercase();
+        Language::ALL.into_iter().find(|l| l.code() == code)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.native_name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLanguage(pub String);
+
+impl fmt::Display for UnknownLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unsupported language '{}'", self.0)
+    }
+}
+
+impl error::Error for UnknownLanguage {}
+
+impl FromStr for Language {
+    type Err = UnknownLanguage;
+
+    fn from_str(s: &str) -> Result<Language, UnknownLanguage> {
+        Language::from_tag(s).ok_or_else(|| UnknownLanguage(String::from(s)))
+    }
+}
+
+// The language part of a locale tag.
+fn primary(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+// Tags are compared in their usual spelling, "zh-TW": the language in lower case, the rest in
+// upper case, separated by '-'.
+fn normalize(tag: &str) -> String {
+    let mut parts = tag.split(['-', '_']);
+    let mut out = parts.next().unwrap_or("").to_ascii_lowercase();
+    for part in parts {
+        out.push('-');
+        out.push_str(&part.to_ascii_uppercase());
+    }
+    out
+}
+
+// A message a catalog lacks.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintIssue {
+    pub locale: String,
+    pub key: String,
+    // For a plural message that has some forms: the one that's missing.
+    pub form: Option<Plural>,
+    // The locale whose message is used instead, if any.
+    pub falls_back_to: Option<String>,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: missing '{}'", self.locale, self.key)?;
+        if let Some(form) = self.form {
+            write!(f, " ({} form)", form)?;
+        }
+        if let Some(locale) = &self.falls_back_to {
+            write!(f, ", falls back to {}", locale)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Localizer {
+    catalogs: HashMap<String, Catalog>,
+    fallbacks: HashMap<String, String>,
+    default_locale: String,
+}
+
+impl Localizer {
+    pub fn new(default_locale: &str) -> Localizer {
+        Localizer {
+            catalogs: HashMap::new(),
+            fallbacks: HashMap::new(),
+            default_locale: normalize(default_locale),
+        }
+    }
+
+    pub fn with_catalog(mut self, locale: &str, catalog: Catalog) -> Self {
+        self.catalogs.insert(normalize(locale), catalog);
+        self
+    }
+
+    // Makes `locale` fall back to `to` instead of to itself less its last part.
+    pub fn with_fallback(mut self, locale: &str, to: &str) -> Self {
+        self.fallbacks.insert(normalize(locale), normalize(to));
+        self
+    }
+
+    // Reads every `<locale>.txt` in `dir`.
+    pub fn load_dir(dir: impl AsRef<Path>, default_locale: &str) -> Result<Localizer, LoadError> {
+        let dir = dir.as_ref();
+        let mut localizer = Localizer::new(default_locale);
+        for entry in fs::read_dir(dir).map_err(|e| LoadError::io(dir, e))? {
+            let path = entry.map_err(|e| LoadError::io(dir, e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                continue;
+            }
+            let Some(locale) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            localizer = localizer.with_catalog(locale, text::load(&path, Catalog::parse)?);
+        }
+        Ok(localizer)
+    }
+
+    pub fn catalog(&self, locale: &str) -> Option<&Catalog> {
+        self.catalogs.get(&normalize(locale))
+    }
+
+    // The locales tried for `locale`, in order.
+    pub fn chain(&self, locale: &str) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut next = Some(normalize(locale));
+        while let Some(locale) = next {
+            if chain.contains(&locale) {
+                break;
+            }
+            next = self.fallbacks.get(&locale).cloned().or_else(|| {
+                locale
+                    .rsplit_once('-')
+                    .map(|(parent, _)| String::from(parent))
+            });
+            chain.push(locale);
+        }
+        if !chain.contains(&self.default_locale) {
+            chain.push(self.default_locale.clone());
+        }
+        chain
+    }
+
+    // The message for `key`, or None if no locale in the chain has it.
+    pub fn lookup(
+        &self,
+        locale: &str,
+        key: &str,
+        args: &[(&str, &dyn fmt::Display)],
+    ) -> Option<String> {
+        self.chain(locale).iter().find_map(|locale| {
+            let message = self.catalogs.get(locale)?.get(key)?;
+            Some(interpolate(message, args))
+        })
+    }
+
+    // Like `lookup`, but gives the key itself when the message is missing, which is more use on
+    // a screen than nothing.
+    pub fn text(&self, locale: &str, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        self.lookup(locale, key, args)
+            .unwrap_or_else(|| String::from(key))
+    }
+
+    // The form of a plural message that `count` takes, with `{count}` filled in as well as
+    // `args`. The form is chosen by the rules of whichever catalog has the message.
+    pub fn plural(
+        &self,
+        locale: &str,
+        key: &str,
+        count: u64,
+        args: &[(&str, &dyn fmt::Display)],
+    ) -> String {
+        let mut all_args: Vec<(&str, &dyn fmt::Display)> = vec![("count", &count)];
+        all_args.extend_from_slice(args);
+        self.chain(locale)
+            .iter()
+            .find_map(|locale| {
+                let form = plural::category(primary(locale), count);
+                let message = self.catalogs.get(locale)?.get_plural(key, form)?;
+                Some(interpolate(message, &all_args))
+            })
+            .unwrap_or_else(|| String::from(key))
+    }
+
+    // Every message some catalog has that another lacks, and every plural form a catalog's
+    // language needs that its message doesn't have, sorted by locale and key.
+    pub fn lint(&self) -> Vec<LintIssue> {
+        // Plain messages, and the base keys of plural ones.
+        let mut plain = BTreeSet::new();
+        let mut plurals = BTreeSet::new();
+        for catalog in self.catalogs.values() {
+            for key in catalog.keys() {
+                match catalog::split_plural(key) {
+                    Some((base, _)) => plurals.insert(base),
+                    None => plain.insert(key),
+                };
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (locale, catalog) in &self.catalogs {
+            let has_plural = |catalog: &Catalog, key: &str| {
+                catalog
+                    .keys()
+                    .any(|k| catalog::split_plural(k).is_some_and(|(b, _)| b == key))
+            };
+            let missing = |key: &str, form| LintIssue {
+                locale: locale.clone(),
+                key: String::from(key),
+                form,
+                falls_back_to: None,
+            };
+            let falls_back_to = |key: &str, is_plural: bool| {
+                self.chain(locale).into_iter().skip(1).find(|other| {
+                    self.catalogs.get(other).is_some_and(|c| {
+                        if is_plural {
+                            has_plural(c, key)
+                        } else {
+                            c.get(key).is_some()
+                        }
+                    })
+                })
+            };
+
+            for key in &plain {
+                if catalog.get(key).is_none() {
+                    issues.push(LintIssue {
+                        falls_back_to: falls_back_to(key, false),
+                        ..missing(key, None)
+                    });
+                }
+            }
+            for key in &plurals {
+                if !has_plural(catalog, key) {
+                    issues.push(LintIssue {
+                        falls_back_to: falls_back_to(key, true),
+                        ..missing(key, None)
+                    });
+                    continue;
+                }
+                for &form in plural::forms(primary(locale)) {
+                    if catalog.get(&catalog::plural_key(key, form)).is_none() {
+                        issues.push(missing(key, Some(form)));
+                    }
+                }
+            }
+        }
+        issues.sort();
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Scratch;
+
+    fn localizer() -> Localizer {
+        let en = Catalog::new()
+            .with("hello", "Hello World!")
+            .with("greeting", "Hello, {name}!")
+            .with("files.one", "{count} file")
+            .with("files.other", "{count} files");
+        let ru = Catalog::new()
+            .with("hello", "Привет мир!")
+            .with("files.one", "{count} файл")
+            .with("files.few", "{count} файла")
+            .with("files.many", "{count} файлов");
+        let zh = Catalog::new()
+            .with("hello", "你好，世界！")
+            .with("greeting", "你好，{name}！")
+            .with("files.other", "{count} 个文件");
+        let zh_tw = Catalog::new().with("hello", "哈囉，世界！");
+        Localizer::new("en")
+            .with_catalog("en", en)
+            .with_catalog("ru", ru)
+            .with_catalog("zh", zh)
+            .with_catalog("zh_tw", zh_tw)
+    }
+
+    #[test]
+    fn knows_its_languages() {
+        assert_eq!(Language::from_tag("zh-TW"), Some(Language::Chinese));
+        assert_eq!("JA".parse(), Ok(Language::Japanese));
+        assert_eq!(
+            "fr".parse::<Language>().unwrap_err().to_string(),
+            "unsupported language 'fr'"
+        );
+        assert_eq!(Language::Russian.to_string(), "Русский");
+    }
+
+    #[test]
+    fn builds_fallback_chains() {
+        let i18n = localizer().with_fallback("pt-BR", "es");
+        assert_eq!(i18n.chain("zh-TW"), ["zh-TW", "zh", "en"]);
+        assert_eq!(
+            i18n.chain("zh_hant_tw"),
+            ["zh-HANT-TW", "zh-HANT", "zh", "en"]
+        );
+        assert_eq!(i18n.chain("EN-gb"), ["en-GB", "en"]);
+        assert_eq!(i18n.chain("pt-BR"), ["pt-BR", "es", "en"]);
+        // A loop of fallbacks ends where it would come round again.
+        let i18n = i18n.with_fallback("es", "pt-BR");
+        assert_eq!(i18n.chain("es"), ["es", "pt-BR", "en"]);
+    }
+
+    #[test]
+    fn looks_messages_up_along_the_chain() {
+        let i18n = localizer();
+        let name = "Ann";
+        assert_eq!(i18n.text("zh-TW", "hello", &[]), "哈囉，世界！");
+        assert_eq!(
+            i18n.text("zh-TW", "greeting", &[("name", &name)]),
+            "你好，Ann！"
+        );
+        assert_eq!(
+            i18n.text("ru", "greeting", &[("name", &name)]),
+            "Hello, Ann!"
+        );
+        assert_eq!(i18n.text("fr", "hello", &[]), "Hello World!");
+        assert_eq!(i18n.lookup("en", "nothing", &[]), None);
+        assert_eq!(i18n.text("en", "nothing", &[]), "nothing");
+    }
+
+    #[test]
+    fn picks_plural_forms_by_language() {
+        let i18n = localizer();
+        let files = |locale, n| i18n.plural(locale, "files", n, &[]);
+        assert_eq!(files("en", 1), "1 file");
+        assert_eq!(files("en", 2), "2 files");
+        assert_eq!(files("ru", 1), "1 файл");
+        assert_eq!(files("ru", 3), "3 файла");
+        assert_eq!(files("ru", 11), "11 файлов");
+        assert_eq!(files("ru", 21), "21 файл");
+        assert_eq!(files("zh-TW", 1), "1 个文件");
+        // Spanish has no catalog, so it's English with English rules.
+        assert_eq!(files("es", 1), "1 file");
+    }
+
+    #[test]
+    fn lints_missing_messages() {
+        let i18n = localizer().with_catalog(
+            "es",
+            Catalog::new()
+                .with("hello", "Hola")
+                .with("files.other", "{count} archivos"),
+        );
+        let issues: Vec<String> = i18n.lint().iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "es: missing 'files' (one form)",
+                "es: missing 'files' (many form)",
+                "es: missing 'greeting', falls back to en",
+                "ru: missing 'greeting', falls back to en",
+                "zh-TW: missing 'files', falls back to zh",
+                "zh-TW: missing 'greeting', falls back to zh",
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_a_directory_of_catalogs() {
+        let scratch = Scratch::new("i18n-test");
+        scratch.write("en.txt", "hello = Hello World!");
+        scratch.write("es.txt", "hello = Hola Mundo!");
+        scratch.write("notes.md", "not a catalog");
+        let i18n = Localizer::load_dir(scratch.path(), "en").unwrap();
+        assert_eq!(i18n.text("es-MX", "hello", &[]), "Hola Mundo!");
+
+        scratch.write("ru.txt", "hello");
+        let err = Localizer::load_dir(scratch.path(), "en")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.ends_with("ru.txt: line 1: expected 'key = message', found 'hello'"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// Writing numbers the way a language does: 1,234.5 in English, 1234,5 and 12.345,5 in Spanish,
+// 12 345,5 in Russian, and, when asked for, 一万二千三百四十五 in Chinese and 1万2345 in Japanese.
+
+use super::locale::NativeNumerals;
+use super::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    language: Language,
+    // Digits after the point, rounding to them. None writes as many as the number needs.
+    decimals: Option<usize>,
+    native: bool,
+}
+
+impl NumberFormat {
+    pub fn new(language: Language) -> NumberFormat {
+        NumberFormat {
+            language,
+            decimals: None,
+            native: false,
+        }
+    }
+
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    // Write numbers in the language's own numerals, for languages that have them. The others
+    // keep using digits.
+    pub fn with_native_numerals(mut self, native: bool) -> Self {
+        self.native = native;
+        self
+    }
+
+    pub fn format(&self, n: f64) -> String {
+        if n.is_nan() {
+            return String::from("NaN");
+        }
+        let digits = match self.decimals {
+            Some(decimals) => format!("{:.*}", decimals, n.abs()),
+            None => format!("{}", n.abs()),
+        };
+        // No "-0" for what rounds to zero.
+        let negative =
+            n < 0.0 && (n.is_infinite() || digits.bytes().any(|b| matches!(b, b'1'..=b'9')));
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits.as_str(), None),
+        };
+        self.write(negative, whole, fraction)
+    }
+
+    pub fn format_int(&self, n: i64) -> String {
+        self.write(n < 0, &n.unsigned_abs().to_string(), None)
+    }
+
+    fn write(&self, negative: bool, whole: &str, fraction: Option<&str>) -> String {
+        let locale = self.language.locale();
+        if let (true, Some(native)) = (self.native, &locale.native) {
+            // Past what a u64 holds there are no units left to write it with.
+            if let Ok(n) = whole.parse::<u64>() {
+                return native_number(native, locale.decimal, negative, n, fraction);
+            }
+        }
+        let mut out = String::new();
+        if negative {
+            out.push('-');
+        }
+        if whole.len() < locale.min_grouping_digits || !whole.bytes().all(|b| b.is_ascii_digit()) {
+            // Too short to group, or "inf".
+            out.push_str(if whole == "inf" { "∞" } else { whole });
+        } else {
+            for (i, digit) in whole.chars().enumerate() {
+                if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                    out.push_str(locale.group);
+                }
+                out.push(digit);
+            }
+        }
+        if let Some(fraction) = fraction {
+            out.push_str(locale.decimal);
+            out.push_str(fraction);
+        }
+        out
+    }
+}
+
+impl Language {
+    pub fn format_number(self, n: f64) -> String {
+        NumberFormat::new(self).format(n)
+    }
+}
+
+fn native_number(
+    native: &NativeNumerals,
+    decimal: &str,
+    negative: bool,
+    n: u64,
+    fraction: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    if negative {
+        out.push_str(native.minus);
+    }
+    if native.digit_groups && n >= 10_000 {
+        out.push_str(&digit_groups(native, n));
+        if let Some(fraction) = fraction {
+            out.push_str(decimal);
+            out.push_str(fraction);
+        }
+        return out;
+    }
+    out.push_str(&native_integer(native, n));
+    if let Some(fraction) = fraction {
+        out.push_str(native.point);
+        // Read out digit by digit: 三点一四.
+        for digit in fraction.bytes() {
+            out.push_str(native.digits[usize::from(digit - b'0')]);
+        }
+    }
+    out
+}
+
+// The groups of four digits, lowest first.
+fn groups_of_four(n: u64) -> Vec<u64> {
+    let mut groups = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        groups.push(rest % 10_000);
+        rest /= 10_000;
+    }
+    groups
+}
+
+// Each group in digits and followed by its unit, leaving out the empty ones: 1億50万.
+fn digit_groups(native: &NativeNumerals, n: u64) -> String {
+    let mut out = String::new();
+    for (unit, &group) in groups_of_four(n).iter().enumerate().rev() {
+        if group > 0 {
+            out.push_str(&group.to_string());
+            out.push_str(native.big_units[unit]);
+        }
+    }
+    out
+}
+
+// Written in groups of four digits, each followed by its unit of ten thousand, 万, 億 and so on.
+fn native_integer(native: &NativeNumerals, n: u64) -> String {
+    if n == 0 {
+        return String::from(native.digits[0]);
+    }
+    let groups = groups_of_four(n);
+    let mut out = String::new();
+    let mut started = false;
+    // Set on passing a zero, and written as one when a digit follows it.
+    let mut skipped = false;
+    for (unit, &group) in groups.iter().enumerate().rev() {
+        if group == 0 {
+            skipped |= started;
+            continue;
+        }
+        for place in (0..4).rev() {
+            let digit = (group / 10u64.pow(place as u32) % 10) as usize;
+            if digit == 0 {
+                skipped |= started;
+                continue;
+            }
+            if skipped && native.marks_zeros {
+                out.push_str(native.digits[0]);
+            }
+            skipped = false;
+            // 十 on its own for ten everywhere it leads; 千 and 百 too where the units stand bare.
+            let bare = digit == 1 && place > 0 && (native.bare_units || (place == 1 && !started));
+            if !bare {
+                out.push_str(native.digits[digit]);
+            }
+            out.push_str(native.small_units[place]);
+            started = true;
+        }
+        out.push_str(native.big_units[unit]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_digits_per_language() {
+        let format = |language: Language, n| NumberFormat::new(language).with_decimals(2).format(n);
+        assert_eq!(format(Language::English, 1234567.891), "1,234,567.89");
+        assert_eq!(format(Language::Spanish, 1234567.891), "1.234.567,89");
+        assert_eq!(
+            format(Language::Russian, -1234567.891),
+            "-1\u{a0}234\u{a0}567,89"
+        );
+        assert_eq!(format(Language::Japanese, 999.5), "999.50");
+        // Spanish and Russian leave four digits alone.
+        assert_eq!(Language::Spanish.format_number(1234.5), "1234,5");
+        assert_eq!(Language::Russian.format_number(12345.0), "12\u{a0}345");
+        assert_eq!(Language::English.format_number(1234.0), "1,234");
+        assert_eq!(format(Language::English, -0.001), "0.00");
+        assert_eq!(Language::English.format_number(f64::NEG_INFINITY), "-∞");
+        assert_eq!(
+            NumberFormat::new(Language::Chinese).format_int(i64::MIN),
+            "-9,223,372,036,854,775,808"
+        );
+    }
+
+    #[test]
+    fn writes_japanese_numerals() {
+        let format = NumberFormat::new(Language::Japanese).with_native_numerals(true);
+        let cases = [
+            (0, "〇"),
+            (10, "十"),
+            (15, "十五"),
+            (1100, "千百"),
+            (1005, "千五"),
+            (9999, "九千九百九十九"),
+            // From ten thousand up in digits, with the units as words.
+            (10050, "1万50"),
+            (12345, "1万2345"),
+            (10_000_000, "1000万"),
+            (100_000_000, "1億"),
+            (123_450_000, "1億2345万"),
+            (-20, "マイナス二十"),
+            (-20_000, "マイナス2万"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(format.format_int(n), expected, "{}", n);
+        }
+        assert_eq!(format.with_decimals(2).format(1.25), "一点二五");
+        assert_eq!(format.with_decimals(1).format(12345.5), "1万2345.5");
+    }
+
+    #[test]
+    fn writes_chinese_numerals() {
+        let format = NumberFormat::new(Language::Chinese).with_native_numerals(true);
+        let cases = [
+            (0, "零"),
+            (10, "十"),
+            (15, "十五"),
+            (110, "一百一十"),
+            (1005, "一千零五"),
+            (10050, "一万零五十"),
+            (100_000, "十万"),
+            (100_010_000, "一亿零一万"),
+            (-3, "负三"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(format.format_int(n), expected, "{}", n);
+        }
+    }
+
+    #[test]
+    fn keeps_digits_without_native_numerals() {
+        let format = NumberFormat::new(Language::Spanish).with_native_numerals(true);
+        assert_eq!(format.format_int(12345), "12.345");
+    }
+}
@@ -0,0 +1,124 @@
+// Which plural form a count takes. Languages differ: English has "1 file" and "2 files", Russian
+// has three forms that depend on the last digits, Spanish has its own form for whole millions ("1
+// millón de"), and Japanese and Chinese don't inflect at all.
+// The categories and rules are the CLDR ones for whole numbers.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Plural {
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl Plural {
+    pub fn name(self) -> &'static str {
+        match self {
+            Plural::One => "one",
+            Plural::Few => "few",
+            Plural::Many => "many",
+            Plural::Other => "other",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Plural> {
+        match name {
+            "one" => Some(Plural::One),
+            "few" => Some(Plural::Few),
+            "many" => Some(Plural::Many),
+            "other" => Some(Plural::Other),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Plural {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// The form for `n` in the language with this code ("en", "ru", ...). Languages without a rule
+// here use the English one.
+pub fn category(language: &str, n: u64) -> Plural {
+    match language {
+        "ja" | "zh" => Plural::Other,
+        "ru" => {
+            let (last, last_two) = (n % 10, n % 100);
+            if last == 1 && last_two != 11 {
+                Plural::One
+            } else if (2..=4).contains(&last) && !(12..=14).contains(&last_two) {
+                Plural::Few
+            } else {
+                Plural::Many
+            }
+        }
+        "es" if n == 1 => Plural::One,
+        "es" if n != 0 && n.is_multiple_of(1_000_000) => Plural::Many,
+        _ if n == 1 => Plural::One,
+        _ => Plural::Other,
+    }
+}
+
+// Every form the language's rule can pick, which a catalog for it should provide.
+pub fn forms(language: &str) -> &'static [Plural] {
+    match language {
+        "ja" | "zh" => &[Plural::Other],
+        "ru" => &[Plural::One, Plural::Few, Plural::Many],
+        "es" => &[Plural::One, Plural::Many, Plural::Other],
+        _ => &[Plural::One, Plural::Other],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_each_language() {
+        let forms = |language, counts: &[u64]| -> Vec<Plural> {
+            counts.iter().map(|&n| category(language, n)).collect()
+        };
+        use Plural::*;
+        assert_eq!(forms("en", &[0, 1, 2, 11]), [Other, One, Other, Other]);
+        assert_eq!(forms("es", &[1, 5]), [One, Other]);
+        assert_eq!(
+            forms("ru", &[1, 2, 4, 5, 11, 12, 21, 22, 25, 101, 111, 112]),
+            [One, Few, Few, Many, Many, Many, One, Few, Many, One, Many, Many]
+        );
+        assert_eq!(forms("ja", &[1, 2]), [Other, Other]);
+        assert_eq!(forms("en", &[1_000_000]), [Other]);
+        assert_eq!(forms("xx", &[1, 2]), [One, Other]);
+    }
+
+    #[test]
+    fn gives_spanish_millions_their_own_form() {
+        use Plural::*;
+        let counts = [
+            0,
+            1,
+            1_000_000,
+            2_000_000,
+            1_000_001,
+            1_500_000,
+            100_000_000,
+        ];
+        let forms: Vec<Plural> = counts.iter().map(|&n| category("es", n)).collect();
+        assert_eq!(forms, [Other, One, Many, Many, Other, Other, Many]);
+    }
+
+    #[test]
+    fn lists_every_form_a_rule_picks() {
+        for language in ["en", "es", "ru", "ja", "zh"] {
+            let mut picked: Vec<Plural> = (0..200)
+                .chain([1_000_000, 2_000_000, 10_000_000])
+                .map(|n| category(language, n))
+                .collect();
+            picked.sort();
+            picked.dedup();
+            assert_eq!(picked, forms(language), "{}", language);
+        }
+    }
+}
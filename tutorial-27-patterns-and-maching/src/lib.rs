@@ -0,0 +1,4 @@
+pub mod auth;
+pub mod i18n;
+pub mod query;
+pub mod text;
@@ -1,14 +1,11 @@
+use tutorial_27_patterns_and_maching::auth::{Policy, Subject};
+use tutorial_27_patterns_and_maching::i18n::{self, Language, Localizer};
+use tutorial_27_patterns_and_maching::query::{Query, Record, Value};
+
 fn main() {
     // --- Pattern ---
 
-    enum Language {
-        English,
-        Spanish,
-        Russian,
-        Japanese,
-        Chinese,
-    }
-
+    // The library's Language, which the catalogs below use too
     let language = Language::Japanese;
 
     match language {
@@ -17,9 +14,47 @@ fn main() {
         Language::Russian => println!("Привет мир!"),
         Language::Japanese => println!("こんにちは世界!"),
         Language::Chinese => println!("你好，世界！"),
-        _ => println!("Unsupported language!"),
     }
 
+    // The same greeting from message catalogs, one file per language in locales/
+    let messages = Localizer::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/locales"), "en")
+        .expect("the catalogs in locales/ should load");
+    for language in Language::ALL {
+        println!(
+            "{}: {}",
+            language,
+            messages.text(language.code(), "hello", &[])
+        );
+    }
+    // Russian has three plural forms, picked by the last digits of the count
+    for count in [1, 3, 5, 21] {
+        println!("{}", messages.plural("ru", "unread", count, &[]));
+    }
+    // zh-TW has no greeting of its own, so it comes from zh
+    println!(
+        "{}",
+        messages.text("zh-TW", "greeting", &[("name", &"Ferris")])
+    );
+    for issue in messages.lint() {
+        println!("lint: {}", issue);
+    }
+
+    // Numbers, dates and sorting, each the way the language writes them
+    let date = i18n::Date::new(2024, 3, 14).unwrap();
+    for language in Language::ALL {
+        let mut words = ["zebra", "Ñandú", "élan", "ёж", "カメラ", "かさ", "nube"];
+        language.sort(&mut words);
+        println!(
+            "{}: {} | {} | {}",
+            language,
+            language.format_number(1234567.5),
+            language.format_date(&date, i18n::DateStyle::Long),
+            words.join(" ")
+        );
+    }
+    let kanji = i18n::NumberFormat::new(Language::Japanese).with_native_numerals(true);
+    println!("{}", kanji.format_int(12345));
+
     // --- Conditional if let Expressions ---
     let authorization_status: Option<&str> = None;
     let is_admin = false;
@@ -39,6 +74,21 @@ fn main() {
         println!("Authorization status: guest");
     }
 
+    // The same decision made by a policy file, which says why as well
+    let policy = Policy::load(concat!(env!("CARGO_MANIFEST_DIR"), "/policy.txt"))
+        .expect("policy.txt should load");
+    let mut subject = Subject::new("tutorial");
+    if let Some(status) = authorization_status {
+        subject = subject.with_role(status);
+    } else if is_admin {
+        subject = subject.with_role("admin");
+    }
+    if let Ok(group_id) = group_id {
+        subject = subject.with_group(&group_id.to_string());
+    }
+    println!("{}", policy.check(&subject, "write", "docs/report"));
+    println!("{}", policy.check(&subject, "write", "docs/policies/leave"));
+
     // --- While let Conditional Loops ---
     let mut stack = Vec::new();
 
@@ -222,6 +272,48 @@ fn main() {
         Message2::Hello { id: 10..=12 } => println!("Found an id in another range"),
         Message2::Hello { id } => println!("Found some other id: {}", id),
     }
+
+    // The same patterns in a query, read at runtime and run over a list of shoes
+    let shoes = [
+        Shoe::new(10, "sneaker"),
+        Shoe::new(13, "sandal"),
+        Shoe::new(10, "boot"),
+        Shoe::new(11, "boot"),
+    ];
+    for src in [
+        r#"size in 9..=11 and style | "boot" | "sneaker""#,
+        "size in n @ 10..=13 if n != 10",
+    ] {
+        match Query::parse(src) {
+            Ok(query) => println!("{}: {:?}", query, query.filter(&shoes)),
+            Err(e) => println!("{}: {}", src, e),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shoe {
+    size: u32,
+    style: String,
+}
+
+impl Shoe {
+    fn new(size: u32, style: &str) -> Shoe {
+        Shoe {
+            size,
+            style: String::from(style),
+        }
+    }
+}
+
+impl Record for Shoe {
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "size" => Some(self.size.into()),
+            "style" => Some(self.style.as_str().into()),
+            _ => None,
+        }
+    }
 }
 
 fn print_coordinates(&(x, y): &(i32, i32)) {
@@ -0,0 +1,521 @@
+// A query language for picking records out of a collection, built from the patterns `match`
+// has: literals, ranges, alternatives, `@` bindings and guards. For example
+//
+//   size in 9..=11 and style | "boot" | "sneaker"
+//   size in n @ 8..=12 if n != 10
+//   not (style in "sandal" or size < 9)
+//
+// A test is `field in pattern`. Like in Rust, a pattern can start with `|`, and then `in` can be
+// left out, as in the first example. The patterns are
+//
+//   - a literal: 10, -3, "boot", 'a', true,
+//   - a range: 9..=11, 9..12, 'a'..='j', or open at one end: ..=5, 10..,
+//   - `_`, which matches anything,
+//   - `name @` before any of those, which binds the field's value to `name` for the guard,
+//   - any of those separated by `|`.
+//
+// A guard is `if` and one more test after the pattern; it sees the bindings, and the record's
+// fields under their own names. Parentheses fit a longer one in: `if (n > 9 and n < 12)`.
+// Besides `in`, tests can compare with ==, !=, <, <=, > and >=, or name a field that's true.
+// They're combined with `not`, `and` and `or`, binding in that order.
+//
+// Strings and characters take the escapes \n, \\, \" and \'. `not`, parentheses and guards
+// nest at most MAX_DEPTH deep.
+//
+// Records are anything that implements `Record`. A field a record doesn't have, or a value of
+// another type than the pattern's, doesn't match.
+
+mod parser;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+pub use parser::{QueryError, MAX_DEPTH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Char(char),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    // None for values of different types, which don't compare.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Char(c) => quoted(f, &c.to_string(), '\''),
+            Value::Str(s) => quoted(f, s, '"'),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+// With only the escapes the parser reads; `{:?}` would write others, like \t and \u{..}.
+fn quoted(f: &mut fmt::Formatter, s: &str, quote: char) -> fmt::Result {
+    write!(f, "{}", quote)?;
+    for c in s.chars() {
+        match c {
+            '\n' => write!(f, "\\n")?,
+            '\\' => write!(f, "\\\\")?,
+            c if c == quote => write!(f, "\\{}", c)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "{}", quote)
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Int(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Value {
+        Value::Int(n.into())
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Value {
+        Value::Int(n.into())
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Value {
+        Value::Char(c)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(String::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+// Something a query can look at: a struct exposing its fields by name.
+pub trait Record {
+    // None for a field it doesn't have.
+    fn field(&self, name: &str) -> Option<Value>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Le => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Atom {
+    Any,
+    Value(Value),
+    // Either end can be open.
+    Range {
+        low: Option<Value>,
+        high: Option<Value>,
+        inclusive: bool,
+    },
+}
+
+impl Atom {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Value(v) => value.compare(v) == Some(Ordering::Equal),
+            Atom::Range {
+                low,
+                high,
+                inclusive,
+            } => {
+                let above_low = low
+                    .as_ref()
+                    .is_none_or(|low| value.compare(low).is_some_and(Ordering::is_ge));
+                let below_high = high.as_ref().is_none_or(|high| {
+                    value.compare(high).is_some_and(|ordering| {
+                        ordering.is_lt() || (*inclusive && ordering.is_eq())
+                    })
+                });
+                above_low && below_high
+            }
+        }
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Atom::Any => write!(f, "_"),
+            Atom::Value(v) => write!(f, "{}", v),
+            Atom::Range {
+                low,
+                high,
+                inclusive,
+            } => {
+                if let Some(low) = low {
+                    write!(f, "{}", low)?;
+                }
+                write!(f, "{}", if *inclusive { "..=" } else { ".." })?;
+                if let Some(high) = high {
+                    write!(f, "{}", high)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// One of a pattern's alternatives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    pub binding: Option<String>,
+    pub atom: Atom,
+}
+
+impl fmt::Display for Alternative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.binding {
+            write!(f, "{} @ ", name)?;
+        }
+        write!(f, "{}", self.atom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    // A binding or a field.
+    Name(String),
+    Value(Value),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Name(name) => write!(f, "{}", name),
+            Operand::Value(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Match {
+        field: String,
+        alternatives: Vec<Alternative>,
+        guard: Option<Box<Query>>,
+    },
+    Compare(Operand, CompareOp, Operand),
+    // A field that must be true.
+    Field(String),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+    pub fn parse(src: &str) -> Result<Query, QueryError> {
+        parser::parse(src)
+    }
+
+    pub fn matches<R: Record + ?Sized>(&self, record: &R) -> bool {
+        self.holds(&|name| record.field(name))
+    }
+
+    // The records the query matches, in their order.
+    pub fn filter<'r, R: Record>(&self, records: &'r [R]) -> Vec<&'r R> {
+        records.iter().filter(|r| self.matches(*r)).collect()
+    }
+
+    // `lookup` gives the value of a name: a field, or in a guard, a binding.
+    fn holds(&self, lookup: &dyn Fn(&str) -> Option<Value>) -> bool {
+        match self {
+            Query::Match {
+                field,
+                alternatives,
+                guard,
+            } => {
+                let Some(value) = lookup(field) else {
+                    return false;
+                };
+                // As in `match`, the guard is tried with the bindings of the alternative that
+                // matched. They all bind the same name, so the first one that matches will do.
+                let Some(alternative) = alternatives.iter().find(|a| a.atom.matches(&value)) else {
+                    return false;
+                };
+                let Some(guard) = guard else {
+                    return true;
+                };
+                match &alternative.binding {
+                    Some(binding) => guard.holds(&|name| {
+                        if name == binding {
+                            Some(value.clone())
+                        } else {
+                            lookup(name)
+                        }
+                    }),
+                    None => guard.holds(lookup),
+                }
+            }
+            Query::Compare(left, op, right) => {
+                let operand = |operand: &Operand| match operand {
+                    Operand::Name(name) => lookup(name),
+                    Operand::Value(v) => Some(v.clone()),
+                };
+                match (operand(left), operand(right)) {
+                    (Some(a), Some(b)) => a.compare(&b).is_some_and(|o| op.holds(o)),
+                    _ => false,
+                }
+            }
+            Query::Field(name) => lookup(name) == Some(Value::Bool(true)),
+            Query::And(queries) => queries.iter().all(|q| q.holds(lookup)),
+            Query::Or(queries) => queries.iter().any(|q| q.holds(lookup)),
+            Query::Not(query) => !query.holds(lookup),
+        }
+    }
+
+    // Whether it prints without parentheses next to `not`, `if` or another query.
+    fn is_simple(&self) -> bool {
+        !matches!(self, Query::And(_) | Query::Or(_))
+    }
+}
+
+// In the syntax `parse` reads, parenthesized only where it must be.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let joined = |f: &mut fmt::Formatter, queries: &[Query], separator: &str| {
+            for (i, query) in queries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", separator)?;
+                }
+                // `and` binds tighter than `or`, so only an `or` inside an `and` needs them.
+                if separator == "and" && matches!(query, Query::Or(_)) {
+                    write!(f, "({})", query)?;
+                } else {
+                    write!(f, "{}", query)?;
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Query::Match {
+                field,
+                alternatives,
+                guard,
+            } => {
+                write!(f, "{} in ", field)?;
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{}", alternative)?;
+                }
+                match guard {
+                    Some(guard) if guard.is_simple() => write!(f, " if {}", guard),
+                    Some(guard) => write!(f, " if ({})", guard),
+                    None => Ok(()),
+                }
+            }
+            Query::Compare(left, op, right) => write!(f, "{} {} {}", left, op.symbol(), right),
+            Query::Field(name) => write!(f, "{}", name),
+            Query::And(queries) => joined(f, queries, "and"),
+            Query::Or(queries) => joined(f, queries, "or"),
+            Query::Not(query) if query.is_simple() => write!(f, "not {}", query),
+            Query::Not(query) => write!(f, "not ({})", query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // As in tutorial-20.
+    #[derive(Debug, PartialEq)]
+    struct Shoe {
+        size: u32,
+        style: String,
+        on_sale: bool,
+    }
+
+    impl Record for Shoe {
+        fn field(&self, name: &str) -> Option<Value> {
+            match name {
+                "size" => Some(self.size.into()),
+                "style" => Some(self.style.as_str().into()),
+                "initial" => self.style.chars().next().map(Value::Char),
+                "on_sale" => Some(self.on_sale.into()),
+                _ => None,
+            }
+        }
+    }
+
+    fn shoes() -> Vec<Shoe> {
+        [
+            (10, "sneaker", false),
+            (13, "sandal", true),
+            (10, "boot", true),
+            (9, "loafer", false),
+            (11, "boot", false),
+            (12, "sneaker", true),
+        ]
+        .into_iter()
+        .map(|(size, style, on_sale)| Shoe {
+            size,
+            style: String::from(style),
+            on_sale,
+        })
+        .collect()
+    }
+
+    fn picked(query: &str) -> Vec<(u32, String)> {
+        let shoes = shoes();
+        let query = Query::parse(query).unwrap();
+        query
+            .filter(&shoes)
+            .into_iter()
+            .map(|s| (s.size, s.style.clone()))
+            .collect()
+    }
+
+    fn sizes(query: &str) -> Vec<u32> {
+        picked(query).into_iter().map(|(size, _)| size).collect()
+    }
+
+    #[test]
+    fn matches_ranges_and_alternatives() {
+        assert_eq!(
+            picked(r#"size in 9..=11 and style | "boot" | "sneaker""#),
+            [
+                (10, String::from("sneaker")),
+                (10, String::from("boot")),
+                (11, String::from("boot"))
+            ]
+        );
+        assert_eq!(sizes("size in 9..11"), [10, 10, 9]);
+        assert_eq!(sizes("size in ..=9 | 13.."), [13, 9]);
+        assert_eq!(sizes("size in 10 | 12"), [10, 10, 12]);
+        assert_eq!(sizes("initial in 'a'..='j'"), [10, 11]);
+        assert_eq!(sizes("size in _"), [10, 13, 10, 9, 11, 12]);
+    }
+
+    #[test]
+    fn binds_values_for_guards() {
+        assert_eq!(sizes("size in n @ 9..=12 if n != 10"), [9, 11, 12]);
+        // Fields are in scope in a guard too.
+        assert_eq!(
+            sizes(r#"size in n @ 10..=12 if (on_sale and n > 10)"#),
+            [12]
+        );
+        // A guard can test the binding with a pattern of its own.
+        assert_eq!(sizes("size in n @ _ if n in 9 | 13"), [13, 9]);
+        // Bindings don't outlive their test.
+        assert_eq!(sizes("size in n @ _ and n == 10"), [] as [u32; 0]);
+    }
+
+    #[test]
+    fn combines_tests() {
+        assert_eq!(sizes("on_sale"), [13, 10, 12]);
+        assert_eq!(
+            sizes(r#"not (style in "sandal" or size < 10)"#),
+            [10, 10, 11, 12]
+        );
+        assert_eq!(
+            sizes("size >= 12 or size == 9 and not on_sale"),
+            [13, 9, 12]
+        );
+        assert_eq!(sizes("10 < size"), [13, 11, 12]);
+    }
+
+    #[test]
+    fn needs_matching_fields_and_types() {
+        assert_eq!(sizes("colour in _"), [] as [u32; 0]);
+        assert_eq!(sizes(r#"size in "10""#), [] as [u32; 0]);
+        assert_eq!(sizes("style < 3"), [] as [u32; 0]);
+        assert_eq!(sizes("style"), [] as [u32; 0]);
+    }
+
+    #[test]
+    fn prints_what_it_parses() {
+        let round_trip = |src: &str| Query::parse(src).unwrap().to_string();
+        assert_eq!(
+            round_trip(r#"size in 9..=11 and style | "boot" | "sneaker""#),
+            r#"size in 9..=11 and style in "boot" | "sneaker""#
+        );
+        assert_eq!(
+            round_trip("(a or b) and not (c and d) or e"),
+            "(a or b) and not (c and d) or e"
+        );
+        assert_eq!(
+            round_trip("x in n @ ..5 | n @ 'z' | n @ _ if (n > 1 or y)"),
+            "x in n @ ..5 | n @ 'z' | n @ _ if (n > 1 or y)"
+        );
+        // Values print back in a form the parser reads, whatever is in them.
+        let tricky = "tab\t, quotes \"'\\, nul \0, bell \u{7}, line\n";
+        let query = Query::Match {
+            field: "x".into(),
+            alternatives: [Value::from(tricky), Value::Char('\''), Value::Char('\t')]
+                .into_iter()
+                .map(|v| Alternative {
+                    binding: None,
+                    atom: Atom::Value(v),
+                })
+                .collect(),
+            guard: None,
+        };
+        assert_eq!(Query::parse(&query.to_string()), Ok(query));
+    }
+}
@@ -0,0 +1,556 @@
+// Reading a query; see the module above for the syntax.
+
+use std::error;
+use std::fmt;
+
+use super::{Alternative, Atom, CompareOp, Operand, Query, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    // Counting characters from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(column: usize, message: impl Into<String>) -> QueryError {
+        QueryError {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl error::Error for QueryError {}
+
+// How deeply `not`, parentheses and guards can nest, which keeps both reading a query and running
+// it from overflowing the stack.
+pub const MAX_DEPTH: usize = 200;
+
+const KEYWORDS: [&str; 7] = ["and", "or", "not", "in", "if", "true", "false"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Int(i64),
+    Str(String),
+    Char(char),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Int(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Char(c) => write!(f, "{:?}", c),
+            Token::Symbol(s) => write!(f, "'{}'", s),
+        }
+    }
+}
+
+// Longest first, so "..=" isn't read as "..".
+const SYMBOLS: [&str; 12] = [
+    "..=", "..", "==", "!=", "<=", ">=", "<", ">", "|", "@", "(", ")",
+];
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let next = chars.get(i + 1).copied();
+        let token = if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit()))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let n = digits
+                .parse()
+                .map_err(|_| QueryError::new(column, format!("{} is too large", digits)))?;
+            Token::Int(n)
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Token::Name(chars[start..i].iter().collect())
+        } else if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(QueryError::new(column, "unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        s.push(unescape(chars.get(i + 1).copied(), i + 1)?);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        s.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            Token::Str(s)
+        } else if c == '\'' {
+            let (c, len) = match next {
+                Some('\\') => (unescape(chars.get(i + 2).copied(), i + 2)?, 3),
+                Some(c) if c != '\'' => (c, 2),
+                _ => return Err(QueryError::new(column, "expected a character after '")),
+            };
+            if chars.get(i + len) != Some(&'\'') {
+                return Err(QueryError::new(column, "unterminated character"));
+            }
+            i += len + 1;
+            Token::Char(c)
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+            let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) else {
+                return Err(QueryError::new(
+                    column,
+                    format!("unexpected character '{}'", c),
+                ));
+            };
+            i += symbol.chars().count();
+            Token::Symbol(symbol)
+        };
+        tokens.push((column, token));
+    }
+    Ok(tokens)
+}
+
+// What follows a backslash at index `at`.
+fn unescape(c: Option<char>, at: usize) -> Result<char, QueryError> {
+    match c {
+        Some('n') => Ok('\n'),
+        Some(c @ ('\\' | '"' | '\'')) => Ok(c),
+        Some(c) => Err(QueryError::new(at + 1, format!("unknown escape '\\{}'", c))),
+        None => Err(QueryError::new(at + 1, "unterminated string")),
+    }
+}
+
+pub(super) fn parse(src: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        end: src.chars().count() + 1,
+        depth: 0,
+    };
+    let query = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(query),
+        Some((column, token)) => Err(QueryError::new(
+            *column,
+            format!("unexpected {} after the query", token),
+        )),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    // The column just past the last character, for errors at the end.
+    end: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Name(name)) if name == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self, expected: &str) -> QueryError {
+        let found = match self.peek() {
+            Some(token) => token.to_string(),
+            None => String::from("the end"),
+        };
+        QueryError::new(
+            self.column(),
+            format!("expected {}, found {}", expected, found),
+        )
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.and()?];
+        while self.eat_keyword("or") {
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.remove(0)
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.unary()?];
+        while self.eat_keyword("and") {
+            queries.push(self.unary()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.remove(0)
+        } else {
+            Query::And(queries)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if self.depth == MAX_DEPTH {
+            return Err(QueryError::new(
+                self.column(),
+                format!("nested more than {} deep", MAX_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        let query = self.unary_at_depth();
+        self.depth -= 1;
+        query
+    }
+
+    fn unary_at_depth(&mut self) -> Result<Query, QueryError> {
+        if self.eat_keyword("not") {
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        if self.eat_symbol("(") {
+            let query = self.or()?;
+            if !self.eat_symbol(")") {
+                return Err(self.error("')'"));
+            }
+            return Ok(query);
+        }
+        self.test()
+    }
+
+    fn test(&mut self) -> Result<Query, QueryError> {
+        let left = match self.peek() {
+            Some(Token::Name(name)) if !KEYWORDS.contains(&name.as_str()) && name != "_" => {
+                let name = name.clone();
+                self.pos += 1;
+                if self.eat_keyword("in") || matches!(self.peek(), Some(Token::Symbol("|"))) {
+                    return self.pattern(name);
+                }
+                Operand::Name(name)
+            }
+            _ => match self.literal() {
+                Some(value) => Operand::Value(value),
+                None => return Err(self.error("a test")),
+            },
+        };
+        let Some(op) = self.compare_op() else {
+            return match left {
+                Operand::Name(name) => Ok(Query::Field(name)),
+                Operand::Value(_) => Err(self.error("a comparison")),
+            };
+        };
+        let right = match self.peek() {
+            Some(Token::Name(name)) if !KEYWORDS.contains(&name.as_str()) && name != "_" => {
+                let name = name.clone();
+                self.pos += 1;
+                Operand::Name(name)
+            }
+            _ => match self.literal() {
+                Some(value) => Operand::Value(value),
+                None => return Err(self.error("a field or a value")),
+            },
+        };
+        Ok(Query::Compare(left, op, right))
+    }
+
+    fn compare_op(&mut self) -> Option<CompareOp> {
+        let op = match self.peek()? {
+            Token::Symbol("==") => CompareOp::Eq,
+            Token::Symbol("!=") => CompareOp::Ne,
+            Token::Symbol("<") => CompareOp::Lt,
+            Token::Symbol("<=") => CompareOp::Le,
+            Token::Symbol(">") => CompareOp::Gt,
+            Token::Symbol(">=") => CompareOp::Ge,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn literal(&mut self) -> Option<Value> {
+        let value = match self.peek()? {
+            Token::Int(n) => Value::Int(*n),
+            Token::Str(s) => Value::Str(s.clone()),
+            Token::Char(c) => Value::Char(*c),
+            Token::Name(name) if name == "true" => Value::Bool(true),
+            Token::Name(name) if name == "false" => Value::Bool(false),
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(value)
+    }
+
+    // After `field in`, or `field` when a `|` follows.
+    fn pattern(&mut self, field: String) -> Result<Query, QueryError> {
+        let column = self.column();
+        self.eat_symbol("|");
+        let mut alternatives = vec![self.alternative()?];
+        while self.eat_symbol("|") {
+            alternatives.push(self.alternative()?);
+        }
+        // As in Rust, so a guard never meets a name its alternative didn't bind.
+        let binding = &alternatives[0].binding;
+        if let Some(other) = alternatives.iter().find(|a| a.binding != *binding) {
+            let name = binding.as_ref().or(other.binding.as_ref());
+            return Err(QueryError::new(
+                column,
+                format!(
+                    "'{}' isn't bound in every alternative",
+                    name.map_or("", String::as_str)
+                ),
+            ));
+        }
+        let guard = if self.eat_keyword("if") {
+            Some(Box::new(self.unary()?))
+        } else {
+            None
+        };
+        Ok(Query::Match {
+            field,
+            alternatives,
+            guard,
+        })
+    }
+
+    fn alternative(&mut self) -> Result<Alternative, QueryError> {
+        let binding = match (self.peek(), self.tokens.get(self.pos + 1)) {
+            (Some(Token::Name(name)), Some((_, Token::Symbol("@")))) => {
+                if KEYWORDS.contains(&name.as_str()) || name == "_" {
+                    return Err(QueryError::new(
+                        self.column(),
+                        format!("'{}' can't be bound", name),
+                    ));
+                }
+                let name = name.clone();
+                self.pos += 2;
+                Some(name)
+            }
+            _ => None,
+        };
+        Ok(Alternative {
+            binding,
+            atom: self.atom()?,
+        })
+    }
+
+    fn atom(&mut self) -> Result<Atom, QueryError> {
+        if self.eat_keyword("_") {
+            return Ok(Atom::Any);
+        }
+        let column = self.column();
+        let low = self.literal();
+        let inclusive = match self.peek() {
+            Some(Token::Symbol("..=")) => true,
+            Some(Token::Symbol("..")) => false,
+            _ => {
+                return match low {
+                    Some(value) => Ok(Atom::Value(value)),
+                    None => Err(self.error("a pattern")),
+                };
+            }
+        };
+        self.pos += 1;
+        let high = self.literal();
+        match (&low, &high) {
+            (None, None) => return Err(self.error("a value to end the range")),
+            (_, None) if inclusive => return Err(self.error("a value after '..='")),
+            (Some(low), Some(high)) => match low.compare(high) {
+                None => {
+                    return Err(QueryError::new(
+                        column,
+                        format!("{} and {} can't be the ends of a range", low, high),
+                    ))
+                }
+                Some(ordering) if ordering.is_gt() => {
+                    return Err(QueryError::new(
+                        column,
+                        format!("the range starts at {}, after its end {}", low, high),
+                    ))
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Ok(Atom::Range {
+            low,
+            high,
+            inclusive,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_patterns() {
+        let query = parse(r#"size in n @ 9..=11 | n @ 13.. if n != 10"#).unwrap();
+        let Query::Match {
+            field,
+            alternatives,
+            guard,
+        } = query
+        else {
+            panic!("not a match: {:?}", query);
+        };
+        assert_eq!(field, "size");
+        assert_eq!(
+            alternatives,
+            [
+                Alternative {
+                    binding: Some("n".into()),
+                    atom: Atom::Range {
+                        low: Some(Value::Int(9)),
+                        high: Some(Value::Int(11)),
+                        inclusive: true,
+                    },
+                },
+                Alternative {
+                    binding: Some("n".into()),
+                    atom: Atom::Range {
+                        low: Some(Value::Int(13)),
+                        high: None,
+                        inclusive: false,
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            guard,
+            Some(Box::new(Query::Compare(
+                Operand::Name("n".into()),
+                CompareOp::Ne,
+                Operand::Value(Value::Int(10))
+            )))
+        );
+    }
+
+    #[test]
+    fn reads_literals() {
+        let query = parse(r#"x in -4 | "a \"b\"\n" | 'c' | '\'' | false"#).unwrap();
+        let Query::Match { alternatives, .. } = query else {
+            panic!("not a match");
+        };
+        let atoms: Vec<Atom> = alternatives.into_iter().map(|a| a.atom).collect();
+        assert_eq!(
+            atoms,
+            [
+                Atom::Value(Value::Int(-4)),
+                Atom::Value(Value::Str("a \"b\"\n".into())),
+                Atom::Value(Value::Char('c')),
+                Atom::Value(Value::Char('\'')),
+                Atom::Value(Value::Bool(false)),
+            ]
+        );
+        // `and` binds tighter than `or`.
+        assert_eq!(
+            parse("a or b and c").unwrap(),
+            Query::Or(vec![
+                Query::Field("a".into()),
+                Query::And(vec![Query::Field("b".into()), Query::Field("c".into())]),
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let err = |src| parse(src).unwrap_err().to_string();
+        assert_eq!(err(""), "column 1: expected a test, found the end");
+        assert_eq!(
+            err("size in"),
+            "column 8: expected a pattern, found the end"
+        );
+        assert_eq!(
+            err("size in 11..=9"),
+            "column 9: the range starts at 11, after its end 9"
+        );
+        assert_eq!(
+            err("size in 1..='z'"),
+            "column 9: 1 and 'z' can't be the ends of a range"
+        );
+        assert_eq!(
+            err("size in 1..="),
+            "column 13: expected a value after '..=', found the end"
+        );
+        assert_eq!(
+            err("size in n @ 1 | 2"),
+            "column 9: 'n' isn't bound in every alternative"
+        );
+        assert_eq!(
+            err("size in 1 | m @ 2"),
+            "column 9: 'm' isn't bound in every alternative"
+        );
+        assert_eq!(
+            err("size in 1 size"),
+            "column 11: unexpected 'size' after the query"
+        );
+        assert_eq!(err("(a or b"), "column 8: expected ')', found the end");
+        assert_eq!(
+            err("3 and a"),
+            "column 3: expected a comparison, found 'and'"
+        );
+        assert_eq!(err("a # b"), "column 3: unexpected character '#'");
+        assert_eq!(err(r#"a in "abc"#), "column 6: unterminated string");
+        assert_eq!(
+            err("a in 99999999999999999999"),
+            "column 6: 99999999999999999999 is too large"
+        );
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let deep = |n| format!("{}a{}", "(".repeat(n), ")".repeat(n));
+        assert!(parse(&deep(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            parse(&deep(MAX_DEPTH)).unwrap_err(),
+            QueryError::new(MAX_DEPTH + 1, "nested more than 200 deep")
+        );
+        assert!(parse(&"not ".repeat(50_000)).is_err());
+        let guards = "a in 1 if ".repeat(MAX_DEPTH + 1) + "a";
+        assert!(parse(&guards).is_err());
+    }
+}
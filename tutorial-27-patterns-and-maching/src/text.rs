@@ -0,0 +1,139 @@
+// What the line-based file formats have in common: a policy and a message catalog are both read
+// a line at a time, and fail the same ways.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// A line that couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+    // Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl LineError {
+    pub fn new(line: usize, message: impl Into<String>) -> LineError {
+        LineError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for LineError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io { path: String, error: io::Error },
+    Parse { path: String, error: LineError },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { path, error } => write!(f, "cannot read {}: {}", path, error),
+            LoadError::Parse { path, error } => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io { error, .. } => Some(error),
+            LoadError::Parse { error, .. } => Some(error),
+        }
+    }
+}
+
+impl LoadError {
+    pub(crate) fn io(path: &Path, error: io::Error) -> LoadError {
+        LoadError::Io {
+            path: path.display().to_string(),
+            error,
+        }
+    }
+}
+
+// Reads the file at `path` and parses it, saying which file any error is in.
+pub(crate) fn load<T>(
+    path: &Path,
+    parse: impl FnOnce(&str) -> Result<T, LineError>,
+) -> Result<T, LoadError> {
+    let src = fs::read_to_string(path).map_err(|error| LoadError::io(path, error))?;
+    parse(&src).map_err(|error| LoadError::Parse {
+        path: path.display().to_string(),
+        error,
+    })
+}
+
+// A directory of its own under the system's temporary one, removed again when dropped, for the
+// tests that read files.
+#[cfg(test)]
+pub(crate) struct Scratch(std::path::PathBuf);
+
+#[cfg(test)]
+impl Scratch {
+    pub(crate) fn new(name: &str) -> Scratch {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Scratch(dir)
+    }
+
+    // Writes `contents` to `file` in the directory, giving its path.
+    pub(crate) fn write(&self, file: &str, contents: &str) -> std::path::PathBuf {
+        let path = self.0.join(file);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(src: &str) -> Result<Vec<i32>, LineError> {
+        src.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                line.parse()
+                    .map_err(|_| LineError::new(i + 1, "not a number"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn says_which_file_and_line() {
+        let scratch = Scratch::new("text-test");
+        let good = scratch.write("good.txt", "1\n2");
+        assert_eq!(load(&good, numbers).unwrap(), [1, 2]);
+
+        let bad = scratch.write("bad.txt", "1\ntwo");
+        let err = load(&bad, numbers).unwrap_err().to_string();
+        assert!(err.ends_with("bad.txt: line 2: not a number"), "{}", err);
+
+        let missing = scratch.path().join("missing.txt");
+        assert!(matches!(load(&missing, numbers), Err(LoadError::Io { .. })));
+    }
+}
@@ -0,0 +1,325 @@
+// Works out values, with variables looked up from the innermost block outward, then the
+// calculator's own `let`s from earlier lines, then the built-in constants.
+
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts;
+use std::fmt;
+
+use super::lexer::tokenize;
+use super::parser::{parse, BinOp, Expr, Stmt};
+use super::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn accepts(self, n: usize) -> bool {
+        match self {
+            Arity::Exactly(expected) => n == expected,
+            Arity::AtLeast(min) => n >= min,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (prefix, n) = match self {
+            Arity::Exactly(n) => ("", n),
+            Arity::AtLeast(n) => ("at least ", n),
+        };
+        let plural = if *n == 1 { "" } else { "s" };
+        write!(f, "{}{} argument{}", prefix, n, plural)
+    }
+}
+
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: Arity,
+    f: fn(&[f64]) -> f64,
+}
+
+impl Builtin {
+    const fn new(name: &'static str, arity: Arity, f: fn(&[f64]) -> f64) -> Builtin {
+        Builtin { name, arity, f }
+    }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("abs", Arity::Exactly(1), |a| a[0].abs()),
+    Builtin::new("sqrt", Arity::Exactly(1), |a| a[0].sqrt()),
+    Builtin::new("cbrt", Arity::Exactly(1), |a| a[0].cbrt()),
+    Builtin::new("exp", Arity::Exactly(1), |a| a[0].exp()),
+    Builtin::new("ln", Arity::Exactly(1), |a| a[0].ln()),
+    Builtin::new("log", Arity::Exactly(2), |a| a[0].log(a[1])),
+    Builtin::new("log10", Arity::Exactly(1), |a| a[0].log10()),
+    Builtin::new("log2", Arity::Exactly(1), |a| a[0].log2()),
+    Builtin::new("sin", Arity::Exactly(1), |a| a[0].sin()),
+    Builtin::new("cos", Arity::Exactly(1), |a| a[0].cos()),
+    Builtin::new("tan", Arity::Exactly(1), |a| a[0].tan()),
+    Builtin::new("asin", Arity::Exactly(1), |a| a[0].asin()),
+    Builtin::new("acos", Arity::Exactly(1), |a| a[0].acos()),
+    Builtin::new("atan", Arity::Exactly(1), |a| a[0].atan()),
+    Builtin::new("atan2", Arity::Exactly(2), |a| a[0].atan2(a[1])),
+    Builtin::new("hypot", Arity::Exactly(2), |a| a[0].hypot(a[1])),
+    Builtin::new("floor", Arity::Exactly(1), |a| a[0].floor()),
+    Builtin::new("ceil", Arity::Exactly(1), |a| a[0].ceil()),
+    Builtin::new("round", Arity::Exactly(1), |a| a[0].round()),
+    Builtin::new("trunc", Arity::Exactly(1), |a| a[0].trunc()),
+    Builtin::new("pow", Arity::Exactly(2), |a| a[0].powf(a[1])),
+    Builtin::new("min", Arity::AtLeast(1), |a| {
+        a.iter().copied().fold(f64::INFINITY, f64::min)
+    }),
+    Builtin::new("max", Arity::AtLeast(1), |a| {
+        a.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+    }),
+];
+
+const CONSTANTS: [(&str, f64); 3] = [("pi", consts::PI), ("e", consts::E), ("tau", consts::TAU)];
+
+// What a line came to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Value(f64),
+    // The line ended with `let name = value`.
+    Bound(String, f64),
+    // Nothing but whitespace.
+    Empty,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Calculator {
+    vars: BTreeMap<String, f64>,
+}
+
+impl Calculator {
+    pub fn new() -> Calculator {
+        Calculator::default()
+    }
+
+    // Runs every statement on the line. If any fails, none of the line's `let`s are kept.
+    pub fn eval(&mut self, line: &str) -> Result<Outcome, Error> {
+        let stmts = parse(tokenize(line)?)?;
+        let mut scope = Scope {
+            globals: self.vars.clone(),
+            blocks: Vec::new(),
+        };
+        let mut outcome = Outcome::Empty;
+        for stmt in &stmts {
+            outcome = match stmt {
+                Stmt::Let(name, value) => {
+                    let value = scope.eval(value)?;
+                    scope.globals.insert(name.clone(), value);
+                    Outcome::Bound(name.clone(), value)
+                }
+                Stmt::Expr(e) => Outcome::Value(scope.eval(e)?),
+            };
+        }
+        self.vars = scope.globals;
+        Ok(outcome)
+    }
+
+    // Variables bound by earlier lines, in alphabetical order.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.vars
+            .iter()
+            .map(|(name, &value)| (name.as_str(), value))
+    }
+}
+
+struct Scope {
+    globals: BTreeMap<String, f64>,
+    // One map per block being evaluated, innermost last.
+    blocks: Vec<HashMap<String, f64>>,
+}
+
+impl Scope {
+    fn lookup(&self, name: &str) -> Option<f64> {
+        self.blocks
+            .iter()
+            .rev()
+            .find_map(|vars| vars.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+            .or_else(|| {
+                CONSTANTS
+                    .iter()
+                    .find(|(constant, _)| *constant == name)
+                    .map(|&(_, value)| value)
+            })
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<f64, Error> {
+        match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Var(name, span) => self
+                .lookup(name)
+                .ok_or_else(|| Error::new(ErrorKind::UnknownVariable(name.clone()), *span)),
+            Expr::Neg(e) => Ok(-self.eval(e)?),
+            Expr::Binary(op, lhs, rhs, span) => {
+                let (a, b) = (self.eval(lhs)?, self.eval(rhs)?);
+                if b == 0.0 && matches!(op, BinOp::Div | BinOp::Rem) {
+                    return Err(Error::new(ErrorKind::DivideByZero, *span));
+                }
+                Ok(match op {
+                    BinOp::Add => a + b,
+                    BinOp::Sub => a - b,
+                    BinOp::Mul => a * b,
+                    BinOp::Div => a / b,
+                    BinOp::Rem => a % b,
+                    BinOp::Pow => a.powf(b),
+                })
+            }
+            Expr::Call(name, args, span) => {
+                let builtin = BUILTINS
+                    .iter()
+                    .find(|b| b.name == name)
+                    .ok_or_else(|| Error::new(ErrorKind::UnknownFunction(name.clone()), *span))?;
+                if !builtin.arity.accepts(args.len()) {
+                    return Err(Error::new(
+                        ErrorKind::WrongArgCount {
+                            function: builtin.name,
+                            expected: builtin.arity,
+                            found: args.len(),
+                        },
+                        *span,
+                    ));
+                }
+                let values = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<f64>, Error>>()?;
+                Ok((builtin.f)(&values))
+            }
+            Expr::Block(stmts, value) => {
+                self.blocks.push(HashMap::new());
+                let result = self.block(stmts, value);
+                self.blocks.pop();
+                result
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt], value: &Expr) -> Result<f64, Error> {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Let(name, e) => {
+                    let v = self.eval(e)?;
+                    self.blocks.last_mut().unwrap().insert(name.clone(), v);
+                }
+                Stmt::Expr(e) => {
+                    self.eval(e)?;
+                }
+            }
+        }
+        self.eval(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(calc: &mut Calculator, line: &str) -> f64 {
+        match calc.eval(line) {
+            Ok(Outcome::Value(v)) => v,
+            other => panic!("{:?} gave {:?}", line, other),
+        }
+    }
+
+    fn error(line: &str) -> (String, String) {
+        let err = Calculator::new().eval(line).unwrap_err();
+        (err.to_string(), err.underline(line))
+    }
+
+    #[test]
+    fn evaluates_arithmetic() {
+        let mut calc = Calculator::new();
+        assert_eq!(value(&mut calc, "1 + 2 * 3"), 7.0);
+        assert_eq!(value(&mut calc, "(1 + 2) * 3"), 9.0);
+        assert_eq!(value(&mut calc, "-2 ^ 2"), -4.0);
+        assert_eq!(value(&mut calc, "2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(value(&mut calc, "7 % 4 - 10 / 4"), 0.5);
+        assert_eq!(value(&mut calc, "max(3, -1, sqrt(16) + 1)"), 5.0);
+        assert_eq!(value(&mut calc, "log(8, 2)"), 3.0);
+        assert_eq!(value(&mut calc, "round(2 * pi)"), 6.0);
+    }
+
+    #[test]
+    fn blocks_are_expressions() {
+        let mut calc = Calculator::new();
+        assert_eq!(value(&mut calc, "{ let x = 3; x + 1 }"), 4.0);
+        // The block's x is gone afterwards, and shadowed only inside it.
+        assert_eq!(
+            calc.eval("x").unwrap_err().kind,
+            ErrorKind::UnknownVariable("x".into())
+        );
+        assert_eq!(
+            calc.eval("let x = 10"),
+            Ok(Outcome::Bound("x".into(), 10.0))
+        );
+        assert_eq!(
+            value(&mut calc, "{ let x = x * 2; { let y = x + 1; y } } + x"),
+            31.0
+        );
+        assert_eq!(value(&mut calc, "x"), 10.0);
+    }
+
+    #[test]
+    fn keeps_lets_between_lines() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            calc.eval("let r = 2; let area = pi * r ^ 2"),
+            Ok(Outcome::Bound("area".into(), consts::PI * 4.0))
+        );
+        assert_eq!(value(&mut calc, "area / r"), consts::PI * 2.0);
+        assert_eq!(calc.eval("   "), Ok(Outcome::Empty));
+        // A failing line keeps none of its bindings.
+        assert!(calc.eval("let q = 1; q / 0").is_err());
+        let names: Vec<&str> = calc.vars().map(|(name, _)| name).collect();
+        assert_eq!(names, ["area", "r"]);
+        // Variables can shadow constants.
+        assert_eq!(calc.eval("let e = 1; e"), Ok(Outcome::Value(1.0)));
+    }
+
+    #[test]
+    fn reports_errors_with_their_position() {
+        assert_eq!(
+            error("1 + * 2"),
+            ("expected an expression, found '*'".into(), "    ^".into())
+        );
+        assert_eq!(
+            error("2 * (3 + y)"),
+            ("no variable named 'y'".into(), "         ^".into())
+        );
+        assert_eq!(
+            error("1 + 2 / (1 - 1)"),
+            ("division by zero".into(), "      ^".into())
+        );
+        assert_eq!(
+            error("foo(1)"),
+            ("no function named 'foo'".into(), "^^^".into())
+        );
+        assert_eq!(
+            error("sqrt(1, 2)"),
+            (
+                "sqrt takes 1 argument, but was given 2".into(),
+                "^^^^".into()
+            )
+        );
+        assert_eq!(
+            error("min()").0,
+            "min takes at least 1 argument, but was given 0"
+        );
+        assert_eq!(
+            error("(1 + 2"),
+            ("expected ')', found end of line".into(), "      ^".into())
+        );
+        assert_eq!(
+            error("12 # 3"),
+            ("unexpected character '#'".into(), "   ^".into())
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// Splits a line into tokens, each with the span of the input it came from so errors can point
+// at it.
+
+use std::fmt;
+
+use super::{Error, ErrorKind, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Ident(String),
+    Let,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Equals,
+    // Always the last token, with an empty span at the end of the line.
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Token::Number(n) => return write!(f, "number {}", n),
+            Token::Ident(name) => return write!(f, "name '{}'", name),
+            Token::Eof => return write!(f, "end of line"),
+            Token::Let => "let",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Caret => "^",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Equals => "=",
+        };
+        write!(f, "'{}'", symbol)
+    }
+}
+
+pub fn tokenize(src: &str) -> Result<Vec<(Token, Span)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let token = if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut seen_point = false;
+            let mut seen_exponent = false;
+            while let Some(&(i, c)) = chars.peek() {
+                let sign_after_exponent =
+                    matches!(c, '+' | '-') && matches!(src[..i].chars().last(), Some('e' | 'E'));
+                if c.is_ascii_digit() || sign_after_exponent {
+                    // Digits, or the sign straight after an exponent's 'e'.
+                } else if c == '.' && !seen_point && !seen_exponent {
+                    seen_point = true;
+                } else if matches!(c, 'e' | 'E') && !seen_exponent {
+                    seen_exponent = true;
+                } else {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let text = &src[start..end];
+            match text.parse() {
+                Ok(n) => Token::Number(n),
+                Err(_) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidNumber(String::from(text)),
+                        Span::new(start, end),
+                    ))
+                }
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            match &src[start..end] {
+                "let" => Token::Let,
+                name => Token::Ident(String::from(name)),
+            }
+        } else {
+            chars.next();
+            match c {
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => Token::Star,
+                '/' => Token::Slash,
+                '%' => Token::Percent,
+                '^' => Token::Caret,
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                ',' => Token::Comma,
+                ';' => Token::Semicolon,
+                '=' => Token::Equals,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedChar(c),
+                        Span::new(start, start + c.len_utf8()),
+                    ))
+                }
+            }
+        };
+        let end = chars.peek().map_or(src.len(), |&(i, _)| i);
+        tokens.push((token, Span::new(start, end)));
+    }
+    tokens.push((Token::Eof, Span::new(src.len(), src.len())));
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<Token> {
+        tokenize(src).unwrap().into_iter().map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn splits_into_tokens() {
+        assert_eq!(
+            kinds("let x1 = -2.5e-3*(y_+1)"),
+            [
+                Token::Let,
+                Token::Ident("x1".into()),
+                Token::Equals,
+                Token::Minus,
+                Token::Number(2.5e-3),
+                Token::Star,
+                Token::LParen,
+                Token::Ident("y_".into()),
+                Token::Plus,
+                Token::Number(1.0),
+                Token::RParen,
+                Token::Eof,
+            ]
+        );
+        assert_eq!(
+            kinds(".5 2."),
+            [Token::Number(0.5), Token::Number(2.0), Token::Eof]
+        );
+        // "1e" alone isn't a number, but "e" is a name.
+        assert_eq!(
+            kinds("2 e"),
+            [Token::Number(2.0), Token::Ident("e".into()), Token::Eof]
+        );
+    }
+
+    #[test]
+    fn records_where_each_token_is() {
+        let spans: Vec<Span> = tokenize(" 12 +  ab")
+            .unwrap()
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                Span::new(1, 3),
+                Span::new(4, 5),
+                Span::new(7, 9),
+                Span::new(9, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read() {
+        let err = tokenize("1 + $").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedChar('$'));
+        assert_eq!(err.span, Span::new(4, 5));
+        let err = tokenize("3 + 1e").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidNumber("1e".into()));
+        assert_eq!(
+            tokenize(".").unwrap_err().kind,
+            ErrorKind::InvalidNumber(".".into())
+        );
+    }
+}
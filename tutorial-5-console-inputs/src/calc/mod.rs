@@ -0,0 +1,115 @@
+// A calculator for lines like `let r = 2; pi * r ^ 2` or `{ let x = 3; x + 1 }`.
+//
+// A line goes through three steps, each of which can fail with an `Error` that knows which
+// columns of the line it is about:
+//
+// - the lexer splits it into tokens
+// - the parser builds expressions from them, respecting precedence
+// - the calculator works out their values, keeping `let` bindings for later lines
+
+mod eval;
+mod lexer;
+mod parser;
+
+use std::fmt;
+
+pub use eval::{Arity, Calculator, Outcome, BUILTINS};
+
+// Byte offsets into the line, end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    InvalidNumber(String),
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    UnknownVariable(String),
+    UnknownFunction(String),
+    WrongArgCount {
+        function: &'static str,
+        expected: Arity,
+        found: usize,
+    },
+    DivideByZero,
+    // More brackets, blocks or operators inside one another than `parser::MAX_DEPTH`.
+    TooDeep,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, span: Span) -> Error {
+        Error { kind, span }
+    }
+
+    // Carets under the part of `line` the error is about, at least one wide so the end of the
+    // line can be pointed at too.
+    pub fn underline(&self, line: &str) -> String {
+        let before = line.get(..self.span.start).unwrap_or(line).chars().count();
+        let width = line
+            .get(self.span.start..self.span.end)
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        format!("{}{}", " ".repeat(before), "^".repeat(width))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::InvalidNumber(text) => write!(f, "{:?} is not a valid number", text),
+            ErrorKind::Unexpected { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ErrorKind::UnknownVariable(name) => write!(f, "no variable named '{}'", name),
+            ErrorKind::UnknownFunction(name) => write!(f, "no function named '{}'", name),
+            ErrorKind::WrongArgCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} takes {}, but was given {}",
+                function, expected, found
+            ),
+            ErrorKind::DivideByZero => write!(f, "division by zero"),
+            ErrorKind::TooDeep => write!(f, "too deeply nested"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn underlines_the_span() {
+        let error = Error::new(ErrorKind::DivideByZero, Span::new(2, 5));
+        assert_eq!(error.underline("1 / 0"), "  ^^^");
+        // Counted in characters, not bytes.
+        let error = Error::new(ErrorKind::UnexpectedChar('$'), Span::new(5, 6));
+        assert_eq!(error.underline("é + $"), "    ^");
+        let error = Error::new(ErrorKind::DivideByZero, Span::new(3, 3));
+        assert_eq!(error.underline("1 +"), "   ^");
+    }
+}
@@ -0,0 +1,394 @@
+// A Pratt parser: each binary operator has a binding power on either side, and an operand
+// belongs to whichever neighbouring operator binds it more tightly. A left power lower than the
+// right makes an operator left-associative, so 1 - 2 - 3 is (1 - 2) - 3; `^` has them the other
+// way round, so 2 ^ 3 ^ 2 is 2 ^ (3 ^ 2).
+//
+// Blocks work like Rust's: `{ let x = 3; x + 1 }` runs its statements and is worth its last
+// expression, which has no semicolon after it.
+
+use std::fmt;
+
+use super::lexer::Token;
+use super::{Error, ErrorKind, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BinOp {
+    // (left, right) binding powers.
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            BinOp::Add | BinOp::Sub => (10, 11),
+            BinOp::Mul | BinOp::Div | BinOp::Rem => (20, 21),
+            BinOp::Pow => (41, 40),
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<BinOp> {
+        Some(match token {
+            Token::Plus => BinOp::Add,
+            Token::Minus => BinOp::Sub,
+            Token::Star => BinOp::Mul,
+            Token::Slash => BinOp::Div,
+            Token::Percent => BinOp::Rem,
+            Token::Caret => BinOp::Pow,
+            _ => return None,
+        })
+    }
+}
+
+// How far expressions can sit inside one another, counting brackets, blocks, signs and
+// operators. Parsing, evaluating and even dropping an expression recurse once per level, so
+// without a limit a long enough line overflows the stack.
+pub const MAX_DEPTH: usize = 256;
+
+// Between `*` and `^`, so -2 ^ 2 is -(2 ^ 2) as in maths, but -2 * 3 negates first.
+const NEGATE_POWER: u8 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(String, Span),
+    Neg(Box<Expr>),
+    // The span is the operator's, which is where errors like division by zero point.
+    Binary(BinOp, Box<Expr>, Box<Expr>, Span),
+    Call(String, Vec<Expr>, Span),
+    Block(Vec<Stmt>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let(String, Expr),
+    Expr(Expr),
+}
+
+// Lisp-style, with every operation in parentheses, so tests can see how things grouped.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Var(name, _) => write!(f, "{}", name),
+            Expr::Neg(e) => write!(f, "(- {})", e),
+            Expr::Binary(op, lhs, rhs, _) => {
+                let symbol = match op {
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                    BinOp::Div => "/",
+                    BinOp::Rem => "%",
+                    BinOp::Pow => "^",
+                };
+                write!(f, "({} {} {})", symbol, lhs, rhs)
+            }
+            Expr::Call(name, args, _) => {
+                write!(f, "({}", name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Block(stmts, value) => {
+                write!(f, "{{")?;
+                for stmt in stmts {
+                    write!(f, "{}; ", stmt)?;
+                }
+                write!(f, "{}}}", value)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stmt::Let(name, value) => write!(f, "let {} = {}", name, value),
+            Stmt::Expr(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+    depth: usize,
+}
+
+// A whole line: statements separated by semicolons, with an optional one at the end.
+pub fn parse(tokens: Vec<(Token, Span)>) -> Result<Vec<Stmt>, Error> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let mut stmts = Vec::new();
+    while parser.peek() != &Token::Eof {
+        stmts.push(parser.stmt()?);
+        if !parser.eat(&Token::Semicolon) {
+            parser.expect(&Token::Eof, "';' or the end of the line")?;
+        }
+    }
+    Ok(stmts)
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> (Token, Span) {
+        let token = self.tokens[self.pos].clone();
+        // Stay on Eof once there.
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &'static str) -> Error {
+        Error::new(
+            ErrorKind::Unexpected {
+                expected,
+                found: self.peek().to_string(),
+            },
+            self.span(),
+        )
+    }
+
+    fn expect(&mut self, token: &Token, expected: &'static str) -> Result<Span, Error> {
+        if self.peek() == token {
+            Ok(self.next().1)
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.next();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, Error> {
+        if self.eat(&Token::Let) {
+            let name = self.ident()?;
+            self.expect(&Token::Equals, "'='")?;
+            Ok(Stmt::Let(name, self.expr(0)?))
+        } else {
+            Ok(Stmt::Expr(self.expr(0)?))
+        }
+    }
+
+    // Goes one level deeper, failing at `span` past MAX_DEPTH.
+    fn nest(&mut self, span: Span) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(Error::new(ErrorKind::TooDeep, span));
+        }
+        Ok(())
+    }
+
+    // Parses operators that bind more tightly than `min_power` on their left.
+    fn expr(&mut self, min_power: u8) -> Result<Expr, Error> {
+        let depth = self.depth;
+        self.nest(self.span())?;
+        let mut lhs = self.operand()?;
+        while let Some(op) = BinOp::from_token(self.peek()) {
+            let (left, right) = op.binding_power();
+            if left < min_power {
+                break;
+            }
+            let (_, span) = self.next();
+            // Each operator puts what came before it one level further down.
+            self.nest(span)?;
+            let rhs = self.expr(right)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), span);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    fn operand(&mut self) -> Result<Expr, Error> {
+        let span = self.span();
+        match self.peek().clone() {
+            Token::Number(n) => {
+                self.next();
+                Ok(Expr::Number(n))
+            }
+            Token::Minus => {
+                self.next();
+                Ok(Expr::Neg(Box::new(self.expr(NEGATE_POWER)?)))
+            }
+            Token::Plus => {
+                self.next();
+                self.expr(NEGATE_POWER)
+            }
+            Token::LParen => {
+                self.next();
+                let e = self.expr(0)?;
+                self.expect(&Token::RParen, "')'")?;
+                Ok(e)
+            }
+            Token::LBrace => {
+                self.next();
+                self.block()
+            }
+            Token::Ident(name) => {
+                self.next();
+                if !self.eat(&Token::LParen) {
+                    return Ok(Expr::Var(name, span));
+                }
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr(0)?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(&Token::Comma, "',' or ')'")?;
+                    }
+                }
+                Ok(Expr::Call(name, args, span))
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    // After the opening brace.
+    fn block(&mut self) -> Result<Expr, Error> {
+        let mut stmts = Vec::new();
+        loop {
+            if self.peek() == &Token::RBrace {
+                // `{ 1; }` has nothing left to be its value.
+                return Err(self.unexpected("an expression for the block's value"));
+            }
+            let stmt = self.stmt()?;
+            if self.eat(&Token::Semicolon) {
+                stmts.push(stmt);
+                continue;
+            }
+            self.expect(&Token::RBrace, "';' or '}'")?;
+            return match stmt {
+                Stmt::Expr(value) => Ok(Expr::Block(stmts, Box::new(value))),
+                Stmt::Let(..) => Err(Error::new(
+                    ErrorKind::Unexpected {
+                        expected: "';' after the let",
+                        found: Token::RBrace.to_string(),
+                    },
+                    self.tokens[self.pos - 1].1,
+                )),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::tokenize;
+    use super::*;
+
+    fn tree(src: &str) -> String {
+        let stmts = parse(tokenize(src).unwrap()).unwrap();
+        let shown: Vec<String> = stmts.iter().map(|s| s.to_string()).collect();
+        shown.join("; ")
+    }
+
+    fn error(src: &str) -> Error {
+        parse(tokenize(src).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn respects_precedence_and_associativity() {
+        assert_eq!(tree("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(tree("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+        assert_eq!(tree("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(tree("2 ^ 3 ^ 2"), "(^ 2 (^ 3 2))");
+        assert_eq!(tree("-2 ^ 2"), "(- (^ 2 2))");
+        assert_eq!(tree("-2 * 3"), "(* (- 2) 3)");
+        assert_eq!(tree("2 ^ -1"), "(^ 2 (- 1))");
+        assert_eq!(tree("--+4 % 3"), "(% (- (- 4)) 3)");
+    }
+
+    #[test]
+    fn parses_calls_lets_and_blocks() {
+        assert_eq!(tree("max(1, x, 2 + y)"), "(max 1 x (+ 2 y))");
+        assert_eq!(tree("pi()"), "(pi)");
+        assert_eq!(tree("let x = 3; x + 1;"), "let x = 3; (+ x 1)");
+        assert_eq!(tree("{ let x = 3; x + 1 }"), "{let x = 3; (+ x 1)}");
+        assert_eq!(tree("2 * { 1; { 5 } }"), "(* 2 {1; {5}})");
+        assert_eq!(tree(""), "");
+    }
+
+    #[test]
+    fn points_at_the_problem() {
+        let err = error("1 + * 2");
+        assert_eq!(err.span, Span::new(4, 5));
+        assert_eq!(err.to_string(), "expected an expression, found '*'");
+
+        let err = error("(1 + 2");
+        assert_eq!(err.span, Span::new(6, 6));
+        assert_eq!(err.to_string(), "expected ')', found end of line");
+
+        assert_eq!(
+            error("1 2").to_string(),
+            "expected ';' or the end of the line, found number 2"
+        );
+        assert_eq!(
+            error("let 5 = 1").to_string(),
+            "expected a name, found number 5"
+        );
+        assert_eq!(
+            error("f(1 2)").to_string(),
+            "expected ',' or ')', found number 2"
+        );
+        assert_eq!(
+            error("{ 1; }").to_string(),
+            "expected an expression for the block's value, found '}'"
+        );
+        assert_eq!(error("{ let x = 1 }").span, Span::new(12, 13));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let deep =
+            |open: &str, close: &str, n: usize| format!("{}1{}", open.repeat(n), close.repeat(n));
+        assert!(parse(tokenize(&deep("(", ")", 100)).unwrap()).is_ok());
+        for src in [
+            deep("(", ")", 10_000),
+            deep("{", "}", 10_000),
+            deep("-", "", 10_000),
+            deep("f(", ")", 10_000),
+            format!("1{}", " + 1".repeat(10_000)),
+            format!("1{}", " ^ 1".repeat(10_000)),
+        ] {
+            let err = error(&src);
+            assert_eq!(err.kind, ErrorKind::TooDeep);
+            assert!(err.span.start < src.len());
+        }
+        assert_eq!(error(&deep("(", ")", 300)).span, Span::new(256, 257));
+    }
+}
@@ -1,10 +1,12 @@
 use std::io;
 
-fn main() {
-    let mut input = String::new();
+use tutorial_5::repl::Repl;
 
-    io::stdin()
-        .read_line(&mut input)
+fn main() {
+    // Rather than reading a single line with `read_line`, keep reading and evaluate each one
+    // as a calculator expression until the input ends
+    let stdin = io::stdin();
+    Repl::new(stdin.lock(), io::stdout())
+        .run()
         .expect("Failed to read line");
-    println!("You typed: {}", input);
 }
@@ -0,0 +1,453 @@
+// Asking for typed values instead of `read_line` + `expect` + `parse().unwrap()`.
+//
+// A `Prompter` asks its questions on any `Write` and reads answers from any `BufRead`, asking
+// again whenever an answer doesn't parse or fails a check. Tests script a whole conversation
+// with byte strings; the free functions like `prompt::<i64>("age")` use stdin and stdout.
+
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, StdinLock, Stdout, Write};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum PromptError {
+    Io(io::Error),
+    // The input ended before a valid answer was given.
+    Eof,
+    // Gave up after this many invalid answers.
+    TooManyAttempts(usize),
+}
+
+impl fmt::Display for PromptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromptError::Io(e) => write!(f, "{}", e),
+            PromptError::Eof => write!(f, "input ended before an answer was given"),
+            PromptError::TooManyAttempts(n) => write!(f, "no valid answer after {} tries", n),
+        }
+    }
+}
+
+impl std::error::Error for PromptError {}
+
+impl From<io::Error> for PromptError {
+    fn from(e: io::Error) -> PromptError {
+        PromptError::Io(e)
+    }
+}
+
+// Turns the terminal's echo of typed characters off for passwords and back on afterwards.
+pub trait Echo {
+    fn set_echo(&mut self, on: bool) -> io::Result<()>;
+}
+
+// For input that isn't a terminal, where there is nothing to turn off.
+pub struct NoEcho;
+
+impl Echo for NoEcho {
+    fn set_echo(&mut self, _on: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Flips the ECHO flag on stdin's terminal settings.
+#[cfg(unix)]
+pub struct TerminalEcho;
+
+#[cfg(unix)]
+impl Echo for TerminalEcho {
+    fn set_echo(&mut self, on: bool) -> io::Result<()> {
+        // SAFETY: termios is plain data that tcgetattr fills in before it's read, and both calls
+        // only touch stdin's settings.
+        unsafe {
+            let mut settings: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut settings) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if on {
+                settings.c_lflag |= libc::ECHO;
+            } else {
+                settings.c_lflag &= !libc::ECHO;
+            }
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &settings) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn stdin_echo() -> Box<dyn Echo> {
+    #[cfg(unix)]
+    if io::stdin().is_terminal() {
+        return Box::new(TerminalEcho);
+    }
+    Box::new(NoEcho)
+}
+
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+    echo: Box<dyn Echo>,
+    max_attempts: Option<usize>,
+}
+
+impl Prompter<StdinLock<'static>, Stdout> {
+    pub fn stdio() -> Self {
+        Prompter {
+            input: io::stdin().lock(),
+            output: io::stdout(),
+            echo: stdin_echo(),
+            max_attempts: None,
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Prompter<R, W> {
+        Prompter {
+            input,
+            output,
+            echo: Box::new(NoEcho),
+            max_attempts: None,
+        }
+    }
+
+    pub fn with_echo(mut self, echo: impl Echo + 'static) -> Self {
+        self.echo = Box::new(echo);
+        self
+    }
+
+    // Gives up with `TooManyAttempts` after this many invalid answers to one question, rather
+    // than asking until the input ends. Every question is asked at least once, so 0 counts as 1.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = Some(attempts.max(1));
+        self
+    }
+
+    // Asks for a `T` until the answer parses.
+    pub fn prompt<T>(&mut self, label: &str) -> Result<T, PromptError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.input(label).ask()
+    }
+
+    // Like `prompt`, but with a default and checks added before asking.
+    pub fn input<T>(&mut self, label: &str) -> Input<'_, T, R, W> {
+        Input {
+            prompter: self,
+            label: String::from(label),
+            default: None,
+            checks: Vec::new(),
+        }
+    }
+
+    // A yes or no answer. With a default, an empty answer picks it.
+    pub fn confirm(&mut self, question: &str, default: Option<bool>) -> Result<bool, PromptError> {
+        let choices = match default {
+            Some(true) => "Y/n",
+            Some(false) => "y/N",
+            None => "y/n",
+        };
+        let question = format!("{} [{}]: ", question, choices);
+        self.ask_until(&question, |answer| match answer.to_lowercase().as_str() {
+            "y" | "yes" => Ok(true),
+            "n" | "no" => Ok(false),
+            "" => default.ok_or_else(|| String::from("please answer y or n")),
+            _ => Err(String::from("please answer y or n")),
+        })
+    }
+
+    // Lists the options, numbered from 1, and returns the index of the one picked, by number or
+    // by name. Panics if there are no options, since there'd be nothing to pick.
+    pub fn select<T: fmt::Display>(
+        &mut self,
+        label: &str,
+        options: &[T],
+    ) -> Result<usize, PromptError> {
+        assert!(!options.is_empty(), "select needs at least one option");
+        let names: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+        for (i, name) in names.iter().enumerate() {
+            writeln!(self.output, "  {}) {}", i + 1, name)?;
+        }
+        let question = format!("{} [1-{}]: ", label, names.len());
+        self.ask_until(&question, |answer| {
+            let by_number = answer
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=names.len()).contains(n))
+                .map(|n| n - 1);
+            by_number
+                .or_else(|| {
+                    names
+                        .iter()
+                        .position(|name| name.eq_ignore_ascii_case(answer))
+                })
+                .ok_or_else(|| format!("please enter a number from 1 to {}", names.len()))
+        })
+    }
+
+    // Reads a line without showing what's typed. Leading and trailing spaces are kept, since
+    // they may be part of the password.
+    pub fn password(&mut self, label: &str) -> Result<String, PromptError> {
+        write!(self.output, "{}: ", label)?;
+        self.output.flush()?;
+        self.echo.set_echo(false)?;
+        let line = self.read_line();
+        // Restore echo even if reading failed, so the terminal isn't left silent.
+        self.echo.set_echo(true)?;
+        // The Enter that ended the line wasn't echoed either.
+        writeln!(self.output)?;
+        line
+    }
+
+    // The next line without its line ending.
+    fn read_line(&mut self) -> Result<String, PromptError> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(PromptError::Eof);
+        }
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(line)
+    }
+
+    // Asks `question` until `parse` accepts the trimmed answer, showing its complaint each time
+    // it doesn't.
+    fn ask_until<T>(
+        &mut self,
+        question: &str,
+        mut parse: impl FnMut(&str) -> Result<T, String>,
+    ) -> Result<T, PromptError> {
+        let mut attempts = 0;
+        loop {
+            write!(self.output, "{}", question)?;
+            self.output.flush()?;
+            let answer = self.read_line()?;
+            match parse(answer.trim()) {
+                Ok(value) => return Ok(value),
+                Err(complaint) => writeln!(self.output, "{}", complaint)?,
+            }
+            attempts += 1;
+            if Some(attempts) == self.max_attempts {
+                return Err(PromptError::TooManyAttempts(attempts));
+            }
+        }
+    }
+}
+
+type Check<'a, T> = Box<dyn Fn(&T) -> Result<(), String> + 'a>;
+
+// A question being put together by `Prompter::input`, asked by `ask`.
+pub struct Input<'p, T, R, W> {
+    prompter: &'p mut Prompter<R, W>,
+    label: String,
+    // The value, and how it's shown in the question.
+    default: Option<(T, String)>,
+    checks: Vec<Check<'p, T>>,
+}
+
+impl<'p, T, R, W> Input<'p, T, R, W>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+    R: BufRead,
+    W: Write,
+{
+    // Used when the answer is left empty, and shown in brackets after the label.
+    pub fn default(mut self, value: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        let shown = value.to_string();
+        self.default = Some((value, shown));
+        self
+    }
+
+    // Rejects parsed values that `check` returns an error for, showing the error and asking
+    // again. Checks run in the order they were added.
+    pub fn validate(mut self, check: impl Fn(&T) -> Result<(), String> + 'p) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    pub fn ask(self) -> Result<T, PromptError> {
+        let Input {
+            prompter,
+            label,
+            mut default,
+            checks,
+        } = self;
+        let question = match &default {
+            Some((_, shown)) => format!("{} [{}]: ", label, shown),
+            None => format!("{}: ", label),
+        };
+        prompter.ask_until(&question, |answer| {
+            if answer.is_empty() {
+                if let Some((value, _)) = default.take() {
+                    return Ok(value);
+                }
+            }
+            let value = answer
+                .parse::<T>()
+                .map_err(|e| format!("{:?} isn't a valid {}: {}", answer, label, e))?;
+            for check in &checks {
+                check(&value)?;
+            }
+            Ok(value)
+        })
+    }
+}
+
+// Asks on stdin and stdout for a `T` until the answer parses.
+pub fn prompt<T>(label: &str) -> Result<T, PromptError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    Prompter::stdio().prompt(label)
+}
+
+pub fn confirm(question: &str, default: Option<bool>) -> Result<bool, PromptError> {
+    Prompter::stdio().confirm(question, default)
+}
+
+pub fn select<T: fmt::Display>(label: &str, options: &[T]) -> Result<usize, PromptError> {
+    Prompter::stdio().select(label, options)
+}
+
+pub fn password(label: &str) -> Result<String, PromptError> {
+    Prompter::stdio().password(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // Runs `ask` against scripted input and returns its result with everything it printed.
+    fn converse<T>(
+        input: &str,
+        ask: impl FnOnce(&mut Prompter<&[u8], &mut Vec<u8>>) -> T,
+    ) -> (T, String) {
+        let mut output = Vec::new();
+        let result = ask(&mut Prompter::new(input.as_bytes(), &mut output));
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn asks_until_the_answer_parses() {
+        let (age, transcript) = converse("abc\n\n 42 \n", |p| p.prompt::<i64>("age"));
+        assert_eq!(age.unwrap(), 42);
+        assert_eq!(
+            transcript,
+            "age: \"abc\" isn't a valid age: invalid digit found in string\n\
+             age: \"\" isn't a valid age: cannot parse integer from empty string\n\
+             age: "
+        );
+
+        let (name, _) = converse("Ferris\n", |p| p.prompt::<String>("name"));
+        assert_eq!(name.unwrap(), "Ferris");
+    }
+
+    #[test]
+    fn uses_defaults_and_checks() {
+        let (port, transcript) = converse("\n", |p| p.input("port").default(8080u16).ask());
+        assert_eq!(port.unwrap(), 8080);
+        assert_eq!(transcript, "port [8080]: ");
+
+        let (port, transcript) = converse("80\n70000\n8443\n", |p| {
+            p.input::<u16>("port")
+                .validate(|&port| match port {
+                    0..=1023 => Err(String::from("ports below 1024 need root")),
+                    _ => Ok(()),
+                })
+                .ask()
+        });
+        assert_eq!(port.unwrap(), 8443);
+        assert_eq!(
+            transcript,
+            "port: ports below 1024 need root\n\
+             port: \"70000\" isn't a valid port: number too large to fit in target type\n\
+             port: "
+        );
+    }
+
+    #[test]
+    fn gives_up_when_told_to() {
+        let mut output = Vec::new();
+        let mut prompter =
+            Prompter::new("x\ny\nz\n1\n".as_bytes(), &mut output).with_max_attempts(2);
+        assert!(matches!(
+            prompter.prompt::<i32>("n"),
+            Err(PromptError::TooManyAttempts(2))
+        ));
+        // The input ends before a number is given.
+        let (result, _) = converse("x\n", |p| p.prompt::<i32>("n"));
+        assert!(matches!(result, Err(PromptError::Eof)));
+
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new("x\n1\n".as_bytes(), &mut output).with_max_attempts(0);
+        assert!(matches!(
+            prompter.prompt::<i32>("n"),
+            Err(PromptError::TooManyAttempts(1))
+        ));
+    }
+
+    #[test]
+    fn confirms_yes_or_no() {
+        let (answer, transcript) = converse("maybe\nYES\n", |p| p.confirm("Delete?", None));
+        assert!(answer.unwrap());
+        assert_eq!(
+            transcript,
+            "Delete? [y/n]: please answer y or n\nDelete? [y/n]: "
+        );
+
+        let (answer, transcript) = converse("\n", |p| p.confirm("Continue?", Some(false)));
+        assert!(!answer.unwrap());
+        assert_eq!(transcript, "Continue? [y/N]: ");
+    }
+
+    #[test]
+    fn selects_by_number_or_name() {
+        let sizes = ["small", "medium", "large"];
+        let (picked, transcript) = converse("0\n4\n2\n", |p| p.select("Size", &sizes));
+        assert_eq!(picked.unwrap(), 1);
+        assert_eq!(
+            transcript,
+            "  1) small\n  2) medium\n  3) large\n\
+             Size [1-3]: please enter a number from 1 to 3\n\
+             Size [1-3]: please enter a number from 1 to 3\n\
+             Size [1-3]: "
+        );
+        let (picked, _) = converse("LARGE\n", |p| p.select("Size", &sizes));
+        assert_eq!(picked.unwrap(), 2);
+    }
+
+    // Records each change to the echo setting.
+    struct Recorder(Rc<RefCell<Vec<bool>>>);
+
+    impl Echo for Recorder {
+        fn set_echo(&mut self, on: bool) -> io::Result<()> {
+            self.0.borrow_mut().push(on);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hides_passwords() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(" s3cret \r\n".as_bytes(), &mut output)
+            .with_echo(Recorder(Rc::clone(&changes)));
+        assert_eq!(prompter.password("Password").unwrap(), " s3cret ");
+        // Echo comes back on even when the input has ended.
+        assert!(matches!(prompter.password("Again"), Err(PromptError::Eof)));
+        drop(prompter);
+        assert_eq!(*changes.borrow(), [false, true, false, true]);
+        assert_eq!(String::from_utf8(output).unwrap(), "Password: \nAgain: \n");
+    }
+}
@@ -0,0 +1,234 @@
+// The read-eval-print loop around `Calculator`.
+//
+// Besides expressions it understands a few commands, and can rerun earlier lines:
+//
+//   :help       what can be typed
+//   :history    the lines entered so far, numbered
+//   :vars       variables bound with `let`
+//   :quit       leave (so does end of input)
+//   !!          run the previous line again
+//   !3          run line 3 from the history again
+
+use std::io::{self, BufRead, Write};
+
+use crate::calc::{Calculator, Outcome, BUILTINS};
+
+const PROMPT: &str = "> ";
+
+// Lines entered, oldest first. Commands aren't kept, and neither is a line repeating the one
+// before it.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    lines: Vec<String>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.lines.last().map(String::as_str) != Some(line) {
+            self.lines.push(String::from(line));
+        }
+    }
+
+    // Numbered from 1, as `:history` shows them.
+    pub fn get(&self, number: usize) -> Option<&str> {
+        number
+            .checked_sub(1)
+            .and_then(|i| self.lines.get(i))
+            .map(String::as_str)
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.lines.last().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.as_str()))
+    }
+}
+
+pub struct Repl<R, W> {
+    input: R,
+    output: W,
+    calc: Calculator,
+    history: History,
+}
+
+impl<R: BufRead, W: Write> Repl<R, W> {
+    pub fn new(input: R, output: W) -> Repl<R, W> {
+        Repl {
+            input,
+            output,
+            calc: Calculator::new(),
+            history: History::new(),
+        }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    // Reads and answers lines until `:quit` or the end of the input.
+    pub fn run(&mut self) -> io::Result<()> {
+        writeln!(self.output, "Type an expression, or :help")?;
+        let mut line = String::new();
+        loop {
+            write!(self.output, "{}", PROMPT)?;
+            self.output.flush()?;
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                writeln!(self.output)?;
+                return Ok(());
+            }
+            if !self.respond(line.trim())? {
+                return Ok(());
+            }
+        }
+    }
+
+    // Answers one line. Returns false when it's time to stop.
+    fn respond(&mut self, line: &str) -> io::Result<bool> {
+        match line {
+            "" => {}
+            ":quit" | ":q" => return Ok(false),
+            ":help" => self.help()?,
+            ":history" => {
+                for (number, line) in self.history.iter() {
+                    writeln!(self.output, "{:>4}  {}", number, line)?;
+                }
+            }
+            ":vars" => {
+                for (name, value) in self.calc.vars() {
+                    writeln!(self.output, "{} = {}", name, value)?;
+                }
+            }
+            _ if line.starts_with(':') => {
+                writeln!(self.output, "unknown command {}; try :help", line)?;
+            }
+            _ if line.starts_with('!') => match self.recall(&line[1..]) {
+                Some(recalled) => {
+                    // Show what is being run, since it isn't what was typed.
+                    writeln!(self.output, "{}", recalled)?;
+                    self.evaluate(&recalled)?;
+                }
+                None => writeln!(self.output, "no line {} in the history", line)?,
+            },
+            _ => self.evaluate(line)?,
+        }
+        Ok(true)
+    }
+
+    fn recall(&self, which: &str) -> Option<String> {
+        let line = match which {
+            "!" => self.history.last(),
+            n => self.history.get(n.parse().ok()?),
+        };
+        line.map(String::from)
+    }
+
+    fn evaluate(&mut self, line: &str) -> io::Result<()> {
+        self.history.push(line);
+        match self.calc.eval(line) {
+            Ok(Outcome::Value(v)) => writeln!(self.output, "{}", v),
+            Ok(Outcome::Bound(name, v)) => writeln!(self.output, "{} = {}", name, v),
+            Ok(Outcome::Empty) => Ok(()),
+            Err(e) => {
+                // Lined up under what was typed after the prompt.
+                let indent = " ".repeat(PROMPT.len());
+                writeln!(self.output, "{}{}", indent, e.underline(line))?;
+                writeln!(self.output, "error: {}", e)
+            }
+        }
+    }
+
+    fn help(&mut self) -> io::Result<()> {
+        let functions: Vec<&str> = BUILTINS.iter().map(|b| b.name).collect();
+        writeln!(self.output, "Operators: + - * / % ^ and parentheses")?;
+        writeln!(self.output, "Variables: let x = 2 * pi")?;
+        writeln!(self.output, "Blocks: {{ let x = 3; x + 1 }}")?;
+        writeln!(self.output, "Constants: pi e tau")?;
+        writeln!(self.output, "Functions: {}", functions.join(" "))?;
+        writeln!(
+            self.output,
+            "Commands: :help :history :vars :quit, !! and !n to rerun a line"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs a session and returns what was printed after the greeting.
+    fn session(input: &str) -> String {
+        let mut output = Vec::new();
+        Repl::new(input.as_bytes(), &mut output).run().unwrap();
+        let output = String::from_utf8(output).unwrap();
+        output.lines().skip(1).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn answers_each_line() {
+        assert_eq!(
+            session("1 + 2\nlet x = 3\n{ let y = x; y + 1 }\n"),
+            "> 3\n> x = 3\n> 4\n> "
+        );
+        assert_eq!(session("1\n:quit\n2\n"), "> 1\n> ");
+    }
+
+    #[test]
+    fn points_at_errors() {
+        assert_eq!(
+            session("1 + (2 *\n"),
+            format!(
+                "> {}^\nerror: expected an expression, found end of line\n> ",
+                " ".repeat(10)
+            )
+        );
+    }
+
+    #[test]
+    fn reruns_lines_from_history() {
+        let out = session("let n = 1\nlet n = n * 2\n!!\n!1\n:history\n!9\n");
+        let expected = [
+            "> n = 1",
+            "> n = 2",
+            "> let n = n * 2",
+            "n = 4",
+            "> let n = 1",
+            "n = 1",
+            ">    1  let n = 1",
+            "   2  let n = n * 2",
+            "   3  let n = 1",
+            "> no line !9 in the history",
+            "> ",
+        ];
+        assert_eq!(out, expected.join("\n"));
+    }
+
+    #[test]
+    fn history_skips_repeats() {
+        let mut history = History::new();
+        history.push("1");
+        history.push("1");
+        history.push("2");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(1), Some("1"));
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.last(), Some("2"));
+    }
+}
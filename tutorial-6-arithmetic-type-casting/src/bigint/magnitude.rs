@@ -0,0 +1,320 @@
+// Arithmetic on unsigned magnitudes stored as base 2^32 digits ("limbs"), least significant
+// first. Results are always trimmed so the last limb is non-zero, which makes zero the empty
+// slice and lets lengths be compared before limbs.
+
+use std::cmp::Ordering;
+
+// Below this many limbs in the shorter factor, schoolbook multiplication wins because Karatsuba's
+// extra additions and allocations cost more than the multiplications it saves.
+pub(crate) const KARATSUBA_THRESHOLD: usize = 32;
+
+const BASE: u64 = 1 << 32;
+
+pub(crate) fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+pub(crate) fn cmp(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+pub(crate) fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0;
+    for (i, &limb) in long.iter().enumerate() {
+        let s = limb as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        sum.push(s as u32);
+        carry = s >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// a - b, where a must be at least b.
+pub(crate) fn sub(a: &[u32], b: &[u32]) -> Vec<u32> {
+    debug_assert!(cmp(a, b) != Ordering::Less);
+    let mut diff = a.to_vec();
+    sub_in_place(&mut diff, b);
+    trim(&mut diff);
+    diff
+}
+
+fn sub_in_place(a: &mut [u32], b: &[u32]) {
+    let mut borrow = false;
+    for (i, limb) in a.iter_mut().enumerate() {
+        if i >= b.len() && !borrow {
+            break;
+        }
+        let (d, b1) = limb.overflowing_sub(b.get(i).copied().unwrap_or(0));
+        let (d, b2) = d.overflowing_sub(borrow as u32);
+        *limb = d;
+        borrow = b1 || b2;
+    }
+    debug_assert!(!borrow);
+}
+
+// Adds b into a starting at limb `offset`. a must be long enough to hold the result.
+fn add_at(a: &mut [u32], b: &[u32], offset: usize) {
+    let mut carry = 0;
+    for (i, slot) in a[offset..].iter_mut().enumerate() {
+        if i >= b.len() && carry == 0 {
+            break;
+        }
+        let s = *slot as u64 + b.get(i).copied().unwrap_or(0) as u64 + carry;
+        *slot = s as u32;
+        carry = s >> 32;
+    }
+    debug_assert_eq!(carry, 0);
+}
+
+pub(crate) fn mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut product = if short.len() < KARATSUBA_THRESHOLD {
+        schoolbook(a, b)
+    } else if long.len() >= 2 * short.len() {
+        unbalanced(long, short)
+    } else {
+        karatsuba(a, b)
+    };
+    trim(&mut product);
+    product
+}
+
+pub(crate) fn schoolbook(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let p = x as u64 * y as u64 + product[i + j] as u64 + carry;
+            product[i + j] = p as u32;
+            carry = p >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    product
+}
+
+// Cuts the long factor into pieces the length of the short one and multiplies each piece by it,
+// so every product is between numbers of about the same size, where Karatsuba does best.
+fn unbalanced(long: &[u32], short: &[u32]) -> Vec<u32> {
+    let mut product = vec![0; long.len() + short.len()];
+    for (i, piece) in long.chunks(short.len()).enumerate() {
+        let mut piece = piece.to_vec();
+        trim(&mut piece);
+        add_at(&mut product, &mul(&piece, short), i * short.len());
+    }
+    product
+}
+
+// Splits both numbers at half the shorter one's length, so a = a1·B + a0 and b = b1·B + b0, and
+// gets a·b from three half-size products instead of four:
+//
+//   a·b = a1·b1·B² + ((a0 + a1)(b0 + b1) - a1·b1 - a0·b0)·B + a0·b0
+//
+// The lengths are within a factor of two of each other here; see `unbalanced`.
+fn karatsuba(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let half = a.len().min(b.len()) / 2;
+    let (a0, a1) = split(a, half);
+    let (b0, b1) = split(b, half);
+
+    let low = mul(&a0, &b0);
+    let high = mul(a1, b1);
+    let mut middle = mul(&add(&a0, a1), &add(&b0, b1));
+    sub_in_place(&mut middle, &low);
+    sub_in_place(&mut middle, &high);
+    trim(&mut middle);
+
+    let mut product = vec![0; a.len() + b.len()];
+    add_at(&mut product, &low, 0);
+    add_at(&mut product, &middle, half);
+    add_at(&mut product, &high, 2 * half);
+    product
+}
+
+// The low `at` limbs, trimmed, and the rest.
+fn split(x: &[u32], at: usize) -> (Vec<u32>, &[u32]) {
+    let (low, high) = x.split_at(at.min(x.len()));
+    let mut low = low.to_vec();
+    trim(&mut low);
+    (low, high)
+}
+
+// Multiplies in place by a single limb and adds another, as when reading digits.
+pub(crate) fn mul_add_small(a: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in a.iter_mut() {
+        let p = *limb as u64 * factor as u64 + carry;
+        *limb = p as u32;
+        carry = p >> 32;
+    }
+    if carry > 0 {
+        a.push(carry as u32);
+    }
+}
+
+// Divides in place by a single non-zero limb and returns the remainder.
+pub(crate) fn div_rem_small(a: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in a.iter_mut().rev() {
+        let cur = (rem << 32) | *limb as u64;
+        *limb = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    trim(a);
+    rem as u32
+}
+
+// Quotient and remainder of a / b for non-zero b, by Knuth's Algorithm D (TAOCP vol. 2, 4.3.1).
+//
+// Each quotient limb is estimated from the top two limbs of what's left of a and the top limb of
+// b. Shifting both so b's top bit is set makes that estimate at most two too large, and the
+// rare case where it is still one too large after checking against b's second limb is caught by
+// the subtraction going negative.
+pub(crate) fn div_rem(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    assert!(!b.is_empty(), "division by zero");
+    if cmp(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let mut q = a.to_vec();
+        let r = div_rem_small(&mut q, b[0]);
+        let r = if r == 0 { Vec::new() } else { vec![r] };
+        return (q, r);
+    }
+
+    let shift = b[b.len() - 1].leading_zeros();
+    let v = shl_bits(b, shift);
+    let mut u = shl_bits(a, shift);
+    u.push(0);
+    let n = v.len();
+    let m = u.len() - n - 1;
+    let (top, next) = (v[n - 1] as u64, v[n - 2] as u64);
+
+    let mut q = vec![0; m + 1];
+    for j in (0..=m).rev() {
+        let num = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = num / top;
+        let mut rhat = num % top;
+        while qhat >= BASE || qhat * next > ((rhat << 32) | u[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += top;
+            if rhat >= BASE {
+                break;
+            }
+        }
+
+        // u[j..=j + n] -= qhat · v
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64 + carry;
+            carry = p >> 32;
+            let t = u[i + j] as i64 - borrow - (p & 0xffff_ffff) as i64;
+            u[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+
+        if t < 0 {
+            // qhat was one too many, so add b back once.
+            qhat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+        q[j] = qhat as u32;
+    }
+
+    trim(&mut q);
+    let mut r = shr_bits(&u[..n], shift);
+    trim(&mut r);
+    (q, r)
+}
+
+// Shifts left by fewer than 32 bits, keeping the length unless bits spill into a new limb.
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut out = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for &limb in a {
+        out.push((limb << shift) | carry);
+        carry = limb >> (32 - shift);
+    }
+    if carry > 0 {
+        out.push(carry);
+    }
+    out
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut out = vec![0; a.len()];
+    for i in 0..a.len() {
+        let high = a.get(i + 1).map_or(0, |&next| next << (32 - shift));
+        out[i] = (a[i] >> shift) | high;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    // Limbs for a number of up to `len` limbs, trimmed like every magnitude.
+    fn limbs(len: std::ops::Range<usize>) -> impl Strategy<Value = Vec<u32>> {
+        vec(any::<u32>(), len).prop_map(|mut limbs| {
+            trim(&mut limbs);
+            limbs
+        })
+    }
+
+    #[test]
+    fn corrects_an_overestimated_quotient_limb() {
+        // One of the test cases from Hacker's Delight's divmnu, widened to 32-bit limbs, where
+        // the estimate survives the two-limb check and has to be fixed by adding b back.
+        let a = [0, 0, 0x8000_0000, 0x7fff_ffff];
+        let b = [1, 0, 0x8000_0000];
+        let (q, r) = div_rem(&a, &b);
+        assert_eq!(add(&mul(&q, &b), &r), a);
+        assert_eq!(cmp(&r, &b), Ordering::Less);
+    }
+
+    proptest! {
+        // Sizes around the threshold and far apart, so all three ways of multiplying are used.
+        #[test]
+        fn karatsuba_matches_schoolbook(a in limbs(20..200), b in limbs(20..600)) {
+            let mut expected = schoolbook(&a, &b);
+            trim(&mut expected);
+            prop_assert_eq!(mul(&a, &b), expected);
+        }
+
+        #[test]
+        fn division_undoes_multiplication(a in limbs(0..200), b in limbs(1..200), r in limbs(0..200)) {
+            prop_assume!(!b.is_empty());
+            let r = if cmp(&r, &b) == Ordering::Less { r } else { Vec::new() };
+            let n = add(&mul(&a, &b), &r);
+            prop_assert_eq!(div_rem(&n, &b), (a, r));
+        }
+    }
+}
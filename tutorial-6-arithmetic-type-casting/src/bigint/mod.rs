@@ -0,0 +1,425 @@
+// A signed integer with no fixed width, for when even i128 overflows.
+//
+// Stored as a sign and a magnitude of 32-bit limbs, least significant first, so that each limb
+// product fits in a u64. Division and remainder truncate toward zero like the built-in types,
+// so the remainder takes the sign of the dividend.
+
+mod magnitude;
+mod radix;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+use crate::ops::forward_binary_op;
+
+pub use radix::ParseBigIntError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    // Never true for zero, so every value has exactly one representation.
+    negative: bool,
+    // Trimmed: the last limb is never 0.
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt::default()
+    }
+
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        magnitude::trim(&mut limbs);
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // -1, 0 or 1.
+    pub fn signum(&self) -> i32 {
+        match (self.negative, self.is_zero()) {
+            (_, true) => 0,
+            (true, _) => -1,
+            _ => 1,
+        }
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.limbs.clone())
+    }
+
+    // How many bits the magnitude needs; 0 for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn pow(&self, mut exp: u32) -> BigInt {
+        let mut base = self.clone();
+        let mut result = BigInt::from(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    // The greatest common divisor of the magnitudes, by Euclid's algorithm. gcd(0, 0) is 0.
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while !b.is_zero() {
+            let r = &a % &b;
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    // Quotient and remainder together, or None when dividing by zero.
+    pub fn checked_div_rem(&self, rhs: &BigInt) -> Option<(BigInt, BigInt)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let (q, r) = magnitude::div_rem(&self.limbs, &rhs.limbs);
+        Some((
+            BigInt::from_parts(self.negative != rhs.negative, q),
+            BigInt::from_parts(self.negative, r),
+        ))
+    }
+
+    // Panics when dividing by zero, like `/` and `%` on the built-in types.
+    pub fn div_rem(&self, rhs: &BigInt) -> (BigInt, BigInt) {
+        self.checked_div_rem(rhs)
+            .expect("attempt to divide by zero")
+    }
+
+    pub fn checked_div(&self, rhs: &BigInt) -> Option<BigInt> {
+        self.checked_div_rem(rhs).map(|(q, _)| q)
+    }
+
+    pub fn checked_rem(&self, rhs: &BigInt) -> Option<BigInt> {
+        self.checked_div_rem(rhs).map(|(_, r)| r)
+    }
+
+    // Adds two signed magnitudes, which also covers subtraction by flipping the second sign.
+    fn add_signed(&self, rhs_negative: bool, rhs: &[u32]) -> BigInt {
+        if self.negative == rhs_negative {
+            return BigInt::from_parts(self.negative, magnitude::add(&self.limbs, rhs));
+        }
+        match magnitude::cmp(&self.limbs, rhs) {
+            Ordering::Less => BigInt::from_parts(rhs_negative, magnitude::sub(rhs, &self.limbs)),
+            _ => BigInt::from_parts(self.negative, magnitude::sub(&self.limbs, rhs)),
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => magnitude::cmp(&self.limbs, &other.limbs),
+            (true, true) => magnitude::cmp(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = radix::magnitude_to_string(&self.limbs, 10);
+        f.pad_integral(!self.negative, "", &digits)
+    }
+}
+
+impl fmt::LowerHex for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = radix::magnitude_to_string(&self.limbs, 16);
+        f.pad_integral(!self.negative, "0x", &digits)
+    }
+}
+
+impl fmt::Binary for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = radix::magnitude_to_string(&self.limbs, 2);
+        f.pad_integral(!self.negative, "0b", &digits)
+    }
+}
+
+macro_rules! from_unsigned {
+    ($($t:ty),*) => {$(
+        impl From<$t> for BigInt {
+            fn from(n: $t) -> BigInt {
+                let n = n as u128;
+                let limbs = (0..4).map(|i| (n >> (32 * i)) as u32).collect();
+                BigInt::from_parts(false, limbs)
+            }
+        }
+    )*};
+}
+
+macro_rules! from_signed {
+    ($($t:ty),*) => {$(
+        impl From<$t> for BigInt {
+            fn from(n: $t) -> BigInt {
+                let magnitude = BigInt::from((n as i128).unsigned_abs());
+                BigInt::from_parts(n < 0, magnitude.limbs)
+            }
+        }
+    )*};
+}
+
+from_unsigned!(u8, u16, u32, u64, u128, usize);
+from_signed!(i8, i16, i32, i64, i128, isize);
+
+// Returned when a BigInt doesn't fit in the type it's being converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange;
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "number out of range for the target type")
+    }
+}
+
+impl std::error::Error for OutOfRange {}
+
+impl TryFrom<&BigInt> for u128 {
+    type Error = OutOfRange;
+
+    fn try_from(n: &BigInt) -> Result<u128, OutOfRange> {
+        if n.negative || n.limbs.len() > 4 {
+            return Err(OutOfRange);
+        }
+        Ok(n.limbs
+            .iter()
+            .rev()
+            .fold(0, |acc, &limb| (acc << 32) | limb as u128))
+    }
+}
+
+impl TryFrom<&BigInt> for i128 {
+    type Error = OutOfRange;
+
+    fn try_from(n: &BigInt) -> Result<i128, OutOfRange> {
+        let magnitude = u128::try_from(&n.abs())?;
+        if n.negative {
+            0i128.checked_sub_unsigned(magnitude).ok_or(OutOfRange)
+        } else {
+            i128::try_from(magnitude).map_err(|_| OutOfRange)
+        }
+    }
+}
+
+macro_rules! try_into_primitive {
+    ($via:ty => $($t:ty),*) => {$(
+        impl TryFrom<&BigInt> for $t {
+            type Error = OutOfRange;
+
+            fn try_from(n: &BigInt) -> Result<$t, OutOfRange> {
+                <$t>::try_from(<$via>::try_from(n)?).map_err(|_| OutOfRange)
+            }
+        }
+    )*};
+}
+
+try_into_primitive!(u128 => u8, u16, u32, u64, usize);
+try_into_primitive!(i128 => i8, i16, i32, i64, isize);
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.limbs)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -self.clone()
+    }
+}
+
+fn add(a: &BigInt, b: &BigInt) -> BigInt {
+    a.add_signed(b.negative, &b.limbs)
+}
+
+fn sub(a: &BigInt, b: &BigInt) -> BigInt {
+    a.add_signed(!b.negative, &b.limbs)
+}
+
+fn mul(a: &BigInt, b: &BigInt) -> BigInt {
+    BigInt::from_parts(a.negative != b.negative, magnitude::mul(&a.limbs, &b.limbs))
+}
+
+fn div(a: &BigInt, b: &BigInt) -> BigInt {
+    a.div_rem(b).0
+}
+
+fn rem(a: &BigInt, b: &BigInt) -> BigInt {
+    a.div_rem(b).1
+}
+
+forward_binary_op!(BigInt, Add add, AddAssign add_assign, add);
+forward_binary_op!(BigInt, Sub sub, SubAssign sub_assign, sub);
+forward_binary_op!(BigInt, Mul mul, MulAssign mul_assign, mul);
+forward_binary_op!(BigInt, Div div, DivAssign div_assign, div);
+forward_binary_op!(BigInt, Rem rem, RemAssign rem_assign, rem);
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn goes_past_i128() {
+        let max = BigInt::from(i128::MAX);
+        let past = &max + BigInt::from(1);
+        assert_eq!(past.to_string(), "170141183460469231731687303715884105728");
+        assert_eq!(i128::try_from(&past), Err(OutOfRange));
+        assert_eq!(i128::try_from(&-&past), Ok(i128::MIN));
+        assert_eq!(u128::try_from(&past), Ok(1 << 127));
+
+        let factorial = (1..=30u32).fold(BigInt::from(1), |acc, n| acc * BigInt::from(n));
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        assert_eq!(BigInt::from(2).pow(200).bits(), 201);
+        assert_eq!(
+            BigInt::from(2).pow(100).gcd(&-BigInt::from(6).pow(40)),
+            BigInt::from(2).pow(40)
+        );
+        assert_eq!(BigInt::zero().gcd(&BigInt::from(-7)), BigInt::from(7));
+    }
+
+    #[test]
+    fn divides_toward_zero() {
+        for (a, b, q, r) in [
+            (7, 2, 3, 1),
+            (-7, 2, -3, -1),
+            (7, -2, -3, 1),
+            (-7, -2, 3, -1),
+        ] {
+            let (a, b) = (BigInt::from(a), BigInt::from(b));
+            assert_eq!(&a / &b, BigInt::from(q));
+            assert_eq!(&a % &b, BigInt::from(r));
+        }
+        assert_eq!(BigInt::from(5).checked_div(&BigInt::zero()), None);
+        // Overflows i128, but not here.
+        assert_eq!(
+            BigInt::from(i128::MIN) / BigInt::from(-1),
+            -BigInt::from(i128::MIN)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn panics_dividing_by_zero() {
+        let _ = BigInt::from(1) / BigInt::zero();
+    }
+
+    #[test]
+    fn multiplies_large_numbers() {
+        // Well past the Karatsuba threshold on both sides.
+        let a = BigInt::from(3).pow(3000) - BigInt::from(1);
+        let b = BigInt::from(7).pow(2000) + BigInt::from(12345);
+        let product = &a * &b;
+        assert_eq!(&product / &a, b);
+        assert_eq!(&product % &b, BigInt::zero());
+        assert_eq!(
+            (&product + BigInt::from(99)).div_rem(&a),
+            (b.clone(), BigInt::from(99))
+        );
+        // (10^n - 1)^2 = 99..9800..01
+        let nines = big(&"9".repeat(500));
+        let square = (&nines * &nines).to_string();
+        assert_eq!(square, format!("{}8{}1", "9".repeat(499), "0".repeat(499)));
+    }
+
+    #[test]
+    fn orders_by_value() {
+        let mut values: Vec<BigInt> = ["5", "-5", "0", "-100000000000000000000", "3"]
+            .iter()
+            .map(|s| big(s))
+            .collect();
+        values.sort();
+        let sorted: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(sorted, ["-100000000000000000000", "-5", "0", "3", "5"]);
+        assert_eq!(-BigInt::zero(), BigInt::zero());
+        assert_eq!(format!("{:+}", BigInt::from(42)), "+42");
+        assert_eq!(format!("{:#x}", BigInt::from(-255)), "-0xff");
+        assert_eq!(format!("{:>6}", BigInt::from(-12)), "   -12");
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_i128_on_arithmetic(a: i128, b: i128) {
+            let (x, y) = (BigInt::from(a), BigInt::from(b));
+            if let Some(sum) = a.checked_add(b) {
+                prop_assert_eq!(&x + &y, BigInt::from(sum));
+            }
+            if let Some(diff) = a.checked_sub(b) {
+                prop_assert_eq!(&x - &y, BigInt::from(diff));
+            }
+            if let Some(q) = a.checked_div(b) {
+                prop_assert_eq!(&x / &y, BigInt::from(q));
+            }
+            if let Some(r) = a.checked_rem(b) {
+                prop_assert_eq!(&x % &y, BigInt::from(r));
+            }
+            prop_assert_eq!(x.cmp(&y), a.cmp(&b));
+            prop_assert_eq!(i128::try_from(&x), Ok(a));
+        }
+
+        #[test]
+        fn agrees_with_i128_on_products(a: i128, b: i128, shift in 0u32..128) {
+            // Shifting b down makes products of every size, not just ones that overflow.
+            let b = b >> shift;
+            let (x, y) = (BigInt::from(a), BigInt::from(b));
+            let product = &x * &y;
+            match a.checked_mul(b) {
+                Some(p) => prop_assert_eq!(product, BigInt::from(p)),
+                // Past i128, so check that dividing gets a back.
+                None => prop_assert_eq!(&product / &y, x),
+            }
+        }
+
+        #[test]
+        fn division_identity_holds(a: i128, b: i128, c: i128) {
+            // Products of i128s are past anything i128 can check, so check q·d + r instead.
+            let n = BigInt::from(a) * BigInt::from(c);
+            let d = BigInt::from(b);
+            prop_assume!(!d.is_zero());
+            let (q, r) = n.div_rem(&d);
+            prop_assert!(r.abs() < d.abs());
+            prop_assert!(r.is_zero() || r.is_negative() == n.is_negative());
+            prop_assert_eq!(&q * &d + &r, n);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// Reading and writing BigInts in any base from 2 to 36, with digits 0-9 then a-z.
+//
+// Going one digit at a time would mean a full pass over the limbs per digit, so digits are
+// handled in chunks: as many as fit in a u32 at once, e.g. nine decimal digits per chunk.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{magnitude, BigInt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseBigIntError {
+    Empty,
+    // The character isn't a digit in the base being read.
+    InvalidDigit(char),
+}
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseBigIntError::Empty => write!(f, "cannot parse a number from an empty string"),
+            ParseBigIntError::InvalidDigit(c) => write!(f, "invalid digit {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+// The most digits that fit in one u32, and radix raised to that many.
+fn chunk(radix: u32) -> (usize, u32) {
+    let mut digits = 1;
+    let mut power = radix;
+    while let Some(next) = power.checked_mul(radix) {
+        digits += 1;
+        power = next;
+    }
+    (digits, power)
+}
+
+fn check_radix(radix: u32) {
+    assert!(
+        (2..=36).contains(&radix),
+        "radix must be between 2 and 36, not {}",
+        radix
+    );
+}
+
+pub(super) fn magnitude_to_string(limbs: &[u32], radix: u32) -> String {
+    if limbs.is_empty() {
+        return String::from("0");
+    }
+    let (width, power) = chunk(radix);
+    let mut rest = limbs.to_vec();
+    // Least significant chunk first.
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        chunks.push(magnitude::div_rem_small(&mut rest, power));
+    }
+
+    let mut out = String::with_capacity(chunks.len() * width);
+    for (i, &chunk) in chunks.iter().rev().enumerate() {
+        let mut digits = Vec::with_capacity(width);
+        let mut n = chunk;
+        while n > 0 {
+            digits.push(std::char::from_digit(n % radix, radix).unwrap());
+            n /= radix;
+        }
+        // Every chunk but the leading one is padded to its full width.
+        if i > 0 {
+            digits.resize(width, '0');
+        }
+        out.extend(digits.iter().rev());
+    }
+    out
+}
+
+impl BigInt {
+    // Like `i128::from_str_radix`: an optional sign, then at least one digit, with letters in
+    // either case. Panics if `radix` isn't between 2 and 36.
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<BigInt, ParseBigIntError> {
+        check_radix(radix);
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        if digits.is_empty() {
+            return Err(ParseBigIntError::Empty);
+        }
+
+        let (width, _) = chunk(radix);
+        let values = digits
+            .chars()
+            .map(|c| c.to_digit(radix).ok_or(ParseBigIntError::InvalidDigit(c)))
+            .collect::<Result<Vec<u32>, _>>()?;
+        let mut limbs = Vec::new();
+        // The first chunk takes the leftover digits so the rest are all full width.
+        let first = match values.len() % width {
+            0 => width,
+            n => n,
+        };
+        let (head, tail) = values.split_at(first);
+        for group in std::iter::once(head).chain(tail.chunks(width)) {
+            let value = group.iter().fold(0, |acc, &d| acc * radix + d);
+            let scale = radix.pow(group.len() as u32);
+            magnitude::mul_add_small(&mut limbs, scale, value);
+        }
+        Ok(BigInt::from_parts(negative, limbs))
+    }
+
+    // Lowercase digits, with a leading '-' for negative numbers. Panics if `radix` isn't between
+    // 2 and 36.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        check_radix(radix);
+        let digits = magnitude_to_string(&self.limbs, radix);
+        if self.negative {
+            format!("-{}", digits)
+        } else {
+            digits
+        }
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<BigInt, ParseBigIntError> {
+        BigInt::from_str_radix(s, 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn reads_and_writes_every_base() {
+        let n = BigInt::from_str_radix("-ZZ", 36).unwrap();
+        assert_eq!(n, BigInt::from(-1295));
+        assert_eq!(n.to_str_radix(36), "-zz");
+        assert_eq!(BigInt::from(255).to_str_radix(2), "11111111");
+        assert_eq!(BigInt::zero().to_str_radix(7), "0");
+        assert_eq!(
+            BigInt::from_str_radix("+000000000000000000001", 10),
+            Ok(BigInt::from(1))
+        );
+
+        let big = BigInt::from(2).pow(256);
+        let hex = format!("1{}", "0".repeat(64));
+        assert_eq!(big.to_str_radix(16), hex);
+        assert_eq!(BigInt::from_str_radix(&hex, 16), Ok(big));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!("".parse::<BigInt>(), Err(ParseBigIntError::Empty));
+        assert_eq!("-".parse::<BigInt>(), Err(ParseBigIntError::Empty));
+        assert_eq!(
+            "12a".parse::<BigInt>(),
+            Err(ParseBigIntError::InvalidDigit('a'))
+        );
+        assert_eq!(
+            BigInt::from_str_radix("102", 2),
+            Err(ParseBigIntError::InvalidDigit('2'))
+        );
+        assert_eq!(
+            " 1".parse::<BigInt>(),
+            Err(ParseBigIntError::InvalidDigit(' '))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "radix must be between 2 and 36")]
+    fn panics_on_a_bad_radix() {
+        BigInt::from(1).to_str_radix(37);
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_i128_on_text(n: i128, radix in 2u32..=36) {
+            let text = BigInt::from(n).to_str_radix(radix);
+            prop_assert_eq!(i128::from_str_radix(&text, radix), Ok(n));
+            prop_assert_eq!(BigInt::from_str_radix(&text.to_uppercase(), radix), Ok(BigInt::from(n)));
+            prop_assert_eq!(BigInt::from(n).to_string(), n.to_string());
+        }
+
+        #[test]
+        fn round_trips_long_numbers(digits in "[1-9][0-9]{0,300}", radix in 2u32..=36) {
+            let n: BigInt = digits.parse().unwrap();
+            prop_assert_eq!(n.to_string(), digits);
+            prop_assert_eq!(BigInt::from_str_radix(&n.to_str_radix(radix), radix), Ok(n));
+        }
+    }
+}
@@ -0,0 +1,396 @@
+// A fixed-point number: a BigInt count of units of 10^-scale, so 12.50 is 1250 at scale 2.
+//
+// Adding, subtracting and multiplying are exact and give as many decimal places as they need.
+// Dividing usually isn't, so it takes the scale and rounding to use. Values compare by what they
+// are worth, so 1.5 and 1.50 are equal, but each keeps its own scale for display.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Neg;
+use std::str::FromStr;
+
+use super::{from_int, round_div, try_into_int, ExactError, Rational, Rounding};
+use crate::bigint::BigInt;
+use crate::ops::forward_binary_op;
+
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    unscaled: BigInt,
+    scale: u32,
+}
+
+fn ten_to(exp: u32) -> BigInt {
+    BigInt::from(10).pow(exp)
+}
+
+impl Decimal {
+    // unscaled · 10^-scale, e.g. `Decimal::new(1999, 2)` is 19.99.
+    pub fn new(unscaled: impl Into<BigInt>, scale: u32) -> Decimal {
+        Decimal {
+            unscaled: unscaled.into(),
+            scale,
+        }
+    }
+
+    pub fn unscaled(&self) -> &BigInt {
+        &self.unscaled
+    }
+
+    // Digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.unscaled.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.unscaled.is_negative()
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal::new(self.unscaled.abs(), self.scale)
+    }
+
+    // Changes the number of decimal places, rounding if there are fewer than before.
+    pub fn rescale(&self, scale: u32, mode: Rounding) -> Decimal {
+        let unscaled = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.unscaled.clone(),
+            Ordering::Greater => &self.unscaled * ten_to(scale - self.scale),
+            Ordering::Less => round_div(&self.unscaled, &ten_to(self.scale - scale), mode),
+        };
+        Decimal::new(unscaled, scale)
+    }
+
+    // Like `rescale`, but fails instead of dropping non-zero digits.
+    pub fn try_rescale(&self, scale: u32) -> Result<Decimal, ExactError> {
+        let rescaled = self.rescale(scale, Rounding::Down);
+        if rescaled == *self {
+            Ok(rescaled)
+        } else {
+            Err(ExactError::Inexact)
+        }
+    }
+
+    // The same value without trailing zeros after the point, so 1.500 becomes 1.5.
+    pub fn normalize(&self) -> Decimal {
+        let ten = BigInt::from(10);
+        let mut unscaled = self.unscaled.clone();
+        let mut scale = self.scale;
+        while scale > 0 {
+            let (q, r) = unscaled.div_rem(&ten);
+            if !r.is_zero() {
+                break;
+            }
+            unscaled = q;
+            scale -= 1;
+        }
+        Decimal::new(unscaled, scale)
+    }
+
+    // self / rhs to `scale` places, or None when dividing by zero.
+    pub fn div_round(&self, rhs: &Decimal, scale: u32, mode: Rounding) -> Option<Decimal> {
+        let quotient = Rational::from(self).checked_div(&Rational::from(rhs))?;
+        Some(Decimal::from_rational(&quotient, scale, mode))
+    }
+
+    // The fraction to `scale` places. Use `Decimal::try_from` to get it exactly or not at all.
+    pub fn from_rational(r: &Rational, scale: u32, mode: Rounding) -> Decimal {
+        let unscaled = round_div(&(r.numer() * ten_to(scale)), r.denom(), mode);
+        Decimal::new(unscaled, scale)
+    }
+
+    // The float's exact binary value rounded to `scale` places, so 0.1 becomes 0.10 at scale
+    // 2 rather than 0.1000000000000000055511151231257827021181583404541015625.
+    pub fn from_f64(x: f64, scale: u32, mode: Rounding) -> Result<Decimal, ExactError> {
+        Ok(Decimal::from_rational(&Rational::try_from(x)?, scale, mode))
+    }
+
+    // The nearest f64.
+    pub fn to_f64(&self) -> f64 {
+        Rational::from(self).to_f64()
+    }
+
+    // The value as a whole number, if it is one.
+    pub fn to_integer(&self) -> Result<BigInt, ExactError> {
+        Ok(self.try_rescale(0)?.unscaled)
+    }
+
+    // Both values as unscaled integers at the larger of their scales.
+    fn aligned(&self, other: &Decimal) -> (BigInt, BigInt, u32) {
+        let scale = self.scale.max(other.scale);
+        let a = self.rescale(scale, Rounding::Down).unscaled;
+        let b = other.rescale(scale, Rounding::Down).unscaled;
+        (a, b, scale)
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(n: BigInt) -> Decimal {
+        Decimal::new(n, 0)
+    }
+}
+
+from_int!(Decimal => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+try_into_int!(Decimal => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// Exact only when the denominator has no prime factors but 2 and 5, since those are the
+// fractions that stop after some number of decimal places.
+impl TryFrom<&Rational> for Decimal {
+    type Error = ExactError;
+
+    fn try_from(r: &Rational) -> Result<Decimal, ExactError> {
+        let mut rest = r.denom().clone();
+        let mut places = [0u32; 2];
+        for (count, prime) in places.iter_mut().zip([2, 5]) {
+            let prime = BigInt::from(prime);
+            loop {
+                let (q, rem) = rest.div_rem(&prime);
+                if !rem.is_zero() {
+                    break;
+                }
+                rest = q;
+                *count += 1;
+            }
+        }
+        if rest != BigInt::from(1) {
+            return Err(ExactError::Inexact);
+        }
+        let scale = places[0].max(places[1]);
+        Ok(Decimal::new(r.numer() * (ten_to(scale) / r.denom()), scale))
+    }
+}
+
+// Every finite float ends after some number of decimal places, though it can take hundreds.
+impl TryFrom<f64> for Decimal {
+    type Error = ExactError;
+
+    fn try_from(x: f64) -> Result<Decimal, ExactError> {
+        Decimal::try_from(&Rational::try_from(x)?)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+// Equal values have to hash the same whatever their scale.
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normal = self.normalize();
+        normal.unscaled.hash(state);
+        normal.scale.hash(state);
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let (a, b, _) = self.aligned(other);
+        a.cmp(&b)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Shows every place the scale has, so 12.50 stays 12.50. A precision like `{:.1}` rounds half to
+// even, as it does for floats.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rounded;
+        let d = match f.precision() {
+            Some(places) => {
+                rounded = self.rescale(places as u32, Rounding::HalfEven);
+                &rounded
+            }
+            None => self,
+        };
+        let mut digits = d.unscaled.abs().to_string();
+        let scale = d.scale as usize;
+        if scale > 0 {
+            if digits.len() <= scale {
+                digits.insert_str(0, &"0".repeat(scale + 1 - digits.len()));
+            }
+            digits.insert(digits.len() - scale, '.');
+        }
+        f.pad_integral(!d.is_negative(), "", &digits)
+    }
+}
+
+// An optional sign, then digits with at most one decimal point. The scale is the number of
+// digits after the point, so "1.50" has scale 2.
+impl FromStr for Decimal {
+    type Err = ExactError;
+
+    fn from_str(s: &str) -> Result<Decimal, ExactError> {
+        let invalid = || ExactError::InvalidNumber(String::from(s));
+        let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(invalid());
+        }
+        let magnitude: BigInt = format!("{}{}", whole, fraction)
+            .parse()
+            .map_err(|_| invalid())?;
+        let unscaled = if s.starts_with('-') {
+            -magnitude
+        } else {
+            magnitude
+        };
+        Ok(Decimal::new(unscaled, fraction.len() as u32))
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-self.unscaled, self.scale)
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        -self.clone()
+    }
+}
+
+fn add(a: &Decimal, b: &Decimal) -> Decimal {
+    let (a, b, scale) = a.aligned(b);
+    Decimal::new(a + b, scale)
+}
+
+fn sub(a: &Decimal, b: &Decimal) -> Decimal {
+    let (a, b, scale) = a.aligned(b);
+    Decimal::new(a - b, scale)
+}
+
+fn mul(a: &Decimal, b: &Decimal) -> Decimal {
+    Decimal::new(&a.unscaled * &b.unscaled, a.scale + b.scale)
+}
+
+forward_binary_op!(Decimal, Add add, AddAssign add_assign, add);
+forward_binary_op!(Decimal, Sub sub, SubAssign sub_assign, sub);
+forward_binary_op!(Decimal, Mul mul, MulAssign mul_assign, mul);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn adds_up_money_exactly() {
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+
+        let price = d("19.99");
+        let total = &price * Decimal::from(3) + d("4.5");
+        assert_eq!(total.to_string(), "64.47");
+        // 8.25% tax, rounded to the cent the way a till would.
+        let tax = (&total * d("0.0825")).rescale(2, Rounding::HalfUp);
+        assert_eq!(tax.to_string(), "5.32");
+        assert_eq!((total - d("70")).to_string(), "-5.53");
+    }
+
+    #[test]
+    fn divides_to_a_chosen_scale() {
+        let share = d("100").div_round(&d("3"), 2, Rounding::HalfEven).unwrap();
+        assert_eq!(share.to_string(), "33.33");
+        let x = d("127003")
+            .div_round(&d("10"), 0, Rounding::HalfUp)
+            .unwrap();
+        assert_eq!(x, d("12700"));
+        assert_eq!(
+            d("127003").div_round(&d("10"), 1, Rounding::Down),
+            Some(d("12700.3"))
+        );
+        assert_eq!(d("1").div_round(&d("0.00"), 2, Rounding::Down), None);
+    }
+
+    #[test]
+    fn compares_by_value() {
+        assert_eq!(d("1.5"), d("1.500"));
+        assert_eq!(d("1.500").to_string(), "1.500");
+        assert_eq!(d("1.500").normalize().to_string(), "1.5");
+        assert!(d("-0.01") < d("0"));
+        assert!(d("2") > d("1.999"));
+        let set: HashSet<Decimal> = [d("1.5"), d("1.50"), d("2")].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn formats_and_parses() {
+        assert_eq!(Decimal::new(5, 3).to_string(), "0.005");
+        assert_eq!(Decimal::new(-5, 1).to_string(), "-0.5");
+        assert_eq!(format!("{:.2}", d("2.675")), "2.68");
+        assert_eq!(format!("{:.2}", d("2.665")), "2.66");
+        assert_eq!(format!("{:.1}", d("-0.04")), "0.0");
+        assert_eq!(format!("{:>8.2}", d("-1")), "   -1.00");
+        assert_eq!(format!("{:+}", d("3.0")), "+3.0");
+        assert_eq!(d(".5"), d("0.5"));
+        assert_eq!(d("+7.").scale(), 0);
+        for bad in ["", "-", ".", "1.2.3", "1,5", "--1", "1e3", " 1"] {
+            assert_eq!(
+                bad.parse::<Decimal>(),
+                Err(ExactError::InvalidNumber(String::from(bad))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn converts_only_when_nothing_is_lost() {
+        assert_eq!(d("12.00").try_rescale(0), Ok(d("12")));
+        assert_eq!(d("12.01").try_rescale(1), Err(ExactError::Inexact));
+        assert_eq!(i32::try_from(&d("42.000")), Ok(42));
+        assert_eq!(i32::try_from(&d("42.5")), Err(ExactError::Inexact));
+        assert_eq!(u8::try_from(&d("-1")), Err(ExactError::OutOfRange));
+
+        assert_eq!(Decimal::try_from(&Rational::new(3, 8)), Ok(d("0.375")));
+        assert_eq!(
+            Decimal::try_from(&Rational::new(1, 3)),
+            Err(ExactError::Inexact)
+        );
+        assert_eq!(
+            Decimal::from_rational(&Rational::new(-2, 3), 3, Rounding::HalfUp),
+            d("-0.667")
+        );
+        assert_eq!(Rational::from(&d("-0.75")), Rational::new(-3, 4));
+    }
+
+    #[test]
+    fn converts_floats() {
+        let exact = Decimal::try_from(0.1).unwrap();
+        assert_eq!(exact.scale(), 55);
+        assert!(exact
+            .to_string()
+            .starts_with("0.1000000000000000055511151231257827"));
+        assert_eq!(Decimal::try_from(0.1).unwrap().to_f64(), 0.1);
+        assert_eq!(Decimal::from_f64(0.1, 2, Rounding::HalfEven), Ok(d("0.10")));
+        // 2.675 is really 2.67499999..., so even HalfUp gives 2.67.
+        assert_eq!(Decimal::from_f64(2.675, 2, Rounding::HalfUp), Ok(d("2.67")));
+        assert_eq!(
+            Decimal::from_f64(f64::INFINITY, 2, Rounding::HalfUp),
+            Err(ExactError::NotFinite)
+        );
+        assert_eq!(d("-1234.5678").to_f64(), -1234.5678);
+    }
+}
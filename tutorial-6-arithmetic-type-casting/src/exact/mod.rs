@@ -0,0 +1,155 @@
+// Numbers that don't round behind your back, for money and anything else where 127_003 / 10
+// quietly becoming 12_700 is a bug.
+//
+// `Rational` holds any fraction exactly. `Decimal` holds a whole number of hundredths, or
+// thousandths, or whatever its scale says, which is how amounts of money are written down.
+//
+// Conversions follow one rule: `From` and `TryFrom` never lose information, and fail with
+// `ExactError::Inexact` rather than round. Anything that may round says so, either by taking a
+// `Rounding` or, for floats, by being called `to_f64`.
+
+mod decimal;
+mod rational;
+
+use std::fmt;
+
+use crate::bigint::BigInt;
+
+pub use decimal::Decimal;
+pub use rational::Rational;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExactError {
+    DivideByZero,
+    // NaN and the infinities aren't numbers that can be held exactly.
+    NotFinite,
+    // The value can't be represented without rounding.
+    Inexact,
+    OutOfRange,
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ExactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExactError::DivideByZero => write!(f, "division by zero"),
+            ExactError::NotFinite => write!(f, "NaN and infinity have no exact value"),
+            ExactError::Inexact => write!(f, "the value can't be represented without rounding"),
+            ExactError::OutOfRange => write!(f, "number out of range for the target type"),
+            ExactError::InvalidNumber(s) => write!(f, "{:?} is not a number", s),
+        }
+    }
+}
+
+impl std::error::Error for ExactError {}
+
+// How to get rid of digits that don't fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    // Toward zero, which is what integer division and `as` do.
+    Down,
+    // Away from zero.
+    Up,
+    // Toward negative infinity.
+    Floor,
+    // Toward positive infinity.
+    Ceiling,
+    // To the nearest, with halves going away from zero. What most people learn at school.
+    HalfUp,
+    // To the nearest, with halves going toward zero.
+    HalfDown,
+    // To the nearest, with halves going to the even neighbour, so that rounding many values
+    // doesn't drift upward. Also called banker's rounding.
+    HalfEven,
+}
+
+// num / den rounded to a whole number. den must be positive.
+fn round_div(num: &BigInt, den: &BigInt, mode: Rounding) -> BigInt {
+    debug_assert!(den > &BigInt::zero());
+    let (q, r) = num.div_rem(den);
+    if r.is_zero() {
+        return q;
+    }
+    // q was truncated toward zero, so the only other candidate is one further from zero.
+    let negative = r.is_negative();
+    let away = match mode {
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Floor => negative,
+        Rounding::Ceiling => !negative,
+        Rounding::HalfUp | Rounding::HalfDown | Rounding::HalfEven => {
+            match (r.abs() * BigInt::from(2)).cmp(den) {
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Equal => match mode {
+                    Rounding::HalfUp => true,
+                    Rounding::HalfDown => false,
+                    _ => !(&q % BigInt::from(2)).is_zero(),
+                },
+            }
+        }
+    };
+    if away {
+        q + BigInt::from(if negative { -1 } else { 1 })
+    } else {
+        q
+    }
+}
+
+// Conversions to every primitive integer, which only succeed for whole numbers in range.
+macro_rules! try_into_int {
+    ($T:ty => $($int:ty),*) => {$(
+        impl TryFrom<&$T> for $int {
+            type Error = ExactError;
+
+            fn try_from(x: &$T) -> Result<$int, ExactError> {
+                <$int>::try_from(&x.to_integer()?).map_err(|_| ExactError::OutOfRange)
+            }
+        }
+    )*};
+}
+
+// Conversions from every primitive integer, which always succeed.
+macro_rules! from_int {
+    ($T:ty => $($int:ty),*) => {$(
+        impl From<$int> for $T {
+            fn from(n: $int) -> $T {
+                <$T>::from(BigInt::from(n))
+            }
+        }
+    )*};
+}
+
+use {from_int, try_into_int};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_each_way() {
+        // Tenths, from -2.5 to 2.5.
+        let cases = [
+            (Rounding::Down, [-2, -1, -1, 0, 1, 1, 2]),
+            (Rounding::Up, [-3, -2, -2, 0, 2, 2, 3]),
+            (Rounding::Floor, [-3, -2, -2, 0, 1, 1, 2]),
+            (Rounding::Ceiling, [-2, -1, -1, 0, 2, 2, 3]),
+            (Rounding::HalfUp, [-3, -2, -1, 0, 1, 2, 3]),
+            (Rounding::HalfDown, [-2, -2, -1, 0, 1, 2, 2]),
+            (Rounding::HalfEven, [-2, -2, -1, 0, 1, 2, 2]),
+        ];
+        let tenths = [-25, -16, -14, 0, 14, 16, 25];
+        for (mode, expected) in cases {
+            let rounded: Vec<BigInt> = tenths
+                .iter()
+                .map(|&n| round_div(&BigInt::from(n), &BigInt::from(10), mode))
+                .collect();
+            let expected: Vec<BigInt> = expected.iter().map(|&n| BigInt::from(n)).collect();
+            assert_eq!(rounded, expected, "{:?}", mode);
+        }
+        let half = |n| round_div(&BigInt::from(n), &BigInt::from(2), Rounding::HalfEven);
+        assert_eq!(half(3), BigInt::from(2));
+        assert_eq!(half(5), BigInt::from(2));
+        assert_eq!(half(-7), BigInt::from(-4));
+    }
+}
@@ -0,0 +1,384 @@
+// A fraction of two BigInts, kept in lowest terms with a positive denominator so that equal
+// values are equal structs.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+use super::{from_int, round_div, try_into_int, Decimal, ExactError, Rounding};
+use crate::bigint::BigInt;
+use crate::ops::forward_binary_op;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: BigInt,
+    den: BigInt,
+}
+
+fn pow2(exp: u32) -> BigInt {
+    BigInt::from(2).pow(exp)
+}
+
+impl Rational {
+    // Panics if `den` is zero, like dividing by zero.
+    pub fn new(num: impl Into<BigInt>, den: impl Into<BigInt>) -> Rational {
+        Rational::checked_new(num.into(), den.into()).expect("denominator is zero")
+    }
+
+    fn checked_new(num: BigInt, den: BigInt) -> Option<Rational> {
+        if den.is_zero() {
+            return None;
+        }
+        let (num, den) = if den.is_negative() {
+            (-num, -den)
+        } else {
+            (num, den)
+        };
+        let gcd = num.gcd(&den);
+        Some(Rational {
+            num: num / &gcd,
+            den: den / gcd,
+        })
+    }
+
+    pub fn zero() -> Rational {
+        Rational::from(0)
+    }
+
+    // Carries the sign.
+    pub fn numer(&self) -> &BigInt {
+        &self.num
+    }
+
+    // Always positive.
+    pub fn denom(&self) -> &BigInt {
+        &self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.num.is_negative()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == BigInt::from(1)
+    }
+
+    pub fn abs(&self) -> Rational {
+        Rational {
+            num: self.num.abs(),
+            den: self.den.clone(),
+        }
+    }
+
+    // 1 / self, or None for zero.
+    pub fn recip(&self) -> Option<Rational> {
+        Rational::checked_new(self.den.clone(), self.num.clone())
+    }
+
+    pub fn checked_div(&self, rhs: &Rational) -> Option<Rational> {
+        Rational::checked_new(&self.num * &rhs.den, &self.den * &rhs.num)
+    }
+
+    // The value as a whole number, if it is one.
+    pub fn to_integer(&self) -> Result<BigInt, ExactError> {
+        if self.is_integer() {
+            Ok(self.num.clone())
+        } else {
+            Err(ExactError::Inexact)
+        }
+    }
+
+    // The nearest whole number in the direction `mode` says.
+    pub fn round(&self, mode: Rounding) -> BigInt {
+        round_div(&self.num, &self.den, mode)
+    }
+
+    // The nearest f64, with ties going to even like every other conversion to float. Values
+    // beyond f64's range become infinite and those too small become zero.
+    pub fn to_f64(&self) -> f64 {
+        if self.is_zero() {
+            return 0.0;
+        }
+        let sign = if self.is_negative() { -1.0 } else { 1.0 };
+        let num = self.num.abs();
+
+        // Scale so the quotient q has 55 or 56 bits: the 53 a mantissa can hold, a bit to round
+        // on, and one spare. The remainder says whether anything was cut off below that.
+        let shift = 55 - (num.bits() as i64 - self.den.bits() as i64);
+        let (q, r) = if shift >= 0 {
+            (num * pow2(shift as u32)).div_rem(&self.den)
+        } else {
+            num.div_rem(&(&self.den * pow2(-shift as u32)))
+        };
+        let q = u128::try_from(&q).unwrap();
+        let len = 128 - q.leading_zeros() as i64;
+
+        // The value is about q·2^-shift, so its top bit is worth 2^top.
+        let top = len - 1 - shift;
+        if top > 1023 {
+            return sign * f64::INFINITY;
+        }
+        // Below the smallest normal float, subnormals have fewer bits to spare.
+        let precision = if top >= -1022 { 53 } else { 53 - (-1022 - top) };
+        if precision < 0 {
+            return sign * 0.0;
+        }
+
+        let dropped = len - precision;
+        let mantissa = q >> dropped;
+        let rest = q & ((1 << dropped) - 1);
+        let half = 1 << (dropped - 1);
+        let round_up = rest > half || (rest == half && (!r.is_zero() || mantissa & 1 == 1));
+        let mantissa = mantissa + round_up as u128;
+
+        // Both factors are exact, and so is their product unless it overflows to infinity.
+        let exp = top - precision + 1;
+        let scale = if exp >= -1022 {
+            f64::from_bits(((exp + 1023) as u64) << 52)
+        } else {
+            f64::from_bits(1 << (exp + 1074))
+        };
+        sign * (mantissa as f64 * scale)
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(n: BigInt) -> Rational {
+        Rational {
+            num: n,
+            den: BigInt::from(1),
+        }
+    }
+}
+
+from_int!(Rational => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+try_into_int!(Rational => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl From<&Decimal> for Rational {
+    fn from(d: &Decimal) -> Rational {
+        Rational::new(d.unscaled().clone(), BigInt::from(10).pow(d.scale()))
+    }
+}
+
+// Every finite float is a fraction with a power of two below it, so this only fails for NaN and
+// the infinities.
+impl TryFrom<f64> for Rational {
+    type Error = ExactError;
+
+    fn try_from(x: f64) -> Result<Rational, ExactError> {
+        if !x.is_finite() {
+            return Err(ExactError::NotFinite);
+        }
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        // Subnormals have no implicit leading 1 and share the smallest exponent.
+        let (mantissa, exp) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), exponent - 1075)
+        };
+        let mantissa = if x.is_sign_negative() {
+            -BigInt::from(mantissa)
+        } else {
+            BigInt::from(mantissa)
+        };
+        Ok(if exp >= 0 {
+            Rational::from(mantissa * pow2(exp as u32))
+        } else {
+            Rational::new(mantissa, pow2(-exp as u32))
+        })
+    }
+}
+
+impl TryFrom<f32> for Rational {
+    type Error = ExactError;
+
+    fn try_from(x: f32) -> Result<Rational, ExactError> {
+        Rational::try_from(x as f64)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (&self.num * &other.den).cmp(&(&other.num * &self.den))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// "-3/4", or just "5" for whole numbers.
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = if self.is_integer() {
+            self.num.abs().to_string()
+        } else {
+            format!("{}/{}", self.num.abs(), self.den)
+        };
+        f.pad_integral(!self.is_negative(), "", &digits)
+    }
+}
+
+// Either a fraction like "-3/4" or a decimal like "0.75".
+impl FromStr for Rational {
+    type Err = ExactError;
+
+    fn from_str(s: &str) -> Result<Rational, ExactError> {
+        let invalid = || ExactError::InvalidNumber(String::from(s));
+        match s.split_once('/') {
+            Some((num, den)) => {
+                let num = num.parse().map_err(|_| invalid())?;
+                let den = den.parse().map_err(|_| invalid())?;
+                Rational::checked_new(num, den).ok_or(ExactError::DivideByZero)
+            }
+            None => Ok(Rational::from(&s.parse::<Decimal>()?)),
+        }
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        -self.clone()
+    }
+}
+
+fn add(a: &Rational, b: &Rational) -> Rational {
+    Rational::new(&a.num * &b.den + &b.num * &a.den, &a.den * &b.den)
+}
+
+fn sub(a: &Rational, b: &Rational) -> Rational {
+    Rational::new(&a.num * &b.den - &b.num * &a.den, &a.den * &b.den)
+}
+
+fn mul(a: &Rational, b: &Rational) -> Rational {
+    Rational::new(&a.num * &b.num, &a.den * &b.den)
+}
+
+// Panics when dividing by zero; `checked_div` doesn't.
+fn div(a: &Rational, b: &Rational) -> Rational {
+    a.checked_div(b).expect("attempt to divide by zero")
+}
+
+forward_binary_op!(Rational, Add add, AddAssign add_assign, add);
+forward_binary_op!(Rational, Sub sub, SubAssign sub_assign, sub);
+forward_binary_op!(Rational, Mul mul, MulAssign mul_assign, mul);
+forward_binary_op!(Rational, Div div, DivAssign div_assign, div);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn r(s: &str) -> Rational {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn keeps_lowest_terms() {
+        let x = Rational::new(6, -8);
+        assert_eq!(
+            (x.numer(), x.denom()),
+            (&BigInt::from(-3), &BigInt::from(4))
+        );
+        assert_eq!(x, r("-0.75"));
+        assert_eq!(r("1/3") + r("1/6"), r("1/2"));
+        assert_eq!(r("1/3") - r("1/2"), r("-1/6"));
+        assert_eq!(r("2/3") * r("9/4"), r("3/2"));
+        assert_eq!(r("2/3") / r("-4/9"), r("-3/2"));
+        assert_eq!(r("10/5").to_string(), "2");
+        assert_eq!(format!("{:>6}", r("-1/3")), "  -1/3");
+        assert!(r("-1/2") < r("-1/3"));
+        assert_eq!(r("0").recip(), None);
+    }
+
+    #[test]
+    fn does_not_truncate_like_integer_division() {
+        let x = Rational::new(127_003, 10);
+        assert_eq!(127_003 / 10, 12_700);
+        assert_eq!(x.to_string(), "127003/10");
+        assert_eq!(i64::try_from(&x), Err(ExactError::Inexact));
+        assert_eq!(x.round(Rounding::Down), BigInt::from(12_700));
+        assert_eq!(x.round(Rounding::Ceiling), BigInt::from(12_701));
+        assert_eq!(i64::try_from(&(x * Rational::from(10))), Ok(127_003));
+        assert_eq!(
+            u8::try_from(&Rational::from(256)),
+            Err(ExactError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!("1/0".parse::<Rational>(), Err(ExactError::DivideByZero));
+        assert_eq!(
+            "1/x".parse::<Rational>(),
+            Err(ExactError::InvalidNumber(String::from("1/x")))
+        );
+        assert_eq!(
+            "1.2.3".parse::<Rational>(),
+            Err(ExactError::InvalidNumber(String::from("1.2.3")))
+        );
+    }
+
+    #[test]
+    fn converts_floats_exactly() {
+        // 0.1 isn't a tenth in binary.
+        let tenth = Rational::try_from(0.1).unwrap();
+        assert_ne!(tenth, r("1/10"));
+        assert_eq!(tenth.denom(), &pow2(55));
+        assert_eq!(tenth.to_f64(), 0.1);
+        assert_eq!(Rational::try_from(-1.5f32), Ok(r("-3/2")));
+        assert_eq!(Rational::try_from(f64::NAN), Err(ExactError::NotFinite));
+        for x in [f64::MAX, f64::MIN_POSITIVE, 5e-324, -2.5e-310, 0.0] {
+            assert_eq!(Rational::try_from(x).unwrap().to_f64(), x, "{:e}", x);
+        }
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_float() {
+        assert_eq!(r("1/3").to_f64(), 1.0 / 3.0);
+        assert_eq!(r("-2/3").to_f64(), -2.0 / 3.0);
+        assert_eq!(r("0.1").to_f64(), 0.1);
+        assert_eq!(r("12345.6789").to_f64(), 12345.6789);
+        // 2^53 + 1 is halfway between two floats and goes to the even one, 2^53 + 1/2^60 doesn't.
+        let two53 = Rational::from(pow2(53));
+        assert_eq!((&two53 + r("1")).to_f64(), 9007199254740992.0);
+        let just_over = &two53 + r("1") + Rational::new(1, pow2(60));
+        assert_eq!(just_over.to_f64(), 9007199254740994.0);
+        assert_eq!(Rational::from(pow2(1024)).to_f64(), f64::INFINITY);
+        assert_eq!(Rational::new(1, pow2(1076)).to_f64(), 0.0);
+        // Just over half the smallest subnormal rounds up to it.
+        assert_eq!(Rational::new(3, pow2(1076)).to_f64(), 5e-324);
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips_any_finite_float(bits: u64) {
+            // Every bit pattern, so subnormals and huge exponents turn up as often as the rest.
+            let x = f64::from_bits(bits);
+            prop_assume!(x.is_finite());
+            prop_assert_eq!(Rational::try_from(x).unwrap().to_f64(), x);
+        }
+    }
+}
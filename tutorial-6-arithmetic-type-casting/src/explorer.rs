@@ -0,0 +1,473 @@
+// Shows what happens when two numbers are combined in a given integer type.
+//
+// Plain `x + y` panics on overflow in debug builds and wraps in release builds. The integer
+// types also have methods that say what to do instead:
+//
+// - checked_add returns None on overflow
+// - wrapping_add wraps around past the maximum back to the minimum
+// - saturating_add stops at the maximum or minimum
+// - overflowing_add wraps and also says whether it did
+//
+// Casting with `as` never fails either. It truncates between integer types and rounds to the
+// nearest float, so each cast is also marked lossless if the original value survives it.
+
+use std::fmt;
+use std::num::IntErrorKind;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    // Not the three parts that make up a line.
+    Usage,
+    UnknownType(String),
+    NotANumber(String),
+    OutOfRange {
+        value: String,
+        ty: &'static str,
+        min: String,
+        max: String,
+    },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Usage => write!(f, "expected two numbers and a type, like: 127 10 i8"),
+            InputError::UnknownType(ty) => write!(
+                f,
+                "{:?} is not an integer type; use one of {}",
+                ty,
+                TYPES.join(", ")
+            ),
+            InputError::NotANumber(value) => write!(f, "{:?} is not a whole number", value),
+            InputError::OutOfRange {
+                value,
+                ty,
+                min,
+                max,
+            } => write!(
+                f,
+                "{} doesn't fit in {}, which holds {} to {}",
+                value, ty, min, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+const TYPES: [&str; 12] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Op {
+    pub const ALL: [Op; 5] = [Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Rem];
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Value(String),
+    // From `overflowing_*`: the wrapped value, when it did overflow.
+    Wrapped(String),
+    // From `checked_*`.
+    Overflow,
+    DivideByZero,
+    // There's no saturating remainder, since a remainder can't overflow except for MIN % -1.
+    NotAvailable,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Value(v) => write!(f, "{}", v),
+            Outcome::Wrapped(v) => write!(f, "{} (overflowed)", v),
+            Outcome::Overflow => write!(f, "None (overflow)"),
+            Outcome::DivideByZero => write!(f, "division by zero"),
+            Outcome::NotAvailable => write!(f, "-"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arithmetic {
+    pub op: Op,
+    pub checked: Outcome,
+    pub wrapping: Outcome,
+    pub saturating: Outcome,
+    pub overflowing: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cast {
+    pub target: &'static str,
+    pub value: String,
+    pub lossless: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub ty: &'static str,
+    pub a: String,
+    pub b: String,
+    pub arithmetic: Vec<Arithmetic>,
+    // What `a as T` and `b as T` give for every primitive number type T.
+    pub casts_a: Vec<Cast>,
+    pub casts_b: Vec<Cast>,
+}
+
+// What the explorer needs from each integer type. The standard library has these as inherent
+// methods on each type rather than a trait, so `int_impl!` writes out the same calls for all of
+// them.
+trait Int: Copy + fmt::Display + FromStr<Err = std::num::ParseIntError> + PartialEq {
+    const NAME: &'static str;
+    const MIN: Self;
+    const MAX: Self;
+
+    fn arithmetic(self, op: Op, rhs: Self) -> Arithmetic;
+    fn casts(self) -> Vec<Cast>;
+}
+
+// Significant bits in the value's magnitude, from the highest set bit to the lowest. A float
+// holds the integer exactly if its mantissa has that many bits.
+fn significant_bits(magnitude: u128) -> u32 {
+    if magnitude == 0 {
+        0
+    } else {
+        128 - magnitude.leading_zeros() - magnitude.trailing_zeros()
+    }
+}
+
+fn value<T: fmt::Display>(v: T) -> Outcome {
+    Outcome::Value(v.to_string())
+}
+
+fn overflowing<T: fmt::Display>((v, overflowed): (T, bool)) -> Outcome {
+    if overflowed {
+        Outcome::Wrapped(v.to_string())
+    } else {
+        Outcome::Value(v.to_string())
+    }
+}
+
+macro_rules! int_impl {
+    ($($t:ident)*) => {$(
+        impl Int for $t {
+            const NAME: &'static str = stringify!($t);
+            const MIN: $t = $t::MIN;
+            const MAX: $t = $t::MAX;
+
+            fn arithmetic(self, op: Op, rhs: $t) -> Arithmetic {
+                let (a, b) = (self, rhs);
+                let checked = |result: Option<$t>| match result {
+                    Some(v) => value(v),
+                    None => Outcome::Overflow,
+                };
+                if b == 0 && matches!(op, Op::Div | Op::Rem) {
+                    return Arithmetic {
+                        op,
+                        checked: Outcome::DivideByZero,
+                        wrapping: Outcome::DivideByZero,
+                        saturating: Outcome::DivideByZero,
+                        overflowing: Outcome::DivideByZero,
+                    };
+                }
+                let (c, w, s, o) = match op {
+                    Op::Add => (
+                        checked(a.checked_add(b)),
+                        value(a.wrapping_add(b)),
+                        value(a.saturating_add(b)),
+                        overflowing(a.overflowing_add(b)),
+                    ),
+                    Op::Sub => (
+                        checked(a.checked_sub(b)),
+                        value(a.wrapping_sub(b)),
+                        value(a.saturating_sub(b)),
+                        overflowing(a.overflowing_sub(b)),
+                    ),
+                    Op::Mul => (
+                        checked(a.checked_mul(b)),
+                        value(a.wrapping_mul(b)),
+                        value(a.saturating_mul(b)),
+                        overflowing(a.overflowing_mul(b)),
+                    ),
+                    Op::Div => (
+                        checked(a.checked_div(b)),
+                        value(a.wrapping_div(b)),
+                        value(a.saturating_div(b)),
+                        overflowing(a.overflowing_div(b)),
+                    ),
+                    Op::Rem => (
+                        checked(a.checked_rem(b)),
+                        value(a.wrapping_rem(b)),
+                        Outcome::NotAvailable,
+                        overflowing(a.overflowing_rem(b)),
+                    ),
+                };
+                Arithmetic {
+                    op,
+                    checked: c,
+                    wrapping: w,
+                    saturating: s,
+                    overflowing: o,
+                }
+            }
+
+            // Includes casts from a type to itself, which are the same on purpose.
+            #[allow(clippy::unnecessary_cast)]
+            fn casts(self) -> Vec<Cast> {
+                let mut casts = int_impl!(
+                    @casts self, i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize
+                );
+
+                let magnitude = match i128::try_from(self) {
+                    Ok(v) => v.unsigned_abs(),
+                    Err(_) => self as u128,
+                };
+                casts.push(Cast {
+                    target: "f32",
+                    value: (self as f32).to_string(),
+                    lossless: significant_bits(magnitude) <= f32::MANTISSA_DIGITS,
+                });
+                casts.push(Cast {
+                    target: "f64",
+                    value: (self as f64).to_string(),
+                    lossless: significant_bits(magnitude) <= f64::MANTISSA_DIGITS,
+                });
+                casts
+            }
+        }
+    )*};
+    (@casts $v:expr, $($u:ident)*) => {
+        vec![$(
+            Cast {
+                target: stringify!($u),
+                value: ($v as $u).to_string(),
+                lossless: $u::try_from($v).is_ok(),
+            },
+        )*]
+    };
+}
+
+int_impl!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+// Parses a number as `T`, allowing underscores like Rust literals do: 127_000.
+fn parse<T: Int>(s: &str) -> Result<T, InputError> {
+    let digits = s.replace('_', "");
+    digits
+        .parse()
+        .map_err(|e: std::num::ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => InputError::OutOfRange {
+                value: String::from(s),
+                ty: T::NAME,
+                min: T::MIN.to_string(),
+                max: T::MAX.to_string(),
+            },
+            // Unsigned types don't accept a sign at all, but "-1" is still a number.
+            IntErrorKind::InvalidDigit
+                if digits.len() > 1
+                    && digits.starts_with('-')
+                    && digits[1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                InputError::OutOfRange {
+                    value: String::from(s),
+                    ty: T::NAME,
+                    min: T::MIN.to_string(),
+                    max: T::MAX.to_string(),
+                }
+            }
+            _ => InputError::NotANumber(String::from(s)),
+        })
+}
+
+fn explore_as<T: Int>(a: &str, b: &str) -> Result<Report, InputError> {
+    let a: T = parse(a)?;
+    let b: T = parse(b)?;
+    Ok(Report {
+        ty: T::NAME,
+        a: a.to_string(),
+        b: b.to_string(),
+        arithmetic: Op::ALL.iter().map(|&op| a.arithmetic(op, b)).collect(),
+        casts_a: a.casts(),
+        casts_b: b.casts(),
+    })
+}
+
+// Explores a line like "127 10 i8": two numbers and the type to work in.
+pub fn explore(line: &str) -> Result<Report, InputError> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let [a, b, ty] = parts[..] else {
+        return Err(InputError::Usage);
+    };
+    match ty {
+        "i8" => explore_as::<i8>(a, b),
+        "i16" => explore_as::<i16>(a, b),
+        "i32" => explore_as::<i32>(a, b),
+        "i64" => explore_as::<i64>(a, b),
+        "i128" => explore_as::<i128>(a, b),
+        "isize" => explore_as::<isize>(a, b),
+        "u8" => explore_as::<u8>(a, b),
+        "u16" => explore_as::<u16>(a, b),
+        "u32" => explore_as::<u32>(a, b),
+        "u64" => explore_as::<u64>(a, b),
+        "u128" => explore_as::<u128>(a, b),
+        "usize" => explore_as::<usize>(a, b),
+        _ => Err(InputError::UnknownType(String::from(ty))),
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:<24} {:<24} {:<24} {:<24}",
+            self.ty, "checked", "wrapping", "saturating", "overflowing"
+        )?;
+        for row in &self.arithmetic {
+            writeln!(
+                f,
+                "{:<14} {:<24} {:<24} {:<24} {:<24}",
+                format!("{} {} {}", self.a, row.op, self.b),
+                row.checked.to_string(),
+                row.wrapping.to_string(),
+                row.saturating.to_string(),
+                row.overflowing.to_string()
+            )?;
+        }
+        let width = self.a.len().max(self.b.len());
+        for (value, casts) in [(&self.a, &self.casts_a), (&self.b, &self.casts_b)] {
+            writeln!(f)?;
+            for cast in casts {
+                writeln!(
+                    f,
+                    "{:>width$} as {:<6} = {:<42} {}",
+                    value,
+                    cast.target,
+                    cast.value,
+                    if cast.lossless { "lossless" } else { "lossy" }
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(report: &Report, op: Op) -> [String; 4] {
+        let row = report.arithmetic.iter().find(|row| row.op == op).unwrap();
+        [
+            row.checked.to_string(),
+            row.wrapping.to_string(),
+            row.saturating.to_string(),
+            row.overflowing.to_string(),
+        ]
+    }
+
+    fn cast<'a>(casts: &'a [Cast], target: &str) -> (&'a str, bool) {
+        let cast = casts.iter().find(|cast| cast.target == target).unwrap();
+        (&cast.value, cast.lossless)
+    }
+
+    #[test]
+    fn shows_each_kind_of_overflow_handling() {
+        let report = explore("127 10 i8").unwrap();
+
+        assert_eq!(
+            row(&report, Op::Add),
+            ["None (overflow)", "-119", "127", "-119 (overflowed)"]
+        );
+        assert_eq!(row(&report, Op::Sub), ["117", "117", "117", "117"]);
+        assert_eq!(row(&report, Op::Rem), ["7", "7", "-", "7"]);
+
+        let report = explore("0 1 u8").unwrap();
+        assert_eq!(
+            row(&report, Op::Sub),
+            ["None (overflow)", "255", "0", "255 (overflowed)"]
+        );
+
+        // The one signed division that overflows.
+        let report = explore("-128 -1 i8").unwrap();
+        assert_eq!(
+            row(&report, Op::Div),
+            ["None (overflow)", "-128", "127", "-128 (overflowed)"]
+        );
+        assert_eq!(row(&report, Op::Rem)[0], "None (overflow)");
+    }
+
+    #[test]
+    fn reports_division_by_zero_instead_of_panicking() {
+        let report = explore("5 0 u32").unwrap();
+        assert_eq!(
+            row(&report, Op::Div),
+            ["division by zero"; 4].map(String::from)
+        );
+        assert_eq!(
+            row(&report, Op::Rem),
+            ["division by zero"; 4].map(String::from)
+        );
+    }
+
+    #[test]
+    fn marks_lossy_casts() {
+        let report = explore("-1 16777217 i64").unwrap();
+
+        assert_eq!(cast(&report.casts_a, "u8"), ("255", false));
+        assert_eq!(cast(&report.casts_a, "i8"), ("-1", true));
+        assert_eq!(cast(&report.casts_a, "f32"), ("-1", true));
+        assert_eq!(cast(&report.casts_b, "i16"), ("1", false));
+        // 2^24 + 1 needs 25 bits of mantissa, one more than f32 has.
+        assert_eq!(cast(&report.casts_b, "f32"), ("16777216", false));
+        assert_eq!(cast(&report.casts_b, "f64"), ("16777217", true));
+        assert_eq!(report.casts_b.len(), 14);
+
+        let report = explore("340282366920938463463374607431768211455 1 u128").unwrap();
+        assert!(!cast(&report.casts_a, "f64").1);
+        assert_eq!(cast(&report.casts_a, "i128"), ("-1", false));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(explore("1 2"), Err(InputError::Usage));
+        assert_eq!(
+            explore("1 2 u7"),
+            Err(InputError::UnknownType(String::from("u7")))
+        );
+        assert_eq!(
+            explore("1 two u8"),
+            Err(InputError::NotANumber(String::from("two")))
+        );
+        assert_eq!(
+            explore("1 -1 u8").unwrap_err().to_string(),
+            "-1 doesn't fit in u8, which holds 0 to 255"
+        );
+        assert_eq!(
+            explore("300 1 u8").unwrap_err().to_string(),
+            "300 doesn't fit in u8, which holds 0 to 255"
+        );
+        assert_eq!(explore("127_000 10 i64").unwrap().a, "127000");
+    }
+}
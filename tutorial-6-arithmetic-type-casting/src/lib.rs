@@ -0,0 +1,4 @@
+pub mod bigint;
+pub mod exact;
+pub mod explorer;
+mod ops;
@@ -1,5 +1,8 @@
 use std::io;
 
+use tutorial_6::exact::{Decimal, Rational, Rounding};
+use tutorial_6::explorer;
+
 fn main() {
     // "overflow" means that the value is too large to be stored in the data type
     let x: u8 = 127; // 0 to 255
@@ -18,12 +21,27 @@ fn main() {
     let z = x / (y as i64);
     println!("The value of z is: {}", z);
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-
-    let int_input: i64 = input.trim().parse().unwrap();
+    // Integer division throws the remainder away; Rational and Decimal keep it
+    let exact = Rational::new(127_003, 10);
+    println!("127_003 / 10 is {} as an integer", 127_003 / 10);
+    println!("127_003 / 10 is {} as a fraction", exact);
+    println!(
+        "127_003 / 10 is {} as a decimal",
+        Decimal::from_rational(&exact, 2, Rounding::HalfEven)
+    );
 
-    println!("{}", input);
+    // Instead of `input.trim().parse().unwrap()`, which panics on bad input, report what was
+    // wrong and ask again
+    println!("Enter two numbers and a type to see how they overflow and cast, e.g. 127 10 i8");
+    println!("An empty line quits");
+    for line in io::stdin().lines() {
+        let line = line.expect("Failed to read line");
+        if line.trim().is_empty() {
+            break;
+        }
+        match explorer::explore(&line) {
+            Ok(report) => println!("{}", report),
+            Err(e) => println!("error: {}", e),
+        }
+    }
 }
@@ -0,0 +1,51 @@
+// Implements an operator for every mix of owned and borrowed operands, plus the assigning form,
+// all forwarding to one function working on references.
+macro_rules! forward_binary_op {
+    ($T:ty, $Op:ident $op:ident, $OpAssign:ident $op_assign:ident, $f:ident) => {
+        impl std::ops::$Op<&$T> for &$T {
+            type Output = $T;
+
+            fn $op(self, rhs: &$T) -> $T {
+                $f(self, rhs)
+            }
+        }
+
+        impl std::ops::$Op<$T> for &$T {
+            type Output = $T;
+
+            fn $op(self, rhs: $T) -> $T {
+                $f(self, &rhs)
+            }
+        }
+
+        impl std::ops::$Op<&$T> for $T {
+            type Output = $T;
+
+            fn $op(self, rhs: &$T) -> $T {
+                $f(&self, rhs)
+            }
+        }
+
+        impl std::ops::$Op<$T> for $T {
+            type Output = $T;
+
+            fn $op(self, rhs: $T) -> $T {
+                $f(&self, &rhs)
+            }
+        }
+
+        impl std::ops::$OpAssign<&$T> for $T {
+            fn $op_assign(&mut self, rhs: &$T) {
+                *self = $f(self, rhs);
+            }
+        }
+
+        impl std::ops::$OpAssign<$T> for $T {
+            fn $op_assign(&mut self, rhs: $T) {
+                *self = $f(self, &rhs);
+            }
+        }
+    };
+}
+
+pub(crate) use forward_binary_op;
@@ -1,3 +1,5 @@
+use tutorial_7::rules::{Facts, Mode, RuleSet};
+
 fn main() {
     let cond = (2 as f32) < 3.3;
     println!("{}", cond);
@@ -10,4 +12,23 @@ fn main() {
     } else {
         println!("I don't like cookies or cake");
     }
+
+    // The same decision made by rules read from a file, so it can change without recompiling
+    let rules = match RuleSet::load(concat!(env!("CARGO_MANIFEST_DIR"), "/food.rules")) {
+        Ok(rules) => rules,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    for food in ["cookie", "cake", "pie"] {
+        let facts = Facts::new().with("food", food).with("sweetness", 8);
+        for message in rules.evaluate(&facts, Mode::FirstMatch).messages {
+            println!("{}", message);
+        }
+    }
+
+    // Explain mode shows which rules were tried and why the one that fired did
+    let facts = Facts::new().with("food", "pie").with("sweetness", 8);
+    println!("{}", rules.explain(&facts, Mode::FirstMatch));
 }
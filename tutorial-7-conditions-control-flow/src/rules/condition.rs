@@ -0,0 +1,641 @@
+// Conditions on facts, how to read them, and how to explain why one did or didn't hold.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Facts, ParseError, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+
+    fn holds(self, ordering: Option<std::cmp::Ordering>) -> bool {
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Le => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Const(bool),
+    // A fact that must be true.
+    Fact(String),
+    Compare(String, CompareOp, Value),
+    InRange {
+        fact: String,
+        low: f64,
+        high: f64,
+        inclusive: bool,
+    },
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn parse(src: &str) -> Result<Condition, ParseError> {
+        let tokens = tokenize(src).map_err(|message| ParseError::new(1, message))?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let condition = parser.or().map_err(|message| ParseError::new(1, message))?;
+        match parser.peek() {
+            None => Ok(condition),
+            Some(token) => Err(ParseError::new(
+                1,
+                format!("unexpected {} after the condition", token),
+            )),
+        }
+    }
+
+    // Whether the condition holds for these facts. A fact that isn't set, or has a value of the
+    // wrong type for the test, makes the test fail.
+    pub fn holds(&self, facts: &Facts) -> bool {
+        match self {
+            Condition::Const(b) => *b,
+            Condition::Fact(name) => facts.get(name) == Some(&Value::Bool(true)),
+            Condition::Compare(name, op, expected) => {
+                let ordering = facts.get(name).and_then(|actual| compare(actual, expected));
+                op.holds(ordering)
+            }
+            Condition::InRange {
+                fact,
+                low,
+                high,
+                inclusive,
+            } => match facts.get(fact) {
+                Some(Value::Num(n)) => {
+                    *n >= *low && (if *inclusive { *n <= *high } else { *n < *high })
+                }
+                _ => false,
+            },
+            Condition::And(all) => all.iter().all(|c| c.holds(facts)),
+            Condition::Or(any) => any.iter().any(|c| c.holds(facts)),
+            Condition::Not(c) => !c.holds(facts),
+        }
+    }
+
+    // Like `holds`, but records the outcome of every part of the condition and the facts that
+    // decided it. Unlike `holds` it doesn't stop early, so every part is shown.
+    pub fn explain(&self, facts: &Facts) -> Explanation {
+        let (text, children) = match self {
+            Condition::And(all) => (String::from("all of"), explain_all(all, facts)),
+            Condition::Or(any) => (String::from("any of"), explain_all(any, facts)),
+            Condition::Not(c) => (String::from("not"), vec![c.explain(facts)]),
+            Condition::Const(_) => (self.to_string(), Vec::new()),
+            Condition::Fact(name)
+            | Condition::Compare(name, ..)
+            | Condition::InRange { fact: name, .. } => {
+                let expected = match self {
+                    Condition::Compare(_, _, expected) => expected.type_name(),
+                    Condition::InRange { .. } => "number",
+                    _ => "bool",
+                };
+                let found = match facts.get(name) {
+                    None => format!("{} is not set", name),
+                    Some(value) if value.type_name() != expected => {
+                        format!("{} is {}, not a {}", name, value, expected)
+                    }
+                    Some(value) => format!("{} is {}", name, value),
+                };
+                (format!("{} ({})", self, found), Vec::new())
+            }
+        };
+        Explanation {
+            holds: self.holds(facts),
+            text,
+            children,
+        }
+    }
+}
+
+fn explain_all(conditions: &[Condition], facts: &Facts) -> Vec<Explanation> {
+    conditions.iter().map(|c| c.explain(facts)).collect()
+}
+
+// Values of different types don't compare. Bools order as in Rust, false before true.
+fn compare(actual: &Value, expected: &Value) -> Option<std::cmp::Ordering> {
+    match (actual, expected) {
+        (Value::Num(a), Value::Num(b)) => a.partial_cmp(b),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Writes a part of an `and`/`or`/`not` in parentheses if it would otherwise read
+        // differently.
+        fn part(f: &mut fmt::Formatter, c: &Condition, parent: &Condition) -> fmt::Result {
+            let needs_parens = matches!(
+                (parent, c),
+                (Condition::Not(_), Condition::And(_) | Condition::Or(_))
+                    | (Condition::And(_), Condition::Or(_))
+            );
+            if needs_parens {
+                write!(f, "({})", c)
+            } else {
+                write!(f, "{}", c)
+            }
+        }
+
+        match self {
+            Condition::Const(b) => write!(f, "{}", b),
+            Condition::Fact(name) => write!(f, "{}", name),
+            Condition::Compare(name, op, value) => write!(f, "{} {} {}", name, op.symbol(), value),
+            Condition::InRange {
+                fact,
+                low,
+                high,
+                inclusive,
+            } => {
+                let dots = if *inclusive { "..=" } else { ".." };
+                write!(f, "{} in {}{}{}", fact, low, dots, high)
+            }
+            Condition::And(parts) | Condition::Or(parts) => {
+                let joiner = if matches!(self, Condition::And(_)) {
+                    " and "
+                } else {
+                    " or "
+                };
+                for (i, c) in parts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(joiner)?;
+                    }
+                    part(f, c, self)?;
+                }
+                Ok(())
+            }
+            Condition::Not(c) => {
+                f.write_str("not ")?;
+                part(f, c, self)
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Condition, ParseError> {
+        Condition::parse(s)
+    }
+}
+
+// Why a condition did or didn't hold, as a tree following the condition's structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub holds: bool,
+    pub text: String,
+    pub children: Vec<Explanation>,
+}
+
+impl Explanation {
+    pub(super) fn fmt_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let verdict = if self.holds { "yes" } else { "no" };
+        write!(f, "{}[{}] {}", "  ".repeat(depth), verdict, self.text)?;
+        for child in &self.children {
+            writeln!(f)?;
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Ident(String),
+    Literal(Value),
+    Compare(CompareOp),
+    And,
+    Or,
+    Not,
+    In,
+    LParen,
+    RParen,
+    DotDot,
+    DotDotEq,
+    // Only used by actions.
+    Assign,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Literal(value) => write!(f, "{}", value),
+            Token::Compare(op) => write!(f, "'{}'", op.symbol()),
+            Token::And => write!(f, "'and'"),
+            Token::Or => write!(f, "'or'"),
+            Token::Not => write!(f, "'not'"),
+            Token::In => write!(f, "'in'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::DotDot => write!(f, "'..'"),
+            Token::DotDotEq => write!(f, "'..='"),
+            Token::Assign => write!(f, "'='"),
+        }
+    }
+}
+
+pub(super) fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let starts_number = c.is_ascii_digit()
+            || (c == '-' && chars.clone().nth(1).is_some_and(|d| d.is_ascii_digit()));
+        let token = if starts_number {
+            let mut text = String::new();
+            text.push(c);
+            chars.next();
+            while let Some(&d) = chars.peek() {
+                // A '.' only belongs to the number if a digit follows, so `1..5` is a range.
+                let fraction = d == '.' && chars.clone().nth(1).is_some_and(|e| e.is_ascii_digit());
+                if !(d.is_ascii_digit() || fraction) {
+                    break;
+                }
+                text.push(d);
+                chars.next();
+            }
+            let n = text
+                .parse()
+                .map_err(|_| format!("invalid number {}", text))?;
+            Token::Literal(Value::Num(n))
+        } else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&d) = chars
+                .peek()
+                .filter(|d| d.is_alphanumeric() || **d == '_' || **d == '.')
+            {
+                // Stop before a range's dots.
+                if d == '.' && chars.clone().nth(1) == Some('.') {
+                    break;
+                }
+                word.push(d);
+                chars.next();
+            }
+            match word.as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                "in" => Token::In,
+                "true" => Token::Literal(Value::Bool(true)),
+                "false" => Token::Literal(Value::Bool(false)),
+                _ => Token::Ident(word),
+            }
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(String::from("unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(e @ ('"' | '\\')) => s.push(e),
+                        Some('n') => s.push('\n'),
+                        _ => return Err(String::from("unknown escape in string")),
+                    },
+                    Some(c) => s.push(c),
+                }
+            }
+            Token::Literal(Value::Str(s))
+        } else {
+            chars.next();
+            let mut followed_by = |next| {
+                let found = chars.peek() == Some(&next);
+                if found {
+                    chars.next();
+                }
+                found
+            };
+            match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '=' if followed_by('=') => Token::Compare(CompareOp::Eq),
+                '=' => Token::Assign,
+                '!' if followed_by('=') => Token::Compare(CompareOp::Ne),
+                '<' if followed_by('=') => Token::Compare(CompareOp::Le),
+                '<' => Token::Compare(CompareOp::Lt),
+                '>' if followed_by('=') => Token::Compare(CompareOp::Ge),
+                '>' => Token::Compare(CompareOp::Gt),
+                '.' if followed_by('.') => {
+                    if followed_by('=') {
+                        Token::DotDotEq
+                    } else {
+                        Token::DotDot
+                    }
+                }
+                _ => return Err(format!("unexpected character {:?}", c)),
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// How deeply `not` and parentheses can nest, so a condition can't overflow the stack while it's
+// read, checked or printed.
+pub const MAX_DEPTH: usize = 200;
+
+// or  := and ("or" and)*
+// and := not ("and" not)*
+// not := "not" not | "(" or ")" | literal | name [op literal | "in" range]
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn found(&self) -> String {
+        self.peek()
+            .map_or(String::from("the end of the condition"), Token::to_string)
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut any = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Condition::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut all = vec![self.not()?];
+        while self.eat(&Token::And) {
+            all.push(self.not()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Condition::And(all)
+        })
+    }
+
+    fn not(&mut self) -> Result<Condition, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("nested more than {} deep", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let condition = self.not_at_depth();
+        self.depth -= 1;
+        condition
+    }
+
+    fn not_at_depth(&mut self) -> Result<Condition, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let inner = self.or()?;
+            if !self.eat(&Token::RParen) {
+                return Err(format!("expected ')', found {}", self.found()));
+            }
+            return Ok(inner);
+        }
+        match self.next() {
+            Some(Token::Literal(Value::Bool(b))) => Ok(Condition::Const(b)),
+            Some(Token::Ident(name)) => self.test(name),
+            _ => {
+                self.pos -= 1;
+                Err(format!("expected a condition, found {}", self.found()))
+            }
+        }
+    }
+
+    // What follows a fact's name.
+    fn test(&mut self, name: String) -> Result<Condition, String> {
+        match self.peek() {
+            Some(Token::Compare(op)) => {
+                let op = *op;
+                self.pos += 1;
+                match self.next() {
+                    Some(Token::Literal(value)) => Ok(Condition::Compare(name, op, value)),
+                    _ => {
+                        self.pos -= 1;
+                        Err(format!(
+                            "expected a value after '{}', found {}",
+                            op.symbol(),
+                            self.found()
+                        ))
+                    }
+                }
+            }
+            Some(Token::In) => {
+                self.pos += 1;
+                let low = self.number()?;
+                let inclusive = match self.next() {
+                    Some(Token::DotDot) => false,
+                    Some(Token::DotDotEq) => true,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(format!("expected '..' or '..=', found {}", self.found()));
+                    }
+                };
+                let high = self.number()?;
+                if low > high || (low == high && !inclusive) {
+                    let dots = if inclusive { "..=" } else { ".." };
+                    return Err(format!("empty range {}{}{}", low, dots, high));
+                }
+                Ok(Condition::InRange {
+                    fact: name,
+                    low,
+                    high,
+                    inclusive,
+                })
+            }
+            _ => Ok(Condition::Fact(name)),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Literal(Value::Num(n))) => Ok(n),
+            _ => {
+                self.pos -= 1;
+                Err(format!("expected a number, found {}", self.found()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts() -> Facts {
+        Facts::new()
+            .with("food", "cake")
+            .with("age", 30)
+            .with("member", true)
+    }
+
+    fn holds(src: &str) -> bool {
+        Condition::parse(src).unwrap().holds(&facts())
+    }
+
+    #[test]
+    fn tests_facts() {
+        assert!(holds(r#"food == "cake""#));
+        assert!(!holds(r#"food == "cookie""#));
+        assert!(holds(r#"food < "cookie""#));
+        assert!(holds("age >= 30 and age != 31"));
+        assert!(holds("age in 18..65"));
+        assert!(!holds("age in 18..30"));
+        assert!(holds("age in 18..=30"));
+        assert!(holds("age in -5.5..100"));
+        assert!(holds("member"));
+        assert!(holds("member == true and not (member == false)"));
+        assert!(holds(r#"food == "pie" or food == "cake""#));
+        assert!(holds("true"));
+    }
+
+    #[test]
+    fn fails_tests_on_missing_or_mistyped_facts() {
+        assert!(!holds("height > 1"));
+        assert!(!holds("height != 1"));
+        assert!(!holds(r#"age == "30""#));
+        assert!(!holds("food"));
+        assert!(!holds("food in 1..2"));
+        assert!(holds("not height > 1"));
+    }
+
+    #[test]
+    fn reads_and_writes_conditions() {
+        // `and` binds tighter than `or`.
+        let c = Condition::parse("a or b and not c").unwrap();
+        assert_eq!(
+            c,
+            Condition::Or(vec![
+                Condition::Fact("a".into()),
+                Condition::And(vec![
+                    Condition::Fact("b".into()),
+                    Condition::Not(Box::new(Condition::Fact("c".into()))),
+                ]),
+            ])
+        );
+        for src in [
+            r#"(a or b) and not (c and d == "x")"#,
+            "order.total in 0..=9.5 or age < -1",
+        ] {
+            assert_eq!(Condition::parse(src).unwrap().to_string(), src);
+        }
+    }
+
+    #[test]
+    fn reports_what_it_cannot_read() {
+        let err = |src: &str| src.parse::<Condition>().unwrap_err().message;
+        assert_eq!(
+            err("age >"),
+            "expected a value after '>', found the end of the condition"
+        );
+        assert_eq!(err("age in 1 to 2"), "expected '..' or '..=', found 'to'");
+        assert_eq!(
+            err("(a or b"),
+            "expected ')', found the end of the condition"
+        );
+        assert_eq!(err("a b"), "unexpected 'b' after the condition");
+        assert_eq!(err("and"), "expected a condition, found 'and'");
+        assert_eq!(err("food == \"cake"), "unterminated string");
+        assert_eq!(err("age % 2"), "unexpected character '%'");
+        assert_eq!(err("age in 65..18"), "empty range 65..18");
+        assert_eq!(err("age in 18..18"), "empty range 18..18");
+        assert!(holds("age in 30..=30"));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let deep = |n| format!("{}a{}", "(".repeat(n), ")".repeat(n));
+        assert!(Condition::parse(&deep(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            Condition::parse(&deep(MAX_DEPTH)).unwrap_err().message,
+            "nested more than 200 deep"
+        );
+        assert!(Condition::parse(&"not ".repeat(50_000)).is_err());
+    }
+
+    #[test]
+    fn explains_every_part() {
+        let c =
+            Condition::parse(r#"food == "cookie" or (age in 18..65 and not member) or height > 2"#)
+                .unwrap();
+        let expected = [
+            "[no] any of",
+            r#"  [no] food == "cookie" (food is "cake")"#,
+            "  [no] all of",
+            "    [yes] age in 18..65 (age is 30)",
+            "    [no] not",
+            "      [yes] member (member is true)",
+            "  [no] height > 2 (height is not set)",
+        ];
+        assert_eq!(c.explain(&facts()).to_string(), expected.join("\n"));
+        let c = Condition::parse(r#"age == "30""#).unwrap();
+        assert_eq!(
+            c.explain(&facts()).to_string(),
+            r#"[no] age == "30" (age is 30, not a string)"#
+        );
+    }
+}
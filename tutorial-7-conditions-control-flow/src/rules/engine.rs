@@ -0,0 +1,448 @@
+// Rules, reading them from a file, and running them against facts.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::condition::{tokenize, Condition, Explanation, Token};
+use super::{Facts, LoadError, ParseError, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    // A message to report. `{name}` in it is replaced by the fact's value.
+    Say(String),
+    // Changes a fact, for the actions and rules that come after.
+    Set(String, Value),
+}
+
+impl Action {
+    fn parse(src: &str) -> Result<Action, String> {
+        let tokens = tokenize(src)?;
+        match tokens.as_slice() {
+            [Token::Ident(verb), Token::Literal(Value::Str(text))] if verb == "say" => {
+                Ok(Action::Say(text.clone()))
+            }
+            [Token::Ident(verb), Token::Ident(name), Token::Assign, Token::Literal(value)]
+                if verb == "set" =>
+            {
+                Ok(Action::Set(name.clone(), value.clone()))
+            }
+            _ => Err(format!(
+                "expected 'say \"text\"' or 'set name = value', found '{}'",
+                src
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Say(text) => write!(f, "say {:?}", text),
+            Action::Set(name, value) => write!(f, "set {} = {}", name, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    // Higher goes first.
+    pub priority: i64,
+    pub condition: Condition,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // Only the first rule that holds fires, like an if/else if chain.
+    FirstMatch,
+    // Every rule that holds fires, seeing the facts set by the rules before it.
+    AllMatch,
+}
+
+// What running the rules did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    // The names of the rules that fired, in the order they did.
+    pub fired: Vec<String>,
+    pub messages: Vec<String>,
+    // The facts as the actions left them.
+    pub facts: Facts,
+}
+
+// A rule that was tried, and why it did or didn't fire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleTrace {
+    pub name: String,
+    pub priority: i64,
+    pub fired: bool,
+    pub why: Explanation,
+}
+
+// An outcome along with how it came about. Rules after the one that fired in first-match mode
+// weren't tried and don't appear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub tried: Vec<RuleTrace>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, trace) in self.tried.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n\n")?;
+            }
+            let verdict = if trace.fired { "fired" } else { "did not fire" };
+            writeln!(
+                f,
+                "rule {} (priority {}) {}",
+                trace.name, trace.priority, verdict
+            )?;
+            trace.why.fmt_indented(f, 1)?;
+        }
+        if self.tried.is_empty() {
+            write!(f, "no rules were tried")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleSet {
+    // Kept in the order they're tried.
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new() -> RuleSet {
+        RuleSet::default()
+    }
+
+    // Adds a rule after those of the same or higher priority.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        let at = self.rules.partition_point(|r| r.priority >= rule.priority);
+        self.rules.insert(at, rule);
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<RuleSet, LoadError> {
+        let src = fs::read_to_string(path)?;
+        Ok(RuleSet::parse(&src)?)
+    }
+
+    // Reads rules in the format described in `rules`.
+    pub fn parse(src: &str) -> Result<RuleSet, ParseError> {
+        let mut set = RuleSet::new();
+        // The rule being read, with the line it started on.
+        let mut current: Option<(Rule, usize)> = None;
+        for (i, line) in src.lines().enumerate() {
+            let number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let error = |message: String| ParseError::new(number, message);
+
+            if keyword == "rule" {
+                if let Some((rule, started)) = current.take() {
+                    set = set.add_parsed(rule, started)?;
+                }
+                current = Some((parse_header(rest).map_err(error)?, number));
+                continue;
+            }
+            let Some((rule, _)) = current.as_mut() else {
+                return Err(error(format!("'{}' outside a rule", keyword)));
+            };
+            match keyword {
+                "when" => {
+                    let condition =
+                        Condition::parse(rest).map_err(|e| ParseError::new(number, e.message))?;
+                    rule.condition =
+                        match std::mem::replace(&mut rule.condition, Condition::Const(true)) {
+                            Condition::Const(true) => condition,
+                            Condition::And(mut all) => {
+                                all.push(condition);
+                                Condition::And(all)
+                            }
+                            earlier => Condition::And(vec![earlier, condition]),
+                        };
+                }
+                "then" => rule.actions.push(Action::parse(rest).map_err(error)?),
+                _ => {
+                    return Err(error(format!(
+                        "expected 'rule', 'when' or 'then', found '{}'",
+                        keyword
+                    )))
+                }
+            }
+        }
+        if let Some((rule, started)) = current {
+            set = set.add_parsed(rule, started)?;
+        }
+        Ok(set)
+    }
+
+    // Checks a rule read from a file is complete before adding it.
+    fn add_parsed(self, rule: Rule, line: usize) -> Result<RuleSet, ParseError> {
+        if self.rules.iter().any(|r| r.name == rule.name) {
+            return Err(ParseError::new(
+                line,
+                format!("there is already a rule named '{}'", rule.name),
+            ));
+        }
+        if rule.actions.is_empty() {
+            return Err(ParseError::new(
+                line,
+                format!("rule '{}' has no 'then' line", rule.name),
+            ));
+        }
+        Ok(self.with_rule(rule))
+    }
+
+    pub fn evaluate(&self, facts: &Facts, mode: Mode) -> Outcome {
+        self.run(facts, mode, false).outcome
+    }
+
+    // Like `evaluate`, but also says which rules were tried and why each did or didn't fire.
+    pub fn explain(&self, facts: &Facts, mode: Mode) -> Report {
+        self.run(facts, mode, true)
+    }
+
+    fn run(&self, facts: &Facts, mode: Mode, explain: bool) -> Report {
+        let mut outcome = Outcome {
+            fired: Vec::new(),
+            messages: Vec::new(),
+            facts: facts.clone(),
+        };
+        let mut tried = Vec::new();
+        for rule in &self.rules {
+            let fired = rule.condition.holds(&outcome.facts);
+            if explain {
+                tried.push(RuleTrace {
+                    name: rule.name.clone(),
+                    priority: rule.priority,
+                    fired,
+                    why: rule.condition.explain(&outcome.facts),
+                });
+            }
+            if !fired {
+                continue;
+            }
+            for action in &rule.actions {
+                match action {
+                    Action::Say(text) => {
+                        let message = interpolate(text, &outcome.facts);
+                        outcome.messages.push(message);
+                    }
+                    Action::Set(name, value) => outcome.facts.set(name, value.clone()),
+                }
+            }
+            outcome.fired.push(rule.name.clone());
+            if mode == Mode::FirstMatch {
+                break;
+            }
+        }
+        Report { outcome, tried }
+    }
+}
+
+// `name [priority N]`, after the `rule`.
+fn parse_header(src: &str) -> Result<Rule, String> {
+    let words: Vec<&str> = src.split_whitespace().collect();
+    let (name, priority) = match words.as_slice() {
+        [name] => (*name, 0),
+        [name, "priority", n] => {
+            let priority = n
+                .parse()
+                .map_err(|_| format!("priority must be a whole number, not '{}'", n))?;
+            (*name, priority)
+        }
+        [] => return Err(String::from("the rule needs a name")),
+        _ => {
+            return Err(format!(
+                "expected 'rule <name> [priority <n>]', found 'rule {}'",
+                src
+            ))
+        }
+    };
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("invalid rule name '{}'", name));
+    }
+    Ok(Rule {
+        name: String::from(name),
+        priority,
+        condition: Condition::Const(true),
+        actions: Vec::new(),
+    })
+}
+
+// Replaces `{name}` with the value of the fact; strings go in without their quotes. Names of facts
+// that aren't set are left as they are.
+fn interpolate(text: &str, facts: &Facts) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| Some((facts.get(&after[..close])?, close)));
+        match value {
+            Some((value, close)) => {
+                match value {
+                    Value::Str(s) => out.push_str(s),
+                    value => out.push_str(&value.to_string()),
+                }
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOOD: &str = r#"
+        # What to say about food
+        rule cookie priority 10
+          when food == "cookie"
+          then say "I like cookies"
+
+        rule fallback
+          when true
+          then say "I don't like {food}"
+
+        rule cake priority 10
+          when food == "cake"
+          then say "I like cake"
+    "#;
+
+    #[test]
+    fn reads_rules_in_priority_order() {
+        let set = RuleSet::parse(FOOD).unwrap();
+        let names: Vec<&str> = set.rules().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["cookie", "cake", "fallback"]);
+        assert_eq!(
+            set.rules()[2].actions,
+            [Action::Say("I don't like {food}".into())]
+        );
+    }
+
+    #[test]
+    fn fires_the_first_match() {
+        let set = RuleSet::parse(FOOD).unwrap();
+        let said = |food: &str| {
+            let facts = Facts::new().with("food", food);
+            set.evaluate(&facts, Mode::FirstMatch).messages
+        };
+        assert_eq!(said("cookie"), ["I like cookies"]);
+        assert_eq!(said("cake"), ["I like cake"]);
+        assert_eq!(said("pie"), ["I don't like pie"]);
+    }
+
+    #[test]
+    fn fires_every_match_in_turn() {
+        let src = r#"
+            rule bulk priority 2
+              when quantity >= 10
+              then set discount = 5
+            rule member priority 1
+              when member
+              when discount == 5
+              then set discount = 8
+            rule report
+              when discount > 0
+              then say "{quantity} items at {discount}% off{"
+        "#;
+        let set = RuleSet::parse(src).unwrap();
+        let facts = Facts::new().with("quantity", 12).with("member", true);
+        let outcome = set.evaluate(&facts, Mode::AllMatch);
+        assert_eq!(outcome.fired, ["bulk", "member", "report"]);
+        assert_eq!(outcome.messages, ["12 items at 8% off{"]);
+        assert_eq!(outcome.facts.get("discount"), Some(&Value::Num(8.0)));
+
+        let outcome = set.evaluate(&facts.clone().with("quantity", 3), Mode::AllMatch);
+        assert!(outcome.fired.is_empty());
+        assert_eq!(outcome.facts, facts.with("quantity", 3));
+    }
+
+    #[test]
+    fn explains_the_decision() {
+        let set = RuleSet::parse(FOOD).unwrap();
+        let report = set.explain(&Facts::new().with("food", "cake"), Mode::FirstMatch);
+        let expected = [
+            "rule cookie (priority 10) did not fire",
+            r#"  [no] food == "cookie" (food is "cake")"#,
+            "",
+            "rule cake (priority 10) fired",
+            r#"  [yes] food == "cake" (food is "cake")"#,
+        ];
+        assert_eq!(report.to_string(), expected.join("\n"));
+        assert_eq!(report.outcome.fired, ["cake"]);
+        assert_eq!(
+            RuleSet::new()
+                .explain(&Facts::new(), Mode::AllMatch)
+                .to_string(),
+            "no rules were tried"
+        );
+    }
+
+    #[test]
+    fn reports_bad_rules_by_line() {
+        let err = |src: &str| RuleSet::parse(src).unwrap_err().to_string();
+        assert_eq!(err("when a"), "line 1: 'when' outside a rule");
+        assert_eq!(
+            err("rule a\n  when b ==\n  then say \"x\""),
+            "line 2: expected a value after '==', found the end of the condition"
+        );
+        assert_eq!(
+            err("rule a\n  when b"),
+            "line 1: rule 'a' has no 'then' line"
+        );
+        assert_eq!(
+            err("rule a priority high"),
+            "line 1: priority must be a whole number, not 'high'"
+        );
+        assert_eq!(
+            err("rule a\nthen shout \"x\""),
+            "line 2: expected 'say \"text\"' or 'set name = value', found 'shout \"x\"'"
+        );
+        assert_eq!(
+            err("rule a\nthen say \"x\"\nrule a\nthen say \"y\""),
+            "line 3: there is already a rule named 'a'"
+        );
+        assert_eq!(
+            err("rule a\nunless b"),
+            "line 2: expected 'rule', 'when' or 'then', found 'unless'"
+        );
+    }
+
+    #[test]
+    fn loads_rules_from_a_file() {
+        // The rules main.rs uses, so nothing has to be written to load.
+        let set = RuleSet::load(concat!(env!("CARGO_MANIFEST_DIR"), "/food.rules")).unwrap();
+        assert!(!set.rules().is_empty());
+        assert!(matches!(
+            RuleSet::load(concat!(env!("CARGO_MANIFEST_DIR"), "/no-such.rules")),
+            Err(LoadError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+// A rule engine, so decisions like the `if food == "cookie" ... else if food == "cake"` chain in
+// main.rs can live in a file instead of in code.
+//
+// A rules file lists rules, each with a condition on some named facts and what to do when it
+// holds:
+//
+//   # Lines starting with '#' are comments
+//   rule cookie priority 10
+//     when food == "cookie"
+//     then say "I like cookies"
+//
+//   rule bulk_discount priority 5
+//     when quantity in 10..=99 and not member
+//     then set discount = 5
+//     then say "{quantity} items get {discount}% off"
+//
+// Conditions compare a fact with `==`, `!=`, `<`, `<=`, `>` or `>=`, test a number against a
+// range (`a..b` leaves out b, `a..=b` includes it), or name a fact that must be true; they
+// combine with `and`, `or`, `not` and parentheses. Several `when` lines must all hold, and a rule
+// with none always fires.
+//
+// Rules are tried from the highest priority down, in file order when priorities are equal.
+// Either the first rule that holds fires, or every rule that holds does; see `Mode`.
+
+mod condition;
+mod engine;
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::io;
+
+pub use condition::{CompareOp, Condition, Explanation};
+pub use engine::{Action, Mode, Outcome, Report, Rule, RuleSet, RuleTrace};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Num(f64),
+    Str(String),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Num(_) => "number",
+            Value::Str(_) => "string",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Num(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Value {
+        Value::Num(f64::from(n))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(String::from(s))
+    }
+}
+
+// What the rules are about, by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Facts {
+    values: BTreeMap<String, Value>,
+}
+
+impl Facts {
+    pub fn new() -> Facts {
+        Facts::default()
+    }
+
+    pub fn with(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.values.insert(String::from(name), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    // Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "cannot read rules: {}", e),
+            LoadError::Parse(e) => write!(f, "invalid rules: {}", e),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> LoadError {
+        LoadError::Parse(e)
+    }
+}
@@ -1,3 +1,7 @@
+use std::io;
+
+use tutorial_8::script;
+
 fn main() {
     println!("Hello, world!");
     test();
@@ -13,7 +17,38 @@ fn main() {
         // return x + 1;
     };
     println!("The number is: {}", number);
+
+    // The same program in the small scripting language from `script`, which compiles it to
+    // bytecode and runs it on a stack machine
+    match script::run(SCRIPT, io::stdout()) {
+        Ok(value) => println!("The script's main returned {}", value),
+        Err(e) => println!("{}", e),
+    }
+}
+
+const SCRIPT: &str = r#"
+fn main() {
+    println("Hello, world!");
+    test();
+    let result = add_numbers(5, 10);
+    println("The result is:", result);
+    let number = {
+        let x = 3;
+        x + 1
+    };
+    println("The number is:", number);
+    number
+}
+
+fn test() {
+    println("Test has been called");
+}
+
+fn add_numbers(x: i32, y: i32) -> i32 {
+    println("Adding", x, "and", y);
+    return x + y;
 }
+"#;
 
 fn test() {
     println!("Test has been called");
@@ -0,0 +1,502 @@
+// Turns the syntax tree into bytecode for the stack machine in `vm`.
+//
+// Every function becomes a `Chunk`: a list of instructions and, alongside it, the source line
+// each came from. Instructions take their operands from the top of the stack and push their
+// result. Locals live in numbered slots at the bottom of the function's part of the stack; the
+// parameters take the first slots, and a slot is reused once the block that declared it ends.
+//
+// Names are resolved here rather than at run time, so calling a function that doesn't exist or
+// with the wrong number of arguments, or using an undeclared variable, is a compile error.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::lexer::tokenize;
+use super::parser::{parse, BinOp, Block, Expr, Function, Stmt, UnOp, MAX_DEPTH};
+use super::{CompileError, Pos, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    // Prints its arguments separated by spaces.
+    Println,
+    // Fails with the second argument, if there is one, when the first is false.
+    Assert,
+}
+
+impl Builtin {
+    fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "println" => Some(Builtin::Println),
+            "assert" => Some(Builtin::Assert),
+            _ => None,
+        }
+    }
+
+    fn accepts(self, argc: usize) -> bool {
+        match self {
+            Builtin::Println => true,
+            Builtin::Assert => (1..=2).contains(&argc),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Push(Value),
+    Load(usize),
+    // Pops into a slot.
+    Store(usize),
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Neg,
+    Not,
+    // Fails unless the top of the stack is a bool, leaving it there.
+    ExpectBool(&'static str),
+    Jump(usize),
+    // Pops a bool and jumps if it's false.
+    JumpIfFalse(usize),
+    // Calls the function with this index in the program, with the arguments on the stack.
+    Call(usize),
+    CallBuiltin(Builtin, usize),
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub name: String,
+    pub arity: usize,
+    // How many local slots a call needs, parameters included.
+    pub slots: usize,
+    pub code: Vec<Op>,
+    pub lines: Vec<u32>,
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "fn {} ({} params, {} slots)",
+            self.name, self.arity, self.slots
+        )?;
+        for (i, (op, line)) in self.code.iter().zip(&self.lines).enumerate() {
+            writeln!(f, "{:>4} {:>4}  {:?}", i, line, op)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    functions: Vec<Chunk>,
+    by_name: HashMap<String, usize>,
+}
+
+impl Program {
+    pub fn compile(src: &str) -> Result<Program, CompileError> {
+        let functions = parse(tokenize(src)?)?;
+        let mut by_name = HashMap::new();
+        for (i, function) in functions.iter().enumerate() {
+            if Builtin::from_name(&function.name).is_some() {
+                return Err(CompileError::new(
+                    format!("'{}' is a built-in function", function.name),
+                    function.pos,
+                ));
+            }
+            if by_name.insert(function.name.clone(), i).is_some() {
+                return Err(CompileError::new(
+                    format!("function '{}' is defined twice", function.name),
+                    function.pos,
+                ));
+            }
+        }
+        let arities: Vec<usize> = functions.iter().map(|f| f.params.len()).collect();
+        let functions = functions
+            .iter()
+            .map(|function| FnCompiler::compile(function, &by_name, &arities))
+            .collect::<Result<_, _>>()?;
+        Ok(Program { functions, by_name })
+    }
+
+    pub fn function(&self, index: usize) -> &Chunk {
+        &self.functions[index]
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &Chunk> {
+        self.functions.iter()
+    }
+}
+
+struct FnCompiler<'a> {
+    by_name: &'a HashMap<String, usize>,
+    arities: &'a [usize],
+    chunk: Chunk,
+    // Variables in scope, innermost last; a variable's slot is its index here.
+    locals: Vec<String>,
+    // Where the code being emitted came from.
+    pos: Pos,
+    // How many blocks and expressions the compiler is inside.
+    depth: usize,
+}
+
+impl FnCompiler<'_> {
+    fn compile(
+        function: &Function,
+        by_name: &HashMap<String, usize>,
+        arities: &[usize],
+    ) -> Result<Chunk, CompileError> {
+        let mut compiler = FnCompiler {
+            by_name,
+            arities,
+            chunk: Chunk {
+                name: function.name.clone(),
+                arity: function.params.len(),
+                slots: function.params.len(),
+                code: Vec::new(),
+                lines: Vec::new(),
+            },
+            locals: function.params.clone(),
+            pos: function.pos,
+            depth: 0,
+        };
+        compiler.block(&function.body)?;
+        compiler.emit(Op::Return);
+        Ok(compiler.chunk)
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.lines.push(self.pos.line);
+        self.chunk.code.len() - 1
+    }
+
+    // Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.chunk.code.len();
+        match &mut self.chunk.code[at] {
+            Op::Jump(to) | Op::JumpIfFalse(to) => *to = target,
+            op => unreachable!("patching {:?}", op),
+        }
+    }
+
+    fn at(&mut self, pos: Pos) {
+        self.pos = pos;
+    }
+
+    // Leaves the block's value on the stack.
+    fn block(&mut self, block: &Block) -> Result<(), CompileError> {
+        let scope = self.locals.len();
+        for stmt in &block.stmts {
+            match stmt {
+                Stmt::Let(name, value) => {
+                    // Compiled before the name is declared, so `let x = x + 1` sees the old `x`.
+                    self.expr(value)?;
+                    self.locals.push(name.clone());
+                    self.chunk.slots = self.chunk.slots.max(self.locals.len());
+                    self.emit(Op::Store(self.locals.len() - 1));
+                }
+                Stmt::Expr(e) => {
+                    self.expr(e)?;
+                    self.emit(Op::Pop);
+                }
+            }
+        }
+        match &block.value {
+            Some(value) => self.expr(value)?,
+            None => {
+                self.emit(Op::Push(Value::Unit));
+            }
+        }
+        self.locals.truncate(scope);
+        Ok(())
+    }
+
+    // The parser keeps trees within MAX_DEPTH, but the compiler recurses just as deeply, so
+    // it checks for itself rather than rely on where the tree came from.
+    fn expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            let pos = match expr {
+                Expr::Var(_, pos)
+                | Expr::Unary(_, _, pos)
+                | Expr::Binary(_, _, _, pos)
+                | Expr::Call(_, _, pos)
+                | Expr::Return(_, pos) => *pos,
+                _ => self.pos,
+            };
+            return Err(CompileError::new(
+                format!("nested more than {} deep", MAX_DEPTH),
+                pos,
+            ));
+        }
+        let result = self.expr_at_depth(expr);
+        self.depth -= 1;
+        result
+    }
+
+    fn expr_at_depth(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Int(n) => {
+                self.emit(Op::Push(Value::Int(*n)));
+            }
+            Expr::Bool(b) => {
+                self.emit(Op::Push(Value::Bool(*b)));
+            }
+            Expr::Str(s) => {
+                self.emit(Op::Push(Value::from(s.as_str())));
+            }
+            Expr::Var(name, pos) => {
+                let Some(slot) = self.locals.iter().rposition(|local| local == name) else {
+                    return Err(CompileError::new(
+                        format!("no variable named '{}'", name),
+                        *pos,
+                    ));
+                };
+                self.emit(Op::Load(slot));
+            }
+            Expr::Unary(op, operand, pos) => {
+                self.expr(operand)?;
+                self.at(*pos);
+                self.emit(match op {
+                    UnOp::Neg => Op::Neg,
+                    UnOp::Not => Op::Not,
+                });
+            }
+            Expr::Binary(op @ (BinOp::And | BinOp::Or), lhs, rhs, pos) => {
+                self.logical(*op, lhs, rhs, *pos)?
+            }
+            Expr::Binary(op, lhs, rhs, pos) => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                self.at(*pos);
+                self.emit(match op {
+                    BinOp::Add => Op::Add,
+                    BinOp::Sub => Op::Sub,
+                    BinOp::Mul => Op::Mul,
+                    BinOp::Div => Op::Div,
+                    BinOp::Rem => Op::Rem,
+                    BinOp::Eq => Op::Eq,
+                    BinOp::Ne => Op::Ne,
+                    BinOp::Lt => Op::Lt,
+                    BinOp::Le => Op::Le,
+                    BinOp::Gt => Op::Gt,
+                    BinOp::Ge => Op::Ge,
+                    BinOp::And | BinOp::Or => unreachable!(),
+                });
+            }
+            Expr::Call(name, args, pos) => self.call(name, args, *pos)?,
+            Expr::Block(block) => self.block(block)?,
+            Expr::If(cond, then, otherwise) => {
+                self.expr(cond)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.block(then)?;
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_else);
+                match otherwise {
+                    Some(e) => self.expr(e)?,
+                    None => {
+                        self.emit(Op::Push(Value::Unit));
+                    }
+                }
+                self.patch(to_end);
+            }
+            Expr::Return(value, pos) => {
+                match value {
+                    Some(e) => self.expr(e)?,
+                    None => {
+                        self.emit(Op::Push(Value::Unit));
+                    }
+                }
+                self.at(*pos);
+                self.emit(Op::Return);
+            }
+        }
+        Ok(())
+    }
+
+    // `&&` and `||` only evaluate their right side when it matters.
+    fn logical(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, pos: Pos) -> Result<(), CompileError> {
+        self.expr(lhs)?;
+        self.at(pos);
+        if op == BinOp::Or {
+            self.emit(Op::Not);
+        }
+        let short_circuit = self.emit(Op::JumpIfFalse(0));
+        self.expr(rhs)?;
+        self.at(pos);
+        self.emit(Op::ExpectBool(op.symbol()));
+        let to_end = self.emit(Op::Jump(0));
+        self.patch(short_circuit);
+        self.emit(Op::Push(Value::Bool(op == BinOp::Or)));
+        self.patch(to_end);
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], pos: Pos) -> Result<(), CompileError> {
+        self.at(pos);
+        for arg in args {
+            self.expr(arg)?;
+        }
+        self.at(pos);
+        if let Some(builtin) = Builtin::from_name(name) {
+            if !builtin.accepts(args.len()) {
+                return Err(CompileError::new(
+                    format!(
+                        "'{}' takes 1 or 2 arguments but was given {}",
+                        name,
+                        args.len()
+                    ),
+                    pos,
+                ));
+            }
+            self.emit(Op::CallBuiltin(builtin, args.len()));
+            return Ok(());
+        }
+        let Some(&index) = self.by_name.get(name) else {
+            return Err(CompileError::new(
+                format!("no function named '{}'", name),
+                pos,
+            ));
+        };
+        let arity = self.arities[index];
+        if arity != args.len() {
+            return Err(CompileError::new(
+                format!(
+                    "'{}' takes {} argument(s) but was given {}",
+                    name,
+                    arity,
+                    args.len()
+                ),
+                pos,
+            ));
+        }
+        self.emit(Op::Call(index));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_main(body: &str) -> Chunk {
+        let program = Program::compile(&format!("fn main() {{ {} }}", body)).unwrap();
+        program.function(0).clone()
+    }
+
+    fn compile_err(src: &str) -> String {
+        Program::compile(src).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn emits_stack_code() {
+        let chunk = compile_main("let x = 2; x * 3");
+        assert_eq!(
+            chunk.code,
+            [
+                Op::Push(Value::Int(2)),
+                Op::Store(0),
+                Op::Load(0),
+                Op::Push(Value::Int(3)),
+                Op::Mul,
+                Op::Return,
+            ]
+        );
+        assert_eq!(chunk.slots, 1);
+    }
+
+    #[test]
+    fn jumps_around_branches() {
+        let chunk = compile_main("if true { 1 } else { 2 }");
+        assert_eq!(
+            chunk.code,
+            [
+                Op::Push(Value::Bool(true)),
+                Op::JumpIfFalse(4),
+                Op::Push(Value::Int(1)),
+                Op::Jump(5),
+                Op::Push(Value::Int(2)),
+                Op::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn reuses_slots_after_a_block() {
+        let chunk = compile_main("let a = { let b = 1; b }; let c = { let d = 2; d }; a + c");
+        assert_eq!(chunk.slots, 2);
+        // Shadowing gets a fresh slot, and the initializer still sees the old variable.
+        let chunk = compile_main("let x = 1; let x = x + 1; x");
+        assert_eq!(chunk.slots, 2);
+        assert_eq!(chunk.code[2], Op::Load(0));
+        assert_eq!(chunk.code[chunk.code.len() - 2], Op::Load(1));
+    }
+
+    #[test]
+    fn resolves_names_at_compile_time() {
+        assert_eq!(
+            compile_err("fn main() {\n  let y = { let x = 1; x };\n  x\n}"),
+            "3:3: no variable named 'x'"
+        );
+        assert_eq!(
+            compile_err("fn main() { add(1) }\nfn add(a, b) { a + b }"),
+            "1:13: 'add' takes 2 argument(s) but was given 1"
+        );
+        assert_eq!(
+            compile_err("fn main() { assert() }"),
+            "1:13: 'assert' takes 1 or 2 arguments but was given 0"
+        );
+        assert_eq!(
+            compile_err("fn f() {}\nfn f() {}"),
+            "2:1: function 'f' is defined twice"
+        );
+        assert_eq!(
+            compile_err("fn println() {}"),
+            "1:1: 'println' is a built-in function"
+        );
+    }
+
+    #[test]
+    fn disassembles_with_lines() {
+        let program = Program::compile("fn main() {\n  f(1)\n}\nfn f(n) { n }").unwrap();
+        assert_eq!(program.lookup("f"), Some(1));
+        assert_eq!(
+            program.function(0).to_string(),
+            "fn main (0 params, 0 slots)\n   0    2  Push(Int(1))\n   1    2  Call(1)\n   2    2  Return\n"
+        );
+    }
+
+    #[test]
+    fn limits_nesting_in_trees_it_is_given() {
+        let pos = Pos { line: 3, col: 7 };
+        let mut deep = Expr::Int(1);
+        for _ in 0..=MAX_DEPTH {
+            deep = Expr::Unary(UnOp::Neg, Box::new(deep), pos);
+        }
+        let function = Function {
+            name: String::from("main"),
+            params: Vec::new(),
+            body: Block {
+                stmts: Vec::new(),
+                value: Some(Box::new(deep)),
+            },
+            pos: Pos { line: 1, col: 1 },
+        };
+        let err = FnCompiler::compile(&function, &HashMap::new(), &[]).unwrap_err();
+        assert_eq!(err.to_string(), "3:7: nested more than 200 deep");
+        assert!(Program::compile(&format!("fn main() {{ {}1 }}", "-".repeat(150))).is_ok());
+    }
+}
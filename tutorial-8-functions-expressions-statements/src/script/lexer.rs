@@ -0,0 +1,307 @@
+// Turns source text into tokens, remembering the line and column each one starts at.
+
+use std::fmt;
+
+use super::{CompileError, Pos};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Int(i64),
+    Str(String),
+    Ident(String),
+    Fn,
+    Let,
+    If,
+    Else,
+    Return,
+    True,
+    False,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Colon,
+    Arrow,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Bang,
+    EqEq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    AndAnd,
+    OrOr,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Token::Int(n) => return write!(f, "{}", n),
+            Token::Str(s) => return write!(f, "{:?}", s),
+            Token::Ident(name) => return write!(f, "'{}'", name),
+            Token::Eof => return write!(f, "end of input"),
+            Token::Fn => "fn",
+            Token::Let => "let",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::Return => "return",
+            Token::True => "true",
+            Token::False => "false",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::Arrow => "->",
+            Token::Assign => "=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Bang => "!",
+            Token::EqEq => "==",
+            Token::NotEq => "!=",
+            Token::Less => "<",
+            Token::LessEq => "<=",
+            Token::Greater => ">",
+            Token::GreaterEq => ">=",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+        };
+        write!(f, "'{}'", text)
+    }
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: u32,
+    col: u32,
+}
+
+impl Lexer<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn pos(&self) -> Pos {
+        Pos {
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    // Consumes `c` if it's next.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    if ahead.peek() != Some(&'/') {
+                        return;
+                    }
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn string(&mut self, start: Pos) -> Result<Token, CompileError> {
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(CompileError::new("unterminated string", start)),
+                Some('"') => return Ok(Token::Str(s)),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        other => {
+                            let shown = other.map_or(String::new(), String::from);
+                            return Err(CompileError::new(
+                                format!("unknown escape '\\{}'", shown),
+                                self.pos(),
+                            ));
+                        }
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<(Token, Pos), CompileError> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos();
+        let Some(c) = self.bump() else {
+            return Ok((Token::Eof, start));
+        };
+        let token = match c {
+            '0'..='9' => {
+                let mut digits = String::from(c);
+                while let Some(d) = self.peek().filter(|d| d.is_ascii_digit() || *d == '_') {
+                    self.bump();
+                    if d != '_' {
+                        digits.push(d);
+                    }
+                }
+                let n = digits
+                    .parse()
+                    .map_err(|_| CompileError::new("integer literal is too large", start))?;
+                Token::Int(n)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(c) = self.peek().filter(|c| c.is_alphanumeric() || *c == '_') {
+                    self.bump();
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "fn" => Token::Fn,
+                    "let" => Token::Let,
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    "return" => Token::Return,
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    _ => Token::Ident(word),
+                }
+            }
+            '"' => self.string(start)?,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            ':' => Token::Colon,
+            '+' => Token::Plus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '-' if self.eat('>') => Token::Arrow,
+            '-' => Token::Minus,
+            '=' if self.eat('=') => Token::EqEq,
+            '=' => Token::Assign,
+            '!' if self.eat('=') => Token::NotEq,
+            '!' => Token::Bang,
+            '<' if self.eat('=') => Token::LessEq,
+            '<' => Token::Less,
+            '>' if self.eat('=') => Token::GreaterEq,
+            '>' => Token::Greater,
+            '&' if self.eat('&') => Token::AndAnd,
+            '|' if self.eat('|') => Token::OrOr,
+            c => {
+                return Err(CompileError::new(
+                    format!("unexpected character {:?}", c),
+                    start,
+                ))
+            }
+        };
+        Ok((token, start))
+    }
+}
+
+pub fn tokenize(src: &str) -> Result<Vec<(Token, Pos)>, CompileError> {
+    let mut lexer = Lexer {
+        chars: src.chars().peekable(),
+        line: 1,
+        col: 1,
+    };
+    let mut tokens = Vec::new();
+    loop {
+        let (token, pos) = lexer.token()?;
+        let done = token == Token::Eof;
+        tokens.push((token, pos));
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tokens_with_positions() {
+        let tokens = tokenize("fn f(x: i32) -> i32 {\n    x >= 1_000 // big\n}").unwrap();
+        let kinds: Vec<&Token> = tokens.iter().map(|(t, _)| t).collect();
+        assert_eq!(
+            kinds,
+            [
+                &Token::Fn,
+                &Token::Ident("f".into()),
+                &Token::LParen,
+                &Token::Ident("x".into()),
+                &Token::Colon,
+                &Token::Ident("i32".into()),
+                &Token::RParen,
+                &Token::Arrow,
+                &Token::Ident("i32".into()),
+                &Token::LBrace,
+                &Token::Ident("x".into()),
+                &Token::GreaterEq,
+                &Token::Int(1000),
+                &Token::RBrace,
+                &Token::Eof,
+            ]
+        );
+        assert_eq!(tokens[10].1, Pos { line: 2, col: 5 });
+        assert_eq!(tokens[13].1, Pos { line: 3, col: 1 });
+    }
+
+    #[test]
+    fn reads_strings_and_reports_bad_input() {
+        let tokens = tokenize(r#""say \"hi\"\n""#).unwrap();
+        assert_eq!(tokens[0].0, Token::Str("say \"hi\"\n".into()));
+
+        let err = tokenize("let s = \"open").unwrap_err();
+        assert_eq!(err.to_string(), "1:9: unterminated string");
+        let err = tokenize("1 +\n  #").unwrap_err();
+        assert_eq!(err.to_string(), "2:3: unexpected character '#'");
+        let err = tokenize("99999999999999999999").unwrap_err();
+        assert_eq!(err.to_string(), "1:1: integer literal is too large");
+    }
+}
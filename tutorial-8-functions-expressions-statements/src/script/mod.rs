@@ -0,0 +1,299 @@
+// A small scripting language built from the ideas in this tutorial: functions with parameters
+// and return values, `let`, blocks that are expressions, `if`/`else` and `return`.
+//
+//   fn add_numbers(x: i32, y: i32) -> i32 {
+//       println("Adding", x, "and", y);
+//       return x + y;
+//   }
+//
+//   fn main() {
+//       let number = { let x = 3; x + 1 };
+//       println("The result is:", add_numbers(5, 10), "and the number is:", number);
+//   }
+//
+// Source is compiled to bytecode for a stack machine (see `compiler`), which `Vm` runs. Compile
+// errors carry the line and column they were found at; run-time errors carry the call stack
+// that led to them.
+
+mod compiler;
+mod lexer;
+mod parser;
+mod vm;
+
+use std::error;
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+pub use compiler::{Chunk, Op, Program};
+pub use vm::{Vm, DEFAULT_MAX_DEPTH};
+
+// Where in the source something is, counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pos {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub message: String,
+    pub pos: Pos,
+}
+
+impl CompileError {
+    pub fn new(message: impl Into<String>, pos: Pos) -> CompileError {
+        CompileError {
+            message: message.into(),
+            pos,
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.pos, self.message)
+    }
+}
+
+impl error::Error for CompileError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Str(Rc<str>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "()",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Str(_) => "str",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Int(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(Rc::from(s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    // The operator's symbol, e.g. "*".
+    Overflow(&'static str),
+    DivideByZero,
+    // Operands of the wrong type, e.g. "cannot apply '+' to int and bool".
+    TypeMismatch(String),
+    AssertionFailed(String),
+    StackOverflow,
+    NoSuchFunction(String),
+    WrongArgCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    // Writing what `println` printed failed.
+    Output(String),
+}
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::Overflow(op) => write!(f, "integer overflow in '{}'", op),
+            RuntimeErrorKind::DivideByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::TypeMismatch(message) => write!(f, "{}", message),
+            RuntimeErrorKind::AssertionFailed(message) => {
+                write!(f, "assertion failed: {}", message)
+            }
+            RuntimeErrorKind::StackOverflow => write!(f, "stack overflow"),
+            RuntimeErrorKind::NoSuchFunction(name) => write!(f, "no function named '{}'", name),
+            RuntimeErrorKind::WrongArgCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' takes {} argument(s) but was given {}",
+                function, expected, found
+            ),
+            RuntimeErrorKind::Output(message) => write!(f, "cannot write output: {}", message),
+        }
+    }
+}
+
+// One call that was in progress when the error happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub line: u32,
+}
+
+const TRACE_SHOWN: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    // Innermost call first.
+    pub trace: Vec<TraceFrame>,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        // A stack overflow would otherwise print hundreds of identical frames.
+        for frame in self.trace.iter().take(TRACE_SHOWN) {
+            write!(f, "\n    in {} at line {}", frame.function, frame.line)?;
+        }
+        if self.trace.len() > TRACE_SHOWN {
+            write!(f, "\n    ... {} more", self.trace.len() - TRACE_SHOWN)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for RuntimeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Compile(CompileError),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Compile(e) => write!(f, "compile error at {}", e),
+            Error::Runtime(e) => write!(f, "runtime error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Compile(e) => Some(e),
+            Error::Runtime(e) => Some(e),
+        }
+    }
+}
+
+impl From<CompileError> for Error {
+    fn from(e: CompileError) -> Error {
+        Error::Compile(e)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(e: RuntimeError) -> Error {
+        Error::Runtime(e)
+    }
+}
+
+// Compiles `src` and calls its `main`, sending what it prints to `output`.
+pub fn run<W: Write>(src: &str, output: W) -> Result<Value, Error> {
+    let program = Program::compile(src)?;
+    let value = Vm::new(&program, output).call("main", &[])?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_capturing(src: &str) -> (Result<Value, Error>, String) {
+        let mut output = Vec::new();
+        let result = run(src, &mut output);
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn runs_the_tutorial_program() {
+        let src = r#"
+            fn main() {
+                println("Hello, world!");
+                test();
+                let result = add_numbers(5, 10);
+                println("The result is:", result);
+                let number = {
+                    let x = 3;
+                    x + 1
+                };
+                println("The number is:", number);
+                number
+            }
+
+            fn test() {
+                println("Test has been called");
+            }
+
+            fn add_numbers(x: i32, y: i32) -> i32 {
+                println("Adding", x, "and", y);
+                return x + y;
+            }
+        "#;
+        let (result, output) = run_capturing(src);
+        assert_eq!(result, Ok(Value::Int(4)));
+        assert_eq!(
+            output,
+            "Hello, world!\nTest has been called\nAdding 5 and 10\nThe result is: 15\n\
+             The number is: 4\n"
+        );
+    }
+
+    #[test]
+    fn reports_both_kinds_of_error() {
+        let (result, _) = run_capturing("fn main() { missing() }");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "compile error at 1:13: no function named 'missing'"
+        );
+
+        let src = "fn main() {\n  half(1)\n}\nfn half(n) {\n  n / 0\n}";
+        let (result, _) = run_capturing(src);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "runtime error: division by zero\n    in half at line 5\n    in main at line 2"
+        );
+
+        let (result, _) = run_capturing("fn start() {}");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "runtime error: no function named 'main'"
+        );
+    }
+}
@@ -0,0 +1,527 @@
+// Builds the syntax tree. The grammar follows Rust's where the two overlap:
+//
+//   fn add_numbers(x: i32, y: i32) -> i32 {
+//       println("Adding", x, y);
+//       return x + y;
+//   }
+//
+// Type annotations are accepted and ignored, since values carry their types at run time. A
+// block's value is its last expression if that has no semicolon after it, and `if` and blocks
+// used as statements don't need one either.
+
+use super::lexer::Token;
+use super::{CompileError, Pos};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinOp {
+    fn from_token(token: &Token) -> Option<BinOp> {
+        Some(match token {
+            Token::Plus => BinOp::Add,
+            Token::Minus => BinOp::Sub,
+            Token::Star => BinOp::Mul,
+            Token::Slash => BinOp::Div,
+            Token::Percent => BinOp::Rem,
+            Token::EqEq => BinOp::Eq,
+            Token::NotEq => BinOp::Ne,
+            Token::Less => BinOp::Lt,
+            Token::LessEq => BinOp::Le,
+            Token::Greater => BinOp::Gt,
+            Token::GreaterEq => BinOp::Ge,
+            Token::AndAnd => BinOp::And,
+            Token::OrOr => BinOp::Or,
+            _ => return None,
+        })
+    }
+
+    // Higher binds tighter. Everything is left-associative.
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 3,
+            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 5,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        }
+    }
+}
+
+// Unary operators bind tighter than any binary one.
+const UNARY_PRECEDENCE: u8 = 6;
+
+// How far blocks and expressions can sit inside one another, counting each operator as a level
+// too. Parsing and compiling recurse once per level, so a limit keeps a deeply nested script
+// from overflowing the stack.
+pub(crate) const MAX_DEPTH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Block,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    // None gives the unit value, as a block ending in `;` does in Rust.
+    pub value: Option<Box<Expr>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let(String, Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Int(i64),
+    Bool(bool),
+    Str(String),
+    Var(String, Pos),
+    Unary(UnOp, Box<Expr>, Pos),
+    Binary(BinOp, Box<Expr>, Box<Expr>, Pos),
+    Call(String, Vec<Expr>, Pos),
+    Block(Block),
+    // The else branch is a block or another if.
+    If(Box<Expr>, Block, Option<Box<Expr>>),
+    Return(Option<Box<Expr>>, Pos),
+}
+
+impl Expr {
+    // Whether the expression ends in a block, so it can stand as a statement without a `;`.
+    fn is_block_like(&self) -> bool {
+        matches!(self, Expr::Block(_) | Expr::If(..))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    pos: usize,
+    depth: usize,
+}
+
+pub fn parse(tokens: Vec<(Token, Pos)>) -> Result<Vec<Function>, CompileError> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let mut functions = Vec::new();
+    while parser.peek() != &Token::Eof {
+        functions.push(parser.function()?);
+    }
+    Ok(functions)
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn here(&self) -> Pos {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> (Token, Pos) {
+        let token = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> CompileError {
+        CompileError::new(
+            format!("expected {}, found {}", expected, self.peek()),
+            self.here(),
+        )
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), CompileError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token.to_string()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.next();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    // Goes one level deeper, failing at `pos` past MAX_DEPTH.
+    fn nest(&mut self, pos: Pos) -> Result<(), CompileError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(CompileError::new(
+                format!("nested more than {} deep", MAX_DEPTH),
+                pos,
+            ));
+        }
+        Ok(())
+    }
+
+    // `: Type`, if there is one.
+    fn skip_annotation(&mut self, introducer: &Token) -> Result<(), CompileError> {
+        if self.eat(introducer) {
+            self.ident()?;
+        }
+        Ok(())
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        let pos = self.here();
+        if !self.eat(&Token::Fn) {
+            return Err(self.unexpected("'fn'"));
+        }
+        let name = self.ident()?;
+        self.expect(&Token::LParen)?;
+        let mut params = Vec::new();
+        while !self.eat(&Token::RParen) {
+            params.push(self.ident()?);
+            self.skip_annotation(&Token::Colon)?;
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RParen)?;
+                break;
+            }
+        }
+        self.skip_annotation(&Token::Arrow)?;
+        self.expect(&Token::LBrace)?;
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            params,
+            body,
+            pos,
+        })
+    }
+
+    // After the opening brace, up to and including the closing one.
+    fn block(&mut self) -> Result<Block, CompileError> {
+        let depth = self.depth;
+        self.nest(self.here())?;
+        let block = self.statements()?;
+        self.depth = depth;
+        Ok(block)
+    }
+
+    fn statements(&mut self) -> Result<Block, CompileError> {
+        let mut stmts = Vec::new();
+        loop {
+            if self.eat(&Token::RBrace) {
+                return Ok(Block { stmts, value: None });
+            }
+            if self.eat(&Token::Let) {
+                let name = self.ident()?;
+                self.skip_annotation(&Token::Colon)?;
+                self.expect(&Token::Assign)?;
+                let value = self.expr(0)?;
+                self.expect(&Token::Semicolon)?;
+                stmts.push(Stmt::Let(name, value));
+                continue;
+            }
+            // As in Rust, a statement starting with `if` or a block ends with it, so
+            // `if c { .. } -x` is two statements rather than a subtraction.
+            let expr = match self.peek() {
+                Token::If | Token::LBrace => self.primary()?,
+                _ => self.expr(0)?,
+            };
+            if self.eat(&Token::Semicolon) {
+                stmts.push(Stmt::Expr(expr));
+            } else if self.eat(&Token::RBrace) {
+                return Ok(Block {
+                    stmts,
+                    value: Some(Box::new(expr)),
+                });
+            } else if expr.is_block_like() {
+                stmts.push(Stmt::Expr(expr));
+            } else {
+                return Err(self.unexpected("';' or '}'"));
+            }
+        }
+    }
+
+    // Binary operators with a precedence of at least `min`.
+    fn expr(&mut self, min: u8) -> Result<Expr, CompileError> {
+        let depth = self.depth;
+        self.nest(self.here())?;
+        let mut lhs = self.unary()?;
+        while let Some(op) = BinOp::from_token(self.peek()) {
+            if op.precedence() < min {
+                break;
+            }
+            let (_, pos) = self.next();
+            // Each operator puts what came before it one level further down.
+            self.nest(pos)?;
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), pos);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let pos = self.here();
+        let op = match self.peek() {
+            Token::Minus => UnOp::Neg,
+            Token::Bang => UnOp::Not,
+            _ => return self.primary(),
+        };
+        self.next();
+        let operand = self.expr(UNARY_PRECEDENCE)?;
+        Ok(Expr::Unary(op, Box::new(operand), pos))
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let pos = self.here();
+        match self.peek().clone() {
+            Token::Int(n) => {
+                self.next();
+                Ok(Expr::Int(n))
+            }
+            Token::Str(s) => {
+                self.next();
+                Ok(Expr::Str(s))
+            }
+            Token::True | Token::False => Ok(Expr::Bool(self.next().0 == Token::True)),
+            Token::LParen => {
+                self.next();
+                let e = self.expr(0)?;
+                self.expect(&Token::RParen)?;
+                Ok(e)
+            }
+            Token::LBrace => {
+                self.next();
+                Ok(Expr::Block(self.block()?))
+            }
+            Token::If => {
+                self.next();
+                self.if_expr()
+            }
+            Token::Return => {
+                self.next();
+                let value = match self.peek() {
+                    Token::Semicolon | Token::RBrace => None,
+                    _ => Some(Box::new(self.expr(0)?)),
+                };
+                Ok(Expr::Return(value, pos))
+            }
+            Token::Ident(name) => {
+                self.next();
+                if !self.eat(&Token::LParen) {
+                    return Ok(Expr::Var(name, pos));
+                }
+                let mut args = Vec::new();
+                while !self.eat(&Token::RParen) {
+                    args.push(self.expr(0)?);
+                    if !self.eat(&Token::Comma) {
+                        self.expect(&Token::RParen)?;
+                        break;
+                    }
+                }
+                Ok(Expr::Call(name, args, pos))
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    // After the `if`.
+    fn if_expr(&mut self) -> Result<Expr, CompileError> {
+        let cond = self.expr(0)?;
+        self.expect(&Token::LBrace)?;
+        let then = self.block()?;
+        let otherwise = if self.eat(&Token::Else) {
+            if self.eat(&Token::If) {
+                Some(Box::new(self.if_expr()?))
+            } else {
+                self.expect(&Token::LBrace)?;
+                Some(Box::new(Expr::Block(self.block()?)))
+            }
+        } else {
+            None
+        };
+        Ok(Expr::If(Box::new(cond), then, otherwise))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::tokenize;
+    use super::*;
+
+    fn parse_src(src: &str) -> Result<Vec<Function>, CompileError> {
+        parse(tokenize(src)?)
+    }
+
+    fn body(src: &str) -> Block {
+        parse_src(&format!("fn main() {{ {} }}", src))
+            .unwrap()
+            .remove(0)
+            .body
+    }
+
+    #[test]
+    fn parses_functions_with_annotations() {
+        let functions =
+            parse_src("fn add_numbers(x: i32, y: i32,) -> i32 { return x + y; }\nfn test() {}")
+                .unwrap();
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].params, ["x", "y"]);
+        assert_eq!(functions[1].name, "test");
+        assert_eq!(functions[1].pos, Pos { line: 2, col: 1 });
+        assert_eq!(
+            functions[1].body,
+            Block {
+                stmts: vec![],
+                value: None
+            }
+        );
+    }
+
+    #[test]
+    fn gives_blocks_a_value() {
+        let block = body("let number = { let x = 3; x + 1 }; number");
+        assert_eq!(block.stmts.len(), 1);
+        let Stmt::Let(name, Expr::Block(inner)) = &block.stmts[0] else {
+            panic!("{:?}", block.stmts[0]);
+        };
+        assert_eq!(name, "number");
+        assert_eq!(inner.stmts.len(), 1);
+        assert!(matches!(
+            inner.value.as_deref(),
+            Some(Expr::Binary(BinOp::Add, ..))
+        ));
+
+        // An `if` statement needs no semicolon; a trailing `;` leaves the block without a value.
+        let block = body("if true { 1 } else { 2 } 3;");
+        assert_eq!(block.stmts.len(), 2);
+        assert_eq!(block.value, None);
+        let block = body("if true { 1 } -2");
+        assert_eq!(block.stmts.len(), 1);
+        assert!(matches!(
+            block.value.as_deref(),
+            Some(Expr::Unary(UnOp::Neg, ..))
+        ));
+    }
+
+    #[test]
+    fn respects_precedence() {
+        let block = body("-1 + 2 * 3 < 4 || !false && x == 1");
+        let Some(value) = block.value else {
+            panic!("no value");
+        };
+        let Expr::Binary(BinOp::Or, lhs, rhs, _) = *value else {
+            panic!("{:?}", value);
+        };
+        assert!(matches!(*lhs, Expr::Binary(BinOp::Lt, ..)));
+        assert!(matches!(*rhs, Expr::Binary(BinOp::And, ..)));
+        let Expr::Binary(BinOp::Lt, sum, _, _) = *lhs else {
+            unreachable!()
+        };
+        let Expr::Binary(BinOp::Add, neg, _, _) = *sum else {
+            panic!("{:?}", sum);
+        };
+        assert!(matches!(*neg, Expr::Unary(UnOp::Neg, ..)));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let err = |src| parse_src(src).unwrap_err().to_string();
+        assert_eq!(
+            err("fn main() { 1 2 }"),
+            "1:15: expected ';' or '}', found 2"
+        );
+        assert_eq!(
+            err("fn main() {\n  let = 1;\n}"),
+            "2:7: expected a name, found '='"
+        );
+        assert_eq!(
+            err("fn main() { if x { 1 } else 2 }"),
+            "1:29: expected '{', found 2"
+        );
+        assert_eq!(err("let x = 1;"), "1:1: expected 'fn', found 'let'");
+        assert_eq!(
+            err("fn main() {"),
+            "1:12: expected an expression, found end of input"
+        );
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |open: &str, close: &str, n| {
+            format!("fn main() {{ {}1{} }}", open.repeat(n), close.repeat(n))
+        };
+        assert!(parse_src(&nested("(", ")", 50)).is_ok());
+        assert!(parse_src(&nested("{", "}", 50)).is_ok());
+        for src in [
+            nested("(", ")", 5000),
+            nested("{", "}", 5000),
+            nested("if true { ", " }", 5000),
+            nested("-", "", 5000),
+            nested("f(", ")", 5000),
+            format!("fn main() {{ 1{} }}", " + 1".repeat(5000)),
+        ] {
+            let err = parse_src(&src).unwrap_err();
+            assert_eq!(err.message, "nested more than 200 deep");
+            assert_eq!(err.pos.line, 1);
+        }
+    }
+}
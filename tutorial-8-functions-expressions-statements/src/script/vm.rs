@@ -0,0 +1,438 @@
+// Runs compiled bytecode.
+//
+// There is one value stack shared by every call. A call's frame starts where its arguments
+// were pushed: the arguments become its first local slots, the rest of its slots follow, and
+// above those is room for the operands of its instructions. Returning drops everything from the
+// frame's start and leaves the return value in its place, which is where the caller expects it.
+
+use std::io::Write;
+use std::rc::Rc;
+
+use super::compiler::{Builtin, Op, Program};
+use super::{RuntimeError, RuntimeErrorKind, TraceFrame, Value};
+
+// How deep calls may nest before the script is stopped with a stack overflow.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    function: usize,
+    // The next instruction to run.
+    ip: usize,
+    // Where the frame's slots start on the stack.
+    base: usize,
+}
+
+pub struct Vm<'p, W> {
+    program: &'p Program,
+    output: W,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    max_depth: usize,
+}
+
+impl<'p, W: Write> Vm<'p, W> {
+    pub fn new(program: &'p Program, output: W) -> Vm<'p, W> {
+        Vm {
+            program,
+            output,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // Calls a function of the program by name. The machine can be used for any number of calls.
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let untraced = |kind| RuntimeError {
+            kind,
+            trace: Vec::new(),
+        };
+        let index = self
+            .program
+            .lookup(name)
+            .ok_or_else(|| untraced(RuntimeErrorKind::NoSuchFunction(String::from(name))))?;
+        let arity = self.program.function(index).arity;
+        if arity != args.len() {
+            return Err(untraced(RuntimeErrorKind::WrongArgCount {
+                function: String::from(name),
+                expected: arity,
+                found: args.len(),
+            }));
+        }
+
+        self.stack.extend_from_slice(args);
+        let result = self.enter(index).and_then(|()| self.run());
+        result.map_err(|kind| {
+            let trace = self.trace();
+            self.stack.clear();
+            self.frames.clear();
+            RuntimeError { kind, trace }
+        })
+    }
+
+    // The calls in progress, innermost first, each with the line it had reached.
+    fn trace(&self) -> Vec<TraceFrame> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let chunk = self.program.function(frame.function);
+                TraceFrame {
+                    function: chunk.name.clone(),
+                    line: chunk.lines[frame.ip.saturating_sub(1)],
+                }
+            })
+            .collect()
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("compiled code never underflows the stack")
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a function is running")
+    }
+
+    // Starts a call to the function whose arguments are on top of the stack.
+    fn enter(&mut self, function: usize) -> Result<(), RuntimeErrorKind> {
+        if self.frames.len() >= self.max_depth {
+            return Err(RuntimeErrorKind::StackOverflow);
+        }
+        let chunk = self.program.function(function);
+        let base = self.stack.len() - chunk.arity;
+        self.stack.resize(base + chunk.slots, Value::Unit);
+        self.frames.push(Frame {
+            function,
+            ip: 0,
+            base,
+        });
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<Value, RuntimeErrorKind> {
+        let program = self.program;
+        loop {
+            let frame = self.frame();
+            let base = frame.base;
+            let op = &program.function(frame.function).code[frame.ip];
+            frame.ip += 1;
+
+            match op {
+                Op::Push(value) => self.stack.push(value.clone()),
+                Op::Load(slot) => self.stack.push(self.stack[base + slot].clone()),
+                Op::Store(slot) => self.stack[base + slot] = self.pop(),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Neg => match self.pop() {
+                    Value::Int(n) => {
+                        let negated = n.checked_neg().ok_or(RuntimeErrorKind::Overflow("-"))?;
+                        self.stack.push(Value::Int(negated));
+                    }
+                    v => return Err(mismatch_unary("-", &v)),
+                },
+                Op::Not => match self.pop() {
+                    Value::Bool(b) => self.stack.push(Value::Bool(!b)),
+                    v => return Err(mismatch_unary("!", &v)),
+                },
+                Op::ExpectBool(symbol) => {
+                    let top = self.stack.last().expect("an operand");
+                    if !matches!(top, Value::Bool(_)) {
+                        return Err(RuntimeErrorKind::TypeMismatch(format!(
+                            "cannot apply '{}' to {}",
+                            symbol,
+                            top.type_name()
+                        )));
+                    }
+                }
+                Op::Jump(to) => self.frame().ip = *to,
+                Op::JumpIfFalse(to) => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => self.frame().ip = *to,
+                    v => {
+                        return Err(RuntimeErrorKind::TypeMismatch(format!(
+                            "expected a bool condition, found {}",
+                            v.type_name()
+                        )))
+                    }
+                },
+                Op::Call(function) => self.enter(*function)?,
+                Op::CallBuiltin(builtin, argc) => {
+                    let args = self.stack.split_off(self.stack.len() - argc);
+                    let value = self.builtin(*builtin, args)?;
+                    self.stack.push(value);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().expect("a function is running");
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                op => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(binary(op, lhs, rhs)?);
+                }
+            }
+        }
+    }
+
+    fn builtin(&mut self, builtin: Builtin, args: Vec<Value>) -> Result<Value, RuntimeErrorKind> {
+        match builtin {
+            Builtin::Println => {
+                let line: Vec<String> = args.iter().map(Value::to_string).collect();
+                writeln!(self.output, "{}", line.join(" "))
+                    .map_err(|e| RuntimeErrorKind::Output(e.to_string()))?;
+            }
+            Builtin::Assert => match args.first() {
+                Some(Value::Bool(true)) => {}
+                Some(Value::Bool(false)) => {
+                    let message = args
+                        .get(1)
+                        .map_or(String::from("condition was false"), Value::to_string);
+                    return Err(RuntimeErrorKind::AssertionFailed(message));
+                }
+                v => {
+                    let found = v.map_or("nothing", Value::type_name);
+                    return Err(RuntimeErrorKind::TypeMismatch(format!(
+                        "assert expects a bool, found {}",
+                        found
+                    )));
+                }
+            },
+        }
+        Ok(Value::Unit)
+    }
+}
+
+fn mismatch_unary(symbol: &str, operand: &Value) -> RuntimeErrorKind {
+    RuntimeErrorKind::TypeMismatch(format!(
+        "cannot apply '{}' to {}",
+        symbol,
+        operand.type_name()
+    ))
+}
+
+fn binary(op: &Op, lhs: Value, rhs: Value) -> Result<Value, RuntimeErrorKind> {
+    let symbol = match op {
+        Op::Add => "+",
+        Op::Sub => "-",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::Rem => "%",
+        Op::Eq => "==",
+        Op::Ne => "!=",
+        Op::Lt => "<",
+        Op::Le => "<=",
+        Op::Gt => ">",
+        Op::Ge => ">=",
+        op => unreachable!("{:?} is not a binary operator", op),
+    };
+    let overflow = || RuntimeErrorKind::Overflow(symbol);
+
+    let value = match (op, &lhs, &rhs) {
+        (Op::Add, Value::Int(a), Value::Int(b)) => {
+            Value::Int(a.checked_add(*b).ok_or_else(overflow)?)
+        }
+        (Op::Sub, Value::Int(a), Value::Int(b)) => {
+            Value::Int(a.checked_sub(*b).ok_or_else(overflow)?)
+        }
+        (Op::Mul, Value::Int(a), Value::Int(b)) => {
+            Value::Int(a.checked_mul(*b).ok_or_else(overflow)?)
+        }
+        (Op::Div | Op::Rem, Value::Int(_), Value::Int(0)) => {
+            return Err(RuntimeErrorKind::DivideByZero)
+        }
+        (Op::Div, Value::Int(a), Value::Int(b)) => {
+            Value::Int(a.checked_div(*b).ok_or_else(overflow)?)
+        }
+        (Op::Rem, Value::Int(a), Value::Int(b)) => {
+            Value::Int(a.checked_rem(*b).ok_or_else(overflow)?)
+        }
+        (Op::Add, Value::Str(a), Value::Str(b)) => Value::Str(Rc::from(format!("{}{}", a, b))),
+        (Op::Eq | Op::Ne, a, b) if a.type_name() == b.type_name() => {
+            Value::Bool((a == b) == (*op == Op::Eq))
+        }
+        (Op::Lt | Op::Le | Op::Gt | Op::Ge, Value::Int(a), Value::Int(b)) => {
+            Value::Bool(compare(op, a.cmp(b)))
+        }
+        (Op::Lt | Op::Le | Op::Gt | Op::Ge, Value::Str(a), Value::Str(b)) => {
+            Value::Bool(compare(op, a.cmp(b)))
+        }
+        _ => {
+            return Err(RuntimeErrorKind::TypeMismatch(format!(
+                "cannot apply '{}' to {} and {}",
+                symbol,
+                lhs.type_name(),
+                rhs.type_name()
+            )))
+        }
+    };
+    Ok(value)
+}
+
+fn compare(op: &Op, ordering: std::cmp::Ordering) -> bool {
+    match op {
+        Op::Lt => ordering.is_lt(),
+        Op::Le => ordering.is_le(),
+        Op::Gt => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(body: &str) -> Result<Value, RuntimeError> {
+        let program = Program::compile(&format!("fn main() {{ {} }}", body)).unwrap();
+        Vm::new(&program, Vec::new()).call("main", &[])
+    }
+
+    fn eval_err(body: &str) -> String {
+        eval(body).unwrap_err().kind.to_string()
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        assert_eq!(eval("1 + 2 * 3 - -4"), Ok(Value::Int(11)));
+        assert_eq!(eval("7 / 2 + 7 % 2"), Ok(Value::Int(4)));
+        assert_eq!(eval(r#""ab" + "c" == "abc""#), Ok(Value::Bool(true)));
+        assert_eq!(
+            eval(r#""apple" < "banana" && !(2 >= 3)"#),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval("let x = 1; let x = x + 1; { let x = 10; x }; x"),
+            Ok(Value::Int(2))
+        );
+        assert_eq!(eval("if 1 > 2 { 1 }"), Ok(Value::Unit));
+    }
+
+    #[test]
+    fn short_circuits_logic() {
+        assert_eq!(eval("false && 1 / 0 == 0"), Ok(Value::Bool(false)));
+        assert_eq!(eval("true || 1 / 0 == 0"), Ok(Value::Bool(true)));
+        assert_eq!(eval("false || 2 > 1"), Ok(Value::Bool(true)));
+        assert_eq!(eval_err("true && 1"), "cannot apply '&&' to int");
+    }
+
+    #[test]
+    fn calls_functions() {
+        let src = "
+            fn fib(n) {
+                if n < 2 { return n; }
+                fib(n - 1) + fib(n - 2)
+            }
+            fn classify(n) {
+                if n < 0 { \"negative\" } else if n == 0 { \"zero\" } else { \"positive\" }
+            }
+            fn early(n) {
+                let doubled = { if n > 5 { return 0; } n * 2 };
+                doubled + 1
+            }
+        ";
+        let program = Program::compile(src).unwrap();
+        let mut vm = Vm::new(&program, Vec::new());
+        assert_eq!(vm.call("fib", &[Value::Int(20)]), Ok(Value::Int(6765)));
+        assert_eq!(
+            vm.call("classify", &[Value::Int(-3)]),
+            Ok(Value::from("negative"))
+        );
+        assert_eq!(
+            vm.call("classify", &[Value::Int(0)]),
+            Ok(Value::from("zero"))
+        );
+        assert_eq!(vm.call("early", &[Value::Int(3)]), Ok(Value::Int(7)));
+        assert_eq!(vm.call("early", &[Value::Int(9)]), Ok(Value::Int(0)));
+        assert_eq!(
+            vm.call("fib", &[]).unwrap_err().kind.to_string(),
+            "'fib' takes 1 argument(s) but was given 0"
+        );
+    }
+
+    #[test]
+    fn prints_and_asserts() {
+        let program = Program::compile(
+            r#"fn main() { println("x is", 1, true); assert(1 == 2, "one is not two") }"#,
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        let err = Vm::new(&program, &mut output)
+            .call("main", &[])
+            .unwrap_err();
+        assert_eq!(output, b"x is 1 true\n");
+        assert_eq!(err.kind.to_string(), "assertion failed: one is not two");
+        assert_eq!(
+            eval_err("assert(false)"),
+            "assertion failed: condition was false"
+        );
+        assert_eq!(eval_err("assert(0)"), "assert expects a bool, found int");
+    }
+
+    #[test]
+    fn reports_runtime_errors() {
+        assert_eq!(
+            eval_err("9223372036854775807 + 1"),
+            "integer overflow in '+'"
+        );
+        assert_eq!(
+            eval_err("-(-9223372036854775807 - 1)"),
+            "integer overflow in '-'"
+        );
+        assert_eq!(
+            eval_err("(-9223372036854775807 - 1) / -1"),
+            "integer overflow in '/'"
+        );
+        assert_eq!(eval_err("5 % 0"), "division by zero");
+        assert_eq!(eval_err("1 + true"), "cannot apply '+' to int and bool");
+        assert_eq!(eval_err(r#"1 == "1""#), "cannot apply '==' to int and str");
+        assert_eq!(eval_err("!1"), "cannot apply '!' to int");
+        assert_eq!(
+            eval_err("if 1 { 2 }"),
+            "expected a bool condition, found int"
+        );
+    }
+
+    #[test]
+    fn traces_the_call_stack() {
+        let src = "fn main() {\n  outer()\n}\nfn outer() {\n  let x = 1;\n  inner(x)\n}\nfn inner(x) {\n  x - true\n}";
+        let program = Program::compile(src).unwrap();
+        let mut vm = Vm::new(&program, Vec::new());
+        let err = vm.call("main", &[]).unwrap_err();
+        let trace: Vec<(&str, u32)> = err
+            .trace
+            .iter()
+            .map(|frame| (frame.function.as_str(), frame.line))
+            .collect();
+        assert_eq!(trace, [("inner", 9), ("outer", 6), ("main", 2)]);
+
+        // The machine is left ready for the next call.
+        assert_eq!(
+            vm.call("inner", &[Value::Int(1)]).unwrap_err().trace.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn stops_runaway_recursion() {
+        let program = Program::compile("fn down(n) { down(n + 1) }").unwrap();
+        let err = Vm::new(&program, Vec::new())
+            .with_max_depth(50)
+            .call("down", &[Value::Int(0)])
+            .unwrap_err();
+        assert_eq!(err.kind, RuntimeErrorKind::StackOverflow);
+        assert_eq!(err.trace.len(), 50);
+    }
+}